@@ -0,0 +1,46 @@
+use anyhow::{anyhow, Context, Result};
+use snapto_core::AnnotationSpec;
+use std::path::{Path, PathBuf};
+
+use super::{input_extension, EditOutput};
+use crate::output;
+
+/// Execute the annotate command
+pub async fn execute(
+    input: PathBuf,
+    spec_path: PathBuf,
+    output_path: Option<PathBuf>,
+    upload: bool,
+    destination: Option<String>,
+    filename: Option<String>,
+) -> Result<()> {
+    let extension = input_extension(&input)?;
+    let spec = load_spec(&spec_path)?;
+    let image_data = std::fs::read(&input).with_context(|| format!("Failed to read {}", input.display()))?;
+
+    let annotated = spec.apply_encoded(&image_data)?;
+    output::success(&format!("Drew {} annotation(s)", spec.annotations.len()));
+
+    let target = EditOutput {
+        path: output_path,
+        upload,
+        destination,
+        filename,
+    };
+    target.finish(&input, "annotated", &annotated, &extension).await
+}
+
+/// Reads a spec as JSON for `.json` files and as TOML otherwise
+fn load_spec(path: &Path) -> Result<AnnotationSpec> {
+    let content = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let spec = match path.extension().and_then(|e| e.to_str()) {
+        Some(e) if e.eq_ignore_ascii_case("json") => AnnotationSpec::from_json(&content),
+        _ => AnnotationSpec::from_toml(&content),
+    }
+    .with_context(|| format!("Invalid annotation spec {}", path.display()))?;
+
+    if spec.is_empty() {
+        return Err(anyhow!("{} has no annotations", path.display()));
+    }
+    Ok(spec)
+}
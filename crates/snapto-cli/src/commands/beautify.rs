@@ -0,0 +1,39 @@
+use anyhow::{anyhow, Context, Result};
+use snapto_core::image_ops::{self, beautify};
+use snapto_core::Config;
+use std::path::PathBuf;
+
+use super::{input_extension, EditOutput};
+use crate::output;
+
+/// Execute the beautify command
+pub async fn execute(
+    input: PathBuf,
+    preset_name: String,
+    output_path: Option<PathBuf>,
+    upload: bool,
+    destination: Option<String>,
+    filename: Option<String>,
+) -> Result<()> {
+    let extension = input_extension(&input)?;
+    let config = Config::load().context("Failed to load configuration")?;
+    let preset = config
+        .beautify
+        .preset(&preset_name)
+        .ok_or_else(|| anyhow!("Unknown beautify preset '{}'", preset_name))?;
+
+    let image_data = std::fs::read(&input).with_context(|| format!("Failed to read {}", input.display()))?;
+    let framed = image_ops::edit_encoded(&image_data, |img| {
+        *img = beautify(img, &preset)?;
+        Ok(())
+    })?;
+    output::success(&format!("Framed with preset '{}'", preset_name));
+
+    let target = EditOutput {
+        path: output_path,
+        upload,
+        destination,
+        filename,
+    };
+    target.finish(&input, "beautified", &framed, &extension).await
+}
@@ -0,0 +1,117 @@
+use anyhow::{anyhow, Context, Result};
+use snapto_core::{Config, Region, ScreenshotConfig, ScreenshotError, ScreenshotManager};
+use snapto_core::screenshot::ImageFormat;
+use std::time::Duration;
+
+use super::upload::{upload_image_from, ImageSource, RedactArgs};
+use crate::{output, progress};
+
+/// What to capture
+pub enum CaptureTarget {
+    Fullscreen,
+    Region(Region),
+    Interactive,
+    Window(u32),
+    Display(u32),
+}
+
+/// Execute the capture command
+pub async fn execute(
+    target: CaptureTarget,
+    delay_secs: u64,
+    destination: Option<String>,
+    filename: Option<String>,
+    redact: RedactArgs,
+) -> Result<()> {
+    let config = Config::load().context("Failed to load configuration")?;
+
+    // Encode in the configured format so the extension matches the bytes
+    let extension = config.naming.default_extension.clone();
+    let format = ImageFormat::from_extension(&extension)
+        .ok_or_else(|| anyhow!("Unsupported default_extension: {} (use png, jpg, webp or avif)", extension))?;
+
+    let manager = ScreenshotManager::with_config(ScreenshotConfig {
+        format,
+        delay_ms: 0,
+        backend: config.capture.backend.clone(),
+        ..Default::default()
+    });
+
+    if delay_secs > 0 {
+        let pb = progress::spinner(&format!("Capturing in {}s...", delay_secs));
+        tokio::time::sleep(Duration::from_secs(delay_secs)).await;
+        pb.finish_and_clear();
+    }
+
+    if matches!(target, CaptureTarget::Interactive) {
+        output::info("Select an area to capture...");
+    }
+
+    let window_id = match target {
+        CaptureTarget::Window(id) => Some(id),
+        _ => None,
+    };
+
+    // Capture tools block, so keep them off the runtime threads
+    let capture = tokio::task::spawn_blocking(move || match target {
+        CaptureTarget::Fullscreen => manager.capture_fullscreen(),
+        CaptureTarget::Region(region) => manager.capture_region(region),
+        CaptureTarget::Interactive => manager.capture_interactive(),
+        CaptureTarget::Window(id) => manager.capture_window(id),
+        CaptureTarget::Display(id) => manager.capture_display(id),
+    })
+    .await
+    .map_err(|e| anyhow!("Capture task failed: {}", e))?;
+
+    let capture = match capture {
+        Ok(capture) => capture,
+        Err(ScreenshotError::Cancelled) => {
+            output::warning("Capture cancelled");
+            return Ok(());
+        }
+        Err(ScreenshotError::DisplayNotFound { id }) => {
+            return Err(anyhow!("No display {}. {}", id, available_displays()));
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    output::success(&format!(
+        "Captured {}x{} ({})",
+        capture.width,
+        capture.height,
+        output::format_size(capture.data.len() as u64)
+    ));
+
+    let data = redact.apply(capture.data)?;
+    let source = ImageSource {
+        window: window_id.and_then(ScreenshotManager::window_info).unwrap_or_default(),
+        edited: redact.is_set(),
+        ..Default::default()
+    };
+    upload_image_from(&config, &data, &extension, destination, filename, source).await
+}
+
+/// Describes the displays that can be passed to `--display`
+pub(super) fn available_displays() -> String {
+    match ScreenshotManager::list_displays() {
+        Ok(displays) if !displays.is_empty() => {
+            let list: Vec<String> = displays
+                .iter()
+                .map(|d| {
+                    format!(
+                        "{}: {} {}x{}+{}+{}{}",
+                        d.id,
+                        d.name,
+                        d.width,
+                        d.height,
+                        d.x,
+                        d.y,
+                        if d.is_primary { " (primary)" } else { "" }
+                    )
+                })
+                .collect();
+            format!("Available displays:\n  {}", list.join("\n  "))
+        }
+        _ => "No displays could be listed".to_string(),
+    }
+}
@@ -1,5 +1,5 @@
-use anyhow::{Context, Result};
-use snapto_core::Config;
+use anyhow::{anyhow, Context, Result};
+use snapto_core::{Config, KeychainManager, SshUploader};
 use std::process::Command;
 
 use crate::output;
@@ -29,6 +29,7 @@ pub async fn show() -> Result<()> {
     output::kv("Date Format", &config.naming.date_format);
     output::kv("Time Format", &config.naming.time_format);
     output::kv("Default Extension", &config.naming.default_extension);
+    output::kv("Recording Extension", &config.naming.recording_extension);
 
     // History settings
     output::section("History");
@@ -37,10 +38,15 @@ pub async fn show() -> Result<()> {
     output::kv("Retention Days", &config.history.retention_days.to_string());
     output::kv("Max Entries", &config.history.max_entries.to_string());
 
+    // Capture settings
+    output::section("Capture");
+    output::kv("Backend", config.capture.backend.as_deref().unwrap_or("auto"));
+
     // Security settings
     output::section("Security");
     output::kv("Use System Keychain", &config.security.use_system_keychain.to_string());
     output::kv("Encrypt Credentials", &config.security.encrypt_credentials.to_string());
+    output::kv("Image Metadata", &format!("{:?}", config.security.metadata));
 
     // Uploaders
     output::section("Uploaders");
@@ -52,6 +58,9 @@ pub async fn show() -> Result<()> {
             let default = if name == &config.general.default_uploader { " (default)" } else { "" };
             output::item(&format!("{} [{}] - {}{}", name, uploader.uploader_type, status, default));
 
+            if let Some(alias) = &uploader.ssh_host {
+                output::kv("  SSH Config Host", alias);
+            }
             if let Some(host) = &uploader.host {
                 output::kv("  Host", host);
             }
@@ -61,6 +70,18 @@ pub async fn show() -> Result<()> {
             if let Some(user) = &uploader.username {
                 output::kv("  Username", user);
             }
+            if let Some(hosts) = &uploader.jump_hosts {
+                let hops: Vec<&str> = hosts
+                    .iter()
+                    .filter_map(|h| h.host.as_deref().or(h.ssh_host.as_deref()))
+                    .collect();
+                output::kv("  Jump Hosts", &hops.join(" → "));
+            } else if let Some(jump) = &uploader.proxy_jump {
+                output::kv("  Jump Hosts", jump);
+            }
+            if let Some(proxy) = &uploader.proxy {
+                output::kv("  Proxy", proxy);
+            }
             if let Some(path) = &uploader.remote_path {
                 output::kv("  Remote Path", path);
             }
@@ -171,3 +192,40 @@ pub async fn init() -> Result<()> {
 
     Ok(())
 }
+
+/// Store or remove the TOTP seed for an SSH destination
+pub async fn totp(destination: &str, remove: bool) -> Result<()> {
+    let config = Config::load().context("Failed to load configuration")?;
+
+    let dest = config
+        .uploads
+        .get(destination)
+        .ok_or_else(|| anyhow!("Destination '{}' not found in configuration", destination))?;
+
+    if !matches!(dest.uploader_type.as_str(), "sftp" | "ssh") {
+        return Err(anyhow!("Destination '{}' is not an SSH destination", destination));
+    }
+
+    let keychain = KeychainManager::new(&config.security);
+    let uploader = SshUploader::new(destination.to_string(), dest.clone());
+
+    if remove {
+        keychain
+            .delete(&format!("ssh_totp_{}", destination))
+            .context("Failed to remove TOTP seed")?;
+        output::success(&format!("TOTP seed removed for '{}'", destination));
+        return Ok(());
+    }
+
+    let secret = rpassword::prompt_password("TOTP seed (base32): ")
+        .context("Failed to read TOTP seed")?;
+
+    uploader
+        .store_totp_secret_in_keychain(&keychain, secret.trim())
+        .context("Failed to store TOTP seed")?;
+
+    output::success(&format!("TOTP seed stored for '{}'", destination));
+    output::info("One-time password prompts will be answered automatically");
+
+    Ok(())
+}
@@ -0,0 +1,113 @@
+use anyhow::{anyhow, Context, Result};
+use clap::Args;
+use snapto_core::image_ops::diff_encoded;
+use snapto_core::screenshot::ImageFormat;
+use snapto_core::{Config, DiffConfig};
+use std::path::PathBuf;
+
+use super::{input_extension, EditOutput};
+use crate::output;
+
+/// Overrides for the [diff] config section, plus CI reporting
+#[derive(Args, Debug, Default)]
+pub struct DiffArgs {
+    /// Largest per-channel difference (0-255) that still counts as equal
+    #[arg(short, long)]
+    pub threshold: Option<u8>,
+
+    /// Changes closer than this many pixels are boxed together
+    #[arg(long, value_name = "PIXELS")]
+    pub merge_distance: Option<u32>,
+
+    /// Ignore regions with fewer changed pixels than this
+    #[arg(long, value_name = "PIXELS")]
+    pub min_region_pixels: Option<u32>,
+
+    /// Fail when similarity (0-1) is below this, e.g. 0.99
+    #[arg(long, value_name = "SCORE")]
+    pub min_similarity: Option<f64>,
+
+    /// Write the report (score and regions) as JSON
+    #[arg(long, value_name = "FILE")]
+    pub report: Option<PathBuf>,
+}
+
+impl DiffArgs {
+    /// The configured options with any flags applied on top
+    fn apply(&self, mut config: DiffConfig) -> Result<DiffConfig> {
+        if let Some(threshold) = self.threshold {
+            config.threshold = threshold;
+        }
+        if let Some(distance) = self.merge_distance {
+            config.merge_distance = distance;
+        }
+        if let Some(pixels) = self.min_region_pixels {
+            config.min_region_pixels = pixels;
+        }
+        if self.min_similarity.is_some() {
+            config.min_similarity = self.min_similarity;
+        }
+        config.validate().map_err(|e| anyhow!("Invalid diff options: {}", e))?;
+        Ok(config)
+    }
+}
+
+/// Execute the diff command
+pub async fn execute(
+    old: PathBuf,
+    new: PathBuf,
+    args: DiffArgs,
+    output_path: Option<PathBuf>,
+    upload: bool,
+    destination: Option<String>,
+    filename: Option<String>,
+) -> Result<()> {
+    // The overlay takes the format of the output file, or of the new image
+    let extension = input_extension(output_path.as_ref().unwrap_or(&new))?;
+    let format = ImageFormat::from_extension(&extension)
+        .ok_or_else(|| anyhow!("Unsupported image: {}", extension))?;
+
+    let config = Config::load().context("Failed to load configuration")?;
+    let options = args.apply(config.diff)?;
+
+    let read = |path: &PathBuf| std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()));
+    let (report, overlay) = diff_encoded(&read(&old)?, &read(&new)?, &options, format)?;
+
+    if report.is_identical() {
+        output::success("No visible changes");
+    } else {
+        output::info(&format!("{} changed region(s)", report.regions.len()));
+        for region in &report.regions {
+            output::list_item(&format!(
+                "{}x{} at {},{} ({} px)",
+                region.width, region.height, region.x, region.y, region.pixels
+            ));
+        }
+    }
+    output::kv("Changed", &format!("{} of {} px", report.changed_pixels, report.width as u64 * report.height as u64));
+    output::kv("Similarity", &format!("{:.6} ({:.2}%)", report.similarity, report.similarity * 100.0));
+
+    if let Some(path) = &args.report {
+        let json = serde_json::to_string_pretty(&report)?;
+        std::fs::write(path, json).with_context(|| format!("Failed to write {}", path.display()))?;
+        output::success(&format!("Report saved to {}", path.display()));
+    }
+
+    let target = EditOutput {
+        path: output_path,
+        upload,
+        destination,
+        filename,
+    };
+    target.finish(&new, "diff", &overlay, &extension).await?;
+
+    // Checked last so CI still gets the overlay and report of a failing run
+    match options.min_similarity {
+        Some(min) if report.similarity < min => Err(anyhow!(
+            "Similarity {:.6} is below the minimum of {:.6}",
+            report.similarity,
+            min
+        )),
+        _ => Ok(()),
+    }
+}
@@ -41,8 +41,20 @@ pub async fn execute(limit: usize, full: bool) -> Result<()> {
             output::kv("  Path", &entry.remote_path);
             output::kv("  Filename", &entry.filename);
             output::kv("  Size", &output::format_size(entry.size as u64));
+            if let Some(original) = entry.original_size {
+                output::kv("  Original Size", &output::format_size(original as u64));
+            }
             output::kv("  Destination", &entry.destination);
             output::kv("  Uploaded", &time_str);
+            if let Some(sources) = entry
+                .metadata
+                .as_ref()
+                .and_then(|metadata| metadata.get("sources"))
+                .and_then(|sources| sources.as_array())
+            {
+                let names: Vec<&str> = sources.iter().filter_map(|source| source.as_str()).collect();
+                output::kv("  Sources", &names.join(", "));
+            }
         } else {
             // Compact view
             let url_or_path = entry.url.as_ref().unwrap_or(&entry.remote_path);
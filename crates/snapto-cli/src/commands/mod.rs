@@ -1,4 +1,462 @@
+pub mod annotate;
+pub mod beautify;
+pub mod capture;
 pub mod config;
+pub mod diff;
 pub mod history;
+pub mod record;
+pub mod stitch;
 pub mod upload;
 pub mod watch;
+
+use anyhow::{anyhow, Context, Result};
+use snapto_core::screenshot::ImageFormat;
+use snapto_core::transform;
+use snapto_core::{
+    Config, CounterStore, DuplicatePolicy, HistoryEntry, HistoryManager, IdGenerator, KeychainManager, LocalUploader,
+    PerceptualHash, ProgressCallback, SessionPool, SshUploader, TemplateParser, Transformed, UploadConfig,
+    UploadResult, Uploader, WindowInfo,
+};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::{output, progress, prompt};
+
+/// Names drawn before giving up on finding a free {id:N}
+const NAME_ATTEMPTS: usize = 8;
+
+/// Create an uploader based on config type
+///
+/// SSH uploaders pick up their password and TOTP seed from the keychain and
+/// fall back to prompting on the terminal for anything else the server asks.
+/// With a `pool`, their sessions stay open between uploads.
+pub fn create_uploader(
+    name: &str,
+    config: &UploadConfig,
+    keychain: &KeychainManager,
+    pool: Option<&SessionPool>,
+) -> Result<Box<dyn Uploader>> {
+    let uploader: Box<dyn Uploader> = match config.uploader_type.as_str() {
+        "sftp" | "ssh" => {
+            let mut uploader = SshUploader::new(name.to_string(), config.clone());
+            if let Some(password) = uploader.get_password_from_keychain(keychain) {
+                uploader.set_password(password);
+            }
+            if let Some(secret) = uploader.get_totp_secret_from_keychain(keychain) {
+                uploader = uploader.with_totp_secret(secret);
+            }
+            if let Some(callback) = prompt::tty_password_callback() {
+                uploader = uploader.with_password_callback(callback);
+            }
+            if let Some(pool) = pool {
+                uploader = uploader.with_session_pool(pool.clone());
+            }
+            Box::new(uploader)
+        }
+        "local" => Box::new(LocalUploader::new(name.to_string(), config.clone())),
+        _ => return Err(anyhow!("Unknown uploader type: {}", config.uploader_type)),
+    };
+    Ok(uploader)
+}
+
+/// A destination an image is sent to
+pub struct Target<'a> {
+    pub name: String,
+    pub config: &'a UploadConfig,
+    pub uploader: Box<dyn Uploader>,
+}
+
+/// The enabled destinations of an upload, primary first
+///
+/// `destination` picks a single one; without it the default uploader is
+/// joined by the additional ones. Disabled destinations are skipped with a
+/// warning, and everything else is checked before anything is uploaded.
+pub fn targets<'a>(
+    config: &'a Config,
+    destination: Option<&str>,
+    keychain: &KeychainManager,
+    pool: Option<&SessionPool>,
+) -> Result<Vec<Target<'a>>> {
+    let primary = destination.unwrap_or(&config.general.default_uploader);
+    let mut names = vec![primary];
+    if destination.is_none() {
+        for additional in &config.general.additional_uploaders {
+            if !names.contains(&additional.as_str()) {
+                names.push(additional);
+            }
+        }
+    }
+
+    let mut targets = Vec::new();
+    for name in names {
+        let dest = config
+            .uploads
+            .get(name)
+            .ok_or_else(|| anyhow!("Destination '{}' not found in configuration", name))?;
+
+        if !dest.enabled {
+            output::warning(&format!("Destination '{}' is disabled, skipping", name));
+            continue;
+        }
+
+        transform::beautify_preset(config, name, dest)?;
+        let uploader = create_uploader(name, dest, keychain, pool)?;
+        uploader.validate()?;
+        targets.push(Target {
+            name: name.to_string(),
+            config: dest,
+            uploader,
+        });
+    }
+
+    match targets.as_slice() {
+        [] => return Err(anyhow!("No enabled uploaders configured")),
+        [only] => output::info(&format!("Using destination: {}", only.name)),
+        all => {
+            output::info(&format!("Uploading to {} destinations:", all.len()));
+            for target in all {
+                output::item(&target.name);
+            }
+        }
+    }
+    Ok(targets)
+}
+
+/// Template parser for an image about to go to `destination`
+pub fn name_parser(
+    config: &Config,
+    image_data: &[u8],
+    content_hash: &str,
+    destination: &str,
+    window: WindowInfo,
+) -> Result<TemplateParser> {
+    let mut parser = TemplateParser::new(config.naming.date_format.clone(), config.naming.time_format.clone())
+        .with_content_hash(content_hash.to_string())
+        .with_destination(destination)
+        .with_counters(CounterStore::default_path()?, config.naming.counter_scope)
+        .with_ids(IdGenerator::from_config(&config.naming)?)
+        .with_window(window);
+    if let Some((width, height)) = snapto_core::image_ops::dimensions(image_data) {
+        parser = parser.with_dimensions(width, height);
+    }
+    Ok(parser)
+}
+
+/// What the primary destination ended up with
+pub struct Delivery {
+    pub result: UploadResult,
+    /// The bytes that were uploaded
+    pub transformed: Transformed,
+    /// Name on the destination, with the extension of its output format
+    pub filename: String,
+    /// Time spent uploading to every destination
+    pub duration: Duration,
+}
+
+/// Uploads `primary`, the first destination's copy of `image_data`, and
+/// runs the other destinations' pipelines to upload theirs, as `filename`
+///
+/// The primary destination gets a progress bar. Its link and history entry
+/// stand for the whole upload, so when it fails the rest are not tried; the
+/// others only report their own failures.
+pub async fn deliver(
+    config: &Config,
+    targets: &[Target<'_>],
+    image_data: &[u8],
+    primary: Transformed,
+    filename: &str,
+    scale_factor: f64,
+) -> Result<Delivery> {
+    let start = Instant::now();
+    let mut delivered: Option<(UploadResult, Transformed, String)> = None;
+    let mut primary = Some(primary);
+
+    for (i, target) in targets.iter().enumerate() {
+        let sent = async {
+            let transformed = match primary.take() {
+                Some(transformed) => transformed,
+                None => transform::for_destination(image_data, config, &target.name, target.config, scale_factor)?,
+            };
+            send(target, transformed, filename, i == 0).await
+        }
+        .await;
+
+        match sent {
+            Ok((result, transformed, dest_filename)) => {
+                output::success(&format!(
+                    "✓ {} → {}",
+                    target.name,
+                    result.url.as_ref().unwrap_or(&result.remote_path)
+                ));
+                if delivered.is_none() {
+                    delivered = Some((result, transformed, dest_filename));
+                }
+            }
+            Err(e) => {
+                output::error(&format!("✗ {} failed: {}", target.name, e));
+                if i == 0 {
+                    return Err(e);
+                }
+            }
+        }
+    }
+
+    let (result, transformed, filename) = delivered.ok_or_else(|| anyhow!("No successful uploads"))?;
+    Ok(Delivery {
+        result,
+        transformed,
+        filename,
+        duration: start.elapsed(),
+    })
+}
+
+/// Uploads one destination's copy
+async fn send(
+    target: &Target<'_>,
+    transformed: Transformed,
+    filename: &str,
+    show_progress: bool,
+) -> Result<(UploadResult, Transformed, String)> {
+    let dest_filename = transformed.filename(filename);
+    if transformed.changed {
+        output::info(&format!(
+            "Transformed for {}: {} → {} ({})",
+            target.name,
+            output::format_size(transformed.original_size as u64),
+            output::format_size(transformed.data.len() as u64),
+            transformed.format.extension()
+        ));
+    }
+
+    let result = if show_progress {
+        let pb = progress::upload_progress(transformed.data.len() as u64);
+        pb.set_message(format!("Uploading to {}...", target.name));
+        let bar = pb.clone();
+        let progress: ProgressCallback = Arc::new(move |sent, _total| bar.set_position(sent));
+        let result = target
+            .uploader
+            .upload_with_progress(&transformed.data, &dest_filename, progress)
+            .await;
+        pb.finish_and_clear();
+        result
+    } else {
+        output::step(&format!("Uploading to {}...", target.name));
+        target.uploader.upload(&transformed.data, &dest_filename).await
+    };
+
+    Ok((result?, transformed, dest_filename))
+}
+
+/// What history keeps about where an upload came from
+pub struct Provenance {
+    pub metadata: Option<serde_json::Value>,
+    pub perceptual_hash: Option<PerceptualHash>,
+    /// Of the primary destination's bytes, as uploaded
+    pub content_hash: String,
+}
+
+/// Adds the primary upload to history; failures only warn
+pub fn record(history: Option<&HistoryManager>, delivery: &Delivery, destination: &str, provenance: Provenance) {
+    let Some(history) = history else {
+        return;
+    };
+
+    let entry = HistoryEntry {
+        id: 0,
+        filename: delivery.filename.clone(),
+        remote_path: delivery.result.remote_path.clone(),
+        url: delivery.result.url.clone(),
+        size: delivery.result.size,
+        destination: destination.to_string(),
+        created_at: chrono::Utc::now(),
+        thumbnail_path: None,
+        local_copy_path: None,
+        mime_type: Some(snapto_core::mime_type_for(&delivery.filename).to_string()),
+        original_size: delivery.transformed.changed.then_some(delivery.transformed.original_size),
+        metadata: provenance.metadata,
+        perceptual_hash: provenance.perceptual_hash,
+        content_hash: Some(provenance.content_hash),
+    };
+    if let Err(e) = history.add(&entry, Some(&delivery.transformed.data)) {
+        output::warning(&format!("Failed to save to history: {}", e));
+    }
+}
+
+/// Where an edited file goes: saved, uploaded or both
+pub struct EditOutput {
+    pub path: Option<PathBuf>,
+    pub upload: bool,
+    pub destination: Option<String>,
+    pub filename: Option<String>,
+}
+
+impl EditOutput {
+    /// Saves and/or uploads `data`, the edited version of `input`
+    ///
+    /// Without an output path or `--upload`, the result lands next to the
+    /// input as `<stem>-<suffix>.<ext>`.
+    pub async fn finish(self, input: &Path, suffix: &str, data: &[u8], extension: &str) -> Result<()> {
+        let path = match self.path {
+            Some(path) => Some(path),
+            None if !self.upload => Some(default_output(input, suffix, extension)),
+            None => None,
+        };
+        if let Some(path) = path {
+            std::fs::write(&path, data).with_context(|| format!("Failed to write {}", path.display()))?;
+            output::success(&format!("Saved to {}", path.display()));
+        }
+
+        if self.upload {
+            let config = Config::load().context("Failed to load configuration")?;
+            let source = upload::ImageSource {
+                edited: true,
+                ..Default::default()
+            };
+            upload::upload_image_from(&config, data, extension, self.destination, self.filename, source).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Lowercase extension of an image file the pipeline can encode
+pub fn input_extension(input: &Path) -> Result<String> {
+    input
+        .extension()
+        .and_then(|e| e.to_str())
+        .filter(|e| ImageFormat::from_extension(e).is_some())
+        .map(str::to_lowercase)
+        .ok_or_else(|| anyhow!("Unsupported image: {} (use png, jpg, webp or avif)", input.display()))
+}
+
+/// `shot.png` becomes `shot-<suffix>.png`
+fn default_output(input: &Path, suffix: &str, extension: &str) -> PathBuf {
+    let stem = input.file_stem().and_then(|s| s.to_str()).unwrap_or("image");
+    input.with_file_name(format!("{}-{}.{}", stem, suffix, extension))
+}
+
+/// An earlier upload of the same picture
+pub struct Duplicate {
+    pub entry: HistoryEntry,
+    /// Same bytes, not just a similar-looking picture
+    pub exact: bool,
+}
+
+impl Duplicate {
+    /// Whether to hand out the earlier upload instead of uploading again
+    pub fn reuse(&self, config: &Config) -> bool {
+        self.exact || config.duplicates.policy == DuplicatePolicy::Reuse
+    }
+}
+
+/// Looks for an earlier upload of the same content to `destination`
+///
+/// `content_hash` is of the bytes `destination` would get, so a change to
+/// its transform, watermark or beautify settings is a new upload.
+///
+/// Exact matches need `dedup`, similar pictures a policy other than upload.
+/// An `edited` image (redacted, annotated, stitched, diffed) only matches
+/// exactly: it looks like the original it was made from, and handing out
+/// the original's link would undo a redaction. Lookup errors count as "not
+/// found" so they never block an upload.
+pub fn find_duplicate(
+    config: &Config,
+    history: Option<&HistoryManager>,
+    content_hash: &str,
+    perceptual_hash: Option<PerceptualHash>,
+    destination: &str,
+    edited: bool,
+) -> Option<Duplicate> {
+    let history = history?;
+
+    if config.duplicates.dedup {
+        if let Ok(Some(entry)) = history.find_by_content_hash(content_hash, destination) {
+            return Some(Duplicate { entry, exact: true });
+        }
+    }
+
+    if edited || config.duplicates.policy == DuplicatePolicy::Upload {
+        return None;
+    }
+    history
+        .find_similar(perceptual_hash?, config.duplicates.max_distance, destination)
+        .ok()
+        .flatten()
+        .map(|entry| Duplicate { entry, exact: false })
+}
+
+/// Names the upload with the configured template
+///
+/// With `{id:N}` in the template, names already in history for the primary
+/// destination or already on its uploader are drawn again, so a short id
+/// never overwrites an earlier upload. Only the id changes between attempts,
+/// and the name checked is the one `prepared` is uploaded as.
+pub async fn unique_filename(
+    config: &Config,
+    parser: &TemplateParser,
+    extension: &str,
+    history: Option<&HistoryManager>,
+    primary: &Target<'_>,
+    prepared: &Transformed,
+) -> Result<String> {
+    let template = &config.naming.template;
+    let draft = parser.draft(template, extension)?;
+    if !TemplateParser::has_ids(template) {
+        return Ok(draft.draw()?);
+    }
+
+    for _ in 0..NAME_ATTEMPTS {
+        let filename = draft.draw()?;
+        let uploaded_as = prepared.filename(&filename);
+        let in_history = history.is_some_and(|h| h.has_filename(&uploaded_as, &primary.name).unwrap_or(false));
+        if !in_history && !primary.uploader.exists(&uploaded_as).await? {
+            return Ok(filename);
+        }
+        output::info(&format!("{} is already taken, drawing another id", uploaded_as));
+    }
+
+    Err(anyhow!(
+        "No free filename after {} attempts; use a longer {{id:N}} in the template",
+        NAME_ATTEMPTS
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use snapto_core::HistoryConfig;
+
+    #[test]
+    fn test_edited_images_only_reuse_exact_copies() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let mut config = Config::default();
+        config.duplicates.policy = DuplicatePolicy::Reuse;
+        config.duplicates.dedup = true;
+        let history = HistoryManager::new(HistoryConfig {
+            path: dir.path().to_path_buf(),
+            ..config.history.clone()
+        })
+        .unwrap();
+
+        // The unredacted screenshot, uploaded earlier
+        let original = PerceptualHash(0x0f0f_0f0f_0f0f_0f0f);
+        let entry = HistoryEntry {
+            filename: "original.png".to_string(),
+            destination: "vps".to_string(),
+            perceptual_hash: Some(original),
+            content_hash: Some("original".to_string()),
+            ..HistoryEntry::default()
+        };
+        history.add(&entry, None).unwrap();
+
+        // A redacted copy looks almost the same
+        let redacted = PerceptualHash(original.0 ^ 1);
+        let similar = find_duplicate(&config, Some(&history), "redacted", Some(redacted), "vps", false);
+        assert!(similar.is_some_and(|d| !d.exact));
+        assert!(find_duplicate(&config, Some(&history), "redacted", Some(redacted), "vps", true).is_none());
+
+        // The same edited bytes again are still reused
+        let exact = find_duplicate(&config, Some(&history), "original", Some(redacted), "vps", true);
+        assert!(exact.is_some_and(|d| d.exact));
+    }
+}
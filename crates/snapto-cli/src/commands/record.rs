@@ -0,0 +1,73 @@
+use anyhow::{anyhow, Context, Result};
+use snapto_core::{Config, RecordingConfig, RecordingError, RecordingFormat, RecordingManager, RecordingTarget};
+use std::time::Duration;
+
+use super::upload::upload_image;
+use crate::{output, progress};
+
+/// Execute the record command
+pub async fn execute(
+    target: RecordingTarget,
+    duration_secs: Option<u64>,
+    format: Option<String>,
+    fps: u32,
+    destination: Option<String>,
+    filename: Option<String>,
+) -> Result<()> {
+    let config = Config::load().context("Failed to load configuration")?;
+
+    let extension = format.unwrap_or_else(|| config.naming.recording_extension.clone());
+    let format = RecordingFormat::from_extension(&extension)
+        .ok_or_else(|| anyhow!("Unsupported recording format: {} (use gif, webp or mp4)", extension))?;
+
+    let duration = duration_secs.map(Duration::from_secs);
+    let manager = RecordingManager::with_config(RecordingConfig {
+        format,
+        fps,
+        duration,
+        ..Default::default()
+    });
+
+    let mut recording = match manager.start(target) {
+        Ok(recording) => recording,
+        Err(RecordingError::DisplayNotFound { id }) => {
+            return Err(anyhow!("No display {}. {}", id, super::capture::available_displays()));
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    let message = match duration {
+        Some(duration) => format!("Recording for {}s (Ctrl+C to stop early)...", duration.as_secs()),
+        None => "Recording (Ctrl+C to stop)...".to_string(),
+    };
+    let pb = progress::spinner(&message);
+
+    // Stop on Ctrl+C, when the duration runs out or if the recorder exits
+    let ctrl_c = tokio::signal::ctrl_c();
+    tokio::pin!(ctrl_c);
+    loop {
+        tokio::select! {
+            _ = &mut ctrl_c => break,
+            _ = tokio::time::sleep(Duration::from_millis(200)) => {
+                if recording.is_finished() || duration.is_some_and(|d| recording.elapsed() >= d) {
+                    break;
+                }
+            }
+        }
+    }
+
+    pb.set_message(format!("Encoding {}...", format.extension()));
+    let result = tokio::task::spawn_blocking(move || recording.stop())
+        .await
+        .map_err(|e| anyhow!("Recording task failed: {}", e))?;
+    pb.finish_and_clear();
+
+    let result = result?;
+    output::success(&format!(
+        "Recorded {:.1}s ({})",
+        result.duration.as_secs_f64(),
+        output::format_size(result.data.len() as u64)
+    ));
+
+    upload_image(&config, &result.data, format.extension(), destination, filename).await
+}
@@ -0,0 +1,99 @@
+use anyhow::{anyhow, Context, Result};
+use clap::Args;
+use snapto_core::image_ops::stitch_encoded;
+use snapto_core::screenshot::ImageFormat;
+use snapto_core::{Config, StitchLayout, StitchOptions};
+use std::path::PathBuf;
+
+use super::upload::{upload_image_from, ImageSource};
+use crate::output;
+
+/// How the images are arranged on the canvas
+#[derive(Args, Debug)]
+pub struct LayoutArgs {
+    /// How to arrange the images: horizontal, vertical or grid
+    #[arg(short, long, default_value = "horizontal")]
+    pub layout: StitchLayout,
+
+    /// Space between images and around the edges, in pixels
+    #[arg(short, long, default_value = "8")]
+    pub gap: u32,
+
+    /// Columns for the grid layout (square grid if not specified)
+    #[arg(short, long)]
+    pub columns: Option<u32>,
+
+    /// Comma-separated labels drawn above the images, e.g. "Before,After"
+    #[arg(long, value_delimiter = ',')]
+    pub labels: Vec<String>,
+
+    /// Background color (#rrggbb or #rrggbbaa)
+    #[arg(short, long, default_value = "#ffffff")]
+    pub background: String,
+}
+
+impl From<LayoutArgs> for StitchOptions {
+    fn from(args: LayoutArgs) -> Self {
+        StitchOptions {
+            layout: args.layout,
+            gap: args.gap,
+            columns: args.columns,
+            background: args.background,
+            labels: args.labels.iter().map(|label| label.trim().to_string()).collect(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Execute the stitch command
+pub async fn execute(
+    inputs: Vec<PathBuf>,
+    layout: LayoutArgs,
+    output_path: Option<PathBuf>,
+    destination: Option<String>,
+    filename: Option<String>,
+) -> Result<()> {
+    let config = Config::load().context("Failed to load configuration")?;
+
+    // Encode in the configured format so the extension matches the bytes
+    let extension = config.naming.default_extension.clone();
+    let format = ImageFormat::from_extension(&extension)
+        .ok_or_else(|| anyhow!("Unsupported default_extension: {} (use png, jpg, webp or avif)", extension))?;
+    let options = StitchOptions::from(layout);
+
+    let images = inputs
+        .iter()
+        .map(|input| std::fs::read(input).with_context(|| format!("Failed to read {}", input.display())))
+        .collect::<Result<Vec<_>>>()?;
+
+    let data = stitch_encoded(&images, &options, format)?;
+    output::success(&format!(
+        "Stitched {} images ({})",
+        images.len(),
+        output::format_size(data.len() as u64)
+    ));
+
+    if let Some(path) = output_path {
+        std::fs::write(&path, &data).with_context(|| format!("Failed to write {}", path.display()))?;
+        output::success(&format!("Saved to {}", path.display()));
+    }
+
+    // Record what went into the image so history can trace it back
+    let sources: Vec<String> = inputs
+        .iter()
+        .map(|input| {
+            input
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| input.display().to_string())
+        })
+        .collect();
+    let metadata = serde_json::json!({ "sources": sources });
+
+    let source = ImageSource {
+        metadata: Some(metadata),
+        edited: true,
+        ..Default::default()
+    };
+    upload_image_from(&config, &data, &extension, destination, filename, source).await
+}
@@ -1,42 +1,89 @@
 use anyhow::{anyhow, Context, Result};
+use clap::Args;
 use snapto_core::{
     ClipboardManager,
     ClipboardCopyMode,
     Config,
     HistoryManager,
-    SftpUploader,
-    LocalUploader,
-    SshUploader,
-    Uploader,
-    TemplateParser,
-    UploadConfig,
+    KeychainManager,
+    PerceptualHash,
+    RedactMode,
+    RedactSpec,
+    Region,
     UploadResult,
+    WindowInfo,
 };
-use std::time::Instant;
+use snapto_core::screenshot::ImageFormat;
+use snapto_core::transform;
+use std::path::PathBuf;
+
+use super::{deliver, find_duplicate, name_parser, record, targets, unique_filename, Provenance};
+use crate::output;
+
+/// Regions to hide before uploading, shared by the commands that upload
+#[derive(Args, Debug, Default)]
+pub struct RedactArgs {
+    /// Hide a region: x,y,width,height (repeatable)
+    #[arg(long, value_name = "X,Y,W,H")]
+    pub redact: Vec<Region>,
+
+    /// JSON file listing regions to hide
+    #[arg(long, value_name = "FILE")]
+    pub redact_spec: Option<PathBuf>,
+
+    /// How regions are hidden: fill (default), pixelate or blur
+    #[arg(long, value_name = "MODE")]
+    pub redact_mode: Option<RedactMode>,
+}
 
-use crate::{output, progress};
+impl RedactArgs {
+    /// Whether any region was asked for
+    pub fn is_set(&self) -> bool {
+        !self.redact.is_empty() || self.redact_spec.is_some()
+    }
 
-/// Create an uploader based on config type
-fn create_uploader(name: &str, config: &UploadConfig) -> Result<Box<dyn Uploader>> {
-    let uploader: Box<dyn Uploader> = match config.uploader_type.as_str() {
-        "sftp" => Box::new(SftpUploader::new(name.to_string(), config.clone())),
-        "ssh" => Box::new(SshUploader::new(name.to_string(), config.clone())),
-        "local" => Box::new(LocalUploader::new(name.to_string(), config.clone())),
-        _ => return Err(anyhow!("Unknown uploader type: {}", config.uploader_type)),
-    };
-    Ok(uploader)
+    /// Redacts `image_data`, or returns it as is when no region was given
+    pub fn apply(&self, image_data: Vec<u8>) -> Result<Vec<u8>> {
+        let mut spec = match &self.redact_spec {
+            Some(path) => {
+                let json = std::fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?;
+                RedactSpec::from_json(&json)?
+            }
+            None => RedactSpec::default(),
+        };
+        if let Some(mode) = self.redact_mode {
+            spec.mode = mode;
+        }
+        spec.regions.extend(self.redact.iter().map(|&region| region.into()));
+
+        if spec.is_empty() {
+            return Ok(image_data);
+        }
+
+        let redacted = spec.apply_encoded(&image_data)?;
+        output::success(&format!("Redacted {} region(s)", spec.regions.len()));
+        Ok(redacted)
+    }
 }
 
 /// Execute the upload command
-pub async fn execute(destination: Option<String>, filename: Option<String>) -> Result<()> {
+pub async fn execute(destination: Option<String>, filename: Option<String>, redact: RedactArgs) -> Result<()> {
+    // Load configuration
+    output::step("Loading configuration...");
+    let config = Config::load().context("Failed to load configuration")?;
+    let extension = config.naming.default_extension.clone();
+    let format = ImageFormat::from_extension(&extension)
+        .ok_or_else(|| anyhow!("Unsupported default_extension: {} (use png, jpg, webp or avif)", extension))?;
+
     output::step("Reading image from clipboard...");
 
     // Initialize clipboard manager
     let mut clipboard = ClipboardManager::new()?;
 
-    // Get image from clipboard
+    // Get image from clipboard, encoded to match the extension
     let image_data = clipboard
-        .get_image()
+        .get_image_as(format)
         .context("Failed to read image from clipboard")?;
 
     if image_data.is_empty() {
@@ -48,114 +95,98 @@ pub async fn execute(destination: Option<String>, filename: Option<String>) -> R
         output::format_size(image_data.len() as u64)
     ));
 
-    // Load configuration
-    output::step("Loading configuration...");
-    let config = Config::load().context("Failed to load configuration")?;
-
-    // Generate filename
-    let parser = TemplateParser::new(
-        config.naming.date_format.clone(),
-        config.naming.time_format.clone(),
-    );
-    let final_filename = if let Some(name) = filename {
-        name
-    } else {
-        parser.generate(&config.naming.template, &config.naming.default_extension)?
+    let source = ImageSource {
+        edited: redact.is_set(),
+        ..Default::default()
     };
+    let image_data = redact.apply(image_data)?;
 
-    output::info(&format!("Filename: {}", final_filename));
+    upload_image_from(&config, &image_data, &extension, destination, filename, source).await
+}
 
-    // Build list of uploaders to use
-    let has_specific_dest = destination.is_some();
-    let primary_name = destination.unwrap_or_else(|| config.general.default_uploader.clone());
-    let mut uploader_names = vec![primary_name.clone()];
+/// What is known about where an image came from
+#[derive(Debug, Default)]
+pub struct ImageSource {
+    /// Recorded with the history entry
+    pub metadata: Option<serde_json::Value>,
+    /// Captured window, for {window_title} and {app} in the filename
+    pub window: WindowInfo,
+    /// Pixels were changed (redacted, annotated, stitched, diffed), so only
+    /// an exact copy may stand in for it
+    pub edited: bool,
+}
 
-    // Add additional uploaders (only if no specific destination was provided)
-    if !has_specific_dest {
-        for additional in &config.general.additional_uploaders {
-            if additional != &primary_name && !uploader_names.contains(additional) {
-                uploader_names.push(additional.clone());
-            }
-        }
-    }
+/// Name, upload, copy the link and record history for an image
+///
+/// Shared by every command that produces an image (clipboard, capture).
+pub async fn upload_image(
+    config: &Config,
+    image_data: &[u8],
+    extension: &str,
+    destination: Option<String>,
+    filename: Option<String>,
+) -> Result<()> {
+    upload_image_from(config, image_data, extension, destination, filename, ImageSource::default()).await
+}
 
-    // Show what we're uploading to
-    if uploader_names.len() > 1 {
-        output::info(&format!("Uploading to {} destinations:", uploader_names.len()));
-        for name in &uploader_names {
-            output::item(name);
-        }
-    } else {
-        output::info(&format!("Using destination: {}", primary_name));
+/// [`upload_image`], naming and recording the image with `source`
+pub async fn upload_image_from(
+    config: &Config,
+    image_data: &[u8],
+    extension: &str,
+    destination: Option<String>,
+    filename: Option<String>,
+    source: ImageSource,
+) -> Result<()> {
+    let keychain = KeychainManager::new(&config.security);
+    let targets = targets(config, destination.as_deref(), &keychain, None)?;
+    let primary = &targets[0];
+    let scale_factor = transform::scale_factor_for(targets.iter().map(|target| target.config));
+
+    // Hashed as uploaded, so new transform settings make a new upload
+    let prepared = transform::for_destination(image_data, config, &primary.name, primary.config, scale_factor)?;
+    let content_hash = snapto_core::content_hash(&prepared.data);
+
+    // Videos and anything else that does not decode get no perceptual hash
+    let perceptual_hash = PerceptualHash::of_encoded(image_data).ok();
+    let history = config
+        .history
+        .enabled
+        .then(|| HistoryManager::new(config.history.clone()).ok())
+        .flatten();
+
+    // An explicit filename asks for that file, so it is always uploaded
+    let duplicate = filename
+        .is_none()
+        .then(|| find_duplicate(config, history.as_ref(), &content_hash, perceptual_hash, &primary.name, source.edited))
+        .flatten();
+    if let Some(duplicate) = duplicate.filter(|d| d.reuse(config)) {
+        output::info(&format!(
+            "Same image as {} uploaded to {}, reusing it",
+            duplicate.entry.filename, primary.name
+        ));
+        let result = duplicate.entry.upload_result();
+        output::success(&format!("✓ {} → {}", primary.name, result.url.as_ref().unwrap_or(&result.remote_path)));
+        return copy_link(config, &result);
     }
 
-    // Upload to each destination
-    let mut primary_result: Option<UploadResult> = None;
-    let start = Instant::now();
-
-    for (i, dest_name) in uploader_names.iter().enumerate() {
-        let dest = config
-            .uploads
-            .get(dest_name)
-            .ok_or_else(|| anyhow!("Destination '{}' not found in configuration", dest_name))?;
-
-        if !dest.enabled {
-            output::warning(&format!("Destination '{}' is disabled, skipping", dest_name));
-            continue;
+    // Generate filename
+    let final_filename = match filename {
+        Some(name) => name,
+        None => {
+            let parser = name_parser(config, image_data, &content_hash, &primary.name, source.window)?;
+            unique_filename(config, &parser, extension, history.as_ref(), primary, &prepared).await?
         }
+    };
 
-        let uploader = create_uploader(dest_name, dest)?;
-        uploader.validate()?;
-
-        // Show progress bar for primary uploader
-        let pb = if i == 0 {
-            let pb = progress::upload_progress(image_data.len() as u64);
-            pb.set_message(format!("Uploading to {}...", dest_name));
-            Some(pb)
-        } else {
-            output::step(&format!("Uploading to {}...", dest_name));
-            None
-        };
-
-        match uploader.upload(&image_data, &final_filename).await {
-            Ok(result) => {
-                if let Some(pb) = pb {
-                    pb.finish_and_clear();
-                }
-
-                output::success(&format!("✓ {} → {}", dest_name,
-                    result.url.as_ref().unwrap_or(&result.remote_path)));
-
-                // Keep first successful result for clipboard
-                if primary_result.is_none() {
-                    primary_result = Some(result);
-                }
-            }
-            Err(e) => {
-                if let Some(pb) = pb {
-                    pb.finish_and_clear();
-                }
-                output::error(&format!("✗ {} failed: {}", dest_name, e));
-
-                // If primary upload failed, it's an error
-                if i == 0 {
-                    return Err(e.into());
-                }
-            }
-        }
-    }
+    output::info(&format!("Filename: {}", final_filename));
 
-    let duration = start.elapsed();
-    let result = primary_result.ok_or_else(|| anyhow!("No successful uploads"))?;
+    let delivery = deliver(config, &targets, image_data, prepared, &final_filename, scale_factor).await?;
 
     // Calculate stats
-    let size_bytes = image_data.len() as u64;
-    let duration_ms = duration.as_millis() as u64;
-    let speed = if duration_ms > 0 {
-        (size_bytes * 1000) / duration_ms
-    } else {
-        size_bytes
-    };
+    let size_bytes = delivery.transformed.data.len() as u64;
+    let duration_ms = delivery.duration.as_millis() as u64;
+    let speed = (size_bytes * 1000).checked_div(duration_ms).unwrap_or(size_bytes);
 
     output::separator();
     output::kv("Size", &output::format_size(size_bytes));
@@ -163,49 +194,43 @@ pub async fn execute(destination: Option<String>, filename: Option<String>) -> R
     output::kv("Speed", &format!("{}/s", output::format_size(speed)));
     output::separator();
 
-    // Copy to clipboard based on mode (using primary result)
-    if config.general.copy_url_to_clipboard {
-        let clipboard_text = match config.general.clipboard_copy_mode {
-            ClipboardCopyMode::Auto => result.url.as_ref().unwrap_or(&result.remote_path),
-            ClipboardCopyMode::Url => {
-                if let Some(url) = &result.url {
-                    url
-                } else {
-                    output::warning("No URL available, skipping clipboard copy");
-                    &result.remote_path
-                }
-            }
-            ClipboardCopyMode::Path => &result.remote_path,
-        };
+    copy_link(config, &delivery.result)?;
 
-        if config.general.clipboard_copy_mode != ClipboardCopyMode::Url || result.url.is_some() {
-            output::step("Copying to clipboard...");
-            clipboard
-                .set_text(clipboard_text)
-                .context("Failed to copy to clipboard")?;
-            output::success(&format!("Copied: {}", clipboard_text));
-        }
+    let provenance = Provenance {
+        metadata: source.metadata,
+        perceptual_hash,
+        content_hash,
+    };
+    record(history.as_ref(), &delivery, &primary.name, provenance);
+
+    Ok(())
+}
+
+/// Copies the URL or path of `result` to the clipboard, as configured
+fn copy_link(config: &Config, result: &UploadResult) -> Result<()> {
+    if !config.general.copy_url_to_clipboard {
+        return Ok(());
     }
 
-    // Save to history (using primary result)
-    if config.history.enabled {
-        if let Ok(history) = HistoryManager::new(config.history.clone()) {
-            let entry = snapto_core::HistoryEntry {
-                id: 0,
-                filename: final_filename.clone(),
-                remote_path: result.remote_path.clone(),
-                url: result.url.clone(),
-                size: result.size,
-                destination: primary_name.clone(),
-                created_at: chrono::Utc::now(),
-                thumbnail_path: None,
-                local_copy_path: None,
-            };
-            if let Err(e) = history.add(&entry, Some(&image_data)) {
-                output::warning(&format!("Failed to save to history: {}", e));
+    let clipboard_text = match config.general.clipboard_copy_mode {
+        ClipboardCopyMode::Auto => result.url.as_ref().unwrap_or(&result.remote_path),
+        ClipboardCopyMode::Url => {
+            if let Some(url) = &result.url {
+                url
+            } else {
+                output::warning("No URL available, skipping clipboard copy");
+                &result.remote_path
             }
         }
-    }
+        ClipboardCopyMode::Path => &result.remote_path,
+    };
 
+    if config.general.clipboard_copy_mode != ClipboardCopyMode::Url || result.url.is_some() {
+        output::step("Copying to clipboard...");
+        ClipboardManager::new()?
+            .set_text(clipboard_text)
+            .context("Failed to copy to clipboard")?;
+        output::success(&format!("Copied: {}", clipboard_text));
+    }
     Ok(())
 }
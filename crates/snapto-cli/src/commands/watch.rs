@@ -4,30 +4,21 @@ use snapto_core::{
     ClipboardCopyMode,
     Config,
     HistoryManager,
-    HistoryEntry,
-    SftpUploader,
-    LocalUploader,
-    SshUploader,
-    Uploader,
+    IdGenerator,
+    KeychainManager,
+    PerceptualHash,
+    SessionPool,
     TemplateParser,
-    UploadConfig,
     UploadResult,
+    WindowInfo,
 };
-use std::time::{Duration, Instant};
+use snapto_core::screenshot::ImageFormat;
+use snapto_core::transform;
+use std::time::Duration;
 use tokio::time::sleep;
 
-use crate::{output, progress};
-
-/// Create an uploader based on config type
-fn create_uploader(name: &str, config: &UploadConfig) -> Result<Box<dyn Uploader>> {
-    let uploader: Box<dyn Uploader> = match config.uploader_type.as_str() {
-        "sftp" => Box::new(SftpUploader::new(name.to_string(), config.clone())),
-        "ssh" => Box::new(SshUploader::new(name.to_string(), config.clone())),
-        "local" => Box::new(LocalUploader::new(name.to_string(), config.clone())),
-        _ => return Err(anyhow!("Unknown uploader type: {}", config.uploader_type)),
-    };
-    Ok(uploader)
-}
+use super::{deliver, find_duplicate, name_parser, record, targets, unique_filename, Provenance};
+use crate::output;
 
 /// Execute the watch command
 pub async fn execute(interval_ms: u64, destination: Option<String>) -> Result<()> {
@@ -39,54 +30,24 @@ pub async fn execute(interval_ms: u64, destination: Option<String>) -> Result<()
 
     // Load configuration
     let config = Config::load().context("Failed to load configuration")?;
-
-    // Build list of uploaders to use
-    let primary_name = destination.clone().unwrap_or_else(|| config.general.default_uploader.clone());
-    let mut uploader_names = vec![primary_name.clone()];
-
-    // Add additional uploaders (only if no specific destination was provided)
-    if destination.is_none() {
-        for additional in &config.general.additional_uploaders {
-            if additional != &primary_name && !uploader_names.contains(additional) {
-                uploader_names.push(additional.clone());
-            }
-        }
-    }
-
-    // Create and validate all uploaders
-    let mut uploaders: Vec<(String, Box<dyn Uploader>)> = Vec::new();
-
-    for name in &uploader_names {
-        let dest = config
-            .uploads
-            .get(name)
-            .ok_or_else(|| anyhow!("Destination '{}' not found in configuration", name))?;
-
-        if !dest.enabled {
-            output::warning(&format!("Destination '{}' is disabled, skipping", name));
-            continue;
-        }
-
-        let uploader = create_uploader(name, dest)?;
-        uploader.validate()?;
-        uploaders.push((name.clone(), uploader));
-    }
-
-    if uploaders.is_empty() {
-        return Err(anyhow!("No enabled uploaders configured"));
-    }
-
-    // Show what we're uploading to
-    if uploaders.len() > 1 {
-        output::info(&format!("Uploading to {} destinations:", uploaders.len()));
-        for (name, _) in &uploaders {
-            output::item(name);
-        }
-    } else {
-        output::info(&format!("Using destination: {}", uploaders[0].0));
-    }
+    let format = ImageFormat::from_extension(&config.naming.default_extension).ok_or_else(|| {
+        anyhow!(
+            "Unsupported default_extension: {} (use png, jpg, webp or avif)",
+            config.naming.default_extension
+        )
+    })?;
+    TemplateParser::validate(&config.naming.template)?;
+    IdGenerator::from_config(&config.naming)?;
+    let keychain = KeychainManager::new(&config.security);
+
+    // SSH sessions stay open between screenshots
+    let pool = SessionPool::new();
+    let targets = targets(&config, destination.as_deref(), &keychain, Some(&pool))?;
+    let primary = &targets[0];
     output::separator();
 
+    let scale_factor = transform::scale_factor_for(targets.iter().map(|target| target.config));
+
     // Initialize clipboard manager
     let mut clipboard = ClipboardManager::new()?;
     let mut last_hash: Option<u64> = None;
@@ -103,118 +64,104 @@ pub async fn execute(interval_ms: u64, destination: Option<String>) -> Result<()
 
     loop {
         // Check clipboard for image
-        match clipboard.get_image() {
-            Ok(image_data) if !image_data.is_empty() => {
-                // Calculate hash to detect changes
-                let current_hash = calculate_hash(&image_data);
+        match clipboard.get_pixels() {
+            Ok(pixels) if !pixels.is_empty() => {
+                // Hash the raw pixels to detect changes; encoding waits for a new image
+                let current_hash = pixels.fingerprint();
 
                 // Only upload if image has changed
-                if last_hash.map_or(true, |h| h != current_hash) {
+                if last_hash != Some(current_hash) {
                     last_hash = Some(current_hash);
 
+                    let image_data = match pixels.encode(format) {
+                        Ok(image_data) => image_data,
+                        Err(e) => {
+                            output::error(&format!("Could not encode the image: {}", e));
+                            sleep(Duration::from_millis(interval_ms)).await;
+                            continue;
+                        }
+                    };
+
                     println!();
                     output::step(&format!(
                         "New image detected ({})",
                         output::format_size(image_data.len() as u64)
                     ));
 
-                    // Generate filename
-                    let parser = TemplateParser::new(
-                        config.naming.date_format.clone(),
-                        config.naming.time_format.clone(),
-                    );
-                    let filename = parser.generate(&config.naming.template, &config.naming.default_extension)?;
-
-                    // Upload to all destinations
-                    let mut primary_result: Option<UploadResult> = None;
-                    let start = Instant::now();
-
-                    for (i, (dest_name, uploader)) in uploaders.iter().enumerate() {
-                        let pb = if i == 0 {
-                            Some(progress::simple_progress(&format!("Uploading to {}...", dest_name)))
+                    // Hashed as uploaded, so new transform settings make a new upload
+                    let prepared = match transform::for_destination(
+                        &image_data,
+                        &config,
+                        &primary.name,
+                        primary.config,
+                        scale_factor,
+                    ) {
+                        Ok(prepared) => prepared,
+                        Err(e) => {
+                            output::error(&format!("✗ {} failed: {}", primary.name, e));
+                            output::separator();
+                            sleep(Duration::from_millis(interval_ms)).await;
+                            continue;
+                        }
+                    };
+                    let content_hash = snapto_core::content_hash(&prepared.data);
+                    let perceptual_hash = PerceptualHash::of_encoded(&image_data).ok();
+                    let duplicate =
+                        find_duplicate(&config, history.as_ref(), &content_hash, perceptual_hash, &primary.name, false);
+                    if let Some(duplicate) = duplicate {
+                        if duplicate.reuse(&config) {
+                            output::info(&format!("Same image as {}, reusing its link", duplicate.entry.filename));
+                            copy_link(&mut clipboard, &config, &duplicate.entry.upload_result());
                         } else {
-                            output::step(&format!("Uploading to {}...", dest_name));
-                            None
-                        };
-
-                        match uploader.upload(&image_data, &filename).await {
-                            Ok(result) => {
-                                if let Some(pb) = pb {
-                                    pb.finish_and_clear();
-                                }
-
-                                output::success(&format!("✓ {} → {}",
-                                    dest_name,
-                                    result.url.as_ref().unwrap_or(&result.remote_path)));
-
-                                if primary_result.is_none() {
-                                    primary_result = Some(result);
-                                }
-                            }
-                            Err(e) => {
-                                if let Some(pb) = pb {
-                                    pb.finish_and_clear();
-                                }
-                                output::error(&format!("✗ {} failed: {}", dest_name, e));
-                            }
+                            output::info(&format!("Same image as {}, skipping", duplicate.entry.filename));
                         }
+                        output::separator();
+                        output::info("Waiting for next image...");
+                        sleep(Duration::from_millis(interval_ms)).await;
+                        continue;
                     }
 
-                    // Process result
-                    if let Some(result) = primary_result {
-                        let duration = start.elapsed();
-                        upload_count += 1;
+                    // Generate filename; a clipboard image has no window to name it after
+                    let named = async {
+                        let parser = name_parser(&config, &image_data, &content_hash, &primary.name, WindowInfo::default())?;
+                        unique_filename(
+                            &config,
+                            &parser,
+                            &config.naming.default_extension,
+                            history.as_ref(),
+                            primary,
+                            &prepared,
+                        )
+                        .await
+                    };
+                    let filename = match named.await {
+                        Ok(filename) => filename,
+                        Err(e) => {
+                            // Checking the destination for a taken id can fail on a flaky link
+                            output::error(&format!("Could not name the image: {}", e));
+                            output::separator();
+                            sleep(Duration::from_millis(interval_ms)).await;
+                            continue;
+                        }
+                    };
 
+                    // Failures were reported per destination
+                    if let Ok(delivery) = deliver(&config, &targets, &image_data, prepared, &filename, scale_factor).await {
+                        upload_count += 1;
                         output::info(&format!(
                             "Upload #{} completed in {}",
                             upload_count,
-                            output::format_duration(duration.as_millis() as u64)
+                            output::format_duration(delivery.duration.as_millis() as u64)
                         ));
 
-                        // Copy to clipboard based on mode
-                        if config.general.copy_url_to_clipboard {
-                            let should_copy = match config.general.clipboard_copy_mode {
-                                ClipboardCopyMode::Url if result.url.is_none() => {
-                                    output::warning("No URL available, skipping clipboard copy");
-                                    false
-                                }
-                                _ => true,
-                            };
-
-                            if should_copy {
-                                let clipboard_text = match config.general.clipboard_copy_mode {
-                                    ClipboardCopyMode::Auto => result.url.as_ref().unwrap_or(&result.remote_path),
-                                    ClipboardCopyMode::Url => result.url.as_ref().unwrap(),
-                                    ClipboardCopyMode::Path => &result.remote_path,
-                                };
-
-                                if let Err(e) = clipboard.set_text(clipboard_text) {
-                                    output::warning(&format!("Failed to copy to clipboard: {}", e));
-                                } else {
-                                    output::info(&format!("Copied: {}", clipboard_text));
-                                }
-                            }
-                        }
+                        copy_link(&mut clipboard, &config, &delivery.result);
 
-                        // Save to history
-                        if let Some(h) = history.as_ref() {
-                            let entry = HistoryEntry {
-                                id: 0,
-                                filename: filename.clone(),
-                                remote_path: result.remote_path.clone(),
-                                url: result.url.clone(),
-                                size: result.size,
-                                destination: primary_name.clone(),
-                                created_at: chrono::Utc::now(),
-                                thumbnail_path: None,
-                                local_copy_path: None,
-                            };
-                            if let Err(e) = h.add(&entry, Some(&image_data)) {
-                                output::warning(&format!("Failed to save to history: {}", e));
-                            }
-                        }
-                    } else {
-                        output::error("All uploads failed!");
+                        let provenance = Provenance {
+                            metadata: None,
+                            perceptual_hash,
+                            content_hash,
+                        };
+                        record(history.as_ref(), &delivery, &primary.name, provenance);
                     }
 
                     output::separator();
@@ -234,12 +181,26 @@ pub async fn execute(interval_ms: u64, destination: Option<String>) -> Result<()
     }
 }
 
-/// Calculate a simple hash of the image data
-fn calculate_hash(data: &[u8]) -> u64 {
-    use std::collections::hash_map::DefaultHasher;
-    use std::hash::{Hash, Hasher};
+/// Copies the URL or path of `result` to the clipboard, as configured
+///
+/// Failures only warn: watch mode keeps going either way.
+fn copy_link(clipboard: &mut ClipboardManager, config: &Config, result: &UploadResult) {
+    if !config.general.copy_url_to_clipboard {
+        return;
+    }
+
+    let clipboard_text = match config.general.clipboard_copy_mode {
+        ClipboardCopyMode::Url if result.url.is_none() => {
+            output::warning("No URL available, skipping clipboard copy");
+            return;
+        }
+        ClipboardCopyMode::Auto | ClipboardCopyMode::Url => result.url.as_ref().unwrap_or(&result.remote_path),
+        ClipboardCopyMode::Path => &result.remote_path,
+    };
 
-    let mut hasher = DefaultHasher::new();
-    data.hash(&mut hasher);
-    hasher.finish()
+    if let Err(e) = clipboard.set_text(clipboard_text) {
+        output::warning(&format!("Failed to copy to clipboard: {}", e));
+    } else {
+        output::info(&format!("Copied: {}", clipboard_text));
+    }
 }
@@ -5,8 +5,14 @@ use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 mod commands;
 mod output;
 mod progress;
+mod prompt;
 
-use commands::{config, history, upload, watch};
+use commands::diff::DiffArgs;
+use commands::stitch::LayoutArgs;
+use commands::upload::RedactArgs;
+use commands::{annotate, beautify, capture, config, diff, history, record, stitch, upload, watch};
+use snapto_core::{RecordingTarget, Region};
+use std::path::PathBuf;
 
 #[derive(Parser)]
 #[command(name = "snapto")]
@@ -32,6 +38,176 @@ enum Commands {
         /// Custom filename (uses template if not specified)
         #[arg(short, long)]
         filename: Option<String>,
+
+        #[command(flatten)]
+        redact: RedactArgs,
+    },
+
+    /// Take a screenshot and upload it
+    Capture {
+        /// Capture a region: x,y,width,height
+        #[arg(long, value_name = "X,Y,W,H", conflicts_with_all = ["interactive", "window", "display"])]
+        region: Option<Region>,
+
+        /// Select the area interactively
+        #[arg(short, long, conflicts_with_all = ["window", "display"])]
+        interactive: bool,
+
+        /// Capture the window with this ID
+        #[arg(short, long, value_name = "ID", conflicts_with = "display")]
+        window: Option<u32>,
+
+        /// Capture one display; numbered left to right from 0
+        #[arg(long, value_name = "ID")]
+        display: Option<u32>,
+
+        /// Seconds to wait before capturing
+        #[arg(long, default_value = "0")]
+        delay: u64,
+
+        /// Override destination (use configured default if not specified)
+        #[arg(short, long)]
+        destination: Option<String>,
+
+        /// Custom filename (uses template if not specified)
+        #[arg(short, long)]
+        filename: Option<String>,
+
+        #[command(flatten)]
+        redact: RedactArgs,
+    },
+
+    /// Record the screen and upload the clip
+    Record {
+        /// Record a region: x,y,width,height
+        #[arg(long, value_name = "X,Y,W,H", conflicts_with = "display")]
+        region: Option<Region>,
+
+        /// Record one display; numbered left to right from 0
+        #[arg(long, value_name = "ID")]
+        display: Option<u32>,
+
+        /// Stop after this many seconds (records until Ctrl+C otherwise)
+        #[arg(short = 't', long, value_name = "SECS")]
+        duration: Option<u64>,
+
+        /// Output format: gif, webp or mp4 (naming.recording_extension if not specified)
+        #[arg(long)]
+        format: Option<String>,
+
+        /// Frames per second
+        #[arg(long, default_value = "15")]
+        fps: u32,
+
+        /// Override destination (use configured default if not specified)
+        #[arg(short, long)]
+        destination: Option<String>,
+
+        /// Custom filename (uses template if not specified)
+        #[arg(short, long)]
+        filename: Option<String>,
+    },
+
+    /// Draw arrows, boxes, text and step badges on an image
+    Annotate {
+        /// Image to annotate
+        input: PathBuf,
+
+        /// Annotation spec (TOML, or JSON for .json files)
+        #[arg(short, long, value_name = "FILE")]
+        spec: PathBuf,
+
+        /// Where to save the result (INPUT-annotated.EXT if not specified and not uploading)
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+
+        /// Upload the result
+        #[arg(short, long)]
+        upload: bool,
+
+        /// Override destination (use configured default if not specified)
+        #[arg(short, long, requires = "upload")]
+        destination: Option<String>,
+
+        /// Custom filename (uses template if not specified)
+        #[arg(short, long, requires = "upload")]
+        filename: Option<String>,
+    },
+
+    /// Frame an image with a background, padding, rounded corners and a shadow
+    Beautify {
+        /// Image to frame
+        input: PathBuf,
+
+        /// Preset from the [beautify] config section
+        #[arg(short, long, default_value = "default")]
+        preset: String,
+
+        /// Where to save the result (INPUT-beautified.EXT if not specified and not uploading)
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+
+        /// Upload the result
+        #[arg(short, long)]
+        upload: bool,
+
+        /// Override destination (use configured default if not specified)
+        #[arg(short, long, requires = "upload")]
+        destination: Option<String>,
+
+        /// Custom filename (uses template if not specified)
+        #[arg(short, long, requires = "upload")]
+        filename: Option<String>,
+    },
+
+    /// Combine several images into one and upload it
+    Stitch {
+        /// Images to combine, in order
+        #[arg(required = true, num_args = 2..)]
+        inputs: Vec<PathBuf>,
+
+        #[command(flatten)]
+        layout: LayoutArgs,
+
+        /// Also save the result locally
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+
+        /// Override destination (use configured default if not specified)
+        #[arg(short, long)]
+        destination: Option<String>,
+
+        /// Custom filename (uses template if not specified)
+        #[arg(short, long)]
+        filename: Option<String>,
+    },
+
+    /// Highlight what changed between two images
+    Diff {
+        /// Image before the change
+        old: PathBuf,
+
+        /// Image after the change
+        new: PathBuf,
+
+        #[command(flatten)]
+        options: DiffArgs,
+
+        /// Where to save the overlay (NEW-diff.EXT if not specified and not uploading)
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+
+        /// Upload the overlay
+        #[arg(short, long)]
+        upload: bool,
+
+        /// Override destination (use configured default if not specified)
+        #[arg(short, long, requires = "upload")]
+        destination: Option<String>,
+
+        /// Custom filename (uses template if not specified)
+        #[arg(short, long, requires = "upload")]
+        filename: Option<String>,
     },
 
     /// Watch clipboard for images and auto-upload
@@ -73,6 +249,15 @@ enum ConfigAction {
     Path,
     /// Initialize default configuration
     Init,
+    /// Store the TOTP seed used to answer one-time password prompts
+    Totp {
+        /// SSH/SFTP destination the seed belongs to
+        destination: String,
+
+        /// Remove the stored seed instead of setting it
+        #[arg(long)]
+        remove: bool,
+    },
 }
 
 #[tokio::main]
@@ -99,7 +284,81 @@ async fn main() -> Result<()> {
         Commands::Upload {
             destination,
             filename,
-        } => upload::execute(destination, filename).await,
+            redact,
+        } => upload::execute(destination, filename, redact).await,
+
+        Commands::Capture {
+            region,
+            interactive,
+            window,
+            display,
+            delay,
+            destination,
+            filename,
+            redact,
+        } => {
+            let target = match (region, interactive, window, display) {
+                (Some(region), _, _, _) => capture::CaptureTarget::Region(region),
+                (_, true, _, _) => capture::CaptureTarget::Interactive,
+                (_, _, Some(id), _) => capture::CaptureTarget::Window(id),
+                (_, _, _, Some(id)) => capture::CaptureTarget::Display(id),
+                _ => capture::CaptureTarget::Fullscreen,
+            };
+            capture::execute(target, delay, destination, filename, redact).await
+        }
+
+        Commands::Record {
+            region,
+            display,
+            duration,
+            format,
+            fps,
+            destination,
+            filename,
+        } => {
+            let target = match (region, display) {
+                (Some(region), _) => RecordingTarget::Region(region),
+                (_, Some(id)) => RecordingTarget::Display(id),
+                _ => RecordingTarget::Fullscreen,
+            };
+            record::execute(target, duration, format, fps, destination, filename).await
+        }
+
+        Commands::Annotate {
+            input,
+            spec,
+            output,
+            upload,
+            destination,
+            filename,
+        } => annotate::execute(input, spec, output, upload, destination, filename).await,
+
+        Commands::Beautify {
+            input,
+            preset,
+            output,
+            upload,
+            destination,
+            filename,
+        } => beautify::execute(input, preset, output, upload, destination, filename).await,
+
+        Commands::Stitch {
+            inputs,
+            layout,
+            output,
+            destination,
+            filename,
+        } => stitch::execute(inputs, layout, output, destination, filename).await,
+
+        Commands::Diff {
+            old,
+            new,
+            options,
+            output,
+            upload,
+            destination,
+            filename,
+        } => diff::execute(old, new, options, output, upload, destination, filename).await,
 
         Commands::Watch {
             interval,
@@ -113,6 +372,9 @@ async fn main() -> Result<()> {
                 ConfigAction::Edit => config::edit().await,
                 ConfigAction::Path => config::path().await,
                 ConfigAction::Init => config::init().await,
+                ConfigAction::Totp { destination, remove } => {
+                    config::totp(&destination, remove).await
+                }
             }
         }
 
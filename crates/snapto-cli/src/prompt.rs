@@ -0,0 +1,30 @@
+use snapto_core::PasswordCallback;
+use std::io::IsTerminal;
+use std::sync::Arc;
+
+/// Builds a callback that answers SSH authentication prompts on the TTY
+///
+/// Input is read without echo so passwords and one-time codes are not
+/// shown. Returns `None` when stdin is not a terminal, so unattended runs
+/// fail authentication instead of blocking.
+pub fn tty_password_callback() -> Option<PasswordCallback> {
+    if !std::io::stdin().is_terminal() {
+        return None;
+    }
+
+    let callback: PasswordCallback = Arc::new(tokio::sync::Mutex::new(Some(Box::new(
+        |prompt: &str| {
+            let prompt = if prompt.ends_with(' ') {
+                prompt.to_string()
+            } else {
+                format!("{} ", prompt)
+            };
+
+            rpassword::prompt_password(prompt)
+                .ok()
+                .filter(|answer| !answer.is_empty())
+        },
+    ))));
+
+    Some(callback)
+}
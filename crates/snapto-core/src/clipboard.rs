@@ -1,10 +1,10 @@
 use arboard::{Clipboard, ImageData};
-use image::{ImageBuffer, ImageFormat, Rgba};
-use std::io::Cursor;
+use image::{DynamicImage, ImageBuffer, Rgba};
 use tokio::sync::mpsc;
 use tracing::{debug, error, info, warn};
 
 use crate::error::{Result, SnaptoError};
+use crate::screenshot::{encode_image, ImageFormat, ScreenshotConfig};
 
 /// Manager for clipboard operations
 pub struct ClipboardManager {
@@ -28,6 +28,36 @@ impl ClipboardManager {
     /// - No image is available in the clipboard
     /// - The image data cannot be converted to PNG format
     pub fn get_image(&mut self) -> Result<Vec<u8>> {
+        self.get_image_as(ImageFormat::Png)
+    }
+
+    /// Read an image from the clipboard and encode it as `format`
+    ///
+    /// Lossy formats use the same quality as screenshots.
+    ///
+    /// # Errors
+    /// Returns an error if:
+    /// - No image is available in the clipboard
+    /// - The image data cannot be encoded as `format`
+    pub fn get_image_as(&mut self, format: ImageFormat) -> Result<Vec<u8>> {
+        let bytes = self.get_pixels()?.encode(format)?;
+
+        info!(
+            "Successfully converted clipboard image to {} ({} bytes)",
+            format.extension(),
+            bytes.len()
+        );
+        Ok(bytes)
+    }
+
+    /// Read an image from the clipboard without encoding it
+    ///
+    /// Cheaper than [`Self::get_image_as`] when the image may be thrown away,
+    /// e.g. to poll for changes.
+    ///
+    /// # Errors
+    /// Returns an error if no image is available in the clipboard
+    pub fn get_pixels(&mut self) -> Result<ClipboardImage> {
         debug!("Attempting to read image from clipboard");
 
         let image_data = self
@@ -43,11 +73,7 @@ impl ClipboardManager {
             image_data.width, image_data.height
         );
 
-        // Convert ImageData to PNG bytes
-        let png_bytes = image_data_to_png(&image_data)?;
-        
-        info!("Successfully converted clipboard image to PNG ({} bytes)", png_bytes.len());
-        Ok(png_bytes)
+        Ok(ClipboardImage { data: image_data })
     }
 
     /// Check if there is an image available in the clipboard
@@ -104,7 +130,7 @@ impl ClipboardManager {
 
                 // Try to get the current image
                 if let Ok(image_data) = clipboard.get_image() {
-                    match image_data_to_png(&image_data) {
+                    match encode_image_data(&image_data, ImageFormat::Png) {
                         Ok(png_bytes) => {
                             // Check if this is a new image (compare bytes)
                             let is_new = match &last_image {
@@ -140,13 +166,45 @@ impl ClipboardManager {
     }
 }
 
-/// Convert arboard ImageData to PNG bytes
-fn image_data_to_png(image_data: &ImageData) -> Result<Vec<u8>> {
+/// Raw RGBA pixels read from the clipboard
+pub struct ClipboardImage {
+    data: ImageData<'static>,
+}
+
+impl ClipboardImage {
+    /// Whether the image has no pixels
+    pub fn is_empty(&self) -> bool {
+        self.data.bytes.is_empty()
+    }
+
+    /// Hash of the size and pixels, to tell whether the clipboard changed
+    pub fn fingerprint(&self) -> u64 {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::{Hash, Hasher};
+
+        let mut hasher = DefaultHasher::new();
+        self.data.width.hash(&mut hasher);
+        self.data.height.hash(&mut hasher);
+        self.data.bytes.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Encode the pixels as `format`, with the same quality as screenshots
+    ///
+    /// # Errors
+    /// Returns an error if the image data cannot be encoded as `format`
+    pub fn encode(&self, format: ImageFormat) -> Result<Vec<u8>> {
+        encode_image_data(&self.data, format)
+    }
+}
+
+/// Convert arboard ImageData to encoded bytes
+fn encode_image_data(image_data: &ImageData, format: ImageFormat) -> Result<Vec<u8>> {
     let width = image_data.width;
     let height = image_data.height;
     let bytes = &image_data.bytes;
 
-    debug!("Converting image data to PNG: {}x{}", width, height);
+    debug!("Converting image data to {}: {}x{}", format.extension(), width, height);
 
     // Create an ImageBuffer from the raw RGBA bytes
     let img_buffer: ImageBuffer<Rgba<u8>, Vec<u8>> = ImageBuffer::from_raw(
@@ -159,18 +217,15 @@ fn image_data_to_png(image_data: &ImageData) -> Result<Vec<u8>> {
         SnaptoError::ImageProcessing("Failed to create image buffer".to_string())
     })?;
 
-    // Convert to PNG bytes
-    let mut png_bytes = Vec::new();
-    let mut cursor = Cursor::new(&mut png_bytes);
-    
-    img_buffer.write_to(&mut cursor, ImageFormat::Png)
+    let quality = ScreenshotConfig::default().quality;
+    let encoded = encode_image(&DynamicImage::ImageRgba8(img_buffer), format, quality)
         .map_err(|e| {
-            error!("Failed to encode image as PNG: {}", e);
-            SnaptoError::from(e)
+            error!("Failed to encode clipboard image: {}", e);
+            SnaptoError::ImageProcessing(e.to_string())
         })?;
 
-    debug!("Successfully converted to PNG ({} bytes)", png_bytes.len());
-    Ok(png_bytes)
+    debug!("Successfully converted to {} ({} bytes)", format.extension(), encoded.len());
+    Ok(encoded)
 }
 
 impl Default for ClipboardManager {
@@ -189,6 +244,39 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_encode_image_data_matches_format() {
+        let image_data = ImageData {
+            width: 4,
+            height: 2,
+            bytes: vec![200u8; 4 * 2 * 4].into(),
+        };
+
+        for (format, expected) in [
+            (ImageFormat::Png, image::ImageFormat::Png),
+            (ImageFormat::Jpeg, image::ImageFormat::Jpeg),
+            (ImageFormat::WebP, image::ImageFormat::WebP),
+        ] {
+            let bytes = encode_image_data(&image_data, format).unwrap();
+            assert_eq!(image::guess_format(&bytes).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_fingerprint_follows_pixels() {
+        let image = |width: usize, fill: u8| ClipboardImage {
+            data: ImageData {
+                width,
+                height: 8 / width,
+                bytes: vec![fill; 8 * 4].into(),
+            },
+        };
+
+        assert_eq!(image(4, 1).fingerprint(), image(4, 1).fingerprint());
+        assert_ne!(image(4, 1).fingerprint(), image(4, 2).fingerprint());
+        assert_ne!(image(4, 1).fingerprint(), image(2, 1).fingerprint());
+    }
+
     #[test]
     fn test_set_and_get_text() {
         let mut manager = ClipboardManager::new().unwrap();
@@ -12,6 +12,14 @@ pub struct Config {
     pub history: HistoryConfig,
     pub uploads: HashMap<String, UploadConfig>,
     pub security: SecurityConfig,
+    #[serde(default)]
+    pub capture: CaptureConfig,
+    #[serde(default)]
+    pub beautify: BeautifyConfig,
+    #[serde(default)]
+    pub diff: DiffConfig,
+    #[serde(default)]
+    pub duplicates: DuplicateConfig,
 }
 
 /// Configuración general
@@ -50,7 +58,8 @@ pub enum ClipboardCopyMode {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NamingConfig {
     /// Template para nombres de archivo
-    /// Soporta: {date}, {time}, {random:N}, {uuid}, {counter}
+    /// Ver [`crate::TemplateParser::generate`] para los placeholders
+    /// soportados
     pub template: String,
     /// Formato de fecha para {date}
     pub date_format: String,
@@ -58,6 +67,58 @@ pub struct NamingConfig {
     pub time_format: String,
     /// Extensión por defecto
     pub default_extension: String,
+    /// Extensión de las grabaciones de pantalla: gif, webp o mp4
+    #[serde(default = "default_recording_extension")]
+    pub recording_extension: String,
+    /// Qué subidas comparten la numeración de {counter}
+    #[serde(default)]
+    pub counter_scope: CounterScope,
+    /// Caracteres de {id:N}: base62, crockford (base32 sin I, L, O ni U)
+    /// o directamente los caracteres a usar
+    #[serde(default = "default_id_alphabet")]
+    pub id_alphabet: String,
+    /// Cómo se eligen los {id:N}
+    #[serde(default)]
+    pub id_mode: IdMode,
+    /// Clave que desordena los ids de `id_mode = "sequence"`; si falta se
+    /// genera una y se guarda junto a los contadores
+    #[serde(default)]
+    pub id_key: Option<String>,
+}
+
+fn default_id_alphabet() -> String {
+    "base62".to_string()
+}
+
+/// Origen de los ids cortos de {id:N}
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum IdMode {
+    /// Bytes aleatorios
+    #[default]
+    Random,
+    /// Un contador persistente pasado por una permutación con clave: no se
+    /// repiten hasta agotar los N caracteres y no revelan el orden
+    Sequence,
+}
+
+/// Ámbito de los contadores de {counter}, que persisten entre ejecuciones
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CounterScope {
+    /// Un único contador para todo
+    #[default]
+    Global,
+    /// Un contador por destino
+    Destination,
+    /// Un contador por día, que vuelve a 1 a medianoche (hora local)
+    Day,
+    /// Un contador por template
+    Template,
+}
+
+fn default_recording_extension() -> String {
+    "gif".to_string()
 }
 
 /// Configuración de historial
@@ -88,7 +149,7 @@ pub enum HistoryMode {
 }
 
 /// Configuración de un uploader
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct UploadConfig {
     /// Tipo de uploader (sftp, local, etc.)
     #[serde(rename = "type")]
@@ -113,6 +174,288 @@ pub struct UploadConfig {
     pub key_path: Option<String>,
     /// Timeout de conexión en segundos
     pub timeout: Option<u64>,
+    /// Métodos de autenticación SSH, en orden de prueba
+    /// (por defecto: agent, key, default_keys, keyboard_interactive, password)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth_methods: Option<Vec<AuthMethod>>,
+    /// Alias de ~/.ssh/config del que tomar HostName, Port, User,
+    /// IdentityFile y ProxyJump (los campos explícitos tienen prioridad)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssh_host: Option<String>,
+    /// Saltos intermedios en sintaxis ProxyJump ("bastion,user@otro:2222")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy_jump: Option<String>,
+    /// Saltos intermedios con autenticación propia (prioridad sobre proxy_jump)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jump_hosts: Option<Vec<JumpHost>>,
+    /// Proxy para la primera conexión TCP (socks5://host:port o http://host:port)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+    /// Comprobación de la clave del host contra ~/.ssh/known_hosts
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host_key_checking: Option<HostKeyChecking>,
+    /// Transformaciones de la imagen antes de subirla a este destino
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transform: Option<TransformConfig>,
+    /// Marca de agua (logo o texto) sobre las imágenes subidas a este destino
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub watermark: Option<WatermarkConfig>,
+    /// Preset de [beautify] con el que enmarcar las imágenes de este destino
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub beautify: Option<String>,
+}
+
+/// Transformaciones aplicadas a la imagen antes de subirla
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct TransformConfig {
+    /// Ancho máximo en píxeles (se reduce manteniendo la proporción)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_width: Option<u32>,
+    /// Alto máximo en píxeles (se reduce manteniendo la proporción)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_height: Option<u32>,
+    /// Reducir por el factor de escala de la pantalla (HiDPI/Retina)
+    #[serde(default)]
+    pub downscale_hidpi: bool,
+    /// Formato de salida: png, jpg, webp o avif (por defecto, el de origen)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+    /// Calidad para jpg y avif (1-100, por defecto 85); webp es sin pérdida
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quality: Option<u8>,
+    /// Recomprimir PNG sin pérdida
+    #[serde(default)]
+    pub optimize_png: bool,
+}
+
+impl TransformConfig {
+    /// Indica si la configuración no cambia nada
+    pub fn is_noop(&self) -> bool {
+        *self == TransformConfig::default()
+    }
+}
+
+/// Marca de agua superpuesta antes de subir la imagen
+///
+/// Con imagen y texto a la vez, el texto va debajo del logo.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WatermarkConfig {
+    /// Imagen a superponer (p. ej. un logo PNG con transparencia)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    /// Texto a dibujar (p. ej. "© 2026 ACME")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    /// Color del texto (#rrggbb o #rrggbbaa)
+    #[serde(default = "default_watermark_color")]
+    pub color: String,
+    /// Posición dentro de la imagen
+    #[serde(default)]
+    pub anchor: WatermarkAnchor,
+    /// Opacidad entre 0.0 y 1.0
+    #[serde(default = "default_watermark_opacity")]
+    pub opacity: f32,
+    /// Separación del borde en píxeles
+    #[serde(default = "default_watermark_margin")]
+    pub margin: u32,
+    /// Ancho máximo de la marca respecto al ancho de la imagen (0.0-1.0)
+    #[serde(default = "default_watermark_scale")]
+    pub scale: f32,
+}
+
+fn default_watermark_color() -> String {
+    "#ffffff".to_string()
+}
+
+fn default_watermark_opacity() -> f32 {
+    0.6
+}
+
+fn default_watermark_margin() -> u32 {
+    16
+}
+
+fn default_watermark_scale() -> f32 {
+    0.2
+}
+
+impl Default for WatermarkConfig {
+    fn default() -> Self {
+        Self {
+            image: None,
+            text: None,
+            color: default_watermark_color(),
+            anchor: WatermarkAnchor::default(),
+            opacity: default_watermark_opacity(),
+            margin: default_watermark_margin(),
+            scale: default_watermark_scale(),
+        }
+    }
+}
+
+/// Posición de la marca de agua
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WatermarkAnchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    #[default]
+    BottomRight,
+}
+
+impl UploadConfig {
+    /// Saltos intermedios a recorrer antes del destino, en orden
+    pub fn jump_chain(&self) -> Result<Vec<JumpHost>> {
+        if let Some(hosts) = &self.jump_hosts {
+            return Ok(hosts.clone());
+        }
+
+        match &self.proxy_jump {
+            Some(spec) if !spec.trim().eq_ignore_ascii_case("none") => spec
+                .split(',')
+                .map(|hop| JumpHost::parse(hop.trim()))
+                .collect(),
+            _ => Ok(Vec::new()),
+        }
+    }
+}
+
+/// Salto intermedio (bastion) hacia el servidor SSH
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct JumpHost {
+    /// Host (o alias de ~/.ssh/config si no hay ssh_host)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub use_key_auth: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth_methods: Option<Vec<AuthMethod>>,
+    /// Alias de ~/.ssh/config para este salto
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssh_host: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host_key_checking: Option<HostKeyChecking>,
+}
+
+impl JumpHost {
+    /// Interpreta un salto en sintaxis ProxyJump: `[ssh://][user@]host[:port]`
+    ///
+    /// El host se trata como alias de ~/.ssh/config, igual que hace `ssh`.
+    pub fn parse(spec: &str) -> Result<Self> {
+        let invalid = || ConfigError::Invalid(format!("Salto ProxyJump inválido: '{}'", spec));
+
+        let rest = spec.strip_prefix("ssh://").unwrap_or(spec);
+        let (user, rest) = match rest.rsplit_once('@') {
+            Some((user, rest)) => (Some(user.to_string()), rest),
+            None => (None, rest),
+        };
+
+        let (host, port) = if let Some(bracketed) = rest.strip_prefix('[') {
+            let (host, tail) = bracketed.split_once(']').ok_or_else(invalid)?;
+            (host, tail.strip_prefix(':'))
+        } else {
+            match rest.rsplit_once(':') {
+                Some((host, port)) => (host, Some(port)),
+                None => (rest, None),
+            }
+        };
+
+        if host.is_empty() || user.as_deref() == Some("") {
+            return Err(invalid().into());
+        }
+
+        let port = match port {
+            Some(port) => Some(port.parse::<u16>().map_err(|_| invalid())?),
+            None => None,
+        };
+
+        Ok(Self {
+            port,
+            username: user,
+            ssh_host: Some(host.to_string()),
+            ..Default::default()
+        })
+    }
+
+    /// Configuración SSH equivalente, para reutilizar conexión y autenticación
+    pub fn to_upload_config(&self) -> UploadConfig {
+        UploadConfig {
+            uploader_type: "ssh".to_string(),
+            enabled: true,
+            host: self.host.clone(),
+            port: self.port,
+            username: self.username.clone(),
+            use_key_auth: self.use_key_auth,
+            key_path: self.key_path.clone(),
+            auth_methods: self.auth_methods.clone(),
+            ssh_host: self.ssh_host.clone(),
+            host_key_checking: self.host_key_checking,
+            ..Default::default()
+        }
+    }
+}
+
+/// Política ante claves de host desconocidas o distintas
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum HostKeyChecking {
+    /// Solo hosts presentes en known_hosts
+    Strict,
+    /// Añade hosts nuevos a known_hosts; rechaza claves distintas
+    #[default]
+    AcceptNew,
+    /// Sin comprobación
+    Off,
+}
+
+/// Método de autenticación SSH
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthMethod {
+    /// Identidades cargadas en ssh-agent
+    Agent,
+    /// Clave privada indicada en `key_path`
+    Key,
+    /// Claves por defecto en ~/.ssh/id_*
+    DefaultKeys,
+    /// Keyboard-interactive (prompts del servidor)
+    KeyboardInteractive,
+    /// Contraseña
+    Password,
+}
+
+impl AuthMethod {
+    /// Orden usado cuando `auth_methods` no está configurado
+    pub const DEFAULT_CHAIN: [AuthMethod; 5] = [
+        AuthMethod::Agent,
+        AuthMethod::Key,
+        AuthMethod::DefaultKeys,
+        AuthMethod::KeyboardInteractive,
+        AuthMethod::Password,
+    ];
+
+    /// Nombre del método tal como aparece en la configuración
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuthMethod::Agent => "agent",
+            AuthMethod::Key => "key",
+            AuthMethod::DefaultKeys => "default_keys",
+            AuthMethod::KeyboardInteractive => "keyboard_interactive",
+            AuthMethod::Password => "password",
+        }
+    }
 }
 
 /// Configuración de seguridad
@@ -122,6 +465,270 @@ pub struct SecurityConfig {
     pub use_system_keychain: bool,
     /// Encriptar credenciales en configuración
     pub encrypt_credentials: bool,
+    /// Metadatos (EXIF, XMP, textos PNG) que se conservan al subir
+    #[serde(default)]
+    pub metadata: MetadataPolicy,
+}
+
+/// Política de metadatos de las imágenes subidas
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MetadataPolicy {
+    /// Eliminar todos los metadatos (GPS, cámara, software, textos)
+    Strip,
+    /// Eliminar todo salvo la orientación EXIF
+    #[default]
+    KeepOrientation,
+    /// Subir el archivo tal cual
+    Keep,
+}
+
+/// Configuración de captura de pantalla
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct CaptureConfig {
+    /// Herramienta de captura en Linux: auto (detectar según la sesión),
+    /// x11 (nativo), grim, portal, spectacle, maim, scrot o gnome-screenshot
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backend: Option<String>,
+}
+
+/// Presets de "beautify": fondo, márgenes, esquinas redondeadas y sombra
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct BeautifyConfig {
+    /// Presets por nombre; "default" existe aunque no se defina
+    #[serde(default)]
+    pub presets: HashMap<String, BeautifyPreset>,
+}
+
+impl BeautifyConfig {
+    /// Preset con ese nombre, o el integrado si el nombre es "default"
+    pub fn preset(&self, name: &str) -> Option<BeautifyPreset> {
+        match self.presets.get(name) {
+            Some(preset) => Some(preset.clone()),
+            None if name == "default" => Some(BeautifyPreset::default()),
+            None => None,
+        }
+    }
+}
+
+/// Marco alrededor de la captura
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BeautifyPreset {
+    /// Margen alrededor de la captura en píxeles
+    #[serde(default = "default_beautify_padding")]
+    pub padding: u32,
+    /// Radio de las esquinas redondeadas en píxeles
+    #[serde(default = "default_beautify_corner_radius")]
+    pub corner_radius: u32,
+    /// Difuminado de la sombra en píxeles (0 = sin sombra)
+    #[serde(default = "default_beautify_shadow_blur")]
+    pub shadow_blur: u32,
+    /// Desplazamiento de la sombra [x, y] en píxeles
+    #[serde(default = "default_beautify_shadow_offset")]
+    pub shadow_offset: [i32; 2],
+    /// Color de la sombra (#rrggbb o #rrggbbaa)
+    #[serde(default = "default_beautify_shadow_color")]
+    pub shadow_color: String,
+    /// Fondo sobre el que se coloca la captura
+    #[serde(default)]
+    pub background: BeautifyBackground,
+}
+
+fn default_beautify_padding() -> u32 {
+    64
+}
+
+fn default_beautify_corner_radius() -> u32 {
+    12
+}
+
+fn default_beautify_shadow_blur() -> u32 {
+    24
+}
+
+fn default_beautify_shadow_offset() -> [i32; 2] {
+    [0, 12]
+}
+
+fn default_beautify_shadow_color() -> String {
+    "#00000080".to_string()
+}
+
+impl Default for BeautifyPreset {
+    fn default() -> Self {
+        Self {
+            padding: default_beautify_padding(),
+            corner_radius: default_beautify_corner_radius(),
+            shadow_blur: default_beautify_shadow_blur(),
+            shadow_offset: default_beautify_shadow_offset(),
+            shadow_color: default_beautify_shadow_color(),
+            background: BeautifyBackground::default(),
+        }
+    }
+}
+
+/// Fondo del marco
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BeautifyBackground {
+    /// Color sólido (#rrggbb o #rrggbbaa; con transparencia queda solo la sombra)
+    Solid { color: String },
+    /// Degradado lineal; el ángulo va en grados, 0 = de izquierda a derecha
+    /// y 90 = de arriba abajo
+    Gradient {
+        from: String,
+        to: String,
+        #[serde(default = "default_gradient_angle")]
+        angle: f32,
+    },
+    /// Imagen escalada para cubrir todo el fondo
+    Image { path: String },
+}
+
+fn default_gradient_angle() -> f32 {
+    45.0
+}
+
+impl Default for BeautifyBackground {
+    fn default() -> Self {
+        BeautifyBackground::Gradient {
+            from: "#667eea".to_string(),
+            to: "#764ba2".to_string(),
+            angle: default_gradient_angle(),
+        }
+    }
+}
+
+impl BeautifyPreset {
+    /// Comprueba los colores del preset
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        let mut colors = vec![&self.shadow_color];
+        match &self.background {
+            BeautifyBackground::Solid { color } => colors.push(color),
+            BeautifyBackground::Gradient { from, to, .. } => colors.extend([from, to]),
+            BeautifyBackground::Image { .. } => {}
+        }
+        for color in colors {
+            crate::image_ops::parse_color(color).map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+}
+
+/// Comparación de imágenes con `snapto diff`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DiffConfig {
+    /// Diferencia máxima por canal (0-255) que aún cuenta como igual
+    #[serde(default = "default_diff_threshold")]
+    pub threshold: u8,
+    /// Cambios separados por menos de estos píxeles forman una sola región
+    #[serde(default = "default_diff_merge_distance")]
+    pub merge_distance: u32,
+    /// Regiones con menos píxeles cambiados se ignoran (ruido de antialiasing)
+    #[serde(default = "default_diff_min_region_pixels")]
+    pub min_region_pixels: u32,
+    /// Color con el que se tiñen los píxeles cambiados (#rrggbb o #rrggbbaa)
+    #[serde(default = "default_diff_tint")]
+    pub tint: String,
+    /// Color de los recuadros alrededor de cada región
+    #[serde(default = "default_diff_box_color")]
+    pub box_color: String,
+    /// Similitud mínima (0-1); por debajo, `snapto diff` termina con error
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_similarity: Option<f64>,
+}
+
+fn default_diff_threshold() -> u8 {
+    16
+}
+
+fn default_diff_merge_distance() -> u32 {
+    16
+}
+
+fn default_diff_min_region_pixels() -> u32 {
+    4
+}
+
+fn default_diff_tint() -> String {
+    "#ff00ffb0".to_string()
+}
+
+fn default_diff_box_color() -> String {
+    "#ff0000".to_string()
+}
+
+impl Default for DiffConfig {
+    fn default() -> Self {
+        Self {
+            threshold: default_diff_threshold(),
+            merge_distance: default_diff_merge_distance(),
+            min_region_pixels: default_diff_min_region_pixels(),
+            tint: default_diff_tint(),
+            box_color: default_diff_box_color(),
+            min_similarity: None,
+        }
+    }
+}
+
+/// Qué hacer con una imagen que ya se subió (según su hash perceptual)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DuplicateConfig {
+    /// upload (subir siempre), skip (ignorar en modo watch) o reuse
+    /// (copiar la URL anterior en lugar de subir)
+    #[serde(default)]
+    pub policy: DuplicatePolicy,
+    /// Distancia de Hamming máxima (0-64) entre hashes para considerar dos
+    /// imágenes iguales; 0 exige el mismo hash
+    #[serde(default = "default_duplicate_max_distance")]
+    pub max_distance: u32,
+    /// Si el mismo contenido exacto (SHA-256) ya se subió a ese destino,
+    /// devolver esa subida sin subir de nuevo, sea cual sea la política
+    #[serde(default)]
+    pub dedup: bool,
+}
+
+fn default_duplicate_max_distance() -> u32 {
+    4
+}
+
+impl Default for DuplicateConfig {
+    fn default() -> Self {
+        Self {
+            policy: DuplicatePolicy::default(),
+            max_distance: default_duplicate_max_distance(),
+            dedup: false,
+        }
+    }
+}
+
+/// Política para imágenes repetidas
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DuplicatePolicy {
+    /// Subir de nuevo (comportamiento por defecto)
+    #[default]
+    Upload,
+    /// En modo watch, no subir ni copiar nada; los comandos explícitos suben
+    Skip,
+    /// No subir y copiar al portapapeles la URL de la subida anterior
+    Reuse,
+}
+
+impl DiffConfig {
+    /// Comprueba colores y límites
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        for color in [&self.tint, &self.box_color] {
+            crate::image_ops::parse_color(color).map_err(|e| e.to_string())?;
+        }
+        if self.merge_distance == 0 {
+            return Err("merge_distance debe ser al menos 1".to_string());
+        }
+        if matches!(self.min_similarity, Some(s) if !(0.0..=1.0).contains(&s)) {
+            return Err("min_similarity debe estar entre 0 y 1".to_string());
+        }
+        Ok(())
+    }
 }
 
 impl Config {
@@ -212,21 +819,123 @@ impl Config {
             )).into());
         }
 
+        // Validar backend de captura
+        if let Some(backend) = &self.capture.backend {
+            if backend != "auto" && !crate::screenshot::BACKEND_NAMES.contains(&backend.as_str()) {
+                return Err(ConfigError::Invalid(format!(
+                    "Backend de captura '{}' no soportado (opciones: auto, {})",
+                    backend,
+                    crate::screenshot::BACKEND_NAMES.join(", ")
+                )).into());
+            }
+        }
+
+        // Validar extensión por defecto: la imagen se codifica en ese formato
+        if crate::screenshot::ImageFormat::from_extension(&self.naming.default_extension).is_none() {
+            return Err(ConfigError::Invalid(format!(
+                "Extensión por defecto '{}' no soportada (opciones: png, jpg, jpeg, webp, avif)",
+                self.naming.default_extension
+            )).into());
+        }
+
+        // Validar el template de nombres
+        crate::naming::TemplateParser::validate(&self.naming.template)
+            .map_err(|e| ConfigError::Invalid(format!("naming.template: {}", e)))?;
+
+        // Validar el alfabeto de {id:N}
+        crate::short_id::Alphabet::parse(&self.naming.id_alphabet)
+            .map_err(|e| ConfigError::Invalid(format!("naming.id_alphabet: {}", e)))?;
+
+        // Validar extensión de grabación
+        if crate::recording::RecordingFormat::from_extension(&self.naming.recording_extension).is_none() {
+            return Err(ConfigError::Invalid(format!(
+                "Extensión de grabación '{}' no soportada (opciones: gif, webp, mp4)",
+                self.naming.recording_extension
+            )).into());
+        }
+
+        // Validar presets de beautify
+        for (name, preset) in &self.beautify.presets {
+            preset.validate().map_err(|e| {
+                ConfigError::Invalid(format!("Preset de beautify '{}': {}", name, e))
+            })?;
+        }
+
+        // Validar opciones de diff
+        self.diff
+            .validate()
+            .map_err(|e| ConfigError::Invalid(format!("Sección [diff]: {}", e)))?;
+
+        if self.duplicates.max_distance > 64 {
+            return Err(ConfigError::Invalid(
+                "duplicates.max_distance debe estar entre 0 y 64".to_string(),
+            ).into());
+        }
+
         // Validar configuraciones de uploaders
         for (name, uploader) in &self.uploads {
             if !uploader.enabled {
                 continue;
             }
 
+            if let Some(transform) = &uploader.transform {
+                if let Some(format) = &transform.format {
+                    if crate::screenshot::ImageFormat::from_extension(format).is_none() {
+                        return Err(ConfigError::Invalid(format!(
+                            "Uploader '{}': formato '{}' no soportado (opciones: png, jpg, webp, avif)",
+                            name, format
+                        )).into());
+                    }
+                }
+                if matches!(transform.quality, Some(q) if q == 0 || q > 100) {
+                    return Err(ConfigError::Invalid(format!(
+                        "Uploader '{}': la calidad debe estar entre 1 y 100",
+                        name
+                    )).into());
+                }
+            }
+
+            if let Some(preset) = &uploader.beautify {
+                if self.beautify.preset(preset).is_none() {
+                    return Err(ConfigError::Invalid(format!(
+                        "Uploader '{}': preset de beautify '{}' no existe",
+                        name, preset
+                    )).into());
+                }
+            }
+
+            if let Some(watermark) = &uploader.watermark {
+                if watermark.image.is_none() && watermark.text.is_none() {
+                    return Err(ConfigError::Invalid(format!(
+                        "Uploader '{}': la marca de agua necesita image o text",
+                        name
+                    )).into());
+                }
+                let scale_ok = watermark.scale > 0.0 && watermark.scale <= 1.0;
+                if !(0.0..=1.0).contains(&watermark.opacity) || !scale_ok {
+                    return Err(ConfigError::Invalid(format!(
+                        "Uploader '{}': opacity debe estar entre 0 y 1 y scale entre 0 (excluido) y 1",
+                        name
+                    )).into());
+                }
+                if crate::image_ops::parse_color(&watermark.color).is_err() {
+                    return Err(ConfigError::Invalid(format!(
+                        "Uploader '{}': color de marca de agua inválido '{}'",
+                        name, watermark.color
+                    )).into());
+                }
+            }
+
             match uploader.uploader_type.as_str() {
-                "sftp" => {
-                    if uploader.host.is_none() {
+                "sftp" | "ssh" => {
+                    // Con ssh_host, host y usuario salen de ~/.ssh/config
+                    if uploader.host.is_none() && uploader.ssh_host.is_none() {
                         return Err(ConfigError::Invalid(format!(
-                            "Uploader '{}': host requerido para SFTP",
+                            "Uploader '{}': host o ssh_host requerido para SFTP",
                             name
                         )).into());
                     }
-                    if uploader.username.is_none() {
+                    if uploader.username.is_none() && uploader.ssh_host.is_none() {
                         return Err(ConfigError::Invalid(format!(
                             "Uploader '{}': username requerido para SFTP",
                             name
@@ -279,6 +988,7 @@ impl Default for Config {
                 use_key_auth: Some(true),
                 key_path: Some("~/.ssh/id_rsa".to_string()),
                 timeout: Some(30),
+                ..Default::default()
             },
         );
 
@@ -297,6 +1007,7 @@ impl Default for Config {
                 use_key_auth: None,
                 key_path: None,
                 timeout: None,
+                ..Default::default()
             },
         );
 
@@ -314,6 +1025,11 @@ impl Default for Config {
                 date_format: "%Y%m%d".to_string(),
                 time_format: "%H%M%S".to_string(),
                 default_extension: "png".to_string(),
+                recording_extension: default_recording_extension(),
+                counter_scope: CounterScope::default(),
+                id_alphabet: default_id_alphabet(),
+                id_mode: IdMode::default(),
+                id_key: None,
             },
             history: HistoryConfig {
                 enabled: true,
@@ -326,7 +1042,12 @@ impl Default for Config {
             security: SecurityConfig {
                 use_system_keychain: true,
                 encrypt_credentials: false,
+                metadata: MetadataPolicy::default(),
             },
+            capture: CaptureConfig::default(),
+            beautify: BeautifyConfig::default(),
+            diff: DiffConfig::default(),
+            duplicates: DuplicateConfig::default(),
         }
     }
 }
@@ -357,4 +1078,243 @@ mod tests {
         let config = Config::default();
         assert!(config.validate().is_ok());
     }
+
+    #[test]
+    fn test_capture_backend_validation() {
+        // Configuraciones antiguas sin [capture] siguen cargando
+        let mut toml_str = toml::to_string_pretty(&Config::default()).unwrap();
+        toml_str = toml_str.replace("[capture]", "");
+        let mut config: Config = toml::from_str(&toml_str).unwrap();
+        assert_eq!(config.capture, CaptureConfig::default());
+
+        config.capture.backend = Some("grim".to_string());
+        assert!(config.validate().is_ok());
+
+        config.capture.backend = Some("flameshot".to_string());
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_default_extension_validation() {
+        let mut config = Config::default();
+
+        for extension in ["png", "jpg", "jpeg", "webp", "avif"] {
+            config.naming.default_extension = extension.to_string();
+            assert!(config.validate().is_ok(), "{}", extension);
+        }
+
+        config.naming.default_extension = "bmp".to_string();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_metadata_policy_parsing() {
+        let security: SecurityConfig = toml::from_str(
+            "use_system_keychain = true\nencrypt_credentials = false",
+        ).unwrap();
+        assert_eq!(security.metadata, MetadataPolicy::KeepOrientation);
+
+        let security: SecurityConfig = toml::from_str(
+            "use_system_keychain = true\nencrypt_credentials = false\nmetadata = \"strip\"",
+        ).unwrap();
+        assert_eq!(security.metadata, MetadataPolicy::Strip);
+    }
+
+    #[test]
+    fn test_recording_extension_validation() {
+        let mut config = Config::default();
+        assert_eq!(config.naming.recording_extension, "gif");
+
+        config.naming.recording_extension = "webp".to_string();
+        assert!(config.validate().is_ok());
+
+        config.naming.recording_extension = "avi".to_string();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_transform_parsing_and_validation() {
+        let toml_str = r#"
+            type = "local"
+            enabled = true
+            local_path = "/tmp/snapto"
+
+            [transform]
+            max_width = 1920
+            downscale_hidpi = true
+            format = "avif"
+            quality = 70
+        "#;
+
+        let upload: UploadConfig = toml::from_str(toml_str).unwrap();
+        let transform = upload.transform.clone().unwrap();
+        assert_eq!(transform.max_width, Some(1920));
+        assert!(transform.downscale_hidpi && !transform.optimize_png);
+        assert!(TransformConfig::default().is_noop());
+
+        let mut config = Config::default();
+        config.uploads.insert("local".to_string(), upload);
+        config.general.default_uploader = "local".to_string();
+        assert!(config.validate().is_ok());
+
+        config.uploads.get_mut("local").unwrap().transform.as_mut().unwrap().format = Some("bmp".to_string());
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_watermark_parsing_and_validation() {
+        let toml_str = r##"
+            type = "local"
+            enabled = true
+            local_path = "/tmp/snapto"
+
+            [watermark]
+            text = "© ACME"
+            anchor = "top_left"
+            opacity = 0.4
+        "##;
+
+        let upload: UploadConfig = toml::from_str(toml_str).unwrap();
+        let watermark = upload.watermark.clone().unwrap();
+        assert_eq!(watermark.anchor, WatermarkAnchor::TopLeft);
+        assert_eq!(watermark.margin, 16);
+        assert_eq!(watermark.color, "#ffffff");
+
+        let mut config = Config::default();
+        config.uploads.insert("local".to_string(), upload);
+        config.general.default_uploader = "local".to_string();
+        assert!(config.validate().is_ok());
+
+        config.uploads.get_mut("local").unwrap().watermark.as_mut().unwrap().opacity = 1.5;
+        assert!(config.validate().is_err());
+
+        config.uploads.get_mut("local").unwrap().watermark = Some(WatermarkConfig::default());
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_beautify_presets() {
+        let toml_str = r##"
+            [presets.docs]
+            padding = 32
+            background = { type = "solid", color = "#f5f5f5" }
+
+            [presets.bad]
+            background = { type = "gradient", from = "#000000", to = "blue" }
+        "##;
+
+        let beautify: BeautifyConfig = toml::from_str(toml_str).unwrap();
+        let docs = beautify.preset("docs").unwrap();
+        assert_eq!(docs.padding, 32);
+        assert_eq!(docs.corner_radius, 12);
+        assert_eq!(docs.background, BeautifyBackground::Solid { color: "#f5f5f5".to_string() });
+        assert!(docs.validate().is_ok());
+        assert!(beautify.preset("bad").unwrap().validate().is_err());
+
+        // The built-in preset is there without being configured
+        assert_eq!(beautify.preset("default"), Some(BeautifyPreset::default()));
+        assert_eq!(beautify.preset("missing"), None);
+
+        let mut config = Config::default();
+        config.uploads.get_mut("local").unwrap().beautify = Some("docs".to_string());
+        assert!(config.validate().is_err());
+
+        config.beautify = beautify;
+        config.beautify.presets.remove("bad");
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_diff_config() {
+        let diff: DiffConfig = toml::from_str("threshold = 0\nmin_similarity = 0.98").unwrap();
+        assert_eq!(diff.threshold, 0);
+        assert_eq!(diff.merge_distance, 16);
+        assert_eq!(diff.min_similarity, Some(0.98));
+        assert!(diff.validate().is_ok());
+
+        let mut config = Config::default();
+        config.diff.min_similarity = Some(1.5);
+        assert!(config.validate().is_err());
+
+        config.diff = DiffConfig { tint: "magenta".to_string(), ..Default::default() };
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_duplicate_config() {
+        let duplicates: DuplicateConfig = toml::from_str("policy = \"reuse\"").unwrap();
+        assert_eq!(duplicates.policy, DuplicatePolicy::Reuse);
+        assert_eq!(duplicates.max_distance, 4);
+        assert!(!duplicates.dedup);
+
+        // Older configs without the section keep uploading everything
+        let mut config = Config::default();
+        assert_eq!(config.duplicates.policy, DuplicatePolicy::Upload);
+        assert_eq!(config.duplicates.max_distance, 4);
+        assert_eq!(toml::from_str::<DuplicateConfig>("").unwrap(), config.duplicates);
+
+        config.duplicates.max_distance = 65;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_auth_methods_parsing() {
+        let toml_str = r#"
+            type = "ssh"
+            enabled = true
+            host = "example.com"
+            auth_methods = ["agent", "default_keys", "keyboard_interactive"]
+        "#;
+        let upload: UploadConfig = toml::from_str(toml_str).unwrap();
+        assert_eq!(
+            upload.auth_methods,
+            Some(vec![
+                AuthMethod::Agent,
+                AuthMethod::DefaultKeys,
+                AuthMethod::KeyboardInteractive,
+            ])
+        );
+    }
+
+    #[test]
+    fn test_proxy_jump_parsing() {
+        let upload = UploadConfig {
+            proxy_jump: Some("bastion, admin@inner:2222,[::1]:22".to_string()),
+            ..Default::default()
+        };
+
+        let hops = upload.jump_chain().unwrap();
+        assert_eq!(hops.len(), 3);
+        assert_eq!(hops[0].ssh_host.as_deref(), Some("bastion"));
+        assert_eq!(hops[0].username, None);
+        assert_eq!(hops[1].ssh_host.as_deref(), Some("inner"));
+        assert_eq!(hops[1].username.as_deref(), Some("admin"));
+        assert_eq!(hops[1].port, Some(2222));
+        assert_eq!(hops[2].ssh_host.as_deref(), Some("::1"));
+
+        assert!(JumpHost::parse("host:notaport").is_err());
+        assert!(JumpHost::parse("@host").is_err());
+    }
+
+    #[test]
+    fn test_jump_hosts_take_priority() {
+        let toml_str = r#"
+            type = "sftp"
+            enabled = true
+            proxy_jump = "ignored"
+            host_key_checking = "strict"
+
+            [[jump_hosts]]
+            host = "bastion.example.com"
+            username = "jump"
+            auth_methods = ["agent"]
+        "#;
+        let upload: UploadConfig = toml::from_str(toml_str).unwrap();
+        let hops = upload.jump_chain().unwrap();
+
+        assert_eq!(upload.host_key_checking, Some(HostKeyChecking::Strict));
+        assert_eq!(hops.len(), 1);
+        assert_eq!(hops[0].host.as_deref(), Some("bastion.example.com"));
+        assert_eq!(hops[0].to_upload_config().auth_methods, Some(vec![AuthMethod::Agent]));
+    }
 }
@@ -0,0 +1,171 @@
+//! Persistent counters for `{counter}` in naming templates
+//!
+//! Values live in a small SQLite database next to the config file, so the
+//! numbering carries on from one run to the next. Every increment is a single
+//! UPSERT, which SQLite serialises, so concurrent `snapto` processes (a watch
+//! loop and a one-off upload) never hand out the same number twice. The same
+//! database keeps the secret behind sequential `{id:N}` values.
+
+use chrono::{Local, NaiveDate};
+use rusqlite::{params, Connection};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::config::{Config, CounterScope};
+use crate::error::{Result, SnaptoError};
+
+/// How long to wait while another process holds the database lock
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Counters stored in `counters.db`
+pub struct CounterStore {
+    conn: Connection,
+}
+
+impl CounterStore {
+    /// Where the counters are kept: `~/.snapto/counters.db`
+    pub fn default_path() -> Result<PathBuf> {
+        Ok(Config::config_dir()?.join("counters.db"))
+    }
+
+    /// Opens (creating if needed) the store at `path`
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            if !parent.exists() {
+                fs::create_dir_all(parent)?;
+            }
+        }
+
+        let conn = Connection::open(path)
+            .map_err(|e| SnaptoError::Database(format!("Failed to open counters: {}", e)))?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS counters (
+                key TEXT PRIMARY KEY,
+                value INTEGER NOT NULL
+            )",
+            [],
+        )?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS keys (
+                name TEXT PRIMARY KEY,
+                value TEXT NOT NULL
+            )",
+            [],
+        )?;
+
+        Ok(Self { conn })
+    }
+
+    /// Increments the counter `key` and returns the new value, starting at 1
+    pub fn next(&self, key: &str) -> Result<u64> {
+        let value: i64 = self.conn.query_row(
+            "INSERT INTO counters (key, value) VALUES (?1, 1)
+             ON CONFLICT(key) DO UPDATE SET value = value + 1
+             RETURNING value",
+            params![key],
+            |row| row.get(0),
+        )?;
+        Ok(value as u64)
+    }
+
+    /// Secret `name`, made up at random the first time it is asked for
+    ///
+    /// Concurrent first calls agree: only one insert wins.
+    pub fn key(&self, name: &str) -> Result<String> {
+        let fresh: String = (0..32).map(|_| format!("{:02x}", rand::random::<u8>())).collect();
+        self.conn.execute(
+            "INSERT OR IGNORE INTO keys (name, value) VALUES (?1, ?2)",
+            params![name, fresh],
+        )?;
+        Ok(self
+            .conn
+            .query_row("SELECT value FROM keys WHERE name = ?1", params![name], |row| row.get(0))?)
+    }
+
+    /// Next value of the counter `scope` selects for an upload
+    ///
+    /// `destination` and `template` only matter for their own scopes.
+    pub fn next_in(&self, scope: CounterScope, destination: Option<&str>, template: &str) -> Result<u64> {
+        let today = Local::now().date_naive();
+        let key = counter_key(scope, destination, template, today);
+
+        if scope == CounterScope::Day {
+            // Earlier days can never be incremented again
+            self.conn.execute(
+                "DELETE FROM counters WHERE key LIKE 'day:%' AND key <> ?1",
+                params![key],
+            )?;
+        }
+        self.next(&key)
+    }
+}
+
+/// Key of the counter `scope` selects
+fn counter_key(scope: CounterScope, destination: Option<&str>, template: &str, today: NaiveDate) -> String {
+    match scope {
+        CounterScope::Global => "global".to_string(),
+        CounterScope::Destination => format!("destination:{}", destination.unwrap_or_default()),
+        CounterScope::Day => format!("day:{}", today.format("%Y-%m-%d")),
+        CounterScope::Template => format!("template:{}", template),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_counters_persist_per_key() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("counters.db");
+
+        let store = CounterStore::open(&path).unwrap();
+        assert_eq!(store.next("global").unwrap(), 1);
+        assert_eq!(store.next("global").unwrap(), 2);
+        assert_eq!(store.next("destination:vps").unwrap(), 1);
+        drop(store);
+
+        // A later run, or another process, carries on
+        let other = CounterStore::open(&path).unwrap();
+        assert_eq!(other.next("global").unwrap(), 3);
+        assert_eq!(other.next_in(CounterScope::Destination, Some("vps"), "").unwrap(), 2);
+        assert_eq!(other.next_in(CounterScope::Template, None, "shot_{counter}").unwrap(), 1);
+        assert_eq!(other.next_in(CounterScope::Day, None, "").unwrap(), 1);
+    }
+
+    #[test]
+    fn test_concurrent_increments_are_unique() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("counters.db");
+        CounterStore::open(&path).unwrap();
+
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    let store = CounterStore::open(&path).unwrap();
+                    (0..25).map(|_| store.next("global").unwrap()).collect::<Vec<_>>()
+                })
+            })
+            .collect();
+
+        let mut values: Vec<u64> = handles.into_iter().flat_map(|h| h.join().unwrap()).collect();
+        values.sort_unstable();
+        assert_eq!(values, (1..=100).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_counter_keys() {
+        let day = NaiveDate::from_ymd_opt(2024, 3, 9).unwrap();
+        assert_eq!(counter_key(CounterScope::Global, Some("vps"), "t", day), "global");
+        assert_eq!(counter_key(CounterScope::Destination, Some("vps"), "t", day), "destination:vps");
+        assert_eq!(counter_key(CounterScope::Day, Some("vps"), "t", day), "day:2024-03-09");
+        assert_eq!(counter_key(CounterScope::Template, Some("vps"), "{counter}", day), "template:{counter}");
+
+        let scope: CounterScope = toml::Value::String("day".to_string()).try_into().unwrap();
+        assert_eq!(scope, CounterScope::Day);
+    }
+}
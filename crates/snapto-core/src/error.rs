@@ -32,12 +32,21 @@ pub enum SnaptoError {
     #[error("Image processing error: {0}")]
     ImageProcessing(String),
 
+    #[error("Screenshot error: {0}")]
+    Screenshot(String),
+
+    #[error("Recording error: {0}")]
+    Recording(String),
+
     #[error("SSH connection error: {0}")]
     SshConnection(String),
 
     #[error("SSH authentication error: {0}")]
     SshAuthentication(String),
 
+    #[error("SSH host key verification failed: {0}")]
+    HostKeyVerification(String),
+
     #[error("SFTP error: {0}")]
     Sftp(String),
 
@@ -81,6 +90,36 @@ impl From<image::ImageError> for SnaptoError {
     }
 }
 
+impl From<crate::screenshot::ScreenshotError> for SnaptoError {
+    fn from(err: crate::screenshot::ScreenshotError) -> Self {
+        SnaptoError::Screenshot(err.to_string())
+    }
+}
+
+impl From<crate::recording::RecordingError> for SnaptoError {
+    fn from(err: crate::recording::RecordingError) -> Self {
+        SnaptoError::Recording(err.to_string())
+    }
+}
+
+impl From<crate::image_ops::ImageOpsError> for SnaptoError {
+    fn from(err: crate::image_ops::ImageOpsError) -> Self {
+        SnaptoError::ImageProcessing(err.to_string())
+    }
+}
+
+impl From<crate::metadata::MetadataError> for SnaptoError {
+    fn from(err: crate::metadata::MetadataError) -> Self {
+        SnaptoError::ImageProcessing(err.to_string())
+    }
+}
+
+impl From<crate::transform::TransformError> for SnaptoError {
+    fn from(err: crate::transform::TransformError) -> Self {
+        SnaptoError::ImageProcessing(err.to_string())
+    }
+}
+
 impl From<ssh2::Error> for SnaptoError {
     fn from(err: ssh2::Error) -> Self {
         SnaptoError::SshConnection(err.to_string())
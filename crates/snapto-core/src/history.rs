@@ -1,5 +1,7 @@
 use crate::config::{HistoryConfig, HistoryMode};
 use crate::error::{Result, SnaptoError};
+use crate::image_ops::PerceptualHash;
+use crate::upload::UploadResult;
 use chrono::{DateTime, Utc};
 use image::{imageops::FilterType, ImageFormat};
 use rusqlite::{params, Connection};
@@ -8,7 +10,7 @@ use std::io::Cursor;
 use std::path::PathBuf;
 
 /// Entry in the upload history
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct HistoryEntry {
     pub id: i64,
     pub filename: String,
@@ -19,6 +21,63 @@ pub struct HistoryEntry {
     pub created_at: DateTime<Utc>,
     pub thumbnail_path: Option<String>,
     pub local_copy_path: Option<String>,
+    /// MIME type of the uploaded file (None for entries from older versions)
+    pub mime_type: Option<String>,
+    /// Size before the upload transform pipeline ran (None when unchanged)
+    pub original_size: Option<usize>,
+    /// Extra details recorded by the command that made the upload, such as
+    /// the source files of a stitched image
+    pub metadata: Option<serde_json::Value>,
+    /// Perceptual hash of the image before transforms, to spot re-uploads of
+    /// the same picture (None for other files and older entries)
+    pub perceptual_hash: Option<PerceptualHash>,
+    /// SHA-256 of the bytes as uploaded, after transforms and metadata
+    /// scrubbing, hex encoded
+    pub content_hash: Option<String>,
+}
+
+impl HistoryEntry {
+    /// The upload this entry recorded, for handing out again instead of
+    /// re-uploading the same file
+    pub fn upload_result(&self) -> UploadResult {
+        UploadResult {
+            remote_path: self.remote_path.clone(),
+            url: self.url.clone(),
+            size: self.size,
+            duration_ms: 0,
+        }
+    }
+}
+
+/// Columns read into a [`HistoryEntry`], in `entry_from_row` order
+const ENTRY_COLUMNS: &str =
+    "id, filename, remote_path, url, destination, size, created_at, thumbnail_path, local_copy_path, mime_type, original_size, metadata, perceptual_hash, content_hash";
+
+fn entry_from_row(row: &rusqlite::Row) -> rusqlite::Result<HistoryEntry> {
+    Ok(HistoryEntry {
+        id: row.get(0)?,
+        filename: row.get(1)?,
+        remote_path: row.get(2)?,
+        url: row.get(3)?,
+        destination: row.get(4)?,
+        size: row.get::<_, i64>(5)? as usize,
+        created_at: {
+            let date_str: String = row.get(6)?;
+            DateTime::parse_from_rfc3339(&date_str)
+                .map(|dt| dt.with_timezone(&Utc))
+                .unwrap_or_else(|_| Utc::now())
+        },
+        thumbnail_path: row.get(7)?,
+        local_copy_path: row.get(8)?,
+        mime_type: row.get(9)?,
+        original_size: row.get::<_, Option<i64>>(10)?.map(|size| size as usize),
+        metadata: row
+            .get::<_, Option<String>>(11)?
+            .and_then(|json| serde_json::from_str(&json).ok()),
+        // Stored as the signed bit pattern, SQLite integers being i64
+        perceptual_hash: row.get::<_, Option<i64>>(12)?.map(|hash| PerceptualHash(hash as u64)),
+        content_hash: row.get(13)?,
+    })
 }
 
 /// Manages the upload history using SQLite
@@ -68,6 +127,13 @@ impl HistoryManager {
             [],
         )?;
 
+        // Columns added after the first release
+        self.ensure_column("mime_type", "TEXT")?;
+        self.ensure_column("original_size", "INTEGER")?;
+        self.ensure_column("metadata", "TEXT")?;
+        self.ensure_column("perceptual_hash", "INTEGER")?;
+        self.ensure_column("content_hash", "TEXT")?;
+
         // Create indexes for better query performance
         self.conn.execute(
             "CREATE INDEX IF NOT EXISTS idx_created_at ON history(created_at DESC)",
@@ -79,6 +145,29 @@ impl HistoryManager {
             [],
         )?;
 
+        self.conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_content_hash ON history(content_hash, destination)",
+            [],
+        )?;
+
+        Ok(())
+    }
+
+    /// Adds a column to databases created by older versions
+    fn ensure_column(&self, name: &str, definition: &str) -> Result<()> {
+        let mut stmt = self.conn.prepare("PRAGMA table_info(history)")?;
+        let exists = stmt
+            .query_map([], |row| row.get::<_, String>(1))?
+            .filter_map(|column| column.ok())
+            .any(|column| column == name);
+
+        if !exists {
+            self.conn.execute(
+                &format!("ALTER TABLE history ADD COLUMN {} {}", name, definition),
+                [],
+            )?;
+        }
+
         Ok(())
     }
 
@@ -91,14 +180,23 @@ impl HistoryManager {
         let mut thumbnail_path = None;
         let mut local_copy_path = None;
 
+        // Videos get no thumbnail, but are still kept in full mode
+        let is_image = entry
+            .mime_type
+            .as_deref()
+            .is_none_or(|mime| mime.starts_with("image/"));
+
         // Process image based on history mode
         if let Some(data) = image_data {
             match self.config.mode {
-                HistoryMode::Thumbnails => {
+                HistoryMode::Thumbnails if is_image => {
                     thumbnail_path = Some(self.save_thumbnail(data, &entry.filename)?);
                 }
+                HistoryMode::Thumbnails => {}
                 HistoryMode::Full => {
-                    thumbnail_path = Some(self.save_thumbnail(data, &entry.filename)?);
+                    if is_image {
+                        thumbnail_path = Some(self.save_thumbnail(data, &entry.filename)?);
+                    }
                     local_copy_path = Some(self.save_full_image(data, &entry.filename)?);
                 }
                 HistoryMode::Metadata => {
@@ -109,8 +207,8 @@ impl HistoryManager {
 
         // Insert into database
         self.conn.execute(
-            "INSERT INTO history (filename, remote_path, url, destination, size, created_at, thumbnail_path, local_copy_path)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            "INSERT INTO history (filename, remote_path, url, destination, size, created_at, thumbnail_path, local_copy_path, mime_type, original_size, metadata, perceptual_hash, content_hash)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
             params![
                 entry.filename,
                 entry.remote_path,
@@ -120,6 +218,11 @@ impl HistoryManager {
                 entry.created_at.to_rfc3339(),
                 thumbnail_path,
                 local_copy_path,
+                entry.mime_type,
+                entry.original_size.map(|size| size as i64),
+                entry.metadata.as_ref().map(|metadata| metadata.to_string()),
+                entry.perceptual_hash.map(|hash| hash.0 as i64),
+                entry.content_hash,
             ],
         )?;
 
@@ -134,30 +237,10 @@ impl HistoryManager {
     /// Gets the most recent N entries
     pub fn get_recent(&self, limit: usize) -> Result<Vec<HistoryEntry>> {
         let mut stmt = self.conn.prepare(
-            "SELECT id, filename, remote_path, url, destination, size, created_at, thumbnail_path, local_copy_path
-             FROM history
-             ORDER BY created_at DESC
-             LIMIT ?1"
+            &format!("SELECT {} FROM history ORDER BY created_at DESC LIMIT ?1", ENTRY_COLUMNS),
         )?;
 
-        let entries = stmt.query_map(params![limit as i64], |row| {
-            Ok(HistoryEntry {
-                id: row.get(0)?,
-                filename: row.get(1)?,
-                remote_path: row.get(2)?,
-                url: row.get(3)?,
-                destination: row.get(4)?,
-                size: row.get::<_, i64>(5)? as usize,
-                created_at: {
-                    let date_str: String = row.get(6)?;
-                    DateTime::parse_from_rfc3339(&date_str)
-                        .map(|dt| dt.with_timezone(&Utc))
-                        .unwrap_or_else(|_| Utc::now())
-                },
-                thumbnail_path: row.get(7)?,
-                local_copy_path: row.get(8)?,
-            })
-        })?
+        let entries = stmt.query_map(params![limit as i64], entry_from_row)?
         .collect::<std::result::Result<Vec<_>, _>>()?;
 
         Ok(entries)
@@ -168,36 +251,79 @@ impl HistoryManager {
         let search_pattern = format!("%{}%", query);
 
         let mut stmt = self.conn.prepare(
-            "SELECT id, filename, remote_path, url, destination, size, created_at, thumbnail_path, local_copy_path
-             FROM history
-             WHERE filename LIKE ?1 OR url LIKE ?1
-             ORDER BY created_at DESC
-             LIMIT 100"
+            &format!(
+                "SELECT {} FROM history
+                 WHERE filename LIKE ?1 OR url LIKE ?1
+                 ORDER BY created_at DESC
+                 LIMIT 100",
+                ENTRY_COLUMNS
+            ),
         )?;
 
-        let entries = stmt.query_map(params![search_pattern], |row| {
-            Ok(HistoryEntry {
-                id: row.get(0)?,
-                filename: row.get(1)?,
-                remote_path: row.get(2)?,
-                url: row.get(3)?,
-                destination: row.get(4)?,
-                size: row.get::<_, i64>(5)? as usize,
-                created_at: {
-                    let date_str: String = row.get(6)?;
-                    DateTime::parse_from_rfc3339(&date_str)
-                        .map(|dt| dt.with_timezone(&Utc))
-                        .unwrap_or_else(|_| Utc::now())
-                },
-                thumbnail_path: row.get(7)?,
-                local_copy_path: row.get(8)?,
-            })
-        })?
+        let entries = stmt.query_map(params![search_pattern], entry_from_row)?
         .collect::<std::result::Result<Vec<_>, _>>()?;
 
         Ok(entries)
     }
 
+    /// Whether `filename` was already uploaded to `destination`
+    pub fn has_filename(&self, filename: &str, destination: &str) -> Result<bool> {
+        let count: i64 = self.conn.query_row(
+            "SELECT COUNT(*) FROM history WHERE filename = ?1 AND destination = ?2",
+            params![filename, destination],
+            |row| row.get(0),
+        )?;
+        Ok(count > 0)
+    }
+
+    /// Most recent upload of exactly this content to `destination`
+    pub fn find_by_content_hash(&self, hash: &str, destination: &str) -> Result<Option<HistoryEntry>> {
+        let mut stmt = self.conn.prepare(
+            &format!(
+                "SELECT {} FROM history
+                 WHERE content_hash = ?1 AND destination = ?2
+                 ORDER BY created_at DESC
+                 LIMIT 1",
+                ENTRY_COLUMNS
+            ),
+        )?;
+
+        match stmt.query_row(params![hash, destination], entry_from_row) {
+            Ok(entry) => Ok(Some(entry)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Most recent upload to `destination` whose perceptual hash is within
+    /// `max_distance` bits of `hash`
+    pub fn find_similar(
+        &self,
+        hash: PerceptualHash,
+        max_distance: u32,
+        destination: &str,
+    ) -> Result<Option<HistoryEntry>> {
+        let mut stmt = self.conn.prepare(
+            &format!(
+                "SELECT {} FROM history
+                 WHERE perceptual_hash IS NOT NULL AND destination = ?1
+                 ORDER BY created_at DESC",
+                ENTRY_COLUMNS
+            ),
+        )?;
+
+        // SQLite has no popcount, so the distance is checked here; history is
+        // capped at max_entries rows
+        for entry in stmt.query_map(params![destination], entry_from_row)? {
+            let entry = entry?;
+            if entry.perceptual_hash.is_some_and(|stored| stored.distance(&hash) <= max_distance) {
+                return Ok(Some(entry));
+            }
+        }
+
+        Ok(None)
+    }
+
     /// Deletes an entry from the history
     pub fn delete(&self, id: i64) -> Result<()> {
         // Get the entry first to delete associated files
@@ -343,29 +469,10 @@ impl HistoryManager {
     /// Gets an entry by ID
     pub fn get_by_id(&self, id: i64) -> Result<Option<HistoryEntry>> {
         let mut stmt = self.conn.prepare(
-            "SELECT id, filename, remote_path, url, destination, size, created_at, thumbnail_path, local_copy_path
-             FROM history
-             WHERE id = ?1"
+            &format!("SELECT {} FROM history WHERE id = ?1", ENTRY_COLUMNS),
         )?;
 
-        let result = stmt.query_row(params![id], |row| {
-            Ok(HistoryEntry {
-                id: row.get(0)?,
-                filename: row.get(1)?,
-                remote_path: row.get(2)?,
-                url: row.get(3)?,
-                destination: row.get(4)?,
-                size: row.get::<_, i64>(5)? as usize,
-                created_at: {
-                    let date_str: String = row.get(6)?;
-                    DateTime::parse_from_rfc3339(&date_str)
-                        .map(|dt| dt.with_timezone(&Utc))
-                        .unwrap_or_else(|_| Utc::now())
-                },
-                thumbnail_path: row.get(7)?,
-                local_copy_path: row.get(8)?,
-            })
-        });
+        let result = stmt.query_row(params![id], entry_from_row);
 
         match result {
             Ok(entry) => Ok(Some(entry)),
@@ -438,6 +545,11 @@ mod tests {
             created_at: Utc::now(),
             thumbnail_path: None,
             local_copy_path: None,
+            mime_type: None,
+            original_size: None,
+            metadata: None,
+            perceptual_hash: None,
+            content_hash: None,
         };
 
         let id = manager.add(&entry, None).unwrap();
@@ -463,6 +575,11 @@ mod tests {
             created_at: Utc::now(),
             thumbnail_path: None,
             local_copy_path: None,
+            mime_type: None,
+            original_size: None,
+            metadata: None,
+            perceptual_hash: None,
+            content_hash: None,
         };
 
         manager.add(&entry, None).unwrap();
@@ -487,6 +604,11 @@ mod tests {
             created_at: Utc::now(),
             thumbnail_path: None,
             local_copy_path: None,
+            mime_type: None,
+            original_size: None,
+            metadata: None,
+            perceptual_hash: None,
+            content_hash: None,
         };
 
         let id = manager.add(&entry, None).unwrap();
@@ -514,6 +636,11 @@ mod tests {
                 created_at: Utc::now(),
                 thumbnail_path: None,
                 local_copy_path: None,
+                mime_type: None,
+                original_size: None,
+                metadata: None,
+                perceptual_hash: None,
+                content_hash: None,
             };
             manager.add(&entry, None).unwrap();
         }
@@ -521,4 +648,158 @@ mod tests {
         let count = manager.count().unwrap();
         assert_eq!(count, 5); // Should only have 5 entries due to cleanup
     }
+
+    #[test]
+    fn test_old_database_gets_mime_type_column() {
+        let config = test_config();
+        let dir = PathBuf::from(&config.path);
+        fs::create_dir_all(&dir).unwrap();
+
+        // Schema from before mime types were recorded
+        Connection::open(dir.join("history.db"))
+            .unwrap()
+            .execute_batch(
+                "CREATE TABLE history (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    filename TEXT NOT NULL, remote_path TEXT NOT NULL, url TEXT,
+                    destination TEXT NOT NULL, size INTEGER NOT NULL, created_at TEXT NOT NULL,
+                    thumbnail_path TEXT, local_copy_path TEXT
+                );
+                INSERT INTO history (filename, remote_path, destination, size, created_at)
+                VALUES ('old.png', '/old.png', 'local', 1, '2024-01-01T00:00:00Z');",
+            )
+            .unwrap();
+
+        let manager = HistoryManager::new(config).unwrap();
+        let entries = manager.get_recent(10).unwrap();
+        assert_eq!(entries[0].filename, "old.png");
+        assert_eq!(entries[0].mime_type, None);
+        assert_eq!(entries[0].original_size, None);
+        assert_eq!(entries[0].metadata, None);
+    }
+
+    #[test]
+    fn test_original_size_round_trip() {
+        let manager = HistoryManager::new(test_config()).unwrap();
+
+        let entry = HistoryEntry {
+            filename: "shot.jpg".to_string(),
+            remote_path: "/shots/shot.jpg".to_string(),
+            destination: "local".to_string(),
+            size: 120_000,
+            original_size: Some(2_400_000),
+            created_at: Utc::now(),
+            ..Default::default()
+        };
+
+        let id = manager.add(&entry, None).unwrap();
+        let stored = manager.get_by_id(id).unwrap().unwrap();
+        assert_eq!((stored.size, stored.original_size), (120_000, Some(2_400_000)));
+    }
+
+    #[test]
+    fn test_metadata_round_trip() {
+        let manager = HistoryManager::new(test_config()).unwrap();
+
+        let metadata = serde_json::json!({ "sources": ["before.png", "after.png"] });
+        let entry = HistoryEntry {
+            filename: "stitched.png".to_string(),
+            remote_path: "/shots/stitched.png".to_string(),
+            destination: "local".to_string(),
+            created_at: Utc::now(),
+            metadata: Some(metadata.clone()),
+            ..Default::default()
+        };
+
+        let id = manager.add(&entry, None).unwrap();
+        let stored = manager.get_by_id(id).unwrap().unwrap();
+        assert_eq!(stored.metadata, Some(metadata));
+    }
+
+    #[test]
+    fn test_find_similar() {
+        let manager = HistoryManager::new(test_config()).unwrap();
+
+        let add = |filename: &str, destination: &str, hash: Option<u64>, age_secs: i64| {
+            let entry = HistoryEntry {
+                filename: filename.to_string(),
+                remote_path: format!("/shots/{}", filename),
+                url: Some(format!("https://example.com/{}", filename)),
+                destination: destination.to_string(),
+                created_at: Utc::now() - chrono::Duration::seconds(age_secs),
+                perceptual_hash: hash.map(PerceptualHash),
+                ..Default::default()
+            };
+            manager.add(&entry, None).unwrap();
+        };
+        add("old.png", "server", Some(0xffff_0000_ffff_0000), 60);
+        add("new.png", "server", Some(0xffff_0000_ffff_0001), 10);
+        add("elsewhere.png", "local", Some(0xffff_0000_ffff_0000), 0);
+        add("clip.mp4", "server", None, 0);
+
+        // The most recent close match wins, including the top bit of the hash
+        let found = manager.find_similar(PerceptualHash(0xffff_0000_ffff_0000), 4, "server").unwrap();
+        assert_eq!(found.unwrap().filename, "new.png");
+
+        let exact = manager.find_similar(PerceptualHash(0xffff_0000_ffff_0000), 0, "server").unwrap();
+        assert_eq!(exact.unwrap().filename, "old.png");
+
+        assert!(manager.find_similar(PerceptualHash(0), 4, "server").unwrap().is_none());
+        assert!(manager.find_similar(PerceptualHash(0xffff_0000_ffff_0000), 4, "other").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_find_by_content_hash() {
+        let manager = HistoryManager::new(test_config()).unwrap();
+
+        let entry = HistoryEntry {
+            filename: "shot.png".to_string(),
+            remote_path: "/shots/shot.png".to_string(),
+            url: Some("https://example.com/shot.png".to_string()),
+            destination: "server".to_string(),
+            size: 2048,
+            created_at: Utc::now(),
+            content_hash: Some(crate::naming::content_hash(b"pixels")),
+            ..Default::default()
+        };
+        manager.add(&entry, None).unwrap();
+
+        let hash = crate::naming::content_hash(b"pixels");
+        let found = manager.find_by_content_hash(&hash, "server").unwrap().unwrap();
+        let result = found.upload_result();
+        assert_eq!(result.url.as_deref(), Some("https://example.com/shot.png"));
+        assert_eq!((result.remote_path.as_str(), result.size), ("/shots/shot.png", 2048));
+
+        assert!(manager.find_by_content_hash(&hash, "local").unwrap().is_none());
+        let other = crate::naming::content_hash(b"other pixels");
+        assert!(manager.find_by_content_hash(&other, "server").unwrap().is_none());
+
+        assert!(manager.has_filename("shot.png", "server").unwrap());
+        assert!(!manager.has_filename("shot.png", "local").unwrap());
+        assert!(!manager.has_filename("other.png", "server").unwrap());
+    }
+
+    #[test]
+    fn test_video_is_kept_without_thumbnail() {
+        let mut config = test_config();
+        config.mode = HistoryMode::Full;
+        let manager = HistoryManager::new(config).unwrap();
+
+        let entry = HistoryEntry {
+            filename: "clip.mp4".to_string(),
+            remote_path: "/clips/clip.mp4".to_string(),
+            destination: "local".to_string(),
+            size: 4,
+            created_at: Utc::now(),
+            mime_type: Some("video/mp4".to_string()),
+            ..Default::default()
+        };
+
+        let id = manager.add(&entry, Some(b"not an image")).unwrap();
+        let stored = manager.get_by_id(id).unwrap().unwrap();
+
+        assert_eq!(stored.mime_type.as_deref(), Some("video/mp4"));
+        assert!(stored.thumbnail_path.is_none());
+        assert!(stored.local_copy_path.is_some());
+    }
 }
@@ -0,0 +1,476 @@
+//! Annotations drawn on top of a screenshot
+//!
+//! Arrows, rectangles, ellipses, highlighter strokes, text labels and
+//! numbered step badges, described declaratively in TOML or JSON:
+//!
+//! ```toml
+//! color = "#ff3b30"
+//!
+//! [[annotations]]
+//! type = "arrow"
+//! from = [40, 40]
+//! to = [220, 130]
+//!
+//! [[annotations]]
+//! type = "highlight"
+//! points = [[300, 80], [520, 80]]
+//!
+//! [[annotations]]
+//! type = "text"
+//! x = 230
+//! y = 140
+//! text = "Click here"
+//! background = "#000000c0"
+//! color = "#ffffff"
+//!
+//! [[annotations]]
+//! type = "step"
+//! x = 250
+//! y = 60
+//! ```
+//!
+//! Steps without a `number` are numbered in order from 1.
+
+use image::{Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use super::draw::{self, Point};
+use super::{font, parse_color, ImageOpsError};
+
+/// A single shape to draw
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Annotation {
+    /// Line from `from` to `to` with a head at `to`
+    Arrow {
+        from: [f32; 2],
+        to: [f32; 2],
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        color: Option<String>,
+        #[serde(default = "default_stroke")]
+        stroke: f32,
+    },
+    /// Rectangle outline, optionally filled
+    Rect {
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        color: Option<String>,
+        #[serde(default = "default_stroke")]
+        stroke: f32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        fill: Option<String>,
+    },
+    /// Ellipse inscribed in the given box, optionally filled
+    Ellipse {
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        color: Option<String>,
+        #[serde(default = "default_stroke")]
+        stroke: f32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        fill: Option<String>,
+    },
+    /// Wide semi-transparent stroke through `points`
+    Highlight {
+        points: Vec<[f32; 2]>,
+        #[serde(default = "default_highlight_color")]
+        color: String,
+        #[serde(default = "default_highlight_stroke")]
+        stroke: f32,
+    },
+    /// Text with its top-left corner at (`x`, `y`)
+    Text {
+        x: f32,
+        y: f32,
+        text: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        color: Option<String>,
+        /// Line height in pixels
+        #[serde(default = "default_text_size")]
+        size: u32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        background: Option<String>,
+    },
+    /// Numbered circle centered on (`x`, `y`)
+    Step {
+        x: f32,
+        y: f32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        number: Option<u32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        color: Option<String>,
+        /// Diameter in pixels
+        #[serde(default = "default_step_size")]
+        size: u32,
+    },
+}
+
+fn default_color() -> String {
+    "#ff3b30".to_string()
+}
+
+fn default_stroke() -> f32 {
+    4.0
+}
+
+fn default_highlight_color() -> String {
+    "#ffeb3b80".to_string()
+}
+
+fn default_highlight_stroke() -> f32 {
+    18.0
+}
+
+fn default_text_size() -> u32 {
+    18
+}
+
+fn default_step_size() -> u32 {
+    28
+}
+
+/// A list of annotations and the color they use unless they set one
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnnotationSpec {
+    /// Default color (#rrggbb or #rrggbbaa)
+    #[serde(default = "default_color")]
+    pub color: String,
+    #[serde(default)]
+    pub annotations: Vec<Annotation>,
+}
+
+impl Default for AnnotationSpec {
+    fn default() -> Self {
+        Self {
+            color: default_color(),
+            annotations: Vec::new(),
+        }
+    }
+}
+
+impl AnnotationSpec {
+    /// Parses a TOML spec
+    pub fn from_toml(toml: &str) -> Result<Self, ImageOpsError> {
+        toml::from_str(toml).map_err(|e| ImageOpsError::InvalidSpec(e.to_string()))
+    }
+
+    /// Parses a JSON spec
+    pub fn from_json(json: &str) -> Result<Self, ImageOpsError> {
+        serde_json::from_str(json).map_err(|e| ImageOpsError::InvalidSpec(e.to_string()))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.annotations.is_empty()
+    }
+
+    /// Draws every annotation in order, so later ones end up on top
+    pub fn apply(&self, img: &mut RgbaImage) -> Result<(), ImageOpsError> {
+        let default = parse_color(&self.color)?;
+        let color = |c: &Option<String>| c.as_deref().map(parse_color).unwrap_or(Ok(default));
+        let mut next_step = 1;
+
+        for annotation in &self.annotations {
+            match annotation {
+                Annotation::Arrow { from, to, color: c, stroke } => {
+                    check_positive("stroke", *stroke)?;
+                    arrow(img, (from[0], from[1]), (to[0], to[1]), *stroke, color(c)?);
+                }
+                Annotation::Rect { x, y, width, height, color: c, stroke, fill } => {
+                    check_positive("stroke", *stroke)?;
+                    let (min, max) = ((*x, *y), (x + width, y + height));
+                    let outline = color(c)?;
+                    let fill = fill.as_deref().map(parse_color).transpose()?;
+                    shape(img, min, max, *stroke, outline, fill, |p| {
+                        draw::rounded_rect_distance(p, min, max, 0.0)
+                    });
+                }
+                Annotation::Ellipse { x, y, width, height, color: c, stroke, fill } => {
+                    check_positive("stroke", *stroke)?;
+                    let (min, max) = ((*x, *y), (x + width, y + height));
+                    let center = (x + width / 2.0, y + height / 2.0);
+                    let radii = (width / 2.0, height / 2.0);
+                    let outline = color(c)?;
+                    let fill = fill.as_deref().map(parse_color).transpose()?;
+                    shape(img, min, max, *stroke, outline, fill, |p| {
+                        draw::ellipse_distance(p, center, radii)
+                    });
+                }
+                Annotation::Highlight { points, color: c, stroke } => {
+                    check_positive("stroke", *stroke)?;
+                    if points.is_empty() {
+                        return Err(ImageOpsError::InvalidSpec("highlight needs at least one point".to_string()));
+                    }
+                    let points: Vec<Point> = points.iter().map(|p| (p[0], p[1])).collect();
+                    draw::stroke(img, &points, *stroke, parse_color(c)?);
+                }
+                Annotation::Text { x, y, text, color: c, size, background } => {
+                    check_positive("size", *size as f32)?;
+                    let background = background.as_deref().map(parse_color).transpose()?;
+                    label(img, (*x, *y), text, *size, color(c)?, background);
+                }
+                Annotation::Step { x, y, number, color: c, size } => {
+                    check_positive("size", *size as f32)?;
+                    let number = number.unwrap_or(next_step);
+                    next_step = number + 1;
+                    step(img, (*x, *y), number, *size, color(c)?);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Annotates an encoded image, keeping its format
+    pub fn apply_encoded(&self, data: &[u8]) -> Result<Vec<u8>, ImageOpsError> {
+        super::edit_encoded(data, |img| self.apply(img))
+    }
+}
+
+fn check_positive(field: &str, value: f32) -> Result<(), ImageOpsError> {
+    if value > 0.0 {
+        Ok(())
+    } else {
+        Err(ImageOpsError::InvalidSpec(format!("{} must be greater than zero", field)))
+    }
+}
+
+/// Draws a line with a triangular head at `to`
+pub fn arrow(img: &mut RgbaImage, from: Point, to: Point, stroke: f32, color: Rgba<u8>) {
+    let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+    let length = (dx * dx + dy * dy).sqrt();
+    if length == 0.0 {
+        return;
+    }
+    let (ux, uy) = (dx / length, dy / length);
+
+    // The head never takes more than half the arrow
+    let head_length = (stroke * 3.5).max(10.0).min(length / 2.0);
+    let head_half = head_length * 0.6;
+    let base = (to.0 - ux * head_length, to.1 - uy * head_length);
+
+    // Stop the shaft inside the head so its round cap does not poke out
+    let shaft_end = (base.0 + ux * stroke / 2.0, base.1 + uy * stroke / 2.0);
+    draw::stroke(img, &[from, shaft_end], stroke, color);
+    draw::fill_polygon(
+        img,
+        &[
+            to,
+            (base.0 - uy * head_half, base.1 + ux * head_half),
+            (base.0 + uy * head_half, base.1 - ux * head_half),
+        ],
+        color,
+    );
+}
+
+/// Fills and outlines the shape described by `distance` within `min`..`max`
+fn shape<F>(img: &mut RgbaImage, min: Point, max: Point, stroke: f32, outline: Rgba<u8>, fill: Option<Rgba<u8>>, distance: F)
+where
+    F: Fn(Point) -> f32,
+{
+    let half = stroke / 2.0;
+    let grown_min = (min.0 - half - 1.0, min.1 - half - 1.0);
+    let grown_max = (max.0 + half + 1.0, max.1 + half + 1.0);
+
+    if let Some(fill) = fill {
+        draw::fill_with(img, min, max, fill, |p| draw::coverage(distance(p)));
+    }
+    draw::fill_with(img, grown_min, grown_max, outline, |p| draw::coverage(distance(p).abs() - half));
+}
+
+/// Draws `text` at `origin`, on a padded box when `background` is set
+pub fn label(img: &mut RgbaImage, origin: Point, text: &str, size: u32, color: Rgba<u8>, background: Option<Rgba<u8>>) {
+    let scale = font::scale_for(size);
+    let (x, y) = (origin.0.round() as i32, origin.1.round() as i32);
+
+    if let Some(background) = background {
+        let (width, height) = font::measure(text, scale);
+        let padding = (scale * 3) as f32;
+        let min = (x as f32 - padding, y as f32 - padding);
+        let max = (x as f32 + width as f32 + padding, y as f32 + height as f32 + padding);
+        draw::fill_with(img, min, max, background, |p| {
+            draw::coverage(draw::rounded_rect_distance(p, min, max, padding))
+        });
+    }
+    font::draw_text(img, x, y, text, scale, color);
+}
+
+/// Draws a filled circle with `number` centered in it
+pub fn step(img: &mut RgbaImage, center: Point, number: u32, size: u32, color: Rgba<u8>) {
+    let radius = size as f32 / 2.0;
+    let min = (center.0 - radius - 1.0, center.1 - radius - 1.0);
+    let max = (center.0 + radius + 1.0, center.1 + radius + 1.0);
+    draw::fill_with(img, min, max, color, |p| {
+        draw::coverage(draw::ellipse_distance(p, center, (radius, radius)))
+    });
+
+    // Shrink the digits until they fit inside the circle
+    let text = number.to_string();
+    let mut scale = (size / 14).max(1);
+    while scale > 1 && font::measure(&text, scale).0 as f32 > size as f32 * 0.75 {
+        scale -= 1;
+    }
+    let (width, height) = font::measure(&text, scale);
+    let x = (center.0 - width as f32 / 2.0).round() as i32;
+    let y = (center.1 - height as f32 / 2.0).round() as i32;
+    font::draw_text(img, x, y, &text, scale, contrasting(color));
+}
+
+/// Black or white, whichever reads better on `color`
+fn contrasting(color: Rgba<u8>) -> Rgba<u8> {
+    let luma = 0.299 * color[0] as f32 + 0.587 * color[1] as f32 + 0.114 * color[2] as f32;
+    if luma > 160.0 {
+        Rgba([0, 0, 0, 255])
+    } else {
+        Rgba([255, 255, 255, 255])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WHITE: Rgba<u8> = Rgba([255, 255, 255, 255]);
+    const RED: Rgba<u8> = Rgba([255, 59, 48, 255]);
+
+    fn canvas() -> RgbaImage {
+        RgbaImage::from_pixel(100, 100, WHITE)
+    }
+
+    #[test]
+    fn test_toml_spec() {
+        let spec = AnnotationSpec::from_toml(
+            r##"
+            [[annotations]]
+            type = "arrow"
+            from = [10, 10]
+            to = [90, 10]
+
+            [[annotations]]
+            type = "rect"
+            x = 10
+            y = 30
+            width = 40
+            height = 20
+            color = "#0000ff"
+            "##,
+        )
+        .unwrap();
+        assert_eq!(spec.color, "#ff3b30");
+        assert!(matches!(spec.annotations[0], Annotation::Arrow { stroke, .. } if stroke == 4.0));
+
+        let mut img = canvas();
+        spec.apply(&mut img).unwrap();
+        assert_eq!(*img.get_pixel(50, 10), RED);
+        // Outline drawn, inside left alone
+        assert_eq!(*img.get_pixel(10, 40), Rgba([0, 0, 255, 255]));
+        assert_eq!(*img.get_pixel(30, 40), WHITE);
+    }
+
+    #[test]
+    fn test_json_spec_rejects_unknown_type() {
+        let spec = AnnotationSpec::from_json(r#"{ "annotations": [ { "type": "step", "x": 20, "y": 20 } ] }"#).unwrap();
+        assert!(matches!(spec.annotations[0], Annotation::Step { size: 28, number: None, .. }));
+
+        assert!(AnnotationSpec::from_json(r#"{ "annotations": [ { "type": "star" } ] }"#).is_err());
+    }
+
+    #[test]
+    fn test_arrow_head_is_wider_than_shaft() {
+        let mut img = canvas();
+        arrow(&mut img, (10.0, 50.0), (90.0, 50.0), 2.0, RED);
+
+        assert_eq!(*img.get_pixel(30, 50), RED);
+        assert_eq!(*img.get_pixel(30, 54), WHITE);
+        assert_eq!(*img.get_pixel(82, 52), RED);
+        assert_eq!(*img.get_pixel(95, 50), WHITE);
+    }
+
+    #[test]
+    fn test_filled_ellipse() {
+        let spec = AnnotationSpec {
+            annotations: vec![Annotation::Ellipse {
+                x: 10.0,
+                y: 10.0,
+                width: 80.0,
+                height: 40.0,
+                color: None,
+                stroke: 2.0,
+                fill: Some("#00ff00".to_string()),
+            }],
+            ..Default::default()
+        };
+        let mut img = canvas();
+        spec.apply(&mut img).unwrap();
+
+        assert_eq!(*img.get_pixel(50, 30), Rgba([0, 255, 0, 255]));
+        assert_eq!(*img.get_pixel(50, 10), RED);
+        // Box corners are outside the ellipse
+        assert_eq!(*img.get_pixel(12, 12), WHITE);
+    }
+
+    #[test]
+    fn test_highlight_is_translucent() {
+        let spec = AnnotationSpec::from_json(r#"{ "annotations": [ { "type": "highlight", "points": [[10, 50], [90, 50]] } ] }"#).unwrap();
+        let mut img = RgbaImage::from_pixel(100, 100, Rgba([0, 0, 0, 255]));
+        spec.apply(&mut img).unwrap();
+
+        let p = img.get_pixel(50, 50);
+        assert!(p[0] > 100 && p[0] < 200, "highlight was {:?}", p);
+        assert_eq!(*img.get_pixel(50, 80), Rgba([0, 0, 0, 255]));
+    }
+
+    #[test]
+    fn test_steps_number_themselves() {
+        let spec = AnnotationSpec::from_json(
+            r#"{ "annotations": [
+                { "type": "step", "x": 20, "y": 20 },
+                { "type": "step", "x": 60, "y": 20, "number": 5 },
+                { "type": "step", "x": 20, "y": 60 }
+            ] }"#,
+        )
+        .unwrap();
+
+        // Render each badge alone and compare with the expected number
+        let mut img = canvas();
+        spec.apply(&mut img).unwrap();
+        for (center, number) in [((20.0, 20.0), 1), ((60.0, 20.0), 5), ((20.0, 60.0), 6)] {
+            let mut expected = canvas();
+            step(&mut expected, center, number, 28, RED);
+            let (x, y) = (center.0 as u32 - 14, center.1 as u32 - 14);
+            for dy in 0..28 {
+                for dx in 0..28 {
+                    assert_eq!(img.get_pixel(x + dx, y + dy), expected.get_pixel(x + dx, y + dy));
+                }
+            }
+        }
+        // White digits on the red badge
+        assert_eq!(contrasting(RED), WHITE);
+    }
+
+    #[test]
+    fn test_text_with_background() {
+        let mut img = canvas();
+        label(&mut img, (20.0, 20.0), "Hi", 18, WHITE, Some(Rgba([0, 0, 0, 255])));
+
+        // Padding around the text is filled, the text itself is white
+        assert_eq!(*img.get_pixel(16, 30), Rgba([0, 0, 0, 255]));
+        assert_eq!(*img.get_pixel(20, 20), WHITE);
+        assert_eq!(*img.get_pixel(80, 80), WHITE);
+        assert_eq!(img.get_pixel(22, 24)[0], 0);
+    }
+
+    #[test]
+    fn test_invalid_values() {
+        let mut img = canvas();
+        let bad_color = AnnotationSpec::from_json(r#"{ "color": "red", "annotations": [] }"#).unwrap();
+        assert!(matches!(bad_color.apply(&mut img), Err(ImageOpsError::InvalidColor(_))));
+
+        let zero_stroke = AnnotationSpec::from_json(r#"{ "annotations": [ { "type": "arrow", "from": [0, 0], "to": [5, 5], "stroke": 0 } ] }"#).unwrap();
+        assert!(matches!(zero_stroke.apply(&mut img), Err(ImageOpsError::InvalidSpec(_))));
+    }
+}
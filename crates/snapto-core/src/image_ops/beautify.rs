@@ -0,0 +1,187 @@
+//! "Beautify" framing for marketing and docs screenshots
+//!
+//! Places the screenshot on a larger canvas with a solid, gradient or image
+//! background, rounds its corners and drops a soft shadow under it. The
+//! shadow comes straight from the rounded rectangle's distance field, so it
+//! costs the same at any blur radius.
+
+use image::imageops::{self, FilterType};
+use image::{Rgba, RgbaImage};
+
+use super::draw::{self, Point};
+use super::{parse_color, ImageOpsError};
+use crate::config::{BeautifyBackground, BeautifyPreset};
+
+/// Returns `img` framed as described by `preset`
+pub fn beautify(img: &RgbaImage, preset: &BeautifyPreset) -> Result<RgbaImage, ImageOpsError> {
+    let shadow_color = parse_color(&preset.shadow_color)?;
+    let padding = preset.padding;
+    let width = img.width() + padding * 2;
+    let height = img.height() + padding * 2;
+
+    let mut canvas = background(&preset.background, width, height)?;
+
+    let min: Point = (padding as f32, padding as f32);
+    let max: Point = ((padding + img.width()) as f32, (padding + img.height()) as f32);
+    let radius = preset.corner_radius as f32;
+
+    if preset.shadow_blur > 0 && shadow_color[3] > 0 {
+        let blur = preset.shadow_blur as f32;
+        let offset = (preset.shadow_offset[0] as f32, preset.shadow_offset[1] as f32);
+        let shadow_min = (min.0 + offset.0, min.1 + offset.1);
+        let shadow_max = (max.0 + offset.0, max.1 + offset.1);
+        let grow = blur + 1.0;
+
+        draw::fill_with(
+            &mut canvas,
+            (shadow_min.0 - grow, shadow_min.1 - grow),
+            (shadow_max.0 + grow, shadow_max.1 + grow),
+            shadow_color,
+            |p| soft_edge(draw::rounded_rect_distance(p, shadow_min, shadow_max, radius), blur),
+        );
+    }
+
+    // The screenshot itself, with antialiased rounded corners
+    for (x, y, pixel) in img.enumerate_pixels() {
+        let (cx, cy) = (x + padding, y + padding);
+        let center = (cx as f32 + 0.5, cy as f32 + 0.5);
+        let coverage = draw::coverage(draw::rounded_rect_distance(center, min, max, radius));
+        draw::blend(&mut canvas, cx as i32, cy as i32, *pixel, coverage);
+    }
+
+    Ok(canvas)
+}
+
+/// Shadow opacity at `distance` outside an edge blurred over `blur` pixels
+fn soft_edge(distance: f32, blur: f32) -> f32 {
+    let t = (0.5 - distance / blur).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// Canvas of `width` x `height` filled with the background
+fn background(background: &BeautifyBackground, width: u32, height: u32) -> Result<RgbaImage, ImageOpsError> {
+    match background {
+        BeautifyBackground::Solid { color } => Ok(RgbaImage::from_pixel(width, height, parse_color(color)?)),
+        BeautifyBackground::Gradient { from, to, angle } => {
+            let (from, to) = (parse_color(from)?, parse_color(to)?);
+            Ok(gradient(width, height, from, to, *angle))
+        }
+        BeautifyBackground::Image { path } => {
+            let expanded = shellexpand::tilde(path).to_string();
+            let img = image::open(&expanded)
+                .map_err(|e| ImageOpsError::Decode(format!("{}: {}", expanded, e)))?
+                .to_rgba8();
+            // Scale to cover the canvas, then crop the overflow evenly
+            let scale = (width as f64 / img.width() as f64).max(height as f64 / img.height() as f64);
+            let scaled_width = ((img.width() as f64 * scale).ceil() as u32).max(width);
+            let scaled_height = ((img.height() as f64 * scale).ceil() as u32).max(height);
+            let scaled = imageops::resize(&img, scaled_width, scaled_height, FilterType::Triangle);
+            let x = (scaled_width - width) / 2;
+            let y = (scaled_height - height) / 2;
+            Ok(imageops::crop_imm(&scaled, x, y, width, height).to_image())
+        }
+    }
+}
+
+/// Linear gradient; `angle` in degrees, 0 runs left to right and 90 top to
+/// bottom
+fn gradient(width: u32, height: u32, from: Rgba<u8>, to: Rgba<u8>, angle: f32) -> RgbaImage {
+    let (sin, cos) = angle.to_radians().sin_cos();
+    let (cx, cy) = (width as f32 / 2.0, height as f32 / 2.0);
+    // Projection of a corner onto the direction: the gradient spans exactly
+    // from one corner to the opposite one
+    let half_length = (cx * cos.abs() + cy * sin.abs()).max(f32::EPSILON);
+
+    RgbaImage::from_fn(width, height, |x, y| {
+        let projected = (x as f32 + 0.5 - cx) * cos + (y as f32 + 0.5 - cy) * sin;
+        let t = ((projected / half_length + 1.0) / 2.0).clamp(0.0, 1.0);
+        let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+        Rgba([mix(from[0], to[0]), mix(from[1], to[1]), mix(from[2], to[2]), mix(from[3], to[3])])
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GRAY: Rgba<u8> = Rgba([128, 128, 128, 255]);
+
+    fn solid(color: &str) -> BeautifyPreset {
+        BeautifyPreset {
+            padding: 20,
+            corner_radius: 8,
+            shadow_blur: 0,
+            background: BeautifyBackground::Solid { color: color.to_string() },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_padding_and_rounded_corners() {
+        let img = RgbaImage::from_pixel(60, 40, GRAY);
+        let framed = beautify(&img, &solid("#ffffff")).unwrap();
+
+        assert_eq!(framed.dimensions(), (100, 80));
+        assert_eq!(*framed.get_pixel(50, 40), GRAY);
+        assert_eq!(*framed.get_pixel(5, 5), Rgba([255, 255, 255, 255]));
+        // The corner pixel of the screenshot is cut off by the radius
+        assert_eq!(*framed.get_pixel(20, 20), Rgba([255, 255, 255, 255]));
+        assert_eq!(*framed.get_pixel(28, 20), GRAY);
+    }
+
+    #[test]
+    fn test_shadow_falls_below_the_screenshot() {
+        let img = RgbaImage::from_pixel(60, 40, GRAY);
+        let preset = BeautifyPreset {
+            shadow_blur: 10,
+            shadow_offset: [0, 8],
+            shadow_color: "#000000ff".to_string(),
+            ..solid("#ffffff")
+        };
+        let framed = beautify(&img, &preset).unwrap();
+
+        // Darker just under the bottom edge than just above the top edge
+        let below = framed.get_pixel(50, 62)[0];
+        let above = framed.get_pixel(50, 17)[0];
+        assert!(below < above, "below {} above {}", below, above);
+        assert!(below < 200);
+        // Fades out away from the screenshot
+        assert_eq!(*framed.get_pixel(2, 2), Rgba([255, 255, 255, 255]));
+    }
+
+    #[test]
+    fn test_gradient_runs_corner_to_corner() {
+        let black = Rgba([0, 0, 0, 255]);
+        let white = Rgba([255, 255, 255, 255]);
+
+        let horizontal = gradient(100, 10, black, white, 0.0);
+        assert!(horizontal.get_pixel(0, 5)[0] < 5);
+        assert!(horizontal.get_pixel(99, 5)[0] > 250);
+        assert_eq!(horizontal.get_pixel(50, 0), horizontal.get_pixel(50, 9));
+
+        let vertical = gradient(10, 100, black, white, 90.0);
+        assert!(vertical.get_pixel(5, 0)[0] < 5 && vertical.get_pixel(5, 99)[0] > 250);
+    }
+
+    #[test]
+    fn test_image_background_covers_canvas() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bg.png");
+        RgbaImage::from_pixel(4, 4, Rgba([0, 0, 255, 255])).save(&path).unwrap();
+
+        let preset = BeautifyPreset {
+            background: BeautifyBackground::Image { path: path.to_string_lossy().into_owned() },
+            ..solid("#ffffff")
+        };
+        let framed = beautify(&RgbaImage::from_pixel(30, 10, GRAY), &preset).unwrap();
+        assert_eq!(framed.dimensions(), (70, 50));
+        assert_eq!(*framed.get_pixel(0, 0), Rgba([0, 0, 255, 255]));
+        assert_eq!(*framed.get_pixel(69, 49), Rgba([0, 0, 255, 255]));
+    }
+
+    #[test]
+    fn test_invalid_color() {
+        let img = RgbaImage::new(4, 4);
+        assert!(matches!(beautify(&img, &solid("white")), Err(ImageOpsError::InvalidColor(_))));
+    }
+}
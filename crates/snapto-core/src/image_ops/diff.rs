@@ -0,0 +1,343 @@
+//! Visual diff between two screenshots
+//!
+//! Pixels whose channels differ by more than the threshold count as changed.
+//! Changes close to each other are grouped into regions by flood-filling a
+//! coarse grid of `merge_distance` cells, which keeps the cost linear in the
+//! image size; regions with only a few changed pixels are dropped as noise.
+//! The overlay shows the new image washed out, changed pixels tinted and a
+//! box around every region.
+
+use image::{DynamicImage, Rgba, RgbaImage};
+use serde::Serialize;
+
+use super::draw::{self, Point};
+use super::{parse_color, ImageOpsError};
+use crate::config::DiffConfig;
+use crate::screenshot::{encode_image, ImageFormat, ScreenshotConfig};
+
+/// Bounding box of a group of changed pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ChangedRegion {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    /// Changed pixels inside the box
+    pub pixels: u64,
+}
+
+/// What changed between two images
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DiffReport {
+    /// Size of the compared area: the larger of both images
+    pub width: u32,
+    pub height: u32,
+    /// Changed pixels, not counting regions dropped as noise
+    pub changed_pixels: u64,
+    /// Share of unchanged pixels, from 0 to 1
+    pub similarity: f64,
+    /// Changed regions, top to bottom and left to right
+    pub regions: Vec<ChangedRegion>,
+}
+
+impl DiffReport {
+    /// Whether the images count as identical
+    pub fn is_identical(&self) -> bool {
+        self.regions.is_empty()
+    }
+}
+
+/// Compares `old` with `new`, returning the report and the overlay image
+///
+/// Images of different sizes are compared over the larger area; whatever
+/// only one of them covers counts as changed.
+pub fn diff(old: &RgbaImage, new: &RgbaImage, config: &DiffConfig) -> Result<(DiffReport, RgbaImage), ImageOpsError> {
+    let tint = parse_color(&config.tint)?;
+    let box_color = parse_color(&config.box_color)?;
+
+    let width = old.width().max(new.width());
+    let height = old.height().max(new.height());
+    let mut changed = changed_mask(old, new, width, height, config.threshold);
+
+    let regions = find_regions(&mut changed, width, height, config);
+    let changed_pixels = regions.iter().map(|r| r.pixels).sum::<u64>();
+    let total = width as u64 * height as u64;
+    let similarity = if total == 0 { 1.0 } else { 1.0 - changed_pixels as f64 / total as f64 };
+
+    let overlay = overlay(new, old, &changed, &regions, tint, box_color);
+    let report = DiffReport {
+        width,
+        height,
+        changed_pixels,
+        similarity,
+        regions,
+    };
+    Ok((report, overlay))
+}
+
+/// Decodes both images, compares them and encodes the overlay as `format`
+pub fn diff_encoded(
+    old: &[u8],
+    new: &[u8],
+    config: &DiffConfig,
+    format: ImageFormat,
+) -> Result<(DiffReport, Vec<u8>), ImageOpsError> {
+    let decode = |data: &[u8]| {
+        image::load_from_memory(data)
+            .map(|img| img.to_rgba8())
+            .map_err(|e| ImageOpsError::Decode(e.to_string()))
+    };
+    let (report, overlay) = diff(&decode(old)?, &decode(new)?, config)?;
+
+    let data = encode_image(&DynamicImage::ImageRgba8(overlay), format, ScreenshotConfig::default().quality)
+        .map_err(|e| ImageOpsError::Encode(e.to_string()))?;
+    Ok((report, data))
+}
+
+/// Row-major flags for every pixel of the compared area
+fn changed_mask(old: &RgbaImage, new: &RgbaImage, width: u32, height: u32, threshold: u8) -> Vec<bool> {
+    let mut changed = vec![false; width as usize * height as usize];
+    for y in 0..height {
+        for x in 0..width {
+            let inside = |img: &RgbaImage| x < img.width() && y < img.height();
+            changed[(y * width + x) as usize] = if inside(old) && inside(new) {
+                let (a, b) = (old.get_pixel(x, y), new.get_pixel(x, y));
+                (0..4).any(|c| a[c].abs_diff(b[c]) > threshold)
+            } else {
+                true
+            };
+        }
+    }
+    changed
+}
+
+/// Groups changed pixels into regions, clearing the ones dropped as noise
+/// from `changed`
+fn find_regions(changed: &mut [bool], width: u32, height: u32, config: &DiffConfig) -> Vec<ChangedRegion> {
+    // Two pixels closer than a cell are in the same or neighbouring cells, so
+    // 8-connected cells merge everything within `merge_distance`
+    let cell = config.merge_distance.max(1);
+    let columns = width.div_ceil(cell) as usize;
+    let rows = height.div_ceil(cell) as usize;
+
+    let mut marked = vec![false; columns * rows];
+    for (i, _) in changed.iter().enumerate().filter(|(_, &c)| c) {
+        let (x, y) = (i as u32 % width, i as u32 / width);
+        marked[(y / cell) as usize * columns + (x / cell) as usize] = true;
+    }
+
+    let mut regions = Vec::new();
+    let mut visited = vec![false; marked.len()];
+    for start in 0..marked.len() {
+        if !marked[start] || visited[start] {
+            continue;
+        }
+
+        let mut component = Vec::new();
+        let mut stack = vec![start];
+        visited[start] = true;
+        while let Some(index) = stack.pop() {
+            component.push(index);
+            let (cx, cy) = ((index % columns) as i64, (index / columns) as i64);
+            for (dx, dy) in [(-1, -1), (0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1), (1, 1)] {
+                let (nx, ny) = (cx + dx, cy + dy);
+                if nx < 0 || ny < 0 || nx >= columns as i64 || ny >= rows as i64 {
+                    continue;
+                }
+                let neighbour = ny as usize * columns + nx as usize;
+                if marked[neighbour] && !visited[neighbour] {
+                    visited[neighbour] = true;
+                    stack.push(neighbour);
+                }
+            }
+        }
+
+        let pixels_of = |index| cell_pixels(index, cell, columns, width, height);
+        let (mut min_x, mut min_y, mut max_x, mut max_y) = (u32::MAX, u32::MAX, 0, 0);
+        let mut pixels = 0u64;
+        for (x, y) in component.iter().flat_map(|&index| pixels_of(index)) {
+            if changed[(y * width + x) as usize] {
+                min_x = min_x.min(x);
+                min_y = min_y.min(y);
+                max_x = max_x.max(x);
+                max_y = max_y.max(y);
+                pixels += 1;
+            }
+        }
+
+        if pixels < config.min_region_pixels as u64 {
+            for (x, y) in component.iter().flat_map(|&index| pixels_of(index)) {
+                changed[(y * width + x) as usize] = false;
+            }
+            continue;
+        }
+
+        regions.push(ChangedRegion {
+            x: min_x,
+            y: min_y,
+            width: max_x - min_x + 1,
+            height: max_y - min_y + 1,
+            pixels,
+        });
+    }
+
+    regions.sort_by_key(|r| (r.y, r.x));
+    regions
+}
+
+/// Coordinates of the pixels in grid cell `index`
+fn cell_pixels(index: usize, cell: u32, columns: usize, width: u32, height: u32) -> impl Iterator<Item = (u32, u32)> {
+    let (x0, y0) = ((index % columns) as u32 * cell, (index / columns) as u32 * cell);
+    (y0..(y0 + cell).min(height)).flat_map(move |y| (x0..(x0 + cell).min(width)).map(move |x| (x, y)))
+}
+
+/// The new image washed out, changes tinted and regions boxed
+fn overlay(
+    new: &RgbaImage,
+    old: &RgbaImage,
+    changed: &[bool],
+    regions: &[ChangedRegion],
+    tint: Rgba<u8>,
+    box_color: Rgba<u8>,
+) -> RgbaImage {
+    let width = old.width().max(new.width());
+    let height = old.height().max(new.height());
+    let mut out = RgbaImage::from_fn(width, height, |x, y| {
+        let source = if x < new.width() && y < new.height() {
+            *new.get_pixel(x, y)
+        } else if x < old.width() && y < old.height() {
+            *old.get_pixel(x, y)
+        } else {
+            Rgba([255, 255, 255, 255])
+        };
+        // Gray, lightened and opaque so the tint stands out
+        let luma = 0.299 * source[0] as f32 + 0.587 * source[1] as f32 + 0.114 * source[2] as f32;
+        let alpha = source[3] as f32 / 255.0;
+        let gray = (255.0 - (255.0 - luma) * alpha * 0.5).round() as u8;
+        Rgba([gray, gray, gray, 255])
+    });
+
+    for (i, _) in changed.iter().enumerate().filter(|(_, &c)| c) {
+        let (x, y) = (i as u32 % width, i as u32 / width);
+        draw::blend(&mut out, x as i32, y as i32, tint, 1.0);
+    }
+
+    for region in regions {
+        // Just outside the changed pixels so the box does not hide them
+        let min: Point = (region.x as f32 - 2.0, region.y as f32 - 2.0);
+        let max: Point = ((region.x + region.width) as f32 + 2.0, (region.y + region.height) as f32 + 2.0);
+        let outline = [min, (max.0, min.1), max, (min.0, max.1), min];
+        draw::stroke(&mut out, &outline, 2.0, box_color);
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WHITE: Rgba<u8> = Rgba([255, 255, 255, 255]);
+    const BLACK: Rgba<u8> = Rgba([0, 0, 0, 255]);
+
+    fn with_square(img: &RgbaImage, x0: u32, y0: u32, size: u32) -> RgbaImage {
+        let mut img = img.clone();
+        for y in y0..y0 + size {
+            for x in x0..x0 + size {
+                img.put_pixel(x, y, BLACK);
+            }
+        }
+        img
+    }
+
+    #[test]
+    fn test_identical_images() {
+        let img = RgbaImage::from_pixel(40, 30, WHITE);
+        let (report, overlay) = diff(&img, &img, &DiffConfig::default()).unwrap();
+
+        assert!(report.is_identical());
+        assert_eq!(report.similarity, 1.0);
+        assert_eq!(overlay.dimensions(), (40, 30));
+    }
+
+    #[test]
+    fn test_regions_and_similarity() {
+        let old = RgbaImage::from_pixel(100, 100, WHITE);
+        let new = with_square(&with_square(&old, 10, 10, 5), 70, 60, 10);
+        let (report, overlay) = diff(&old, &new, &DiffConfig::default()).unwrap();
+
+        assert_eq!(report.changed_pixels, 125);
+        assert!((report.similarity - 0.9875).abs() < 1e-9);
+        assert_eq!(
+            report.regions,
+            vec![
+                ChangedRegion { x: 10, y: 10, width: 5, height: 5, pixels: 25 },
+                ChangedRegion { x: 70, y: 60, width: 10, height: 10, pixels: 100 },
+            ]
+        );
+
+        // Changed pixels are tinted, unchanged ones washed out to gray
+        let tinted = overlay.get_pixel(75, 65);
+        assert!(tinted[0] > tinted[1] && tinted[2] > tinted[1]);
+        assert_eq!(*overlay.get_pixel(40, 40), WHITE);
+    }
+
+    #[test]
+    fn test_nearby_changes_merge() {
+        let old = RgbaImage::from_pixel(100, 40, WHITE);
+        let new = with_square(&with_square(&old, 10, 10, 4), 24, 10, 4);
+
+        let (merged, _) = diff(&old, &new, &DiffConfig::default()).unwrap();
+        assert_eq!(merged.regions.len(), 1);
+        assert_eq!((merged.regions[0].x, merged.regions[0].width), (10, 18));
+
+        let config = DiffConfig { merge_distance: 4, ..Default::default() };
+        let (separate, _) = diff(&old, &new, &config).unwrap();
+        assert_eq!(separate.regions.len(), 2);
+    }
+
+    #[test]
+    fn test_threshold_and_noise() {
+        let old = RgbaImage::from_pixel(50, 50, WHITE);
+        let mut new = old.clone();
+        // A faint shift everywhere in one area, and a single stray pixel
+        for x in 0..10 {
+            new.put_pixel(x, 0, Rgba([250, 250, 250, 255]));
+        }
+        new.put_pixel(40, 40, BLACK);
+
+        let (report, _) = diff(&old, &new, &DiffConfig::default()).unwrap();
+        assert!(report.is_identical());
+        assert_eq!(report.changed_pixels, 0);
+
+        let strict = DiffConfig { threshold: 0, min_region_pixels: 1, ..Default::default() };
+        let (report, _) = diff(&old, &new, &strict).unwrap();
+        assert_eq!(report.regions.len(), 2);
+        assert_eq!(report.changed_pixels, 11);
+    }
+
+    #[test]
+    fn test_different_sizes() {
+        let old = RgbaImage::from_pixel(20, 10, WHITE);
+        let new = RgbaImage::from_pixel(20, 20, WHITE);
+        let (report, _) = diff(&old, &new, &DiffConfig::default()).unwrap();
+
+        assert_eq!((report.width, report.height), (20, 20));
+        assert_eq!(report.regions, vec![ChangedRegion { x: 0, y: 10, width: 20, height: 10, pixels: 200 }]);
+        assert!((report.similarity - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_diff_encoded() {
+        let encode = |img: &RgbaImage| encode_image(&DynamicImage::ImageRgba8(img.clone()), ImageFormat::Png, 90).unwrap();
+        let old = RgbaImage::from_pixel(30, 30, WHITE);
+        let new = with_square(&old, 5, 5, 6);
+
+        let (report, data) = diff_encoded(&encode(&old), &encode(&new), &DiffConfig::default(), ImageFormat::Png).unwrap();
+        assert_eq!(report.regions.len(), 1);
+        assert_eq!(image::load_from_memory(&data).unwrap().width(), 30);
+
+        let bad = DiffConfig { tint: "pink".to_string(), ..Default::default() };
+        assert!(matches!(diff(&old, &new, &bad), Err(ImageOpsError::InvalidColor(_))));
+    }
+}
@@ -0,0 +1,203 @@
+//! Antialiased drawing primitives
+//!
+//! Shapes are rendered from signed distances: each pixel in a shape's
+//! bounding box gets a coverage between 0 and 1 and is blended once, so
+//! semi-transparent strokes do not darken where segments overlap.
+
+use image::{Rgba, RgbaImage};
+
+/// A point in image coordinates
+pub type Point = (f32, f32);
+
+/// Blends `color` over the pixel at (`x`, `y`) with extra `coverage`
+pub fn blend(img: &mut RgbaImage, x: i32, y: i32, color: Rgba<u8>, coverage: f32) {
+    if x < 0 || y < 0 || x as u32 >= img.width() || y as u32 >= img.height() {
+        return;
+    }
+    let alpha = color[3] as f32 / 255.0 * coverage.clamp(0.0, 1.0);
+    if alpha <= 0.0 {
+        return;
+    }
+
+    let pixel = img.get_pixel_mut(x as u32, y as u32);
+    let base_alpha = pixel[3] as f32 / 255.0;
+    let out_alpha = alpha + base_alpha * (1.0 - alpha);
+
+    for i in 0..3 {
+        let value = (color[i] as f32 * alpha + pixel[i] as f32 * base_alpha * (1.0 - alpha)) / out_alpha;
+        pixel[i] = value.round() as u8;
+    }
+    pixel[3] = (out_alpha * 255.0).round() as u8;
+}
+
+/// Blends `color` wherever `coverage` is positive inside the bounding box
+/// `min`..`max`
+pub fn fill_with<F>(img: &mut RgbaImage, min: Point, max: Point, color: Rgba<u8>, coverage: F)
+where
+    F: Fn(Point) -> f32,
+{
+    let x0 = min.0.floor().max(0.0) as i32;
+    let y0 = min.1.floor().max(0.0) as i32;
+    let x1 = (max.0.ceil() as i32).min(img.width() as i32 - 1);
+    let y1 = (max.1.ceil() as i32).min(img.height() as i32 - 1);
+
+    for y in y0..=y1 {
+        for x in x0..=x1 {
+            // Sample at the pixel center
+            let c = coverage((x as f32 + 0.5, y as f32 + 0.5));
+            if c > 0.0 {
+                blend(img, x, y, color, c);
+            }
+        }
+    }
+}
+
+/// Coverage of a pixel whose center is `distance` outside an edge
+pub fn coverage(distance: f32) -> f32 {
+    (0.5 - distance).clamp(0.0, 1.0)
+}
+
+/// Distance from `p` to the segment `a`-`b`
+pub fn segment_distance(p: Point, a: Point, b: Point) -> f32 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let length_sq = dx * dx + dy * dy;
+    let t = if length_sq == 0.0 {
+        0.0
+    } else {
+        (((p.0 - a.0) * dx + (p.1 - a.1) * dy) / length_sq).clamp(0.0, 1.0)
+    };
+    let (cx, cy) = (a.0 + t * dx, a.1 + t * dy);
+    ((p.0 - cx).powi(2) + (p.1 - cy).powi(2)).sqrt()
+}
+
+/// Strokes a polyline with round joins and caps
+pub fn stroke(img: &mut RgbaImage, points: &[Point], width: f32, color: Rgba<u8>) {
+    if points.is_empty() {
+        return;
+    }
+    let half = width / 2.0;
+    let (min, max) = bounds(points, half + 1.0);
+    let segments: Vec<(Point, Point)> = if points.len() == 1 {
+        vec![(points[0], points[0])]
+    } else {
+        points.windows(2).map(|w| (w[0], w[1])).collect()
+    };
+
+    fill_with(img, min, max, color, |p| {
+        let distance = segments
+            .iter()
+            .map(|&(a, b)| segment_distance(p, a, b))
+            .fold(f32::INFINITY, f32::min);
+        coverage(distance - half)
+    });
+}
+
+/// Fills a convex polygon given in either winding order
+pub fn fill_polygon(img: &mut RgbaImage, points: &[Point], color: Rgba<u8>) {
+    if points.len() < 3 {
+        return;
+    }
+    let (min, max) = bounds(points, 1.0);
+    let area: f32 = points
+        .iter()
+        .zip(points.iter().cycle().skip(1))
+        .map(|(a, b)| a.0 * b.1 - b.0 * a.1)
+        .sum();
+    let sign = if area < 0.0 { -1.0 } else { 1.0 };
+
+    fill_with(img, min, max, color, |p| {
+        // Largest signed distance outside any edge
+        let outside = points
+            .iter()
+            .zip(points.iter().cycle().skip(1))
+            .map(|(a, b)| {
+                let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+                let length = (dx * dx + dy * dy).sqrt().max(f32::EPSILON);
+                -sign * (dx * (p.1 - a.1) - dy * (p.0 - a.0)) / length
+            })
+            .fold(f32::NEG_INFINITY, f32::max);
+        coverage(outside)
+    });
+}
+
+/// Signed distance from `p` to a rectangle with rounded corners
+/// (negative inside)
+pub fn rounded_rect_distance(p: Point, min: Point, max: Point, radius: f32) -> f32 {
+    let half = ((max.0 - min.0) / 2.0, (max.1 - min.1) / 2.0);
+    let radius = radius.min(half.0).min(half.1).max(0.0);
+    let center = (min.0 + half.0, min.1 + half.1);
+
+    let qx = (p.0 - center.0).abs() - half.0 + radius;
+    let qy = (p.1 - center.1).abs() - half.1 + radius;
+    let outside = (qx.max(0.0).powi(2) + qy.max(0.0).powi(2)).sqrt();
+    outside + qx.max(qy).min(0.0) - radius
+}
+
+/// Approximate signed distance from `p` to an axis-aligned ellipse
+pub fn ellipse_distance(p: Point, center: Point, radii: Point) -> f32 {
+    let (rx, ry) = (radii.0.max(0.5), radii.1.max(0.5));
+    let (nx, ny) = ((p.0 - center.0) / rx, (p.1 - center.1) / ry);
+    let k = (nx * nx + ny * ny).sqrt();
+    if k == 0.0 {
+        return -rx.min(ry);
+    }
+    // First-order correction by the gradient length
+    let gradient = ((nx / rx).powi(2) + (ny / ry).powi(2)).sqrt();
+    (k - 1.0) * k / gradient.max(f32::EPSILON)
+}
+
+/// Bounding box of `points` grown by `margin`
+fn bounds(points: &[Point], margin: f32) -> (Point, Point) {
+    let min = points.iter().fold((f32::INFINITY, f32::INFINITY), |m, p| (m.0.min(p.0), m.1.min(p.1)));
+    let max = points.iter().fold((f32::NEG_INFINITY, f32::NEG_INFINITY), |m, p| (m.0.max(p.0), m.1.max(p.1)));
+    ((min.0 - margin, min.1 - margin), (max.0 + margin, max.1 + margin))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: Rgba<u8> = Rgba([255, 0, 0, 255]);
+
+    #[test]
+    fn test_blend_half_transparent() {
+        let mut img = RgbaImage::from_pixel(1, 1, Rgba([0, 0, 255, 255]));
+        blend(&mut img, 0, 0, Rgba([255, 0, 0, 128]), 1.0);
+        let p = img.get_pixel(0, 0);
+        assert!((126..=130).contains(&p[0]) && (125..=129).contains(&p[2]));
+        assert_eq!(p[3], 255);
+    }
+
+    #[test]
+    fn test_stroke_does_not_double_blend_joints() {
+        let mut img = RgbaImage::from_pixel(40, 40, Rgba([255, 255, 255, 255]));
+        let yellow = Rgba([255, 255, 0, 128]);
+        stroke(&mut img, &[(5.0, 20.0), (20.0, 20.0), (35.0, 20.0)], 6.0, yellow);
+
+        // The joint looks like the rest of the line
+        assert_eq!(img.get_pixel(20, 20), img.get_pixel(10, 20));
+        assert_eq!(*img.get_pixel(20, 5), Rgba([255, 255, 255, 255]));
+    }
+
+    #[test]
+    fn test_fill_polygon_either_winding() {
+        for triangle in [
+            [(0.0, 0.0), (20.0, 0.0), (0.0, 20.0)],
+            [(0.0, 0.0), (0.0, 20.0), (20.0, 0.0)],
+        ] {
+            let mut img = RgbaImage::new(20, 20);
+            fill_polygon(&mut img, &triangle, RED);
+            assert_eq!(*img.get_pixel(3, 3), RED);
+            assert_eq!(img.get_pixel(18, 18)[3], 0);
+        }
+    }
+
+    #[test]
+    fn test_distances() {
+        assert!(rounded_rect_distance((5.0, 5.0), (0.0, 0.0), (10.0, 10.0), 2.0) < 0.0);
+        // The corner is cut off by the radius
+        assert!(rounded_rect_distance((0.2, 0.2), (0.0, 0.0), (10.0, 10.0), 4.0) > 0.0);
+        assert!((ellipse_distance((15.0, 0.0), (0.0, 0.0), (10.0, 5.0)) - 5.0).abs() < 0.5);
+        assert!(ellipse_distance((0.0, 0.0), (0.0, 0.0), (10.0, 5.0)) < 0.0);
+    }
+}
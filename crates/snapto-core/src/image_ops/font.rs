@@ -0,0 +1,205 @@
+//! Bundled bitmap font for labels, badges and watermarks
+//!
+//! The classic 5x7 ASCII font, drawn at whole-pixel scales so it stays
+//! crisp at any size and needs no font files or rasterizer. Besides
+//! printable ASCII it has `©` for copyright notices; accented Latin-1
+//! letters are drawn as their base letter and anything else as `?`.
+
+use image::{Rgba, RgbaImage};
+
+use super::draw::blend;
+
+/// Glyph width in font pixels, without spacing
+const GLYPH_WIDTH: u32 = 5;
+/// Glyph height in font pixels
+const GLYPH_HEIGHT: u32 = 7;
+/// Advance per character and per line, in font pixels
+const ADVANCE: u32 = GLYPH_WIDTH + 1;
+const LINE_HEIGHT: u32 = GLYPH_HEIGHT + 2;
+
+/// Columns of each glyph from ' ' to '~', bit 0 at the top
+const GLYPHS: [[u8; 5]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00], // ' '
+    [0x00, 0x00, 0x5F, 0x00, 0x00], // !
+    [0x00, 0x07, 0x00, 0x07, 0x00], // "
+    [0x14, 0x7F, 0x14, 0x7F, 0x14], // #
+    [0x24, 0x2A, 0x7F, 0x2A, 0x12], // $
+    [0x23, 0x13, 0x08, 0x64, 0x62], // %
+    [0x36, 0x49, 0x56, 0x20, 0x50], // &
+    [0x00, 0x05, 0x03, 0x00, 0x00], // '
+    [0x00, 0x1C, 0x22, 0x41, 0x00], // (
+    [0x00, 0x41, 0x22, 0x1C, 0x00], // )
+    [0x14, 0x08, 0x3E, 0x08, 0x14], // *
+    [0x08, 0x08, 0x3E, 0x08, 0x08], // +
+    [0x00, 0x50, 0x30, 0x00, 0x00], // ,
+    [0x08, 0x08, 0x08, 0x08, 0x08], // -
+    [0x00, 0x60, 0x60, 0x00, 0x00], // .
+    [0x20, 0x10, 0x08, 0x04, 0x02], // /
+    [0x3E, 0x51, 0x49, 0x45, 0x3E], // 0
+    [0x00, 0x42, 0x7F, 0x40, 0x00], // 1
+    [0x42, 0x61, 0x51, 0x49, 0x46], // 2
+    [0x21, 0x41, 0x45, 0x4B, 0x31], // 3
+    [0x18, 0x14, 0x12, 0x7F, 0x10], // 4
+    [0x27, 0x45, 0x45, 0x45, 0x39], // 5
+    [0x3C, 0x4A, 0x49, 0x49, 0x30], // 6
+    [0x01, 0x71, 0x09, 0x05, 0x03], // 7
+    [0x36, 0x49, 0x49, 0x49, 0x36], // 8
+    [0x06, 0x49, 0x49, 0x29, 0x1E], // 9
+    [0x00, 0x36, 0x36, 0x00, 0x00], // :
+    [0x00, 0x56, 0x36, 0x00, 0x00], // ;
+    [0x08, 0x14, 0x22, 0x41, 0x00], // <
+    [0x14, 0x14, 0x14, 0x14, 0x14], // =
+    [0x00, 0x41, 0x22, 0x14, 0x08], // >
+    [0x02, 0x01, 0x51, 0x09, 0x06], // ?
+    [0x32, 0x49, 0x79, 0x41, 0x3E], // @
+    [0x7E, 0x11, 0x11, 0x11, 0x7E], // A
+    [0x7F, 0x49, 0x49, 0x49, 0x36], // B
+    [0x3E, 0x41, 0x41, 0x41, 0x22], // C
+    [0x7F, 0x41, 0x41, 0x22, 0x1C], // D
+    [0x7F, 0x49, 0x49, 0x49, 0x41], // E
+    [0x7F, 0x09, 0x09, 0x09, 0x01], // F
+    [0x3E, 0x41, 0x49, 0x49, 0x7A], // G
+    [0x7F, 0x08, 0x08, 0x08, 0x7F], // H
+    [0x00, 0x41, 0x7F, 0x41, 0x00], // I
+    [0x20, 0x40, 0x41, 0x3F, 0x01], // J
+    [0x7F, 0x08, 0x14, 0x22, 0x41], // K
+    [0x7F, 0x40, 0x40, 0x40, 0x40], // L
+    [0x7F, 0x02, 0x0C, 0x02, 0x7F], // M
+    [0x7F, 0x04, 0x08, 0x10, 0x7F], // N
+    [0x3E, 0x41, 0x41, 0x41, 0x3E], // O
+    [0x7F, 0x09, 0x09, 0x09, 0x06], // P
+    [0x3E, 0x41, 0x51, 0x21, 0x5E], // Q
+    [0x7F, 0x09, 0x19, 0x29, 0x46], // R
+    [0x46, 0x49, 0x49, 0x49, 0x31], // S
+    [0x01, 0x01, 0x7F, 0x01, 0x01], // T
+    [0x3F, 0x40, 0x40, 0x40, 0x3F], // U
+    [0x1F, 0x20, 0x40, 0x20, 0x1F], // V
+    [0x3F, 0x40, 0x38, 0x40, 0x3F], // W
+    [0x63, 0x14, 0x08, 0x14, 0x63], // X
+    [0x07, 0x08, 0x70, 0x08, 0x07], // Y
+    [0x61, 0x51, 0x49, 0x45, 0x43], // Z
+    [0x00, 0x7F, 0x41, 0x41, 0x00], // [
+    [0x02, 0x04, 0x08, 0x10, 0x20], // \
+    [0x00, 0x41, 0x41, 0x7F, 0x00], // ]
+    [0x04, 0x02, 0x01, 0x02, 0x04], // ^
+    [0x40, 0x40, 0x40, 0x40, 0x40], // _
+    [0x00, 0x01, 0x02, 0x04, 0x00], // `
+    [0x20, 0x54, 0x54, 0x54, 0x78], // a
+    [0x7F, 0x48, 0x44, 0x44, 0x38], // b
+    [0x38, 0x44, 0x44, 0x44, 0x20], // c
+    [0x38, 0x44, 0x44, 0x48, 0x7F], // d
+    [0x38, 0x54, 0x54, 0x54, 0x18], // e
+    [0x08, 0x7E, 0x09, 0x01, 0x02], // f
+    [0x0C, 0x52, 0x52, 0x52, 0x3E], // g
+    [0x7F, 0x08, 0x04, 0x04, 0x78], // h
+    [0x00, 0x44, 0x7D, 0x40, 0x00], // i
+    [0x20, 0x40, 0x44, 0x3D, 0x00], // j
+    [0x7F, 0x10, 0x28, 0x44, 0x00], // k
+    [0x00, 0x41, 0x7F, 0x40, 0x00], // l
+    [0x7C, 0x04, 0x18, 0x04, 0x78], // m
+    [0x7C, 0x08, 0x04, 0x04, 0x78], // n
+    [0x38, 0x44, 0x44, 0x44, 0x38], // o
+    [0x7C, 0x14, 0x14, 0x14, 0x08], // p
+    [0x08, 0x14, 0x14, 0x18, 0x7C], // q
+    [0x7C, 0x08, 0x04, 0x04, 0x08], // r
+    [0x48, 0x54, 0x54, 0x54, 0x20], // s
+    [0x04, 0x3F, 0x44, 0x40, 0x20], // t
+    [0x3C, 0x40, 0x40, 0x20, 0x7C], // u
+    [0x1C, 0x20, 0x40, 0x20, 0x1C], // v
+    [0x3C, 0x40, 0x30, 0x40, 0x3C], // w
+    [0x44, 0x28, 0x10, 0x28, 0x44], // x
+    [0x0C, 0x50, 0x50, 0x50, 0x3C], // y
+    [0x44, 0x64, 0x54, 0x4C, 0x44], // z
+    [0x00, 0x08, 0x36, 0x41, 0x00], // {
+    [0x00, 0x00, 0x7F, 0x00, 0x00], // |
+    [0x00, 0x41, 0x36, 0x08, 0x00], // }
+    [0x08, 0x04, 0x08, 0x10, 0x08], // ~
+];
+
+const COPYRIGHT: [u8; 5] = [0x3E, 0x41, 0x5D, 0x55, 0x3E];
+
+fn glyph(c: char) -> &'static [u8; 5] {
+    match c {
+        ' '..='~' => &GLYPHS[c as usize - ' ' as usize],
+        '©' => &COPYRIGHT,
+        _ => match crate::naming::fold_accent(c) {
+            base @ ' '..='~' => &GLYPHS[base as usize - ' ' as usize],
+            _ => &GLYPHS['?' as usize - ' ' as usize],
+        },
+    }
+}
+
+/// Font pixel scale for a text `size` in image pixels (the line height)
+pub fn scale_for(size: u32) -> u32 {
+    (size / LINE_HEIGHT).max(1)
+}
+
+/// Size in image pixels of `text` drawn at `scale`
+pub fn measure(text: &str, scale: u32) -> (u32, u32) {
+    let lines: Vec<&str> = text.lines().collect();
+    let columns = lines.iter().map(|line| line.chars().count() as u32).max().unwrap_or(0);
+    let rows = lines.len().max(1) as u32;
+
+    let width = (columns * ADVANCE).saturating_sub(1) * scale;
+    let height = (rows * LINE_HEIGHT - (LINE_HEIGHT - GLYPH_HEIGHT)) * scale;
+    (width, height)
+}
+
+/// Draws `text` with its top-left corner at (`x`, `y`)
+pub fn draw_text(img: &mut RgbaImage, x: i32, y: i32, text: &str, scale: u32, color: Rgba<u8>) {
+    let scale = scale.max(1) as i32;
+
+    for (row, line) in text.lines().enumerate() {
+        let top = y + row as i32 * LINE_HEIGHT as i32 * scale;
+        for (column, c) in line.chars().enumerate() {
+            let left = x + column as i32 * ADVANCE as i32 * scale;
+            for (gx, bits) in glyph(c).iter().enumerate() {
+                for gy in 0..GLYPH_HEIGHT as i32 {
+                    if bits & (1 << gy) == 0 {
+                        continue;
+                    }
+                    for dy in 0..scale {
+                        for dx in 0..scale {
+                            blend(img, left + gx as i32 * scale + dx, top + gy * scale + dy, color, 1.0);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_measure() {
+        assert_eq!(measure("Hi", 1), (11, 7));
+        assert_eq!(measure("Hi", 2), (22, 14));
+        assert_eq!(measure("a\nbcd", 1), (17, 16));
+        assert_eq!(measure("© ACME", 1), measure("c ACME", 1));
+    }
+
+    #[test]
+    fn test_accented_letters_use_base_glyph() {
+        assert_eq!(glyph('é'), glyph('e'));
+        assert_eq!(glyph('Ñ'), glyph('N'));
+        assert_eq!(glyph('Ø'), glyph('O'));
+        assert_eq!(glyph('\u{4e2d}'), glyph('?'));
+    }
+
+    #[test]
+    fn test_draw_text_stays_in_bounds() {
+        let mut img = RgbaImage::new(30, 12);
+        draw_text(&mut img, 1, 1, "T", 1, Rgba([255, 0, 0, 255]));
+
+        // Top bar of the T, then its stem
+        assert_eq!(img.get_pixel(1, 1)[0], 255);
+        assert_eq!(img.get_pixel(3, 7)[0], 255);
+        assert_eq!(img.get_pixel(1, 7)[3], 0);
+
+        // Text running off the image is clipped, not a panic
+        draw_text(&mut img, 25, 8, "long text\u{e9}", 2, Rgba([255, 0, 0, 255]));
+    }
+}
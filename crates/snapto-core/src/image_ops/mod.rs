@@ -0,0 +1,137 @@
+//! Image editing operations for SnapTo
+//!
+//! Pixel-level edits applied to a screenshot before it is uploaded:
+//! redaction of sensitive areas, annotations, watermarks, beautify framing,
+//! stitching several shots together, visual diffs, perceptual hashes and the
+//! helpers they share to decode, re-encode, draw and parse colors.
+
+pub mod annotate;
+pub mod beautify;
+pub mod diff;
+pub mod draw;
+pub mod font;
+pub mod phash;
+pub mod redact;
+pub mod stitch;
+pub mod watermark;
+
+use image::{DynamicImage, Rgba, RgbaImage};
+
+use crate::screenshot::{encode_image, ImageFormat, Region, ScreenshotConfig};
+
+pub use annotate::{Annotation, AnnotationSpec};
+pub use beautify::beautify;
+pub use diff::{diff, diff_encoded, ChangedRegion, DiffReport};
+pub use phash::PerceptualHash;
+pub use redact::{redact, RedactMode, RedactRegion, RedactSpec};
+pub use stitch::{stitch, stitch_encoded, StitchLayout, StitchOptions};
+pub use watermark::watermark;
+
+/// Image operation errors
+#[derive(Debug, thiserror::Error)]
+pub enum ImageOpsError {
+    #[error("Failed to decode image: {0}")]
+    Decode(String),
+
+    #[error("Failed to encode image: {0}")]
+    Encode(String),
+
+    #[error("Invalid color '{0}': expected #rrggbb or #rrggbbaa")]
+    InvalidColor(String),
+
+    #[error("Invalid spec: {0}")]
+    InvalidSpec(String),
+}
+
+/// Decodes `data`, lets `edit` change the pixels and encodes the result in
+/// the same format
+pub fn edit_encoded<F>(data: &[u8], edit: F) -> Result<Vec<u8>, ImageOpsError>
+where
+    F: FnOnce(&mut RgbaImage) -> Result<(), ImageOpsError>,
+{
+    let format = image::guess_format(data)
+        .ok()
+        .and_then(|f| ImageFormat::from_extension(f.extensions_str().first()?))
+        .unwrap_or(ImageFormat::Png);
+
+    let mut img = image::load_from_memory(data)
+        .map_err(|e| ImageOpsError::Decode(e.to_string()))?
+        .to_rgba8();
+    edit(&mut img)?;
+
+    encode_image(&DynamicImage::ImageRgba8(img), format, ScreenshotConfig::default().quality)
+        .map_err(|e| ImageOpsError::Encode(e.to_string()))
+}
+
+/// Width and height of an encoded image, read from its header
+pub fn dimensions(data: &[u8]) -> Option<(u32, u32)> {
+    image::ImageReader::new(std::io::Cursor::new(data))
+        .with_guessed_format()
+        .ok()?
+        .into_dimensions()
+        .ok()
+}
+
+/// Parses `#rrggbb` or `#rrggbbaa`
+pub fn parse_color(color: &str) -> Result<Rgba<u8>, ImageOpsError> {
+    let invalid = || ImageOpsError::InvalidColor(color.to_string());
+    let hex = color.trim().strip_prefix('#').ok_or_else(invalid)?;
+    if !matches!(hex.len(), 6 | 8) || !hex.is_ascii() {
+        return Err(invalid());
+    }
+
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| invalid());
+    let alpha = if hex.len() == 8 { channel(6)? } else { 255 };
+    Ok(Rgba([channel(0)?, channel(2)?, channel(4)?, alpha]))
+}
+
+/// Part of `region` inside an image of `width` x `height`, if any
+pub fn clip(region: Region, width: u32, height: u32) -> Option<Region> {
+    let x0 = region.x.max(0) as i64;
+    let y0 = region.y.max(0) as i64;
+    let x1 = (region.x as i64 + region.width as i64).min(width as i64);
+    let y1 = (region.y as i64 + region.height as i64).min(height as i64);
+
+    (x1 > x0 && y1 > y0).then(|| Region {
+        x: x0 as i32,
+        y: y0 as i32,
+        width: (x1 - x0) as u32,
+        height: (y1 - y0) as u32,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_color() {
+        assert_eq!(parse_color("#ff8000").unwrap(), Rgba([255, 128, 0, 255]));
+        assert_eq!(parse_color("#00000080").unwrap(), Rgba([0, 0, 0, 128]));
+        assert!(parse_color("ff8000").is_err());
+        assert!(parse_color("#ff80").is_err());
+        assert!(parse_color("#gg0000").is_err());
+    }
+
+    #[test]
+    fn test_clip() {
+        let region = Region { x: -10, y: 5, width: 30, height: 100 };
+        assert_eq!(clip(region, 50, 50), Some(Region { x: 0, y: 5, width: 20, height: 45 }));
+        assert_eq!(clip(Region { x: 60, y: 0, width: 5, height: 5 }, 50, 50), None);
+    }
+
+    #[test]
+    fn test_edit_encoded_keeps_format() {
+        let mut jpeg = Vec::new();
+        DynamicImage::ImageRgb8(image::RgbImage::new(8, 8))
+            .write_to(&mut std::io::Cursor::new(&mut jpeg), image::ImageFormat::Jpeg)
+            .unwrap();
+
+        let edited = edit_encoded(&jpeg, |img| {
+            img.put_pixel(0, 0, Rgba([255, 255, 255, 255]));
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(image::guess_format(&edited).unwrap(), image::ImageFormat::Jpeg);
+    }
+}
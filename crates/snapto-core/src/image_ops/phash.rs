@@ -0,0 +1,126 @@
+//! Perceptual hashing to recognise the same screenshot twice
+//!
+//! A difference hash (dHash): the image is shrunk to 9x8 grayscale pixels
+//! and each bit records whether a pixel is brighter than its right
+//! neighbour. Re-encoding, scaling or a few changed pixels (a blinking cursor)
+//! flip at most a handful of the 64 bits, so two captures are "the same"
+//! when the Hamming distance between their hashes is small.
+
+use image::imageops::{self, FilterType};
+use image::{GrayImage, Luma, RgbaImage};
+use std::fmt;
+use std::str::FromStr;
+
+use super::ImageOpsError;
+
+/// 64-bit perceptual hash of an image
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PerceptualHash(pub u64);
+
+impl PerceptualHash {
+    /// Hashes decoded pixels
+    pub fn of(img: &RgbaImage) -> Self {
+        // Flatten onto white so transparent areas hash the same however
+        // their hidden color channels were encoded
+        let gray = GrayImage::from_fn(img.width(), img.height(), |x, y| {
+            let p = img.get_pixel(x, y);
+            let alpha = p[3] as f32 / 255.0;
+            let luma = 0.299 * p[0] as f32 + 0.587 * p[1] as f32 + 0.114 * p[2] as f32;
+            Luma([(luma * alpha + 255.0 * (1.0 - alpha)).round() as u8])
+        });
+        let small = imageops::resize(&gray, 9, 8, FilterType::Triangle);
+
+        let mut bits = 0u64;
+        for y in 0..8 {
+            for x in 0..8 {
+                bits <<= 1;
+                if small.get_pixel(x, y)[0] > small.get_pixel(x + 1, y)[0] {
+                    bits |= 1;
+                }
+            }
+        }
+        PerceptualHash(bits)
+    }
+
+    /// Hashes an encoded image
+    pub fn of_encoded(data: &[u8]) -> Result<Self, ImageOpsError> {
+        let img = image::load_from_memory(data).map_err(|e| ImageOpsError::Decode(e.to_string()))?;
+        Ok(Self::of(&img.to_rgba8()))
+    }
+
+    /// Number of differing bits, from 0 (same image) to 64
+    pub fn distance(&self, other: &PerceptualHash) -> u32 {
+        (self.0 ^ other.0).count_ones()
+    }
+}
+
+impl fmt::Display for PerceptualHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+impl FromStr for PerceptualHash {
+    type Err = ImageOpsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        u64::from_str_radix(s, 16)
+            .map(PerceptualHash)
+            .map_err(|_| ImageOpsError::InvalidSpec(format!("invalid perceptual hash '{}'", s)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::screenshot::{encode_image, ImageFormat};
+    use image::{DynamicImage, Rgba};
+
+    /// A window-like test picture: gradient background with a dark panel
+    fn picture(width: u32, height: u32) -> RgbaImage {
+        RgbaImage::from_fn(width, height, |x, y| {
+            let inside = x > width / 4 && x < width * 3 / 4 && y > height / 3 && y < height * 2 / 3;
+            if inside {
+                Rgba([30, 30, 40, 255])
+            } else {
+                let v = (x * 255 / width) as u8;
+                Rgba([v, 255 - v, 128, 255])
+            }
+        })
+    }
+
+    #[test]
+    fn test_same_picture_survives_reencoding_and_scaling() {
+        let original = picture(320, 200);
+        let hash = PerceptualHash::of(&original);
+
+        let jpeg = encode_image(&DynamicImage::ImageRgba8(original.clone()), ImageFormat::Jpeg, 70).unwrap();
+        assert!(hash.distance(&PerceptualHash::of_encoded(&jpeg).unwrap()) <= 2);
+
+        let scaled = imageops::resize(&original, 640, 400, FilterType::Triangle);
+        assert!(hash.distance(&PerceptualHash::of(&scaled)) <= 2);
+
+        // A cursor-sized change barely moves the hash
+        let mut cursor = original.clone();
+        for y in 10..20 {
+            cursor.put_pixel(50, y, Rgba([0, 0, 0, 255]));
+        }
+        assert!(hash.distance(&PerceptualHash::of(&cursor)) <= 2);
+    }
+
+    #[test]
+    fn test_different_pictures_are_far_apart() {
+        let a = PerceptualHash::of(&picture(320, 200));
+        let mirrored = PerceptualHash::of(&imageops::flip_horizontal(&picture(320, 200)));
+        assert!(a.distance(&mirrored) > 16);
+    }
+
+    #[test]
+    fn test_display_round_trip() {
+        let hash = PerceptualHash(0x00ff_1234_abcd_0001);
+        assert_eq!(hash.to_string(), "00ff1234abcd0001");
+        assert_eq!("00ff1234abcd0001".parse::<PerceptualHash>().unwrap(), hash);
+        assert!("xyz".parse::<PerceptualHash>().is_err());
+        assert_eq!(hash.distance(&PerceptualHash(0x00ff_1234_abcd_0000)), 1);
+    }
+}
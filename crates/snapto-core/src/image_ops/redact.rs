@@ -0,0 +1,312 @@
+//! Redaction of sensitive areas (tokens, emails, faces)
+//!
+//! Each region is covered with a solid fill, pixelated or blurred. A
+//! [`RedactSpec`] describes several regions at once and can be loaded from
+//! JSON:
+//!
+//! ```json
+//! {
+//!   "mode": "blur",
+//!   "sigma": 10,
+//!   "regions": [
+//!     { "x": 40, "y": 120, "width": 300, "height": 24 },
+//!     { "x": 40, "y": 200, "width": 180, "height": 24, "mode": "fill" }
+//!   ]
+//! }
+//! ```
+
+use image::{imageops, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+use super::{clip, parse_color, ImageOpsError};
+use crate::screenshot::Region;
+
+/// How a region is hidden
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RedactMode {
+    /// Solid color; the only mode that cannot be reversed
+    #[default]
+    Fill,
+    /// Blocks of averaged color
+    Pixelate,
+    /// Gaussian blur
+    Blur,
+}
+
+impl FromStr for RedactMode {
+    type Err = ImageOpsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "fill" => Ok(RedactMode::Fill),
+            "pixelate" => Ok(RedactMode::Pixelate),
+            "blur" => Ok(RedactMode::Blur),
+            _ => Err(ImageOpsError::InvalidSpec(format!(
+                "unknown redaction mode '{}' (use fill, pixelate or blur)",
+                s
+            ))),
+        }
+    }
+}
+
+/// A region to redact, optionally with its own mode
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RedactRegion {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<RedactMode>,
+}
+
+impl RedactRegion {
+    pub fn region(&self) -> Region {
+        Region {
+            x: self.x,
+            y: self.y,
+            width: self.width,
+            height: self.height,
+        }
+    }
+}
+
+impl From<Region> for RedactRegion {
+    fn from(region: Region) -> Self {
+        Self {
+            x: region.x,
+            y: region.y,
+            width: region.width,
+            height: region.height,
+            mode: None,
+        }
+    }
+}
+
+/// Regions to redact and how
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RedactSpec {
+    /// Mode for regions that set none
+    #[serde(default)]
+    pub mode: RedactMode,
+    /// Fill color (#rrggbb or #rrggbbaa)
+    #[serde(default = "default_color")]
+    pub color: String,
+    /// Pixel block size for pixelation
+    #[serde(default = "default_block_size")]
+    pub block_size: u32,
+    /// Gaussian blur strength
+    #[serde(default = "default_sigma")]
+    pub sigma: f32,
+    #[serde(default)]
+    pub regions: Vec<RedactRegion>,
+}
+
+fn default_color() -> String {
+    "#000000".to_string()
+}
+
+fn default_block_size() -> u32 {
+    12
+}
+
+fn default_sigma() -> f32 {
+    8.0
+}
+
+impl Default for RedactSpec {
+    fn default() -> Self {
+        Self {
+            mode: RedactMode::default(),
+            color: default_color(),
+            block_size: default_block_size(),
+            sigma: default_sigma(),
+            regions: Vec::new(),
+        }
+    }
+}
+
+impl RedactSpec {
+    /// Parses a JSON spec
+    pub fn from_json(json: &str) -> Result<Self, ImageOpsError> {
+        serde_json::from_str(json).map_err(|e| ImageOpsError::InvalidSpec(e.to_string()))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.regions.is_empty()
+    }
+
+    /// Redacts every region of the spec in `img`
+    pub fn apply(&self, img: &mut RgbaImage) -> Result<(), ImageOpsError> {
+        let color = parse_color(&self.color)?;
+        if self.block_size == 0 || self.sigma <= 0.0 {
+            return Err(ImageOpsError::InvalidSpec(
+                "block_size and sigma must be greater than zero".to_string(),
+            ));
+        }
+
+        for region in &self.regions {
+            let mode = region.mode.unwrap_or(self.mode);
+            redact(img, region.region(), mode, color, self.block_size, self.sigma);
+        }
+        Ok(())
+    }
+
+    /// Redacts an encoded image, keeping its format
+    pub fn apply_encoded(&self, data: &[u8]) -> Result<Vec<u8>, ImageOpsError> {
+        super::edit_encoded(data, |img| self.apply(img))
+    }
+}
+
+/// Hides `region` of `img`; parts outside the image are ignored
+pub fn redact(img: &mut RgbaImage, region: Region, mode: RedactMode, color: Rgba<u8>, block_size: u32, sigma: f32) {
+    let Some(r) = clip(region, img.width(), img.height()) else {
+        return;
+    };
+    let (x, y) = (r.x as u32, r.y as u32);
+
+    match mode {
+        RedactMode::Fill => {
+            for py in y..y + r.height {
+                for px in x..x + r.width {
+                    img.put_pixel(px, py, color);
+                }
+            }
+        }
+        RedactMode::Pixelate => {
+            let block = block_size.max(1);
+            for by in (y..y + r.height).step_by(block as usize) {
+                for bx in (x..x + r.width).step_by(block as usize) {
+                    let w = block.min(x + r.width - bx);
+                    let h = block.min(y + r.height - by);
+                    let average = average(img, bx, by, w, h);
+                    for py in by..by + h {
+                        for px in bx..bx + w {
+                            img.put_pixel(px, py, average);
+                        }
+                    }
+                }
+            }
+        }
+        RedactMode::Blur => {
+            // Blur with a margin so the edges are not sharp, then paste the
+            // region itself back
+            let margin = (sigma * 3.0).ceil() as u32;
+            let mx = x.saturating_sub(margin);
+            let my = y.saturating_sub(margin);
+            let mw = (x + r.width + margin).min(img.width()) - mx;
+            let mh = (y + r.height + margin).min(img.height()) - my;
+
+            let area = imageops::crop_imm(img, mx, my, mw, mh).to_image();
+            let blurred = imageops::blur(&area, sigma);
+            let inner = imageops::crop_imm(&blurred, x - mx, y - my, r.width, r.height).to_image();
+            imageops::replace(img, &inner, x as i64, y as i64);
+        }
+    }
+}
+
+fn average(img: &RgbaImage, x: u32, y: u32, width: u32, height: u32) -> Rgba<u8> {
+    let mut sum = [0u64; 4];
+    for py in y..y + height {
+        for px in x..x + width {
+            for (total, value) in sum.iter_mut().zip(img.get_pixel(px, py).0) {
+                *total += value as u64;
+            }
+        }
+    }
+    let count = (width as u64 * height as u64).max(1);
+    Rgba(sum.map(|total| (total / count) as u8))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Alternating black and white columns
+    fn stripes() -> RgbaImage {
+        RgbaImage::from_fn(40, 40, |x, _| {
+            if x % 2 == 0 { Rgba([0, 0, 0, 255]) } else { Rgba([255, 255, 255, 255]) }
+        })
+    }
+
+    fn region(x: i32, y: i32, width: u32, height: u32) -> Region {
+        Region { x, y, width, height }
+    }
+
+    #[test]
+    fn test_fill() {
+        let mut img = stripes();
+        redact(&mut img, region(10, 10, 5, 5), RedactMode::Fill, Rgba([255, 0, 0, 255]), 4, 1.0);
+
+        assert_eq!(*img.get_pixel(12, 12), Rgba([255, 0, 0, 255]));
+        // Outside the region is untouched
+        assert_eq!(*img.get_pixel(9, 9), Rgba([255, 255, 255, 255]));
+        assert_eq!(*img.get_pixel(15, 15), Rgba([255, 255, 255, 255]));
+    }
+
+    #[test]
+    fn test_pixelate_averages_blocks() {
+        let mut img = stripes();
+        redact(&mut img, region(0, 0, 8, 8), RedactMode::Pixelate, Rgba([0, 0, 0, 255]), 4, 1.0);
+
+        let gray = *img.get_pixel(0, 0);
+        assert_eq!(gray, Rgba([127, 127, 127, 255]));
+        assert_eq!(*img.get_pixel(3, 3), gray);
+        assert_eq!(*img.get_pixel(8, 0), Rgba([0, 0, 0, 255]));
+    }
+
+    #[test]
+    fn test_blur_smooths_region_only() {
+        let mut img = stripes();
+        redact(&mut img, region(10, 10, 20, 20), RedactMode::Blur, Rgba([0, 0, 0, 255]), 4, 3.0);
+
+        let center = img.get_pixel(20, 20)[0];
+        assert!((100..=155).contains(&center), "center was {}", center);
+        assert_eq!(*img.get_pixel(0, 0), Rgba([0, 0, 0, 255]));
+    }
+
+    #[test]
+    fn test_region_outside_image_is_ignored() {
+        let mut img = stripes();
+        let before = img.clone();
+        redact(&mut img, region(100, 100, 10, 10), RedactMode::Fill, Rgba([255, 0, 0, 255]), 4, 1.0);
+        assert_eq!(img, before);
+    }
+
+    #[test]
+    fn test_json_spec() {
+        let spec = RedactSpec::from_json(
+            r#"{ "mode": "pixelate", "regions": [
+                { "x": 0, "y": 0, "width": 4, "height": 4 },
+                { "x": 20, "y": 0, "width": 4, "height": 4, "mode": "fill" }
+            ] }"#,
+        )
+        .unwrap();
+        assert_eq!(spec.color, "#000000");
+        assert_eq!(spec.regions[1].mode, Some(RedactMode::Fill));
+
+        let mut img = stripes();
+        spec.apply(&mut img).unwrap();
+        assert_eq!(*img.get_pixel(21, 1), Rgba([0, 0, 0, 255]));
+
+        assert!(RedactSpec::from_json(r#"{ "mode": "smudge" }"#).is_err());
+    }
+
+    #[test]
+    fn test_apply_encoded_roundtrip() {
+        let mut png = Vec::new();
+        image::DynamicImage::ImageRgba8(stripes())
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+
+        let spec = RedactSpec {
+            regions: vec![region(0, 0, 10, 10).into()],
+            color: "#ff0000".to_string(),
+            ..Default::default()
+        };
+        let redacted = image::load_from_memory(&spec.apply_encoded(&png).unwrap()).unwrap().to_rgba8();
+        assert_eq!(*redacted.get_pixel(5, 5), Rgba([255, 0, 0, 255]));
+    }
+}
@@ -0,0 +1,281 @@
+//! Stitching several screenshots into one image
+//!
+//! Images are placed on a grid: one row for [`StitchLayout::Horizontal`],
+//! one column for [`StitchLayout::Vertical`] and a roughly square grid for
+//! [`StitchLayout::Grid`]. Each column is as wide as its widest image and
+//! each row as tall as its tallest, so mixed sizes line up without scaling.
+//! Images are centered in their cells, with an optional label above each.
+
+use image::{imageops, DynamicImage, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+use super::{font, parse_color, ImageOpsError};
+use crate::screenshot::{encode_image, ImageFormat, ScreenshotConfig};
+
+/// How the images are arranged
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StitchLayout {
+    /// Side by side, left to right
+    #[default]
+    Horizontal,
+    /// Stacked, top to bottom
+    Vertical,
+    /// Rows of `columns` images
+    Grid,
+}
+
+impl FromStr for StitchLayout {
+    type Err = ImageOpsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "horizontal" => Ok(StitchLayout::Horizontal),
+            "vertical" => Ok(StitchLayout::Vertical),
+            "grid" => Ok(StitchLayout::Grid),
+            _ => Err(ImageOpsError::InvalidSpec(format!(
+                "unknown layout '{}' (use horizontal, vertical or grid)",
+                s
+            ))),
+        }
+    }
+}
+
+/// How to combine the images
+#[derive(Debug, Clone, PartialEq)]
+pub struct StitchOptions {
+    pub layout: StitchLayout,
+    /// Space between images and around the edges, in pixels
+    pub gap: u32,
+    /// Columns for [`StitchLayout::Grid`]; the square root of the image
+    /// count, rounded up, if not set
+    pub columns: Option<u32>,
+    /// Canvas color (#rrggbb or #rrggbbaa)
+    pub background: String,
+    /// Labels drawn above the images, in order; may be fewer than images
+    pub labels: Vec<String>,
+    /// Label line height in pixels
+    pub label_size: u32,
+}
+
+impl Default for StitchOptions {
+    fn default() -> Self {
+        Self {
+            layout: StitchLayout::default(),
+            gap: 8,
+            columns: None,
+            background: "#ffffff".to_string(),
+            labels: Vec::new(),
+            label_size: 24,
+        }
+    }
+}
+
+/// Combines `images` into one as described by `options`
+pub fn stitch(images: &[RgbaImage], options: &StitchOptions) -> Result<RgbaImage, ImageOpsError> {
+    if images.is_empty() {
+        return Err(ImageOpsError::InvalidSpec("nothing to stitch".to_string()));
+    }
+    if options.labels.len() > images.len() {
+        return Err(ImageOpsError::InvalidSpec(format!(
+            "{} labels for {} images",
+            options.labels.len(),
+            images.len()
+        )));
+    }
+    let background = parse_color(&options.background)?;
+
+    let count = images.len() as u32;
+    let columns = match options.layout {
+        StitchLayout::Horizontal => count,
+        StitchLayout::Vertical => 1,
+        StitchLayout::Grid => options
+            .columns
+            .unwrap_or_else(|| (count as f64).sqrt().ceil() as u32)
+            .clamp(1, count),
+    } as usize;
+    let rows = images.len().div_ceil(columns);
+
+    // Labels take a band of the same height above every image
+    let label_scale = font::scale_for(options.label_size);
+    let label_band = if options.labels.iter().any(|l| !l.is_empty()) {
+        font::measure("X", label_scale).1 + label_scale * 4
+    } else {
+        0
+    };
+
+    let mut column_widths = vec![0u32; columns];
+    let mut row_heights = vec![0u32; rows];
+    for (i, img) in images.iter().enumerate() {
+        let label_width = options.labels.get(i).map_or(0, |l| font::measure(l, label_scale).0);
+        column_widths[i % columns] = column_widths[i % columns].max(img.width()).max(label_width);
+        row_heights[i / columns] = row_heights[i / columns].max(img.height() + label_band);
+    }
+
+    let gap = options.gap;
+    let width = column_widths.iter().sum::<u32>() + gap * (columns as u32 + 1);
+    let height = row_heights.iter().sum::<u32>() + gap * (rows as u32 + 1);
+    let mut canvas = RgbaImage::from_pixel(width, height, background);
+    let label_color = contrasting(background);
+
+    let mut top = gap;
+    for (row, row_height) in row_heights.iter().enumerate() {
+        let mut left = gap;
+        for (column, column_width) in column_widths.iter().enumerate() {
+            let Some(img) = images.get(row * columns + column) else {
+                break;
+            };
+
+            let x = left + (column_width - img.width()) / 2;
+            let y = top + label_band + (row_height - label_band - img.height()) / 2;
+            imageops::overlay(&mut canvas, img, x as i64, y as i64);
+
+            if let Some(label) = options.labels.get(row * columns + column) {
+                let (label_width, label_height) = font::measure(label, label_scale);
+                let label_x = left + (column_width - label_width) / 2;
+                let label_y = y - label_band + (label_band - label_height) / 2;
+                font::draw_text(&mut canvas, label_x as i32, label_y as i32, label, label_scale, label_color);
+            }
+            left += column_width + gap;
+        }
+        top += row_height + gap;
+    }
+
+    Ok(canvas)
+}
+
+/// Decodes `inputs`, stitches them and encodes the result as `format`
+pub fn stitch_encoded(
+    inputs: &[Vec<u8>],
+    options: &StitchOptions,
+    format: ImageFormat,
+) -> Result<Vec<u8>, ImageOpsError> {
+    let images = inputs
+        .iter()
+        .map(|data| {
+            image::load_from_memory(data)
+                .map(|img| img.to_rgba8())
+                .map_err(|e| ImageOpsError::Decode(e.to_string()))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let stitched = stitch(&images, options)?;
+    encode_image(&DynamicImage::ImageRgba8(stitched), format, ScreenshotConfig::default().quality)
+        .map_err(|e| ImageOpsError::Encode(e.to_string()))
+}
+
+/// Black or white, whichever reads better on `color`
+fn contrasting(color: Rgba<u8>) -> Rgba<u8> {
+    let luma = 0.299 * color[0] as f32 + 0.587 * color[1] as f32 + 0.114 * color[2] as f32;
+    if luma > 128.0 || color[3] < 128 {
+        Rgba([0, 0, 0, 255])
+    } else {
+        Rgba([255, 255, 255, 255])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: Rgba<u8> = Rgba([255, 0, 0, 255]);
+    const BLUE: Rgba<u8> = Rgba([0, 0, 255, 255]);
+    const WHITE: Rgba<u8> = Rgba([255, 255, 255, 255]);
+
+    fn images() -> Vec<RgbaImage> {
+        vec![RgbaImage::from_pixel(20, 10, RED), RgbaImage::from_pixel(10, 20, BLUE)]
+    }
+
+    fn options(layout: StitchLayout) -> StitchOptions {
+        StitchOptions {
+            layout,
+            gap: 2,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_horizontal_centers_vertically() {
+        let out = stitch(&images(), &options(StitchLayout::Horizontal)).unwrap();
+
+        assert_eq!(out.dimensions(), (2 + 20 + 2 + 10 + 2, 2 + 20 + 2));
+        assert_eq!(*out.get_pixel(2, 7), RED);
+        assert_eq!(*out.get_pixel(2, 3), WHITE);
+        assert_eq!(*out.get_pixel(24, 2), BLUE);
+    }
+
+    #[test]
+    fn test_vertical_centers_horizontally() {
+        let out = stitch(&images(), &options(StitchLayout::Vertical)).unwrap();
+
+        assert_eq!(out.dimensions(), (24, 2 + 10 + 2 + 20 + 2));
+        assert_eq!(*out.get_pixel(2, 2), RED);
+        assert_eq!(*out.get_pixel(7, 14), BLUE);
+        assert_eq!(*out.get_pixel(3, 14), WHITE);
+    }
+
+    #[test]
+    fn test_grid_fills_rows_first() {
+        let four: Vec<RgbaImage> = [RED, BLUE, BLUE, RED]
+            .iter()
+            .map(|&c| RgbaImage::from_pixel(10, 10, c))
+            .collect();
+        let out = stitch(&four, &options(StitchLayout::Grid)).unwrap();
+
+        assert_eq!(out.dimensions(), (26, 26));
+        assert_eq!(*out.get_pixel(2, 2), RED);
+        assert_eq!(*out.get_pixel(14, 2), BLUE);
+        assert_eq!(*out.get_pixel(2, 14), BLUE);
+        assert_eq!(*out.get_pixel(14, 14), RED);
+
+        // Three columns leave the second row partly empty
+        let wide = StitchOptions { columns: Some(3), ..options(StitchLayout::Grid) };
+        assert_eq!(stitch(&four, &wide).unwrap().dimensions(), (38, 26));
+    }
+
+    #[test]
+    fn test_labels_add_a_band_above_images() {
+        let with_labels = StitchOptions {
+            labels: vec!["Before".to_string(), "After".to_string()],
+            ..options(StitchLayout::Horizontal)
+        };
+        let plain = stitch(&images(), &options(StitchLayout::Horizontal)).unwrap();
+        let labeled = stitch(&images(), &with_labels).unwrap();
+
+        assert!(labeled.height() > plain.height());
+        // "Before" is wider than the 20px image, so the column grows
+        assert!(labeled.width() > plain.width());
+        assert!(labeled.pixels().any(|p| *p == Rgba([0, 0, 0, 255])));
+    }
+
+    #[test]
+    fn test_invalid_input() {
+        assert!(stitch(&[], &StitchOptions::default()).is_err());
+
+        let too_many_labels = StitchOptions {
+            labels: vec!["a".to_string(); 3],
+            ..Default::default()
+        };
+        assert!(stitch(&images(), &too_many_labels).is_err());
+        assert!("diagonal".parse::<StitchLayout>().is_err());
+        assert_eq!("Grid".parse::<StitchLayout>().unwrap(), StitchLayout::Grid);
+    }
+
+    #[test]
+    fn test_stitch_encoded_round_trip() {
+        let encode = |img: RgbaImage| {
+            encode_image(&DynamicImage::ImageRgba8(img), ImageFormat::Png, 90).unwrap()
+        };
+        let inputs: Vec<Vec<u8>> = images().into_iter().map(encode).collect();
+
+        let out = stitch_encoded(&inputs, &options(StitchLayout::Vertical), ImageFormat::Png).unwrap();
+        let decoded = image::load_from_memory(&out).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (24, 36));
+
+        assert!(matches!(
+            stitch_encoded(&[b"not an image".to_vec()], &StitchOptions::default(), ImageFormat::Png),
+            Err(ImageOpsError::Decode(_))
+        ));
+    }
+}
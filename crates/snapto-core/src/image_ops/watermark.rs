@@ -0,0 +1,201 @@
+//! Watermark and branding overlay
+//!
+//! Draws a logo, a line of text or both (text under the logo) in a corner,
+//! edge or the center of the image. The mark is sized relative to the image
+//! width, so it looks the same on a small crop and on a full 4K capture.
+
+use image::imageops::{self, FilterType};
+use image::{Rgba, RgbaImage};
+
+use super::{draw, font, parse_color, ImageOpsError};
+use crate::config::{WatermarkAnchor, WatermarkConfig};
+
+/// Draws the watermark described by `config` onto `img`
+pub fn watermark(img: &mut RgbaImage, config: &WatermarkConfig) -> Result<(), ImageOpsError> {
+    let color = parse_color(&config.color)?;
+    let max_width = ((img.width() as f32 * config.scale.clamp(0.0, 1.0)).round() as u32).max(1);
+
+    let logo = match &config.image {
+        Some(path) => Some(load_logo(path, max_width)?),
+        None => None,
+    };
+    let mark = compose(logo, config.text.as_deref(), color, max_width, config.anchor);
+
+    let margin = config.margin as i64;
+    let (x, y) = position(
+        (img.width(), img.height()),
+        (mark.width(), mark.height()),
+        margin,
+        config.anchor,
+    );
+
+    let opacity = config.opacity.clamp(0.0, 1.0);
+    for (mx, my, pixel) in mark.enumerate_pixels() {
+        if pixel[3] > 0 {
+            draw::blend(img, (x + mx as i64) as i32, (y + my as i64) as i32, *pixel, opacity);
+        }
+    }
+    Ok(())
+}
+
+/// Loads the logo, scaled to `width` pixels wide
+fn load_logo(path: &str, width: u32) -> Result<RgbaImage, ImageOpsError> {
+    let expanded = shellexpand::tilde(path).to_string();
+    let logo = image::open(&expanded)
+        .map_err(|e| ImageOpsError::Decode(format!("{}: {}", expanded, e)))?
+        .to_rgba8();
+
+    if logo.width() == width {
+        return Ok(logo);
+    }
+    let height = ((logo.height() as f64 * width as f64 / logo.width() as f64).round() as u32).max(1);
+    Ok(imageops::resize(&logo, width, height, FilterType::Lanczos3))
+}
+
+/// Stacks the logo and the text on a transparent canvas
+fn compose(logo: Option<RgbaImage>, text: Option<&str>, color: Rgba<u8>, max_width: u32, anchor: WatermarkAnchor) -> RgbaImage {
+    let text = text.filter(|t| !t.is_empty());
+
+    // Largest whole-pixel font scale that still fits in the width
+    let text_scale = text.map(|t| {
+        let unit = font::measure(t, 1).0.max(1);
+        (max_width / unit).max(1)
+    });
+    let text_size = match (text, text_scale) {
+        (Some(t), Some(scale)) => font::measure(t, scale),
+        _ => (0, 0),
+    };
+    let logo_size = logo.as_ref().map_or((0, 0), |l| l.dimensions());
+    let gap = match (&logo, text_scale) {
+        (Some(_), Some(scale)) => scale * 3,
+        _ => 0,
+    };
+
+    let width = logo_size.0.max(text_size.0).max(1);
+    let height = (logo_size.1 + gap + text_size.1).max(1);
+    let mut mark = RgbaImage::new(width, height);
+
+    // Both parts line up with the edge the mark is anchored to
+    let align = |part_width: u32| match anchor {
+        WatermarkAnchor::TopLeft | WatermarkAnchor::Left | WatermarkAnchor::BottomLeft => 0,
+        WatermarkAnchor::TopRight | WatermarkAnchor::Right | WatermarkAnchor::BottomRight => width - part_width,
+        _ => (width - part_width) / 2,
+    };
+
+    if let Some(logo) = &logo {
+        imageops::overlay(&mut mark, logo, align(logo_size.0) as i64, 0);
+    }
+    if let (Some(text), Some(scale)) = (text, text_scale) {
+        let top = (logo_size.1 + gap) as i32;
+        font::draw_text(&mut mark, align(text_size.0) as i32, top, text, scale, color);
+    }
+    mark
+}
+
+/// Top-left corner of a `mark` anchored inside an `image`
+fn position(image: (u32, u32), mark: (u32, u32), margin: i64, anchor: WatermarkAnchor) -> (i64, i64) {
+    let start = margin;
+    let center = |outer: u32, inner: u32| (outer as i64 - inner as i64) / 2;
+    let end = |outer: u32, inner: u32| outer as i64 - inner as i64 - margin;
+
+    let x = match anchor {
+        WatermarkAnchor::TopLeft | WatermarkAnchor::Left | WatermarkAnchor::BottomLeft => start,
+        WatermarkAnchor::Top | WatermarkAnchor::Center | WatermarkAnchor::Bottom => center(image.0, mark.0),
+        WatermarkAnchor::TopRight | WatermarkAnchor::Right | WatermarkAnchor::BottomRight => end(image.0, mark.0),
+    };
+    let y = match anchor {
+        WatermarkAnchor::TopLeft | WatermarkAnchor::Top | WatermarkAnchor::TopRight => start,
+        WatermarkAnchor::Left | WatermarkAnchor::Center | WatermarkAnchor::Right => center(image.1, mark.1),
+        WatermarkAnchor::BottomLeft | WatermarkAnchor::Bottom | WatermarkAnchor::BottomRight => end(image.1, mark.1),
+    };
+    (x, y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLACK: Rgba<u8> = Rgba([0, 0, 0, 255]);
+
+    fn text_config(anchor: WatermarkAnchor) -> WatermarkConfig {
+        WatermarkConfig {
+            text: Some("ACME".to_string()),
+            anchor,
+            opacity: 1.0,
+            margin: 4,
+            scale: 0.5,
+            ..Default::default()
+        }
+    }
+
+    /// Bounding box of the pixels that are no longer black
+    fn marked_area(img: &RgbaImage) -> (u32, u32, u32, u32) {
+        let marked: Vec<(u32, u32)> = img
+            .enumerate_pixels()
+            .filter(|(_, _, p)| **p != BLACK)
+            .map(|(x, y, _)| (x, y))
+            .collect();
+        let min_x = marked.iter().map(|p| p.0).min().unwrap();
+        let min_y = marked.iter().map(|p| p.1).min().unwrap();
+        let max_x = marked.iter().map(|p| p.0).max().unwrap();
+        let max_y = marked.iter().map(|p| p.1).max().unwrap();
+        (min_x, min_y, max_x, max_y)
+    }
+
+    #[test]
+    fn test_text_in_bottom_right_corner() {
+        let mut img = RgbaImage::from_pixel(200, 100, BLACK);
+        watermark(&mut img, &text_config(WatermarkAnchor::BottomRight)).unwrap();
+
+        // "ACME" is 23 font pixels wide, scaled 4x to fit 100px
+        let (min_x, min_y, max_x, max_y) = marked_area(&img);
+        assert_eq!((max_x, max_y), (195, 95));
+        assert_eq!((min_x, min_y), (104, 68));
+    }
+
+    #[test]
+    fn test_top_left_with_opacity() {
+        let mut img = RgbaImage::from_pixel(200, 100, BLACK);
+        let config = WatermarkConfig {
+            opacity: 0.5,
+            ..text_config(WatermarkAnchor::TopLeft)
+        };
+        watermark(&mut img, &config).unwrap();
+
+        let (min_x, min_y, _, _) = marked_area(&img);
+        assert_eq!((min_x, min_y), (4, 4));
+        // 'A' starts one font pixel down; half-transparent white on black
+        let p = img.get_pixel(4, 8);
+        assert!((120..=135).contains(&p[0]), "pixel was {:?}", p);
+    }
+
+    #[test]
+    fn test_logo_scaled_to_image_width() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("logo.png");
+        RgbaImage::from_pixel(10, 5, Rgba([255, 0, 0, 255])).save(&path).unwrap();
+
+        let mut img = RgbaImage::from_pixel(200, 100, BLACK);
+        let config = WatermarkConfig {
+            image: Some(path.to_string_lossy().into_owned()),
+            anchor: WatermarkAnchor::Center,
+            opacity: 1.0,
+            scale: 0.2,
+            ..Default::default()
+        };
+        watermark(&mut img, &config).unwrap();
+
+        assert_eq!(marked_area(&img), (80, 40, 119, 59));
+        assert_eq!(*img.get_pixel(100, 50), Rgba([255, 0, 0, 255]));
+    }
+
+    #[test]
+    fn test_missing_logo_is_an_error() {
+        let mut img = RgbaImage::new(10, 10);
+        let config = WatermarkConfig {
+            image: Some("/nonexistent/logo.png".to_string()),
+            ..Default::default()
+        };
+        assert!(matches!(watermark(&mut img, &config), Err(ImageOpsError::Decode(_))));
+    }
+}
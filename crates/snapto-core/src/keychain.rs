@@ -361,6 +361,7 @@ mod tests {
         SecurityConfig {
             use_system_keychain: false, // Use encrypted file for tests
             encrypt_credentials: true,
+            metadata: Default::default(),
         }
     }
 
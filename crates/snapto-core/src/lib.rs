@@ -5,20 +5,32 @@
 
 pub mod clipboard;
 pub mod config;
+pub mod counter;
 pub mod error;
 pub mod history;
+pub mod image_ops;
 pub mod keychain;
+pub mod metadata;
 pub mod naming;
+pub mod recording;
+pub mod screenshot;
+pub mod short_id;
+pub mod transform;
 pub mod upload;
 
 // Re-export commonly used types
-pub use clipboard::ClipboardManager;
-pub use config::{Config, GeneralConfig, HistoryConfig, HistoryMode, NamingConfig, SecurityConfig, UploadConfig, ClipboardCopyMode};
+pub use clipboard::{ClipboardImage, ClipboardManager};
+pub use config::{AuthMethod, BeautifyBackground, BeautifyConfig, BeautifyPreset, Config, CounterScope, DiffConfig, DuplicateConfig, DuplicatePolicy, GeneralConfig, HistoryConfig, HistoryMode, HostKeyChecking, IdMode, JumpHost, MetadataPolicy, NamingConfig, SecurityConfig, TransformConfig, UploadConfig, WatermarkAnchor, WatermarkConfig, ClipboardCopyMode};
+pub use counter::CounterStore;
 pub use error::{Result, SnaptoError};
 pub use history::{HistoryEntry, HistoryManager};
+pub use image_ops::{Annotation, AnnotationSpec, DiffReport, ImageOpsError, PerceptualHash, RedactMode, RedactSpec, StitchLayout, StitchOptions};
 pub use keychain::KeychainManager;
-pub use naming::{TemplateParser, generate_filename};
-pub use upload::{UploadResult, Uploader, UploaderInfo};
-pub use upload::sftp::SftpUploader;
+pub use naming::{content_hash, Draft, TemplateError, TemplateParser, generate_filename};
+pub use recording::{Recording, RecordingConfig, RecordingError, RecordingFormat, RecordingManager, RecordingResult, RecordingTarget};
+pub use short_id::{Alphabet, IdGenerator};
+pub use screenshot::{CaptureResult, DisplayInfo, Region, ScreenshotConfig, ScreenshotError, ScreenshotManager, WindowInfo};
+pub use transform::{TransformError, Transformed};
+pub use upload::{mime_type_for, ProgressCallback, UploadResult, Uploader, UploaderInfo};
 pub use upload::local::LocalUploader;
-pub use upload::ssh::SshUploader;
+pub use upload::ssh::{is_otp_prompt, is_password_prompt, PasswordCallback, SessionPool, SftpUploader, SshUploader};
@@ -0,0 +1,810 @@
+//! Image metadata scrubbing for SnapTo
+//!
+//! Removes EXIF (GPS, camera serials, software), XMP, IPTC and PNG text
+//! chunks before upload, according to the [`MetadataPolicy`] in
+//! `SecurityConfig`. Files are rewritten at the container level, so pixel
+//! data is never re-encoded:
+//!
+//! - JPEG: APPn and COM segments are dropped, except JFIF, ICC profiles and
+//!   the Adobe color transform marker
+//! - PNG: text, time and eXIf chunks are dropped
+//! - WebP: EXIF and XMP chunks are dropped and the VP8X flags updated
+//! - AVIF: Exif and XMP items are blanked in place (orientation lives in
+//!   `irot`/`imir` properties, which are kept)
+//! - GIF: comment and application extensions are dropped, except the
+//!   NETSCAPE/ANIMEXTS one that makes animations loop
+//!
+//! With [`MetadataPolicy::KeepOrientation`], JPEG, PNG and WebP get a new
+//! EXIF block holding only the orientation tag; GIF has no orientation.
+//! Other formats (video) pass through untouched.
+
+use tracing::debug;
+
+use crate::config::MetadataPolicy;
+
+/// EXIF tag holding the image orientation
+const ORIENTATION_TAG: u16 = 0x0112;
+
+/// Prefix of EXIF data in JPEG APP1 segments
+const EXIF_HEADER: &[u8] = b"Exif\0\0";
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// Metadata errors
+#[derive(Debug, thiserror::Error)]
+pub enum MetadataError {
+    #[error("Malformed {format} file: {message}")]
+    Malformed { format: String, message: String },
+}
+
+fn malformed(format: &str, message: &str) -> MetadataError {
+    MetadataError::Malformed {
+        format: format.to_string(),
+        message: message.to_string(),
+    }
+}
+
+/// Applies `policy` to an encoded image and returns the new bytes
+pub fn apply(data: &[u8], policy: MetadataPolicy) -> Result<Vec<u8>, MetadataError> {
+    if policy == MetadataPolicy::Keep {
+        return Ok(data.to_vec());
+    }
+    let keep_orientation = policy == MetadataPolicy::KeepOrientation;
+
+    let result = if data.starts_with(&[0xFF, 0xD8]) {
+        scrub_jpeg(data, keep_orientation)?
+    } else if data.starts_with(PNG_SIGNATURE) {
+        scrub_png(data, keep_orientation)?
+    } else if data.len() >= 12 && &data[0..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+        scrub_webp(data, keep_orientation)?
+    } else if data.len() >= 12 && &data[4..8] == b"ftyp" {
+        scrub_isobmff(data)?
+    } else if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+        scrub_gif(data)?
+    } else {
+        return Ok(data.to_vec());
+    };
+
+    debug!("Metadata scrubbed: {} -> {} bytes", data.len(), result.len());
+    Ok(result)
+}
+
+/// Orientation (1-8) stored in a TIFF-structured EXIF block
+fn exif_orientation(tiff: &[u8]) -> Option<u16> {
+    let tiff = tiff.strip_prefix(EXIF_HEADER).unwrap_or(tiff);
+    let reader = TiffReader::new(tiff)?;
+    let ifd = reader.u32(4)? as usize;
+    let count = reader.u16(ifd)? as usize;
+
+    (0..count).find_map(|i| {
+        let entry = ifd + 2 + i * 12;
+        // SHORT values fit in the entry's value field
+        if reader.u16(entry)? == ORIENTATION_TAG && reader.u16(entry + 2)? == 3 {
+            reader.u16(entry + 8).filter(|o| (1..=8).contains(o))
+        } else {
+            None
+        }
+    })
+}
+
+/// Minimal little-endian EXIF block with only the orientation tag
+fn orientation_exif(orientation: u16) -> Vec<u8> {
+    let mut tiff = Vec::with_capacity(26);
+    tiff.extend_from_slice(b"II*\0");
+    tiff.extend_from_slice(&8u32.to_le_bytes());
+    tiff.extend_from_slice(&1u16.to_le_bytes());
+    tiff.extend_from_slice(&ORIENTATION_TAG.to_le_bytes());
+    tiff.extend_from_slice(&3u16.to_le_bytes());
+    tiff.extend_from_slice(&1u32.to_le_bytes());
+    tiff.extend_from_slice(&orientation.to_le_bytes());
+    tiff.extend_from_slice(&[0, 0]);
+    tiff.extend_from_slice(&0u32.to_le_bytes());
+    tiff
+}
+
+/// Reads integers from a TIFF block in its byte order
+struct TiffReader<'a> {
+    data: &'a [u8],
+    little_endian: bool,
+}
+
+impl<'a> TiffReader<'a> {
+    fn new(data: &'a [u8]) -> Option<Self> {
+        let little_endian = match data.get(0..2)? {
+            b"II" => true,
+            b"MM" => false,
+            _ => return None,
+        };
+        Some(Self { data, little_endian })
+    }
+
+    fn u16(&self, at: usize) -> Option<u16> {
+        let bytes = [*self.data.get(at)?, *self.data.get(at + 1)?];
+        Some(if self.little_endian { u16::from_le_bytes(bytes) } else { u16::from_be_bytes(bytes) })
+    }
+
+    fn u32(&self, at: usize) -> Option<u32> {
+        let bytes: [u8; 4] = self.data.get(at..at + 4)?.try_into().ok()?;
+        Some(if self.little_endian { u32::from_le_bytes(bytes) } else { u32::from_be_bytes(bytes) })
+    }
+}
+
+fn scrub_jpeg(data: &[u8], keep_orientation: bool) -> Result<Vec<u8>, MetadataError> {
+    let mut out = Vec::with_capacity(data.len());
+    out.extend_from_slice(&data[..2]);
+    let mut pos = 2;
+
+    loop {
+        if pos + 2 > data.len() || data[pos] != 0xFF {
+            return Err(malformed("JPEG", "expected a marker"));
+        }
+        let marker = data[pos + 1];
+
+        // Fill bytes and standalone markers have no length
+        if marker == 0xFF {
+            pos += 1;
+            continue;
+        }
+        if marker == 0x01 || (0xD0..=0xD7).contains(&marker) {
+            out.extend_from_slice(&data[pos..pos + 2]);
+            pos += 2;
+            continue;
+        }
+        if marker == 0xD9 {
+            out.extend_from_slice(&data[pos..pos + 2]);
+            return Ok(out);
+        }
+
+        let length = data
+            .get(pos + 2..pos + 4)
+            .map(|b| u16::from_be_bytes([b[0], b[1]]) as usize)
+            .filter(|length| *length >= 2 && pos + 2 + length <= data.len())
+            .ok_or_else(|| malformed("JPEG", "truncated segment"))?;
+        let segment = &data[pos..pos + 2 + length];
+        let payload = &segment[4..];
+
+        if marker == 0xDA {
+            // Entropy-coded data runs to the end of image; anything after
+            // EOI (such as MPF preview images) is dropped
+            let end = find(&data[pos..], &[0xFF, 0xD9])
+                .map(|i| pos + i + 2)
+                .unwrap_or(data.len());
+            out.extend_from_slice(&data[pos..end]);
+            return Ok(out);
+        }
+
+        let keep = match marker {
+            0xE0 => payload.starts_with(b"JFIF\0") || payload.starts_with(b"JFXX\0"),
+            0xE1 => {
+                // EXIF is replaced in place by one holding only the orientation
+                let orientation = payload
+                    .starts_with(EXIF_HEADER)
+                    .then(|| exif_orientation(payload))
+                    .flatten();
+                if let Some(o) = orientation.filter(|_| keep_orientation) {
+                    let mut exif = EXIF_HEADER.to_vec();
+                    exif.extend_from_slice(&orientation_exif(o));
+                    out.extend_from_slice(&[0xFF, 0xE1]);
+                    out.extend_from_slice(&((exif.len() + 2) as u16).to_be_bytes());
+                    out.extend_from_slice(&exif);
+                }
+                false
+            }
+            0xE2 => payload.starts_with(b"ICC_PROFILE\0"),
+            0xEE => payload.starts_with(b"Adobe"),
+            0xE3..=0xED | 0xEF | 0xFE => false,
+            _ => true,
+        };
+
+        if keep {
+            out.extend_from_slice(segment);
+        }
+        pos += 2 + length;
+    }
+}
+
+fn scrub_png(data: &[u8], keep_orientation: bool) -> Result<Vec<u8>, MetadataError> {
+    let mut out = Vec::with_capacity(data.len());
+    out.extend_from_slice(PNG_SIGNATURE);
+    let mut pos = PNG_SIGNATURE.len();
+
+    while pos < data.len() {
+        let length = data
+            .get(pos..pos + 4)
+            .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]) as usize)
+            .filter(|length| pos + 12 + length <= data.len())
+            .ok_or_else(|| malformed("PNG", "truncated chunk"))?;
+        let chunk_type = &data[pos + 4..pos + 8];
+        let chunk = &data[pos..pos + 12 + length];
+
+        match chunk_type {
+            b"tEXt" | b"zTXt" | b"iTXt" | b"tIME" => {}
+            b"eXIf" => {
+                if let Some(o) = exif_orientation(&chunk[8..8 + length]).filter(|_| keep_orientation) {
+                    write_png_chunk(&mut out, b"eXIf", &orientation_exif(o));
+                }
+            }
+            _ => out.extend_from_slice(chunk),
+        }
+
+        pos += 12 + length;
+        if chunk_type == b"IEND" {
+            break;
+        }
+    }
+
+    Ok(out)
+}
+
+fn write_png_chunk(out: &mut Vec<u8>, chunk_type: &[u8; 4], data: &[u8]) {
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(chunk_type);
+    hasher.update(data);
+
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    out.extend_from_slice(chunk_type);
+    out.extend_from_slice(data);
+    out.extend_from_slice(&hasher.finalize().to_be_bytes());
+}
+
+fn scrub_webp(data: &[u8], keep_orientation: bool) -> Result<Vec<u8>, MetadataError> {
+    const EXIF_FLAG: u8 = 0x08;
+    const XMP_FLAG: u8 = 0x04;
+
+    let mut chunks: Vec<([u8; 4], Vec<u8>)> = Vec::new();
+    let mut pos = 12;
+
+    while pos + 8 <= data.len() {
+        let fourcc: [u8; 4] = data[pos..pos + 4].try_into().unwrap_or_default();
+        let size = u32::from_le_bytes(data[pos + 4..pos + 8].try_into().unwrap_or_default()) as usize;
+        let body = data
+            .get(pos + 8..pos + 8 + size)
+            .ok_or_else(|| malformed("WebP", "truncated chunk"))?;
+
+        match &fourcc {
+            b"EXIF" => {
+                if let Some(o) = exif_orientation(body).filter(|_| keep_orientation) {
+                    chunks.push((*b"EXIF", orientation_exif(o)));
+                }
+            }
+            b"XMP " => {}
+            _ => chunks.push((fourcc, body.to_vec())),
+        }
+        pos += 8 + size + (size & 1);
+    }
+
+    let has_exif = chunks.iter().any(|(fourcc, _)| fourcc == b"EXIF");
+    if let Some((_, vp8x)) = chunks.iter_mut().find(|(fourcc, _)| fourcc == b"VP8X") {
+        if let Some(flags) = vp8x.first_mut() {
+            *flags &= !(EXIF_FLAG | XMP_FLAG);
+            if has_exif {
+                *flags |= EXIF_FLAG;
+            }
+        }
+    }
+
+    let mut out = Vec::with_capacity(data.len());
+    out.extend_from_slice(b"RIFF\0\0\0\0WEBP");
+    for (fourcc, body) in &chunks {
+        out.extend_from_slice(fourcc);
+        out.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        out.extend_from_slice(body);
+        if body.len() % 2 == 1 {
+            out.push(0);
+        }
+    }
+    let riff_size = (out.len() - 8) as u32;
+    out[4..8].copy_from_slice(&riff_size.to_le_bytes());
+    Ok(out)
+}
+
+/// Drops GIF comment and application extensions, keeping the loop count
+fn scrub_gif(data: &[u8]) -> Result<Vec<u8>, MetadataError> {
+    const LOOP_APPLICATIONS: [&[u8]; 2] = [b"NETSCAPE2.0", b"ANIMEXTS1.0"];
+
+    // Header and logical screen descriptor, then the global color table
+    let flags = *data.get(10).ok_or_else(|| malformed("GIF", "truncated header"))?;
+    let mut pos = 13 + color_table_size(flags);
+    let mut out = data
+        .get(..pos)
+        .ok_or_else(|| malformed("GIF", "truncated color table"))?
+        .to_vec();
+
+    loop {
+        match data.get(pos) {
+            Some(0x21) => {
+                let label = *data.get(pos + 1).ok_or_else(|| malformed("GIF", "truncated extension"))?;
+                let end = sub_blocks_end(data, pos + 2)?;
+                // The application identifier is the first sub-block
+                let keep = match label {
+                    0xFE => false,
+                    0xFF => data
+                        .get(pos + 3..pos + 14)
+                        .is_some_and(|id| data[pos + 2] == 11 && LOOP_APPLICATIONS.contains(&id)),
+                    _ => true,
+                };
+                if keep {
+                    out.extend_from_slice(&data[pos..end]);
+                }
+                pos = end;
+            }
+            Some(0x2C) => {
+                let flags = *data.get(pos + 9).ok_or_else(|| malformed("GIF", "truncated image descriptor"))?;
+                // Descriptor, local color table and LZW code size
+                let start = pos + 10 + color_table_size(flags) + 1;
+                let end = sub_blocks_end(data, start)?;
+                out.extend_from_slice(&data[pos..end]);
+                pos = end;
+            }
+            Some(0x3B) => {
+                out.push(0x3B);
+                return Ok(out);
+            }
+            _ => return Err(malformed("GIF", "unexpected block")),
+        }
+    }
+}
+
+/// Bytes in the color table announced by a GIF descriptor's `flags`
+fn color_table_size(flags: u8) -> usize {
+    if flags & 0x80 != 0 {
+        3 << ((flags & 0x07) + 1)
+    } else {
+        0
+    }
+}
+
+/// End of the GIF data sub-blocks starting at `pos`, past the terminator
+fn sub_blocks_end(data: &[u8], mut pos: usize) -> Result<usize, MetadataError> {
+    loop {
+        let size = *data.get(pos).ok_or_else(|| malformed("GIF", "truncated data block"))? as usize;
+        pos += 1 + size;
+        if size == 0 {
+            return Ok(pos);
+        }
+    }
+}
+
+/// Blanks Exif and XMP items of an ISOBMFF image (AVIF, HEIF)
+///
+/// Item data is overwritten with zeros rather than removed, so every
+/// offset in the file stays valid.
+fn scrub_isobmff(data: &[u8]) -> Result<Vec<u8>, MetadataError> {
+    let mut out = data.to_vec();
+
+    let Some(meta) = boxes(data, 0, data.len()).find(|b| &b.kind == b"meta") else {
+        return Ok(out);
+    };
+    // meta is a full box: skip version and flags
+    let children: Vec<IsoBox> = boxes(data, meta.body + 4, meta.end).collect();
+
+    let Some(iinf) = children.iter().find(|b| &b.kind == b"iinf") else {
+        return Ok(out);
+    };
+    let Some(iloc) = children.iter().find(|b| &b.kind == b"iloc") else {
+        return Ok(out);
+    };
+
+    let metadata_items = metadata_item_ids(data, iinf)
+        .ok_or_else(|| malformed("AVIF", "invalid item info"))?;
+    if metadata_items.is_empty() {
+        return Ok(out);
+    }
+
+    for (item_id, offset, length) in item_extents(data, iloc).ok_or_else(|| malformed("AVIF", "invalid item locations"))? {
+        if metadata_items.contains(&item_id) {
+            let range = offset
+                .checked_add(length)
+                .and_then(|end| out.get_mut(offset..end))
+                .ok_or_else(|| malformed("AVIF", "item data out of range"))?;
+            range.fill(0);
+        }
+    }
+
+    Ok(out)
+}
+
+/// An ISOBMFF box: its type and where its body starts and ends
+struct IsoBox {
+    kind: [u8; 4],
+    body: usize,
+    end: usize,
+}
+
+/// Boxes between `start` and `end`
+fn boxes(data: &[u8], start: usize, end: usize) -> impl Iterator<Item = IsoBox> + '_ {
+    let mut pos = start;
+    std::iter::from_fn(move || {
+        let header = data.get(pos..pos + 8)?;
+        let size = u32::from_be_bytes(header[0..4].try_into().ok()?) as usize;
+        let kind: [u8; 4] = header[4..8].try_into().ok()?;
+
+        let (body, box_end) = match size {
+            0 => (pos + 8, end),
+            1 => {
+                let large = u64::from_be_bytes(data.get(pos + 8..pos + 16)?.try_into().ok()?) as usize;
+                (pos + 16, pos.checked_add(large)?)
+            }
+            _ => (pos + 8, pos + size),
+        };
+        if box_end > end || box_end < body {
+            return None;
+        }
+
+        pos = box_end;
+        Some(IsoBox { kind, body, end: box_end })
+    })
+}
+
+fn be(data: &[u8], at: usize, size: usize) -> Option<usize> {
+    let bytes = data.get(at..at + size)?;
+    Some(bytes.iter().fold(0usize, |value, b| (value << 8) | *b as usize))
+}
+
+/// IDs of Exif and XMP items listed in `iinf`
+fn metadata_item_ids(data: &[u8], iinf: &IsoBox) -> Option<Vec<u32>> {
+    let version = *data.get(iinf.body)?;
+    let count_size = if version == 0 { 2 } else { 4 };
+    let entries = iinf.body + 4 + count_size;
+
+    let mut ids = Vec::new();
+    for infe in boxes(data, entries, iinf.end).filter(|b| &b.kind == b"infe") {
+        let version = *data.get(infe.body)?;
+        if version < 2 {
+            continue;
+        }
+        let id_size = if version == 2 { 2 } else { 4 };
+        let id = be(data, infe.body + 4, id_size)? as u32;
+        let item_type = data.get(infe.body + 4 + id_size + 2..infe.body + 4 + id_size + 6)?;
+
+        let is_xmp = item_type == b"mime" && {
+            // item_name, then content_type, both NUL-terminated
+            let rest = data.get(infe.body + 4 + id_size + 6..infe.end)?;
+            let name_end = rest.iter().position(|b| *b == 0)?;
+            rest[name_end + 1..].starts_with(b"application/rdf+xml")
+        };
+        if item_type == b"Exif" || is_xmp {
+            ids.push(id);
+        }
+    }
+    Some(ids)
+}
+
+/// File extents `(item_id, offset, length)` listed in `iloc`
+fn item_extents(data: &[u8], iloc: &IsoBox) -> Option<Vec<(u32, usize, usize)>> {
+    let version = *data.get(iloc.body)?;
+    let mut pos = iloc.body + 4;
+
+    let sizes = be(data, pos, 2)?;
+    let offset_size = (sizes >> 12) & 0xF;
+    let length_size = (sizes >> 8) & 0xF;
+    let base_offset_size = (sizes >> 4) & 0xF;
+    let index_size = if version >= 1 { sizes & 0xF } else { 0 };
+    pos += 2;
+
+    let id_size = if version < 2 { 2 } else { 4 };
+    let item_count = be(data, pos, id_size)?;
+    pos += id_size;
+
+    let mut extents = Vec::new();
+    for _ in 0..item_count {
+        let item_id = be(data, pos, id_size)? as u32;
+        pos += id_size;
+
+        let construction_method = if version >= 1 {
+            pos += 2;
+            be(data, pos - 2, 2)? & 0xF
+        } else {
+            0
+        };
+        pos += 2; // data_reference_index
+        let base_offset = be(data, pos, base_offset_size)?;
+        pos += base_offset_size;
+        let extent_count = be(data, pos, 2)?;
+        pos += 2;
+
+        for _ in 0..extent_count {
+            pos += index_size;
+            let offset = be(data, pos, offset_size)?;
+            pos += offset_size;
+            let length = be(data, pos, length_size)?;
+            pos += length_size;
+
+            // Only file offsets; idat-constructed items hold no metadata here
+            if construction_method == 0 {
+                extents.push((item_id, base_offset.checked_add(offset)?, length));
+            }
+        }
+    }
+    Some(extents)
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{DynamicImage, RgbaImage};
+
+    const GPS_IFD_TAG: u16 = 0x8825;
+    const GPS_LATITUDE: &[u8] = b"40 26 46 N";
+
+    /// Big-endian EXIF with Make, Orientation and a GPS IFD
+    fn exif_with_gps(orientation: u16) -> Vec<u8> {
+        let mut tiff = b"MM\0*".to_vec();
+        tiff.extend_from_slice(&8u32.to_be_bytes());
+        tiff.extend_from_slice(&3u16.to_be_bytes());
+
+        let entry = |tiff: &mut Vec<u8>, tag: u16, kind: u16, count: u32, value: [u8; 4]| {
+            tiff.extend_from_slice(&tag.to_be_bytes());
+            tiff.extend_from_slice(&kind.to_be_bytes());
+            tiff.extend_from_slice(&count.to_be_bytes());
+            tiff.extend_from_slice(&value);
+        };
+        entry(&mut tiff, 0x010F, 2, 4, *b"Cam\0");
+        let o = orientation.to_be_bytes();
+        entry(&mut tiff, ORIENTATION_TAG, 3, 1, [o[0], o[1], 0, 0]);
+        // GPS IFD right after IFD0 (8 + 2 + 3 * 12 + 4 = 50)
+        entry(&mut tiff, GPS_IFD_TAG, 4, 1, 50u32.to_be_bytes());
+        tiff.extend_from_slice(&0u32.to_be_bytes());
+
+        // GPSLatitudeRef entry plus an ASCII value that stands out
+        tiff.extend_from_slice(&1u16.to_be_bytes());
+        entry(&mut tiff, 0x0002, 2, GPS_LATITUDE.len() as u32, 68u32.to_be_bytes());
+        tiff.extend_from_slice(&0u32.to_be_bytes());
+        tiff.extend_from_slice(GPS_LATITUDE);
+        tiff
+    }
+
+    fn has_gps(data: &[u8]) -> bool {
+        find(data, GPS_LATITUDE).is_some()
+    }
+
+    /// Asserts `data` holds exactly the minimal orientation-only EXIF after `marker`
+    fn assert_orientation_only(data: &[u8], marker: &[u8], skip: usize, orientation: u16) {
+        let at = find(data, marker).expect("EXIF kept") + marker.len() + skip;
+        let expected = orientation_exif(orientation);
+        assert_eq!(&data[at..at + expected.len()], expected.as_slice());
+        assert_eq!(exif_orientation(&data[at..]), Some(orientation));
+    }
+
+    fn encoded(format: image::ImageFormat) -> Vec<u8> {
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_pixel(8, 8, image::Rgba([10, 20, 30, 255])));
+        let img = if format == image::ImageFormat::Jpeg { DynamicImage::ImageRgb8(img.to_rgb8()) } else { img };
+        let mut data = std::io::Cursor::new(Vec::new());
+        img.write_to(&mut data, format).unwrap();
+        data.into_inner()
+    }
+
+    fn jpeg_with_metadata() -> Vec<u8> {
+        let jpeg = encoded(image::ImageFormat::Jpeg);
+        let mut exif = EXIF_HEADER.to_vec();
+        exif.extend_from_slice(&exif_with_gps(6));
+
+        let mut out = jpeg[..2].to_vec();
+        out.extend_from_slice(&[0xFF, 0xE1]);
+        out.extend_from_slice(&((exif.len() + 2) as u16).to_be_bytes());
+        out.extend_from_slice(&exif);
+        out.extend_from_slice(&[0xFF, 0xFE, 0x00, 0x0A]);
+        out.extend_from_slice(b"hostname");
+        out.extend_from_slice(&jpeg[2..]);
+        out
+    }
+
+    fn png_with_metadata() -> Vec<u8> {
+        let png = encoded(image::ImageFormat::Png);
+        let ihdr_end = PNG_SIGNATURE.len() + 12 + 13;
+
+        let mut out = png[..ihdr_end].to_vec();
+        write_png_chunk(&mut out, b"eXIf", &exif_with_gps(3));
+        write_png_chunk(&mut out, b"tEXt", b"Comment\0build-host.internal");
+        out.extend_from_slice(&png[ihdr_end..]);
+        out
+    }
+
+    fn webp_with_metadata() -> Vec<u8> {
+        let exif = exif_with_gps(8);
+        let mut body = Vec::new();
+        // VP8X with the EXIF flag, 8x8 canvas
+        body.extend_from_slice(b"VP8X");
+        body.extend_from_slice(&10u32.to_le_bytes());
+        body.extend_from_slice(&[0x08, 0, 0, 0, 7, 0, 0, 7, 0, 0]);
+        let webp = encoded(image::ImageFormat::WebP);
+        body.extend_from_slice(&webp[12..]);
+        body.extend_from_slice(b"EXIF");
+        body.extend_from_slice(&(exif.len() as u32).to_le_bytes());
+        body.extend_from_slice(&exif);
+        if exif.len() % 2 == 1 {
+            body.push(0);
+        }
+
+        let mut out = b"RIFF".to_vec();
+        out.extend_from_slice(&((body.len() + 4) as u32).to_le_bytes());
+        out.extend_from_slice(b"WEBP");
+        out.extend_from_slice(&body);
+        out
+    }
+
+    #[test]
+    fn test_jpeg_gps_removed() {
+        let data = jpeg_with_metadata();
+        assert!(has_gps(&data));
+
+        let stripped = apply(&data, MetadataPolicy::Strip).unwrap();
+        assert!(!has_gps(&stripped));
+        assert!(find(&stripped, EXIF_HEADER).is_none());
+        assert!(find(&stripped, b"hostname").is_none());
+        assert!(image::load_from_memory(&stripped).is_ok());
+    }
+
+    #[test]
+    fn test_jpeg_keep_orientation() {
+        let kept = apply(&jpeg_with_metadata(), MetadataPolicy::KeepOrientation).unwrap();
+        assert!(!has_gps(&kept));
+        assert!(find(&kept, b"Cam").is_none());
+        assert_orientation_only(&kept, EXIF_HEADER, 0, 6);
+        assert!(image::load_from_memory(&kept).is_ok());
+    }
+
+    #[test]
+    fn test_png_gps_and_text_removed() {
+        let data = png_with_metadata();
+        assert!(has_gps(&data));
+
+        let stripped = apply(&data, MetadataPolicy::Strip).unwrap();
+        assert!(!has_gps(&stripped));
+        assert!(find(&stripped, b"build-host").is_none());
+        assert!(find(&stripped, b"eXIf").is_none());
+        assert!(image::load_from_memory(&stripped).is_ok());
+
+        let kept = apply(&data, MetadataPolicy::KeepOrientation).unwrap();
+        assert!(!has_gps(&kept));
+        assert_orientation_only(&kept, b"eXIf", 0, 3);
+        assert!(image::load_from_memory(&kept).is_ok());
+    }
+
+    #[test]
+    fn test_webp_gps_removed() {
+        let data = webp_with_metadata();
+        assert!(has_gps(&data));
+
+        let stripped = apply(&data, MetadataPolicy::Strip).unwrap();
+        assert!(!has_gps(&stripped));
+        assert!(find(&stripped, b"EXIF").is_none());
+        // VP8X no longer claims EXIF and the RIFF size matches
+        let vp8x = find(&stripped, b"VP8X").unwrap();
+        assert_eq!(stripped[vp8x + 8] & 0x08, 0);
+        assert_eq!(u32::from_le_bytes(stripped[4..8].try_into().unwrap()) as usize, stripped.len() - 8);
+
+        let kept = apply(&data, MetadataPolicy::KeepOrientation).unwrap();
+        assert!(!has_gps(&kept));
+        assert_orientation_only(&kept, b"EXIF", 4, 8);
+    }
+
+    #[test]
+    fn test_avif_exif_item_blanked() {
+        let exif = exif_with_gps(1);
+
+        let full_box = |kind: &[u8; 4], version: u8, body: &[u8]| {
+            let mut b = ((body.len() + 12) as u32).to_be_bytes().to_vec();
+            b.extend_from_slice(kind);
+            b.extend_from_slice(&[version, 0, 0, 0]);
+            b.extend_from_slice(body);
+            b
+        };
+
+        let mut infe = 1u16.to_be_bytes().to_vec();
+        infe.extend_from_slice(&[0, 0]);
+        infe.extend_from_slice(b"Exif\0");
+        let mut iinf_body = 1u16.to_be_bytes().to_vec();
+        iinf_body.extend_from_slice(&full_box(b"infe", 2, &infe));
+        let iinf = full_box(b"iinf", 0, &iinf_body);
+
+        let ftyp = [&24u32.to_be_bytes()[..], b"ftypavif", &[0, 0, 0, 0], b"avifmif1"].concat();
+        // iloc v0: 4-byte offsets and lengths, no base offset; one item, one extent
+        let iloc_len = 12 + 2 + 2 + 2 + 2 + 2 + 8;
+        let meta_len = 12 + iinf.len() + iloc_len;
+        let exif_offset = ftyp.len() + meta_len + 8;
+
+        let mut iloc_body = vec![0x44, 0x00];
+        iloc_body.extend_from_slice(&1u16.to_be_bytes());
+        iloc_body.extend_from_slice(&1u16.to_be_bytes());
+        iloc_body.extend_from_slice(&0u16.to_be_bytes());
+        iloc_body.extend_from_slice(&1u16.to_be_bytes());
+        iloc_body.extend_from_slice(&(exif_offset as u32).to_be_bytes());
+        iloc_body.extend_from_slice(&(exif.len() as u32).to_be_bytes());
+        let iloc = full_box(b"iloc", 0, &iloc_body);
+
+        let meta = full_box(b"meta", 0, &[iinf, iloc].concat());
+        let mut mdat = ((exif.len() + 8) as u32).to_be_bytes().to_vec();
+        mdat.extend_from_slice(b"mdat");
+        mdat.extend_from_slice(&exif);
+
+        let data = [ftyp, meta, mdat].concat();
+        assert!(has_gps(&data));
+
+        let stripped = apply(&data, MetadataPolicy::Strip).unwrap();
+        assert_eq!(stripped.len(), data.len());
+        assert!(!has_gps(&stripped));
+        assert!(stripped[exif_offset..].iter().all(|b| *b == 0));
+    }
+
+    #[test]
+    fn test_avif_offsets_overflow_is_an_error() {
+        let ftyp = [&24u32.to_be_bytes()[..], b"ftypavif", &[0, 0, 0, 0], b"avifmif1"].concat();
+        let full_box = |kind: &[u8; 4], body: &[u8]| {
+            let mut b = ((body.len() + 12) as u32).to_be_bytes().to_vec();
+            b.extend_from_slice(kind);
+            b.extend_from_slice(&[0, 0, 0, 0]);
+            b.extend_from_slice(body);
+            b
+        };
+
+        let mut infe = 1u16.to_be_bytes().to_vec();
+        infe.extend_from_slice(&[0, 0]);
+        infe.extend_from_slice(b"Exif\0");
+        let mut infe_box = ((infe.len() + 12) as u32).to_be_bytes().to_vec();
+        infe_box.extend_from_slice(b"infe");
+        infe_box.extend_from_slice(&[2, 0, 0, 0]);
+        infe_box.extend_from_slice(&infe);
+        let iinf = full_box(b"iinf", &[&1u16.to_be_bytes()[..], &infe_box].concat());
+
+        // 8-byte offsets and lengths: offset + length wraps around
+        for (base_offset, offset, length) in [(0u64, 1u64, u64::MAX), (u64::MAX, 1, 1)] {
+            let mut iloc_body = vec![0x88, 0x80];
+            iloc_body.extend_from_slice(&1u16.to_be_bytes());
+            iloc_body.extend_from_slice(&1u16.to_be_bytes());
+            iloc_body.extend_from_slice(&0u16.to_be_bytes());
+            iloc_body.extend_from_slice(&base_offset.to_be_bytes());
+            iloc_body.extend_from_slice(&1u16.to_be_bytes());
+            iloc_body.extend_from_slice(&offset.to_be_bytes());
+            iloc_body.extend_from_slice(&length.to_be_bytes());
+            let meta = full_box(b"meta", &[iinf.clone(), full_box(b"iloc", &iloc_body)].concat());
+
+            let data = [ftyp.clone(), meta].concat();
+            assert!(matches!(apply(&data, MetadataPolicy::Strip), Err(MetadataError::Malformed { .. })));
+        }
+    }
+
+    #[test]
+    fn test_keep_and_unknown_formats_pass_through() {
+        let data = jpeg_with_metadata();
+        assert_eq!(apply(&data, MetadataPolicy::Keep).unwrap(), data);
+
+        let webm = b"\x1a\x45\xdf\xa3 not really".to_vec();
+        assert_eq!(apply(&webm, MetadataPolicy::Strip).unwrap(), webm);
+    }
+
+    #[test]
+    fn test_gif_comments_and_xmp_removed() {
+        let extension = |label: u8, blocks: &[&[u8]]| {
+            let mut b = vec![0x21, label];
+            for block in blocks {
+                b.push(block.len() as u8);
+                b.extend_from_slice(block);
+            }
+            b.push(0);
+            b
+        };
+        let looping = extension(0xFF, &[b"NETSCAPE2.0", &[1, 0, 0]]);
+        let xmp = extension(0xFF, &[b"XMP DataXMP", GPS_LATITUDE]);
+        let comment = extension(0xFE, &[GPS_LATITUDE]);
+
+        // 1x1, two-color global table, one pixel
+        let header = [&b"GIF89a"[..], &[1, 0, 1, 0, 0x80, 0, 0], &[0, 0, 0, 255, 255, 255]].concat();
+        let image = [0x2C, 0, 0, 0, 0, 1, 0, 1, 0, 0, 2, 2, 0x44, 0x01, 0];
+        let data = [&header[..], &looping, &comment, &xmp, &image, &[0x3B]].concat();
+        assert!(image::load_from_memory(&data).is_ok());
+
+        for policy in [MetadataPolicy::Strip, MetadataPolicy::KeepOrientation] {
+            let stripped = apply(&data, policy).unwrap();
+            assert!(!has_gps(&stripped));
+            assert_eq!(stripped, [&header[..], &looping, &image, &[0x3B]].concat());
+            assert!(image::load_from_memory(&stripped).is_ok());
+        }
+
+        assert!(apply(b"GIF89a not really", MetadataPolicy::Strip).is_err());
+    }
+}
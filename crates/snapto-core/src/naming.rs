@@ -1,16 +1,295 @@
-use crate::error::{ConfigError, Result};
+use crate::config::CounterScope;
+use crate::counter::CounterStore;
+use crate::error::{ConfigError, Result, SnaptoError};
+use crate::screenshot::WindowInfo;
+use crate::short_id::{IdGenerator, MAX_ID_LENGTH};
+use chrono::format::{Item, StrftimeItems};
 use chrono::Local;
 use rand::Rng;
+use sha2::{Digest, Sha256};
+use thiserror::Error;
 use uuid::Uuid;
+use std::path::PathBuf;
 use std::sync::atomic::{AtomicU64, Ordering};
 
-/// Contador global para {counter}
+/// Ancho máximo del relleno con ceros de {counter:0N}
+const MAX_COUNTER_WIDTH: usize = 20;
+
+/// Contador del proceso para {counter}, cuando no hay
+/// [`TemplateParser::with_counters`]
 static COUNTER: AtomicU64 = AtomicU64::new(1);
 
+/// Longitud máxima de los valores externos (título de ventana, hostname...)
+/// dentro de un nombre de archivo
+const MAX_VALUE_CHARS: usize = 64;
+
+/// Valor usado cuando un dato del contexto no está disponible
+const UNKNOWN: &str = "unknown";
+
+/// Error en un template, con la columna (desde 1, en caracteres) donde está
+#[derive(Error, Debug, Clone, PartialEq)]
+#[error("Invalid template at column {column}: {message}")]
+pub struct TemplateError {
+    pub column: usize,
+    pub message: String,
+}
+
+impl TemplateError {
+    fn new(column: usize, message: impl Into<String>) -> Self {
+        Self {
+            column,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<TemplateError> for SnaptoError {
+    fn from(err: TemplateError) -> Self {
+        ConfigError::Invalid(err.to_string()).into()
+    }
+}
+
+/// Fragmento de un template analizado
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Literal(String),
+    Placeholder(Placeholder),
+}
+
+/// Un `{nombre:argumento|filtro}` del template
+#[derive(Debug, Clone, PartialEq)]
+struct Placeholder {
+    kind: Kind,
+    filters: Vec<Filter>,
+    /// Columna de la llave de apertura
+    column: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Kind {
+    /// Formato en línea, o el configurado si no hay
+    Date(Option<String>),
+    Time(Option<String>),
+    Uuid,
+    /// Ancho mínimo, rellenando con ceros
+    Counter(usize),
+    Random(usize),
+    Id(usize),
+    Hash(usize),
+    Hostname,
+    User,
+    Destination,
+    Width,
+    Height,
+    Ext,
+    WindowTitle,
+    App,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Filter {
+    Upper,
+    Lower,
+    Slug,
+}
+
+impl Kind {
+    /// `arg` lleva la columna donde empieza el argumento
+    fn parse(name: &str, column: usize, arg: Option<(usize, &str)>) -> std::result::Result<Self, TemplateError> {
+        let no_arg = |kind: Kind| match arg {
+            Some((arg_column, _)) => Err(TemplateError::new(arg_column, format!("{{{}}} takes no argument", name))),
+            None => Ok(kind),
+        };
+        let length = |max: usize| match arg {
+            None => Err(TemplateError::new(
+                column,
+                format!("{{{}:N}} needs a length between 1 and {}", name, max),
+            )),
+            Some((arg_column, arg)) => match arg.parse::<usize>() {
+                Ok(n) if (1..=max).contains(&n) => Ok(n),
+                _ => Err(TemplateError::new(
+                    arg_column,
+                    format!("invalid {} length '{}': must be between 1 and {}", name, arg, max),
+                )),
+            },
+        };
+        let format = || {
+            arg.map(|(arg_column, format)| {
+                check_strftime(format)
+                    .map(|_| format.to_string())
+                    .map_err(|e| TemplateError::new(arg_column, e))
+            })
+            .transpose()
+        };
+
+        match name {
+            "date" => Ok(Kind::Date(format()?)),
+            "time" => Ok(Kind::Time(format()?)),
+            "random" => Ok(Kind::Random(length(32)?)),
+            "id" => Ok(Kind::Id(length(MAX_ID_LENGTH)?)),
+            "hash" => Ok(Kind::Hash(length(64)?)),
+            "uuid" => no_arg(Kind::Uuid),
+            "counter" => match arg {
+                None => Ok(Kind::Counter(0)),
+                Some((arg_column, arg)) => arg
+                    .strip_prefix('0')
+                    .and_then(|width| width.parse::<usize>().ok())
+                    .filter(|width| (1..=MAX_COUNTER_WIDTH).contains(width))
+                    .map(Kind::Counter)
+                    .ok_or_else(|| {
+                        TemplateError::new(
+                            arg_column,
+                            format!(
+                                "invalid counter padding '{}': use 0 and a width up to {}, e.g. {{counter:04}}",
+                                arg, MAX_COUNTER_WIDTH
+                            ),
+                        )
+                    }),
+            },
+            "hostname" => no_arg(Kind::Hostname),
+            "user" => no_arg(Kind::User),
+            "destination" => no_arg(Kind::Destination),
+            "width" => no_arg(Kind::Width),
+            "height" => no_arg(Kind::Height),
+            "ext" => no_arg(Kind::Ext),
+            "window_title" | "title" => no_arg(Kind::WindowTitle),
+            "app" => no_arg(Kind::App),
+            "" => Err(TemplateError::new(column, "empty placeholder")),
+            _ => Err(TemplateError::new(column, format!("unknown placeholder {{{}}}", name))),
+        }
+    }
+}
+
+impl Filter {
+    fn parse(name: &str, column: usize) -> std::result::Result<Self, TemplateError> {
+        match name {
+            "upper" => Ok(Filter::Upper),
+            "lower" => Ok(Filter::Lower),
+            "slug" => Ok(Filter::Slug),
+            _ => Err(TemplateError::new(
+                column,
+                format!("unknown filter '{}' (use upper, lower or slug)", name),
+            )),
+        }
+    }
+
+    fn apply(self, value: String) -> String {
+        match self {
+            Filter::Upper => value.to_uppercase(),
+            Filter::Lower => value.to_lowercase(),
+            Filter::Slug => slugify(&value),
+        }
+    }
+}
+
+/// Analiza un template completo
+///
+/// `{{` y `}}` son llaves literales; cualquier otra llave abre o cierra un
+/// placeholder.
+fn parse(template: &str) -> std::result::Result<Vec<Segment>, TemplateError> {
+    let chars: Vec<char> = template.chars().collect();
+    let mut segments = Vec::new();
+    let mut literal = String::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        match chars[i] {
+            '{' if chars.get(i + 1) == Some(&'{') => {
+                literal.push('{');
+                i += 2;
+            }
+            '}' if chars.get(i + 1) == Some(&'}') => {
+                literal.push('}');
+                i += 2;
+            }
+            '}' => {
+                return Err(TemplateError::new(i + 1, "unmatched '}' (write '}}' for a literal brace)"));
+            }
+            '{' => {
+                let end = chars[i + 1..]
+                    .iter()
+                    .position(|&c| c == '{' || c == '}')
+                    .map(|offset| i + 1 + offset)
+                    .filter(|&end| chars[end] == '}')
+                    .ok_or_else(|| TemplateError::new(i + 1, "unclosed '{' (write '{{' for a literal brace)"))?;
+
+                if !literal.is_empty() {
+                    segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                }
+                segments.push(Segment::Placeholder(parse_placeholder(&chars[i + 1..end], i + 2)?));
+                i = end + 1;
+            }
+            c => {
+                literal.push(c);
+                i += 1;
+            }
+        }
+    }
+
+    if !literal.is_empty() {
+        segments.push(Segment::Literal(literal));
+    }
+    Ok(segments)
+}
+
+/// Analiza el interior de un placeholder, que empieza en `column`
+fn parse_placeholder(body: &[char], column: usize) -> std::result::Result<Placeholder, TemplateError> {
+    // Partes separadas por '|', con la columna donde empieza cada una
+    let mut parts = Vec::new();
+    let mut part_start = 0;
+    for i in 0..=body.len() {
+        if i == body.len() || body[i] == '|' {
+            parts.push((column + part_start, body[part_start..i].iter().collect::<String>()));
+            part_start = i + 1;
+        }
+    }
+
+    let (name_column, head) = parts.remove(0);
+    let (name, arg) = match head.split_once(':') {
+        Some((name, arg)) => (name, Some((name_column + name.chars().count() + 1, arg))),
+        None => (head.as_str(), None),
+    };
+    let kind = Kind::parse(name, name_column, arg)?;
+    let filters = parts
+        .iter()
+        .map(|(filter_column, filter)| Filter::parse(filter, *filter_column))
+        .collect::<std::result::Result<_, _>>()?;
+
+    Ok(Placeholder {
+        kind,
+        filters,
+        column: column - 1,
+    })
+}
+
+/// Comprueba que `format` es un formato strftime válido para chrono
+fn check_strftime(format: &str) -> std::result::Result<(), String> {
+    if format.is_empty() {
+        return Err("empty date format".to_string());
+    }
+    if StrftimeItems::new(format).any(|item| matches!(item, Item::Error)) {
+        return Err(format!("invalid date format '{}'", format));
+    }
+    Ok(())
+}
+
 /// Parser de templates para nombres de archivo
+#[derive(Debug, Clone)]
 pub struct TemplateParser {
     date_format: String,
     time_format: String,
+    /// SHA-256 en hexadecimal del contenido, para {hash:N}
+    content_hash: Option<String>,
+    /// Destino de la subida, para {destination}
+    destination: Option<String>,
+    /// Tamaño de la imagen, para {width} y {height}
+    dimensions: Option<(u32, u32)>,
+    /// Ventana capturada, para {window_title} y {app}
+    window: WindowInfo,
+    /// Base de datos de contadores persistentes y su ámbito
+    counters: Option<(PathBuf, CounterScope)>,
+    /// Cómo se generan los {id:N}
+    ids: IdGenerator,
 }
 
 impl TemplateParser {
@@ -19,101 +298,300 @@ impl TemplateParser {
         Self {
             date_format,
             time_format,
+            content_hash: None,
+            destination: None,
+            dimensions: None,
+            window: WindowInfo::default(),
+            counters: None,
+            ids: IdGenerator::default(),
         }
     }
 
+    /// Usa `hash` (ver [`content_hash`]) para los placeholders {hash:N}
+    pub fn with_content_hash(mut self, hash: String) -> Self {
+        self.content_hash = Some(hash);
+        self
+    }
+
+    /// Nombre del destino para {destination}
+    pub fn with_destination(mut self, destination: impl Into<String>) -> Self {
+        self.destination = Some(destination.into());
+        self
+    }
+
+    /// Tamaño de la imagen para {width} y {height}
+    pub fn with_dimensions(mut self, width: u32, height: u32) -> Self {
+        self.dimensions = Some((width, height));
+        self
+    }
+
+    /// Ventana capturada para {window_title} y {app}
+    pub fn with_window(mut self, window: WindowInfo) -> Self {
+        self.window = window;
+        self
+    }
+
+    /// Numera {counter} con los contadores guardados en `path` (ver
+    /// [`CounterStore`]) en lugar del contador del proceso
+    pub fn with_counters(mut self, path: impl Into<PathBuf>, scope: CounterScope) -> Self {
+        self.counters = Some((path.into(), scope));
+        self
+    }
+
+    /// Alfabeto y modo de los {id:N}; por defecto, base62 aleatorio
+    pub fn with_ids(mut self, ids: IdGenerator) -> Self {
+        self.ids = ids;
+        self
+    }
+
+    /// Si el template usa {id:N}, cuyo valor conviene comprobar que no esté
+    /// ya ocupado antes de subir
+    pub fn has_ids(template: &str) -> bool {
+        parse(template).is_ok_and(|segments| {
+            segments
+                .iter()
+                .any(|segment| matches!(segment, Segment::Placeholder(Placeholder { kind: Kind::Id(_), .. })))
+        })
+    }
+
+    /// Comprueba la sintaxis de un template sin generar nada
+    pub fn validate(template: &str) -> std::result::Result<(), TemplateError> {
+        parse(template).map(|_| ())
+    }
+
     /// Genera un nombre de archivo basado en un template
     ///
     /// Soporta los siguientes placeholders:
-    /// - {date}: Fecha actual con formato configurable
-    /// - {time}: Hora actual con formato configurable
+    /// - {date}, {date:FORMATO}: Fecha actual, con el formato configurado o
+    ///   uno strftime en línea (`{date:%Y-%m}`)
+    /// - {time}, {time:FORMATO}: Hora actual, igual que {date}
     /// - {random:N}: N caracteres aleatorios (alfanuméricos)
+    /// - {id:N}: Id corto de N caracteres (1-32), ver [`IdGenerator`]
     /// - {uuid}: UUID v4
-    /// - {counter}: Contador incremental
+    /// - {counter}, {counter:0N}: Contador incremental, opcionalmente
+    ///   rellenado con ceros hasta N cifras (`{counter:04}` → `0007`)
+    /// - {hash:N}: Primeros N caracteres (1-64) del SHA-256 del contenido;
+    ///   requiere [`TemplateParser::with_content_hash`]
+    /// - {hostname}, {user}: Equipo y usuario actuales
+    /// - {destination}: Destino de la subida
+    /// - {width}, {height}: Tamaño de la imagen
+    /// - {ext}: La extensión, sin punto
+    /// - {window_title} (o {title}), {app}: Ventana capturada, si se conoce
+    ///
+    /// Los datos que no estén disponibles se sustituyen por `unknown`. Los
+    /// filtros `upper`, `lower` y `slug` se encadenan con `|`
+    /// (`{title|slug}`), y `{{` / `}}` escriben llaves literales.
     ///
     /// # Ejemplos
     /// ```
+    /// use snapto_core::TemplateParser;
+    ///
     /// let parser = TemplateParser::new("%Y%m%d".to_string(), "%H%M%S".to_string());
-    /// let filename = parser.generate("screenshot_{date}_{time}", "png");
+    /// let filename = parser.generate("screenshot_{date}_{time}", "png").unwrap();
     /// // Resultado: screenshot_20231225_143022.png
+    /// assert!(filename.starts_with("screenshot_") && filename.ends_with(".png"));
     /// ```
     pub fn generate(&self, template: &str, extension: &str) -> Result<String> {
-        let mut result = template.to_string();
+        self.draft(template, extension)?.draw()
+    }
+
+    /// Resuelve el template salvo los {id:N}, que se sortean en cada
+    /// [`Draft::draw`]
+    ///
+    /// Así, al repetir un nombre ocupado solo cambia el id: {counter} no
+    /// avanza ni la fecha cambia entre intentos.
+    pub fn draft(&self, template: &str, extension: &str) -> Result<Draft<'_>> {
+        let extension = extension.trim_start_matches('.');
         let now = Local::now();
 
-        // Reemplazar {date}
-        if result.contains("{date}") {
-            let date_str = now.format(&self.date_format).to_string();
-            result = result.replace("{date}", &date_str);
-        }
+        // Igual que antes: varios {uuid} o {counter} comparten valor
+        let mut uuid = None;
+        let mut counter = None;
+        let mut pieces = Vec::new();
 
-        // Reemplazar {time}
-        if result.contains("{time}") {
-            let time_str = now.format(&self.time_format).to_string();
-            result = result.replace("{time}", &time_str);
+        for segment in parse(template)? {
+            let placeholder = match segment {
+                Segment::Literal(text) => {
+                    pieces.push(Piece::Text(text));
+                    continue;
+                }
+                Segment::Placeholder(placeholder) => placeholder,
+            };
+
+            let value = match &placeholder.kind {
+                Kind::Date(format) | Kind::Time(format) => {
+                    let configured = if matches!(placeholder.kind, Kind::Date(_)) {
+                        &self.date_format
+                    } else {
+                        &self.time_format
+                    };
+                    let format = format.as_deref().unwrap_or(configured);
+                    check_strftime(format).map_err(|e| TemplateError::new(placeholder.column, e))?;
+                    now.format(format).to_string()
+                }
+                Kind::Uuid => uuid.get_or_insert_with(|| Uuid::new_v4().to_string()).clone(),
+                Kind::Counter(width) => {
+                    let value = match counter {
+                        Some(value) => value,
+                        None => *counter.insert(self.next_counter(template)?),
+                    };
+                    format!("{:0width$}", value, width = *width)
+                }
+                Kind::Random(length) => generate_random_string(*length),
+                Kind::Id(length) => {
+                    pieces.push(Piece::Id(*length, placeholder.filters));
+                    continue;
+                }
+                Kind::Hash(length) => {
+                    let hash = self.content_hash.as_deref().ok_or_else(|| {
+                        TemplateError::new(
+                            placeholder.column,
+                            "{hash:N} needs the file content, which is not available here",
+                        )
+                    })?;
+                    hash.get(..*length).unwrap_or(hash).to_string()
+                }
+                Kind::Hostname => sanitize(Some(&gethostname::gethostname().to_string_lossy())),
+                Kind::User => sanitize(std::env::var("USER").or_else(|_| std::env::var("USERNAME")).ok().as_deref()),
+                Kind::Destination => sanitize(self.destination.as_deref()),
+                Kind::Width => self.dimensions.map_or(UNKNOWN.to_string(), |(width, _)| width.to_string()),
+                Kind::Height => self.dimensions.map_or(UNKNOWN.to_string(), |(_, height)| height.to_string()),
+                Kind::Ext => sanitize(Some(extension)),
+                Kind::WindowTitle => sanitize(self.window.title.as_deref()),
+                Kind::App => sanitize(self.window.app.as_deref()),
+            };
+
+            let value = placeholder.filters.iter().fold(value, |value, filter| filter.apply(value));
+            pieces.push(Piece::Text(value));
         }
 
-        // Reemplazar {uuid}
-        if result.contains("{uuid}") {
-            let uuid = Uuid::new_v4().to_string();
-            result = result.replace("{uuid}", &uuid);
-        }
+        Ok(Draft {
+            parser: self,
+            pieces,
+            extension: extension.to_string(),
+        })
+    }
 
-        // Reemplazar {counter}
-        if result.contains("{counter}") {
-            let counter = COUNTER.fetch_add(1, Ordering::SeqCst);
-            result = result.replace("{counter}", &counter.to_string());
+    /// Siguiente valor de {counter}
+    fn next_counter(&self, template: &str) -> Result<u64> {
+        match &self.counters {
+            Some((path, scope)) => CounterStore::open(path)?.next_in(*scope, self.destination.as_deref(), template),
+            None => Ok(COUNTER.fetch_add(1, Ordering::SeqCst)),
         }
+    }
+
+    /// Resetea el contador (útil para tests)
+    #[cfg(test)]
+    pub fn reset_counter() {
+        COUNTER.store(1, Ordering::SeqCst);
+    }
+}
+
+/// Parte de un [`Draft`]: texto ya resuelto o un {id:N} por sortear
+#[derive(Debug, Clone)]
+enum Piece {
+    Text(String),
+    Id(usize, Vec<Filter>),
+}
+
+/// Nombre de archivo a falta de sortear sus {id:N}, ver
+/// [`TemplateParser::draft`]
+#[derive(Debug, Clone)]
+pub struct Draft<'a> {
+    parser: &'a TemplateParser,
+    pieces: Vec<Piece>,
+    extension: String,
+}
 
-        // Reemplazar {random:N}
-        while let Some(start) = result.find("{random:") {
-            if let Some(end) = result[start..].find('}') {
-                let end = start + end;
-                let num_str = &result[start + 8..end];
-
-                match num_str.parse::<usize>() {
-                    Ok(n) if n > 0 && n <= 32 => {
-                        let random = generate_random_string(n);
-                        result.replace_range(start..=end, &random);
-                    }
-                    _ => {
-                        return Err(ConfigError::Invalid(format!(
-                            "Invalid random length in template: {}. Must be between 1 and 32",
-                            num_str
-                        )).into());
-                    }
+impl Draft<'_> {
+    /// Sortea los {id:N} y devuelve el nombre completo, con la extensión
+    pub fn draw(&self) -> Result<String> {
+        let mut result = String::new();
+        for piece in &self.pieces {
+            match piece {
+                Piece::Text(text) => result.push_str(text),
+                Piece::Id(length, filters) => {
+                    let store = self.parser.counters.as_ref().map(|(path, _)| path.as_path());
+                    let id = self.parser.ids.generate(*length, store)?;
+                    result.push_str(&filters.iter().fold(id, |value, filter| filter.apply(value)));
                 }
-            } else {
-                return Err(ConfigError::Invalid(
-                    "Malformed {random:N} placeholder".to_string()
-                ).into());
             }
         }
 
         // Agregar extensión
-        let filename = if extension.is_empty() {
-            result
+        if self.extension.is_empty() {
+            Ok(result)
         } else {
-            format!("{}.{}", result, extension.trim_start_matches('.'))
-        };
+            Ok(format!("{}.{}", result, self.extension))
+        }
+    }
+}
 
-        Ok(filename)
+impl Default for TemplateParser {
+    fn default() -> Self {
+        Self::new("%Y%m%d".to_string(), "%H%M%S".to_string())
     }
+}
 
-    /// Resetea el contador (útil para tests)
-    #[cfg(test)]
-    pub fn reset_counter() {
-        COUNTER.store(1, Ordering::SeqCst);
+/// Prepara un valor externo para ir dentro de un nombre de archivo: sin
+/// separadores de ruta ni caracteres de control, y de longitud acotada
+fn sanitize(value: Option<&str>) -> String {
+    let value: String = value
+        .unwrap_or_default()
+        .trim()
+        .chars()
+        .map(|c| if c == '/' || c == '\\' || c.is_control() { '_' } else { c })
+        .take(MAX_VALUE_CHARS)
+        .collect();
+    let value = value.trim();
+
+    if value.is_empty() {
+        UNKNOWN.to_string()
+    } else {
+        value.to_string()
     }
 }
 
-impl Default for TemplateParser {
-    fn default() -> Self {
-        Self {
-            date_format: "%Y%m%d".to_string(),
-            time_format: "%H%M%S".to_string(),
+/// Minúsculas ASCII y guiones: "Configuración — Firefox" → "configuracion-firefox"
+fn slugify(value: &str) -> String {
+    let mut slug = String::new();
+    for c in value.chars().flat_map(char::to_lowercase).map(fold_accent) {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
         }
     }
+    slug.trim_end_matches('-').to_string()
+}
+
+/// Quita el acento de las letras latinas (Latin-1), en minúscula o mayúscula
+pub(crate) fn fold_accent(c: char) -> char {
+    match c {
+        'á' | 'à' | 'ä' | 'â' | 'ã' | 'å' => 'a',
+        'é' | 'è' | 'ë' | 'ê' => 'e',
+        'í' | 'ì' | 'ï' | 'î' => 'i',
+        'ó' | 'ò' | 'ö' | 'ô' | 'õ' | 'ø' => 'o',
+        'ú' | 'ù' | 'ü' | 'û' => 'u',
+        'ý' | 'ÿ' => 'y',
+        'ñ' => 'n',
+        'ç' => 'c',
+        'Á' | 'À' | 'Ä' | 'Â' | 'Ã' | 'Å' => 'A',
+        'É' | 'È' | 'Ë' | 'Ê' => 'E',
+        'Í' | 'Ì' | 'Ï' | 'Î' => 'I',
+        'Ó' | 'Ò' | 'Ö' | 'Ô' | 'Õ' | 'Ø' => 'O',
+        'Ú' | 'Ù' | 'Ü' | 'Û' => 'U',
+        'Ý' => 'Y',
+        'Ñ' => 'N',
+        'Ç' => 'C',
+        _ => c,
+    }
+}
+
+/// SHA-256 del contenido en hexadecimal (64 caracteres), tal como lo usan
+/// {hash:N} y la deduplicación de subidas
+pub fn content_hash(data: &[u8]) -> String {
+    Sha256::digest(data).iter().map(|byte| format!("{:02x}", byte)).collect()
 }
 
 /// Genera una cadena aleatoria de caracteres alfanuméricos
@@ -188,6 +666,61 @@ mod tests {
         assert_eq!(result2, "file_2.png");
     }
 
+    #[test]
+    fn test_persistent_counter() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("counters.db");
+        let parser = TemplateParser::default().with_counters(&path, CounterScope::Destination);
+
+        let vps = parser.clone().with_destination("vps");
+        assert_eq!(vps.generate("shot_{counter:04}", "png").unwrap(), "shot_0001.png");
+        assert_eq!(vps.generate("{counter}/{counter:03}", "").unwrap(), "2/002");
+        assert_eq!(parser.with_destination("nas").generate("{counter}", "").unwrap(), "1");
+
+        // Un proceso nuevo sigue donde lo dejó el anterior
+        let again = TemplateParser::default()
+            .with_counters(&path, CounterScope::Destination)
+            .with_destination("vps");
+        assert_eq!(again.generate("{counter:04}", "").unwrap(), "0003");
+
+        for padding in ["{counter:4}", "{counter:0}", "{counter:021}", "{counter:0x}"] {
+            assert_eq!(parse(padding).unwrap_err().column, 10, "{}", padding);
+        }
+    }
+
+    #[test]
+    fn test_id_template() {
+        let random = TemplateParser::default().generate("{id:8}", "png").unwrap();
+        assert_eq!(random.len(), 12);
+        assert!(random[..8].chars().all(|c| c.is_ascii_alphanumeric()));
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let naming = crate::NamingConfig {
+            id_alphabet: "crockford".to_string(),
+            id_mode: crate::IdMode::Sequence,
+            ..crate::Config::default().naming
+        };
+        let parser = TemplateParser::default()
+            .with_counters(dir.path().join("counters.db"), CounterScope::Global)
+            .with_ids(IdGenerator::from_config(&naming).unwrap());
+        let ids: std::collections::HashSet<String> =
+            (0..50).map(|_| parser.generate("{id:2}", "").unwrap()).collect();
+        assert_eq!(ids.len(), 50);
+        assert!(ids.iter().all(|id| id.chars().all(|c| crate::short_id::CROCKFORD.contains(c))));
+
+        // Al repetir un borrador solo cambia el id, no el contador
+        let draft = parser.draft("{counter}_{id:2|lower}", "png").unwrap();
+        let (first, second) = (draft.draw().unwrap(), draft.draw().unwrap());
+        assert_ne!(first, second);
+        assert_eq!(first.split_once('_').unwrap().0, second.split_once('_').unwrap().0);
+        assert_eq!(parser.generate("{counter}", "").unwrap(), "2");
+
+        assert!(TemplateParser::has_ids("shot_{id:6|upper}"));
+        assert!(!TemplateParser::has_ids("shot_{random:6}"));
+        assert!(parser.generate("{id}", "").is_err());
+        assert!(parser.generate("{id:33}", "").is_err());
+    }
+
     #[test]
     fn test_complex_template() {
         let parser = TemplateParser::new("%Y%m%d".to_string(), "%H%M%S".to_string());
@@ -229,6 +762,73 @@ mod tests {
         assert_eq!(result, "file");
     }
 
+    #[test]
+    fn test_hash_template() {
+        let hash = content_hash(b"abc");
+        assert_eq!(hash, "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+
+        let parser = TemplateParser::default().with_content_hash(hash);
+        assert_eq!(parser.generate("shot_{hash:8}", "png").unwrap(), "shot_ba7816bf.png");
+        assert_eq!(parser.generate("{hash:2}/{hash:12}", "png").unwrap(), "ba/ba7816bf8f01.png");
+
+        assert!(parser.generate("{hash:0}", "png").is_err());
+        assert!(parser.generate("{hash:65}", "png").is_err());
+        assert!(parser.generate("{hash:8", "png").is_err());
+
+        // Sin contenido no hay nada que hashear
+        assert!(TemplateParser::default().generate("{hash:8}", "png").is_err());
+    }
+
+    #[test]
+    fn test_inline_formats_and_escapes() {
+        let parser = TemplateParser::default();
+        let year = Local::now().format("%Y").to_string();
+        assert_eq!(parser.generate("{date:%Y}", "png").unwrap(), format!("{}.png", year));
+        assert_eq!(parser.generate("{{literal}}_{ext}", "png").unwrap(), "{literal}_png.png");
+        assert_eq!(parser.generate("a}}b{{", "").unwrap(), "a}b{");
+    }
+
+    #[test]
+    fn test_context_placeholders() {
+        let parser = TemplateParser::default()
+            .with_destination("vps")
+            .with_dimensions(1920, 1080)
+            .with_window(WindowInfo {
+                title: Some("Configuración — Firefox/Nightly".to_string()),
+                app: Some("firefox".to_string()),
+            });
+
+        assert_eq!(parser.generate("{destination}_{width}x{height}", "png").unwrap(), "vps_1920x1080.png");
+        assert_eq!(parser.generate("{app|upper}", "png").unwrap(), "FIREFOX.png");
+        assert_eq!(parser.generate("{title|slug}", "png").unwrap(), "configuracion-firefox-nightly.png");
+        // Los separadores de ruta nunca llegan al nombre
+        assert_eq!(parser.generate("{window_title}", "").unwrap(), "Configuración — Firefox_Nightly");
+
+        // Si falta contexto se usa un valor por defecto en lugar de fallar
+        let bare = TemplateParser::default();
+        assert_eq!(bare.generate("{app}_{width}_{destination}", "png").unwrap(), "unknown_unknown_unknown.png");
+        assert!(!bare.generate("{hostname}-{user}", "png").unwrap().contains('/'));
+    }
+
+    #[test]
+    fn test_template_errors_report_column() {
+        let column = |template: &str| parse(template).unwrap_err().column;
+
+        assert_eq!(column("shot_{nope}"), 7);
+        assert_eq!(column("shot_{date"), 6);
+        assert_eq!(column("shot}"), 5);
+        assert_eq!(column("{random:abc}"), 9);
+        assert_eq!(column("{date:%Q}"), 7);
+        assert_eq!(column("{uuid:4}"), 7);
+        assert_eq!(column("{title|slug|shout}"), 13);
+        assert_eq!(column("ñ{date"), 2);
+        assert_eq!(column("ñ{}"), 3);
+
+        let err = TemplateParser::default().generate("a_{foo}", "png").unwrap_err();
+        assert!(err.to_string().contains("column 4"), "{}", err);
+        assert!(TemplateParser::validate("{date:%Y/%m}/{title|slug}_{{x}}").is_ok());
+    }
+
     #[test]
     fn test_generate_random_string() {
         let s1 = generate_random_string(10);
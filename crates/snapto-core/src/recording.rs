@@ -0,0 +1,612 @@
+//! Screen recording module for SnapTo
+//!
+//! Records the screen, a display or a region with ffmpeg (X11, macOS,
+//! Windows) or wf-recorder (Wayland), then converts the clip to an
+//! optimized GIF or animated WebP when asked. A recording runs for a fixed
+//! duration or until [`Recording::stop`] is called.
+
+use std::fs;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+
+use crate::screenshot::{Region, ScreenshotManager};
+
+/// How long a recorder gets to finalize its file after being stopped
+const STOP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Recording configuration
+#[derive(Debug, Clone)]
+pub struct RecordingConfig {
+    /// Output format
+    pub format: RecordingFormat,
+    /// Frames per second
+    pub fps: u32,
+    /// Stop automatically after this long
+    pub duration: Option<Duration>,
+    /// Whether to draw the cursor (where the recorder supports it)
+    pub include_cursor: bool,
+    /// Scale GIF and WebP output down to this width
+    pub max_width: Option<u32>,
+}
+
+impl Default for RecordingConfig {
+    fn default() -> Self {
+        Self {
+            format: RecordingFormat::Gif,
+            fps: 15,
+            duration: None,
+            include_cursor: true,
+            max_width: Some(960),
+        }
+    }
+}
+
+/// Supported recording formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordingFormat {
+    Mp4,
+    Gif,
+    WebP,
+}
+
+impl RecordingFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            RecordingFormat::Mp4 => "mp4",
+            RecordingFormat::Gif => "gif",
+            RecordingFormat::WebP => "webp",
+        }
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            RecordingFormat::Mp4 => "video/mp4",
+            RecordingFormat::Gif => "image/gif",
+            RecordingFormat::WebP => "image/webp",
+        }
+    }
+
+    /// Format for a file extension, if supported
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.trim_start_matches('.').to_lowercase().as_str() {
+            "mp4" => Some(RecordingFormat::Mp4),
+            "gif" => Some(RecordingFormat::Gif),
+            "webp" => Some(RecordingFormat::WebP),
+            _ => None,
+        }
+    }
+}
+
+/// What to record
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordingTarget {
+    Fullscreen,
+    Region(Region),
+    /// A display from [`ScreenshotManager::list_displays`]
+    Display(u32),
+}
+
+/// A finished recording
+#[derive(Debug)]
+pub struct RecordingResult {
+    /// Encoded file contents
+    pub data: Vec<u8>,
+    /// Format of the data
+    pub format: RecordingFormat,
+    /// Length of the recording
+    pub duration: Duration,
+}
+
+/// Recording errors
+#[derive(Debug, thiserror::Error)]
+pub enum RecordingError {
+    #[error("{tool} not found; install it to record the screen")]
+    ToolNotFound { tool: String },
+
+    #[error("Recording failed: {message}")]
+    RecordingFailed { message: String },
+
+    #[error("Conversion to {format} failed: {message}")]
+    ConversionFailed { format: String, message: String },
+
+    #[error("Recording is empty")]
+    Empty,
+
+    #[error("Display not found: {id}")]
+    DisplayNotFound { id: u32 },
+}
+
+/// The program doing the recording
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Recorder {
+    Ffmpeg,
+    WfRecorder,
+}
+
+impl Recorder {
+    fn program(&self) -> &'static str {
+        match self {
+            Recorder::Ffmpeg => "ffmpeg",
+            Recorder::WfRecorder => "wf-recorder",
+        }
+    }
+
+    /// ffmpeg cannot read the screen on Wayland
+    fn detect() -> Self {
+        #[cfg(target_os = "linux")]
+        {
+            use crate::screenshot::backend::SessionType;
+            if SessionType::detect() == SessionType::Wayland {
+                return Recorder::WfRecorder;
+            }
+        }
+        Recorder::Ffmpeg
+    }
+}
+
+/// Where a recorder should read from
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Source {
+    Fullscreen,
+    Region(Region),
+    /// Named output (Wayland) with its layout region
+    Output { name: String, region: Region },
+}
+
+/// Screen recording manager
+pub struct RecordingManager {
+    config: RecordingConfig,
+}
+
+impl RecordingManager {
+    /// Create a new recording manager with default config
+    pub fn new() -> Self {
+        Self {
+            config: RecordingConfig::default(),
+        }
+    }
+
+    /// Create a new recording manager with custom config
+    pub fn with_config(config: RecordingConfig) -> Self {
+        Self { config }
+    }
+
+    /// Starts recording `target` and returns at once
+    pub fn start(&self, target: RecordingTarget) -> Result<Recording, RecordingError> {
+        let recorder = Recorder::detect();
+        let source = self.source(target, recorder)?;
+
+        let raw_path = std::env::temp_dir().join(format!(
+            "snapto_recording_{}.mp4",
+            uuid::Uuid::new_v4()
+        ));
+
+        let args = match recorder {
+            Recorder::Ffmpeg => ffmpeg_capture_args(&self.config, &source, &raw_path),
+            Recorder::WfRecorder => wf_recorder_args(&source, &raw_path),
+        };
+        debug!("Starting {} {}", recorder.program(), args.join(" "));
+
+        let child = Command::new(recorder.program())
+            .args(&args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| spawn_error(recorder.program(), e))?;
+
+        Ok(Recording {
+            child: Some(child),
+            recorder,
+            raw_path,
+            started: Instant::now(),
+            config: self.config.clone(),
+        })
+    }
+
+    /// Records `target` for the configured duration
+    pub fn record(&self, target: RecordingTarget) -> Result<RecordingResult, RecordingError> {
+        let duration = self.config.duration.ok_or_else(|| RecordingError::RecordingFailed {
+            message: "a duration is required; use start() and stop() otherwise".to_string(),
+        })?;
+
+        let recording = self.start(target)?;
+        thread::sleep(duration);
+        recording.stop()
+    }
+
+    fn source(&self, target: RecordingTarget, recorder: Recorder) -> Result<Source, RecordingError> {
+        let display = |id: u32| {
+            ScreenshotManager::list_displays()
+                .ok()
+                .and_then(|displays| displays.into_iter().find(|d| d.id == id))
+                .ok_or(RecordingError::DisplayNotFound { id })
+        };
+
+        Ok(match (target, recorder) {
+            (RecordingTarget::Region(region), _) => Source::Region(region),
+            (RecordingTarget::Display(id), Recorder::WfRecorder) => {
+                let d = display(id)?;
+                Source::Output { region: d.region(), name: d.name }
+            }
+            (RecordingTarget::Display(id), Recorder::Ffmpeg) => Source::Region(display(id)?.region()),
+            // wf-recorder records one output and would prompt for which
+            (RecordingTarget::Fullscreen, Recorder::WfRecorder) => {
+                match ScreenshotManager::list_displays()
+                    .ok()
+                    .and_then(|displays| displays.into_iter().find(|d| d.is_primary))
+                {
+                    Some(d) => Source::Output { region: d.region(), name: d.name },
+                    None => Source::Fullscreen,
+                }
+            }
+            (RecordingTarget::Fullscreen, Recorder::Ffmpeg) => Source::Fullscreen,
+        })
+    }
+}
+
+impl Default for RecordingManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A recording in progress
+///
+/// Dropping it without calling [`stop`](Self::stop) kills the recorder and
+/// discards the clip.
+pub struct Recording {
+    child: Option<Child>,
+    recorder: Recorder,
+    raw_path: PathBuf,
+    started: Instant,
+    config: RecordingConfig,
+}
+
+impl Recording {
+    /// Time since recording started
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    /// Whether the recorder stopped on its own (duration reached or failure)
+    pub fn is_finished(&mut self) -> bool {
+        match self.child.as_mut() {
+            Some(child) => !matches!(child.try_wait(), Ok(None)),
+            None => true,
+        }
+    }
+
+    /// Stops recording and returns the clip in the configured format
+    pub fn stop(mut self) -> Result<RecordingResult, RecordingError> {
+        let duration = self.elapsed();
+        let mut child = self.child.take().ok_or(RecordingError::Empty)?;
+
+        self.request_stop(&mut child);
+        let output = wait_with_timeout(child, STOP_TIMEOUT)?;
+
+        let raw_size = fs::metadata(&self.raw_path).map(|m| m.len()).unwrap_or(0);
+        if raw_size == 0 {
+            let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+            return Err(if stderr.is_empty() {
+                RecordingError::Empty
+            } else {
+                RecordingError::RecordingFailed { message: stderr }
+            });
+        }
+
+        let data = match self.config.format {
+            RecordingFormat::Mp4 => fs::read(&self.raw_path).map_err(|e| RecordingError::RecordingFailed {
+                message: format!("Failed to read recording: {}", e),
+            })?,
+            format => convert(&self.raw_path, format, &self.config)?,
+        };
+
+        Ok(RecordingResult {
+            data,
+            format: self.config.format,
+            duration: self.config.duration.map_or(duration, |limit| duration.min(limit)),
+        })
+    }
+
+    /// Asks the recorder to finish writing its file
+    fn request_stop(&self, child: &mut Child) {
+        if !matches!(child.try_wait(), Ok(None)) {
+            return;
+        }
+
+        match self.recorder {
+            // ffmpeg quits cleanly on "q"
+            Recorder::Ffmpeg => {
+                if let Some(stdin) = child.stdin.as_mut() {
+                    let _ = stdin.write_all(b"q");
+                    let _ = stdin.flush();
+                }
+            }
+            // wf-recorder finalizes on SIGINT
+            Recorder::WfRecorder => {
+                let status = Command::new("kill")
+                    .args(["-INT", &child.id().to_string()])
+                    .status();
+                if !matches!(status, Ok(s) if s.success()) {
+                    warn!("Could not signal wf-recorder, killing it");
+                    let _ = child.kill();
+                }
+            }
+        }
+    }
+}
+
+impl Drop for Recording {
+    fn drop(&mut self) {
+        if let Some(mut child) = self.child.take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+        let _ = fs::remove_file(&self.raw_path);
+    }
+}
+
+fn spawn_error(program: &str, e: std::io::Error) -> RecordingError {
+    if e.kind() == ErrorKind::NotFound {
+        RecordingError::ToolNotFound {
+            tool: program.to_string(),
+        }
+    } else {
+        RecordingError::RecordingFailed {
+            message: format!("Failed to execute {}: {}", program, e),
+        }
+    }
+}
+
+/// Waits for `child`, killing it if it does not exit in time
+fn wait_with_timeout(mut child: Child, timeout: Duration) -> Result<std::process::Output, RecordingError> {
+    let deadline = Instant::now() + timeout;
+    while matches!(child.try_wait(), Ok(None)) && Instant::now() < deadline {
+        thread::sleep(Duration::from_millis(50));
+    }
+
+    if matches!(child.try_wait(), Ok(None)) {
+        warn!("Recorder did not stop in time, killing it");
+        let _ = child.kill();
+    }
+
+    child.wait_with_output().map_err(|e| RecordingError::RecordingFailed {
+        message: format!("Failed to wait for recorder: {}", e),
+    })
+}
+
+/// ffmpeg arguments to record `source` into an MP4 at `output`
+fn ffmpeg_capture_args(config: &RecordingConfig, source: &Source, output: &Path) -> Vec<String> {
+    let mut args: Vec<String> = ["-hide_banner", "-loglevel", "error", "-y"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    let region = match source {
+        Source::Region(region) | Source::Output { region, .. } => Some(*region),
+        Source::Fullscreen => None,
+    };
+    let cursor = if config.include_cursor { "1" } else { "0" };
+    let fps = config.fps.max(1).to_string();
+    // Crop filter for inputs that cannot grab a region themselves
+    let mut crop = None;
+
+    if cfg!(target_os = "macos") {
+        args.extend(["-f", "avfoundation", "-capture_cursor", cursor, "-framerate", &fps, "-i"].map(String::from));
+        args.push("Capture screen 0:none".to_string());
+        crop = region.map(|r| format!("crop={}:{}:{}:{}", r.width, r.height, r.x, r.y));
+    } else if cfg!(target_os = "windows") {
+        args.extend(["-f", "gdigrab", "-draw_mouse", cursor, "-framerate", &fps].map(String::from));
+        if let Some(r) = region {
+            args.extend([
+                "-offset_x".to_string(),
+                r.x.to_string(),
+                "-offset_y".to_string(),
+                r.y.to_string(),
+                "-video_size".to_string(),
+                format!("{}x{}", r.width, r.height),
+            ]);
+        }
+        args.extend(["-i", "desktop"].map(String::from));
+    } else {
+        let display = std::env::var("DISPLAY").unwrap_or_else(|_| ":0".to_string());
+        args.extend(["-f", "x11grab", "-draw_mouse", cursor, "-framerate", &fps].map(String::from));
+        match region {
+            Some(r) => args.extend([
+                "-video_size".to_string(),
+                format!("{}x{}", r.width, r.height),
+                "-i".to_string(),
+                format!("{}+{},{}", display, r.x, r.y),
+            ]),
+            None => args.extend(["-i".to_string(), display]),
+        }
+    }
+
+    if let Some(duration) = config.duration {
+        args.extend(["-t".to_string(), format!("{:.3}", duration.as_secs_f64())]);
+    }
+
+    // H.264 in yuv420p needs even dimensions
+    let even = "scale=trunc(iw/2)*2:trunc(ih/2)*2".to_string();
+    let filter = match crop {
+        Some(crop) => format!("{},{}", crop, even),
+        None => even,
+    };
+
+    args.extend(
+        [
+            "-vf", &filter, "-c:v", "libx264", "-preset", "ultrafast", "-crf", "18",
+            "-pix_fmt", "yuv420p", "-movflags", "+faststart",
+        ]
+        .map(String::from),
+    );
+    args.push(output.to_string_lossy().into_owned());
+    args
+}
+
+/// wf-recorder arguments to record `source` into an MP4 at `output`
+fn wf_recorder_args(source: &Source, output: &Path) -> Vec<String> {
+    let mut args = Vec::new();
+    match source {
+        Source::Fullscreen => {}
+        Source::Region(r) => {
+            args.push("-g".to_string());
+            args.push(format!("{},{} {}x{}", r.x, r.y, r.width, r.height));
+        }
+        Source::Output { name, .. } => {
+            args.push("-o".to_string());
+            args.push(name.clone());
+        }
+    }
+    args.push("-f".to_string());
+    args.push(output.to_string_lossy().into_owned());
+    args
+}
+
+/// ffmpeg arguments to convert the raw clip at `input` to `format`
+fn conversion_args(input: &Path, output: &Path, format: RecordingFormat, config: &RecordingConfig) -> Vec<String> {
+    let scale = match config.max_width {
+        Some(width) => format!("scale='min({},iw)':-1:flags=lanczos", width),
+        None => "scale=iw:-1:flags=lanczos".to_string(),
+    };
+    let base = format!("fps={},{}", config.fps.max(1), scale);
+
+    let mut args: Vec<String> = ["-hide_banner", "-loglevel", "error", "-y", "-i"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    args.push(input.to_string_lossy().into_owned());
+
+    match format {
+        // One pass with a palette generated from the clip itself
+        RecordingFormat::Gif => args.extend([
+            "-vf".to_string(),
+            format!(
+                "{},split[a][b];[a]palettegen=stats_mode=diff[p];[b][p]paletteuse=dither=bayer:bayer_scale=5:diff_mode=rectangle",
+                base
+            ),
+            "-loop".to_string(),
+            "0".to_string(),
+        ]),
+        RecordingFormat::WebP => args.extend([
+            "-vf".to_string(),
+            base,
+            "-c:v".to_string(),
+            "libwebp".to_string(),
+            "-quality".to_string(),
+            "75".to_string(),
+            "-compression_level".to_string(),
+            "6".to_string(),
+            "-loop".to_string(),
+            "0".to_string(),
+            "-an".to_string(),
+        ]),
+        RecordingFormat::Mp4 => args.extend(["-c".to_string(), "copy".to_string()]),
+    }
+
+    args.push(output.to_string_lossy().into_owned());
+    args
+}
+
+/// Converts the raw clip to `format` and returns the encoded bytes
+fn convert(input: &Path, format: RecordingFormat, config: &RecordingConfig) -> Result<Vec<u8>, RecordingError> {
+    let output = input.with_extension(format.extension());
+    let args = conversion_args(input, &output, format, config);
+    debug!("Converting recording: ffmpeg {}", args.join(" "));
+
+    let failed = |message: String| RecordingError::ConversionFailed {
+        format: format.extension().to_string(),
+        message,
+    };
+
+    let result = Command::new("ffmpeg")
+        .args(&args)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| spawn_error("ffmpeg", e));
+
+    let data = match result {
+        Ok(out) if out.status.success() => fs::read(&output).map_err(|e| failed(e.to_string())),
+        Ok(out) => Err(failed(String::from_utf8_lossy(&out.stderr).trim().to_string())),
+        Err(e) => Err(e),
+    };
+
+    let _ = fs::remove_file(&output);
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REGION: Region = Region { x: 10, y: 20, width: 641, height: 480 };
+
+    #[test]
+    fn test_format_from_extension() {
+        assert_eq!(RecordingFormat::from_extension("GIF"), Some(RecordingFormat::Gif));
+        assert_eq!(RecordingFormat::from_extension(".mp4"), Some(RecordingFormat::Mp4));
+        assert_eq!(RecordingFormat::from_extension("webp"), Some(RecordingFormat::WebP));
+        assert_eq!(RecordingFormat::from_extension("png"), None);
+        assert_eq!(RecordingFormat::WebP.mime_type(), "image/webp");
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_ffmpeg_region_args() {
+        let config = RecordingConfig {
+            duration: Some(Duration::from_secs(5)),
+            include_cursor: false,
+            ..Default::default()
+        };
+        let args = ffmpeg_capture_args(&config, &Source::Region(REGION), Path::new("/tmp/raw.mp4"));
+        let joined = args.join(" ");
+
+        assert!(joined.contains("-f x11grab -draw_mouse 0 -framerate 15"));
+        assert!(joined.contains("-video_size 641x480"));
+        assert!(args.iter().any(|a| a.ends_with("+10,20")));
+        assert!(joined.contains("-t 5.000"));
+        assert_eq!(args.last().unwrap(), "/tmp/raw.mp4");
+    }
+
+    #[test]
+    fn test_wf_recorder_args() {
+        let out = Path::new("/tmp/raw.mp4");
+
+        assert_eq!(
+            wf_recorder_args(&Source::Region(REGION), out),
+            vec!["-g", "10,20 641x480", "-f", "/tmp/raw.mp4"]
+        );
+        let output = Source::Output { name: "DP-1".to_string(), region: REGION };
+        assert_eq!(wf_recorder_args(&output, out), vec!["-o", "DP-1", "-f", "/tmp/raw.mp4"]);
+    }
+
+    #[test]
+    fn test_gif_conversion_uses_palette() {
+        let config = RecordingConfig { fps: 10, max_width: Some(800), ..Default::default() };
+        let args = conversion_args(Path::new("in.mp4"), Path::new("out.gif"), RecordingFormat::Gif, &config);
+        let filter = &args[args.iter().position(|a| a == "-vf").unwrap() + 1];
+
+        assert!(filter.starts_with("fps=10,scale='min(800,iw)'"));
+        assert!(filter.contains("palettegen") && filter.contains("paletteuse"));
+        assert_eq!(args.last().unwrap(), "out.gif");
+    }
+
+    #[test]
+    fn test_webp_conversion_is_animated() {
+        let config = RecordingConfig { max_width: None, ..Default::default() };
+        let args = conversion_args(Path::new("in.mp4"), Path::new("out.webp"), RecordingFormat::WebP, &config);
+
+        assert!(args.windows(2).any(|w| w == ["-c:v", "libwebp"]));
+        assert!(args.windows(2).any(|w| w == ["-loop", "0"]));
+    }
+
+    #[test]
+    fn test_record_requires_duration() {
+        let manager = RecordingManager::new();
+        assert!(manager.record(RecordingTarget::Fullscreen).is_err());
+    }
+}
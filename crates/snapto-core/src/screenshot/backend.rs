@@ -0,0 +1,525 @@
+//! Pluggable capture backends for Linux desktops
+//!
+//! Every backend wraps one screenshot tool. [`select`] picks the first one
+//! that is installed for the current session type (Wayland or X11), unless
+//! the configuration names a backend explicitly.
+
+use image::DynamicImage;
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+use tracing::debug;
+
+use super::{Region, ScreenshotError, BACKEND_NAMES};
+
+/// What a backend is asked to capture
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaptureMode {
+    Fullscreen,
+    Region(Region),
+    Interactive,
+    Window(u32),
+}
+
+/// A screenshot tool that writes PNG files
+pub trait CaptureBackend: Send + Sync {
+    /// Name used in the configuration
+    fn name(&self) -> &'static str;
+
+    /// Whether the tool can run on this machine
+    fn is_available(&self) -> bool;
+
+    /// Whether `mode` is captured natively
+    ///
+    /// Regions a backend cannot capture are cropped from a full-screen
+    /// capture instead.
+    fn supports(&self, mode: &CaptureMode) -> bool;
+
+    /// Captures `mode` into a PNG file at `output`
+    fn capture(
+        &self,
+        mode: &CaptureMode,
+        include_cursor: bool,
+        output: &Path,
+    ) -> Result<(), ScreenshotError>;
+
+    /// Captures `mode` and returns the image
+    ///
+    /// The default runs [`capture`](Self::capture) into a temporary file.
+    /// Backends that read pixels directly override it to skip the PNG round
+    /// trip.
+    fn grab(&self, mode: &CaptureMode, include_cursor: bool) -> Result<DynamicImage, ScreenshotError> {
+        grab_via_file(self, mode, include_cursor)
+    }
+}
+
+/// Kind of graphical session snapto runs in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionType {
+    Wayland,
+    X11,
+    Unknown,
+}
+
+impl SessionType {
+    /// Detects the session from `XDG_SESSION_TYPE`, `WAYLAND_DISPLAY` and `DISPLAY`
+    pub fn detect() -> Self {
+        Self::from_env(
+            env::var("XDG_SESSION_TYPE").ok().as_deref(),
+            env::var_os("WAYLAND_DISPLAY").is_some(),
+            env::var_os("DISPLAY").is_some(),
+        )
+    }
+
+    fn from_env(session_type: Option<&str>, wayland_display: bool, display: bool) -> Self {
+        match session_type.map(str::to_ascii_lowercase).as_deref() {
+            Some("wayland") => return SessionType::Wayland,
+            Some("x11") => return SessionType::X11,
+            _ => {}
+        }
+
+        // XDG_SESSION_TYPE is missing or "tty" under startx and nested
+        // compositors, so fall back to the display variables
+        if wayland_display {
+            SessionType::Wayland
+        } else if display {
+            SessionType::X11
+        } else {
+            SessionType::Unknown
+        }
+    }
+}
+
+/// Backend names to try for a session, best first
+fn preference(session: SessionType, desktop: &str) -> &'static [&'static str] {
+    let desktop = desktop.to_ascii_lowercase();
+
+    match session {
+        // grim needs wlr-screencopy, which GNOME and KDE do not implement
+        SessionType::Wayland if desktop.contains("kde") => &["spectacle", "portal", "grim"],
+        SessionType::Wayland if desktop.contains("gnome") => &["portal", "gnome-screenshot", "grim"],
+        SessionType::Wayland => &["grim", "portal", "spectacle"],
+        // The native backend needs no tools but cannot select areas
+        SessionType::X11 => &["x11", "maim", "scrot", "gnome-screenshot", "spectacle"],
+        SessionType::Unknown => &["grim", "maim", "scrot", "portal"],
+    }
+}
+
+/// Creates the backend called `name`
+pub fn backend_by_name(name: &str) -> Option<Box<dyn CaptureBackend>> {
+    let backend: Box<dyn CaptureBackend> = match name.to_ascii_lowercase().as_str() {
+        "x11" => Box::new(super::x11::X11),
+        "grim" => Box::new(Grim),
+        "portal" => Box::new(super::portal::Portal),
+        "spectacle" => Box::new(Tool::SPECTACLE),
+        "maim" => Box::new(Tool::MAIM),
+        "scrot" => Box::new(Tool::SCROT),
+        "gnome-screenshot" => Box::new(Tool::GNOME_SCREENSHOT),
+        _ => return None,
+    };
+    Some(backend)
+}
+
+/// Picks the configured backend, or detects one for `mode` when
+/// `preferred` is `None` or `auto`
+pub fn select(
+    preferred: Option<&str>,
+    mode: &CaptureMode,
+) -> Result<Box<dyn CaptureBackend>, ScreenshotError> {
+    if let Some(name) = preferred.filter(|name| !name.eq_ignore_ascii_case("auto")) {
+        let backend = backend_by_name(name).ok_or_else(|| ScreenshotError::CaptureError {
+            message: format!(
+                "unknown capture backend '{}' (expected auto, {})",
+                name,
+                BACKEND_NAMES.join(", ")
+            ),
+        })?;
+
+        if !backend.is_available() {
+            return Err(ScreenshotError::CaptureError {
+                message: format!("capture backend '{}' is not available", backend.name()),
+            });
+        }
+        return Ok(backend);
+    }
+
+    let session = SessionType::detect();
+    let desktop = env::var("XDG_CURRENT_DESKTOP").unwrap_or_default();
+
+    // Prefer a backend that handles the mode natively; any region can be
+    // cropped from a full-screen capture
+    let mut fallback = None;
+    for name in preference(session, &desktop) {
+        let Some(backend) = backend_by_name(name).filter(|b| b.is_available()) else {
+            continue;
+        };
+        if backend.supports(mode) || matches!(mode, CaptureMode::Region(_)) {
+            debug!("Using {} capture backend ({:?} session)", name, session);
+            return Ok(backend);
+        }
+        fallback.get_or_insert(backend);
+    }
+
+    if let Some(backend) = fallback {
+        return Ok(backend);
+    }
+
+    let hint = match session {
+        SessionType::Wayland => "install grim (sway, Hyprland) or xdg-desktop-portal",
+        SessionType::X11 => "could not open the X display; install maim or scrot",
+        SessionType::Unknown => "no graphical session found (XDG_SESSION_TYPE, WAYLAND_DISPLAY and DISPLAY are unset)",
+    };
+    Err(ScreenshotError::CaptureError {
+        message: format!("no screenshot tool available: {}", hint),
+    })
+}
+
+/// Runs `backend` into a temporary file and loads the image it wrote
+fn grab_via_file<B: CaptureBackend + ?Sized>(
+    backend: &B,
+    mode: &CaptureMode,
+    include_cursor: bool,
+) -> Result<DynamicImage, ScreenshotError> {
+    let temp_path = env::temp_dir().join(format!(
+        "snapto_screenshot_{}.png",
+        uuid::Uuid::new_v4()
+    ));
+
+    let result = backend.capture(mode, include_cursor, &temp_path);
+    if let Err(e) = result {
+        let _ = fs::remove_file(&temp_path);
+        return Err(e);
+    }
+
+    if !temp_path.exists() {
+        // Selection tools exit without writing a file when dismissed
+        return Err(match mode {
+            CaptureMode::Interactive => ScreenshotError::Cancelled,
+            _ => ScreenshotError::CaptureError {
+                message: format!("{} did not create a screenshot", backend.name()),
+            },
+        });
+    }
+
+    let data = fs::read(&temp_path).map_err(|e| ScreenshotError::CaptureError {
+        message: format!("Failed to read screenshot: {}", e),
+    });
+    let _ = fs::remove_file(&temp_path);
+
+    image::load_from_memory(&data?).map_err(|e| ScreenshotError::CaptureError {
+        message: format!("Failed to load image: {}", e),
+    })
+}
+
+/// Whether `program` is an executable file on `PATH`
+fn in_path(program: &str) -> bool {
+    env::var_os("PATH")
+        .map(|paths| env::split_paths(&paths).any(|dir| dir.join(program).is_file()))
+        .unwrap_or(false)
+}
+
+/// Runs a tool and turns a failed exit into an error
+///
+/// Selection tools exit with an error when the user presses Escape, so a
+/// failed interactive capture counts as cancelled.
+fn run(program: &str, args: &[String], interactive: bool) -> Result<Output, ScreenshotError> {
+    debug!("Running {} {}", program, args.join(" "));
+
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .map_err(|e| ScreenshotError::CaptureError {
+            message: format!("Failed to execute {}: {}", program, e),
+        })?;
+
+    if output.status.success() {
+        Ok(output)
+    } else if interactive {
+        Err(ScreenshotError::Cancelled)
+    } else {
+        Err(ScreenshotError::CaptureError {
+            message: format!(
+                "{} failed: {}",
+                program,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+        })
+    }
+}
+
+fn path_arg(path: &Path) -> String {
+    path.to_string_lossy().into_owned()
+}
+
+/// grim for wlroots compositors, with slurp for area selection
+struct Grim;
+
+impl Grim {
+    fn args(mode: &CaptureMode, geometry: Option<&str>, include_cursor: bool, output: &Path) -> Vec<String> {
+        let mut args = Vec::new();
+        if include_cursor {
+            args.push("-c".to_string());
+        }
+
+        let region = match mode {
+            CaptureMode::Region(r) => Some(format!("{},{} {}x{}", r.x, r.y, r.width, r.height)),
+            _ => geometry.map(str::to_string),
+        };
+        if let Some(region) = region {
+            args.push("-g".to_string());
+            args.push(region);
+        }
+
+        args.push(path_arg(output));
+        args
+    }
+}
+
+impl CaptureBackend for Grim {
+    fn name(&self) -> &'static str {
+        "grim"
+    }
+
+    fn is_available(&self) -> bool {
+        in_path("grim")
+    }
+
+    fn supports(&self, mode: &CaptureMode) -> bool {
+        match mode {
+            CaptureMode::Fullscreen | CaptureMode::Region(_) => true,
+            CaptureMode::Interactive => in_path("slurp"),
+            CaptureMode::Window(_) => false,
+        }
+    }
+
+    fn capture(
+        &self,
+        mode: &CaptureMode,
+        include_cursor: bool,
+        output: &Path,
+    ) -> Result<(), ScreenshotError> {
+        let geometry = if matches!(mode, CaptureMode::Interactive) {
+            let selection = run("slurp", &[], true)?;
+            let geometry = String::from_utf8_lossy(&selection.stdout).trim().to_string();
+            if geometry.is_empty() {
+                return Err(ScreenshotError::Cancelled);
+            }
+            Some(geometry)
+        } else {
+            None
+        };
+
+        run(
+            "grim",
+            &Grim::args(mode, geometry.as_deref(), include_cursor, output),
+            false,
+        )?;
+        Ok(())
+    }
+}
+
+/// A tool driven entirely by command-line flags
+struct Tool {
+    name: &'static str,
+    /// Arguments for a mode, or `None` when the tool cannot capture it
+    args: fn(&CaptureMode, bool, String) -> Option<Vec<String>>,
+}
+
+impl Tool {
+    const SPECTACLE: Tool = Tool {
+        name: "spectacle",
+        args: |mode, include_cursor, output| {
+            let flag = match mode {
+                CaptureMode::Fullscreen => "-f",
+                CaptureMode::Interactive => "-r",
+                CaptureMode::Region(_) | CaptureMode::Window(_) => return None,
+            };
+            // Background mode without notification
+            let mut args = vec!["-b".to_string(), "-n".to_string(), flag.to_string()];
+            if include_cursor {
+                args.push("-p".to_string());
+            }
+            args.extend(["-o".to_string(), output]);
+            Some(args)
+        },
+    };
+
+    const MAIM: Tool = Tool {
+        name: "maim",
+        args: |mode, include_cursor, output| {
+            let mut args = Vec::new();
+            if !include_cursor {
+                args.push("-u".to_string());
+            }
+            match mode {
+                CaptureMode::Fullscreen => {}
+                CaptureMode::Region(r) => {
+                    args.push("-g".to_string());
+                    args.push(format!("{}x{}{:+}{:+}", r.width, r.height, r.x, r.y));
+                }
+                CaptureMode::Interactive => args.push("-s".to_string()),
+                CaptureMode::Window(id) => {
+                    args.push("-i".to_string());
+                    args.push(id.to_string());
+                }
+            }
+            args.push(output);
+            Some(args)
+        },
+    };
+
+    const SCROT: Tool = Tool {
+        name: "scrot",
+        args: |mode, include_cursor, output| {
+            let mut args = Vec::new();
+            if include_cursor {
+                args.push("-p".to_string());
+            }
+            match mode {
+                CaptureMode::Fullscreen => {}
+                CaptureMode::Region(r) => {
+                    args.push("-a".to_string());
+                    args.push(format!("{},{},{},{}", r.x, r.y, r.width, r.height));
+                }
+                CaptureMode::Interactive => args.push("-s".to_string()),
+                CaptureMode::Window(_) => return None,
+            }
+            args.push(output);
+            Some(args)
+        },
+    };
+
+    const GNOME_SCREENSHOT: Tool = Tool {
+        name: "gnome-screenshot",
+        args: |mode, include_cursor, output| {
+            let mut args = Vec::new();
+            if include_cursor {
+                args.push("-p".to_string());
+            }
+            match mode {
+                CaptureMode::Fullscreen => {}
+                CaptureMode::Interactive => args.push("-a".to_string()),
+                CaptureMode::Region(_) | CaptureMode::Window(_) => return None,
+            }
+            args.extend(["-f".to_string(), output]);
+            Some(args)
+        },
+    };
+}
+
+impl CaptureBackend for Tool {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn is_available(&self) -> bool {
+        in_path(self.name)
+    }
+
+    fn supports(&self, mode: &CaptureMode) -> bool {
+        (self.args)(mode, false, String::new()).is_some()
+    }
+
+    fn capture(
+        &self,
+        mode: &CaptureMode,
+        include_cursor: bool,
+        output: &Path,
+    ) -> Result<(), ScreenshotError> {
+        let args = (self.args)(mode, include_cursor, path_arg(output)).ok_or_else(|| {
+            ScreenshotError::CaptureError {
+                message: format!("{} cannot capture {:?}", self.name, mode),
+            }
+        })?;
+
+        run(self.name, &args, matches!(mode, CaptureMode::Interactive))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REGION: Region = Region { x: 10, y: -5, width: 300, height: 200 };
+
+    #[test]
+    fn test_session_detection() {
+        use SessionType::*;
+
+        assert_eq!(SessionType::from_env(Some("wayland"), false, true), Wayland);
+        assert_eq!(SessionType::from_env(Some("X11"), true, true), X11);
+        assert_eq!(SessionType::from_env(Some("tty"), true, false), Wayland);
+        assert_eq!(SessionType::from_env(None, false, true), X11);
+        assert_eq!(SessionType::from_env(None, false, false), Unknown);
+    }
+
+    #[test]
+    fn test_preference_by_desktop() {
+        assert_eq!(preference(SessionType::Wayland, "sway")[0], "grim");
+        assert_eq!(preference(SessionType::Wayland, "Hyprland")[0], "grim");
+        assert_eq!(preference(SessionType::Wayland, "KDE")[0], "spectacle");
+        assert_eq!(preference(SessionType::Wayland, "ubuntu:GNOME")[0], "portal");
+        assert_eq!(preference(SessionType::X11, "i3")[0], "x11");
+    }
+
+    #[test]
+    fn test_every_name_has_a_backend() {
+        for name in BACKEND_NAMES {
+            assert_eq!(backend_by_name(name).unwrap().name(), *name);
+        }
+        assert!(backend_by_name("flameshot").is_none());
+    }
+
+    #[test]
+    fn test_unknown_backend_is_rejected() {
+        let err = select(Some("flameshot"), &CaptureMode::Fullscreen).err().unwrap();
+        assert!(err.to_string().contains("unknown capture backend"));
+    }
+
+    #[test]
+    fn test_grim_args() {
+        let out = Path::new("/tmp/shot.png");
+
+        assert_eq!(Grim::args(&CaptureMode::Fullscreen, None, false, out), vec!["/tmp/shot.png"]);
+        assert_eq!(
+            Grim::args(&CaptureMode::Region(REGION), None, true, out),
+            vec!["-c", "-g", "10,-5 300x200", "/tmp/shot.png"]
+        );
+        assert_eq!(
+            Grim::args(&CaptureMode::Interactive, Some("1,2 3x4"), false, out),
+            vec!["-g", "1,2 3x4", "/tmp/shot.png"]
+        );
+    }
+
+    #[test]
+    fn test_tool_args() {
+        let out = || "/tmp/shot.png".to_string();
+
+        assert_eq!(
+            (Tool::MAIM.args)(&CaptureMode::Region(REGION), false, out()).unwrap(),
+            vec!["-u", "-g", "300x200+10-5", "/tmp/shot.png"]
+        );
+        assert_eq!(
+            (Tool::MAIM.args)(&CaptureMode::Window(42), true, out()).unwrap(),
+            vec!["-i", "42", "/tmp/shot.png"]
+        );
+        assert_eq!(
+            (Tool::SCROT.args)(&CaptureMode::Region(REGION), false, out()).unwrap(),
+            vec!["-a", "10,-5,300,200", "/tmp/shot.png"]
+        );
+        assert_eq!(
+            (Tool::SPECTACLE.args)(&CaptureMode::Interactive, false, out()).unwrap(),
+            vec!["-b", "-n", "-r", "-o", "/tmp/shot.png"]
+        );
+    }
+
+    #[test]
+    fn test_unsupported_modes() {
+        assert!(!Tool::SCROT.supports(&CaptureMode::Window(1)));
+        assert!(!Tool::SPECTACLE.supports(&CaptureMode::Region(REGION)));
+        assert!(Tool::MAIM.supports(&CaptureMode::Window(1)));
+        assert!(!Grim.supports(&CaptureMode::Window(1)));
+    }
+}
@@ -0,0 +1,267 @@
+//! Monitor enumeration on Linux
+//!
+//! Wayland has no portable way for a client to see the output layout, so
+//! the compositor's own tool is asked (swaymsg, hyprctl, wlr-randr). When
+//! none answers, XRandR is used, which also covers XWayland on GNOME and
+//! KDE.
+//!
+//! Geometry is in desktop layout coordinates, the same space capture
+//! regions use. Displays are numbered left to right, then top to bottom.
+
+use serde_json::Value;
+use std::process::Command;
+use tracing::debug;
+
+use super::backend::SessionType;
+use super::{x11, DisplayInfo, ScreenshotError};
+
+/// Parses a compositor tool's JSON output
+type Parser = fn(&Value) -> Vec<DisplayInfo>;
+
+/// Wayland compositor tools, with their arguments and parser
+const WAYLAND_TOOLS: [(&str, &[&str], Parser); 3] = [
+    ("swaymsg", &["-t", "get_outputs", "-r"], parse_sway),
+    ("hyprctl", &["monitors", "-j"], parse_hyprland),
+    ("wlr-randr", &["--json"], parse_wlr_randr),
+];
+
+/// Lists the monitors of the current session
+pub fn list() -> Result<Vec<DisplayInfo>, ScreenshotError> {
+    if SessionType::detect() == SessionType::Wayland {
+        for (program, args, parse) in WAYLAND_TOOLS {
+            if let Some(json) = run_json(program, args) {
+                let displays = parse(&json);
+                if !displays.is_empty() {
+                    debug!("Listed {} displays with {}", displays.len(), program);
+                    return Ok(finish(displays));
+                }
+            }
+        }
+    }
+
+    if x11::has_display() {
+        return x11::list_displays().map(finish);
+    }
+
+    Err(ScreenshotError::CaptureError {
+        message: "could not list displays: no compositor tool or X display available".to_string(),
+    })
+}
+
+/// Runs a tool and parses its output as JSON
+fn run_json(program: &str, args: &[&str]) -> Option<Value> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    serde_json::from_slice(&output.stdout).ok()
+}
+
+/// Sorts displays by position, numbers them and makes sure one is primary
+///
+/// Compositors without a primary output get the one at the layout origin,
+/// or else the first.
+pub(super) fn finish(mut displays: Vec<DisplayInfo>) -> Vec<DisplayInfo> {
+    displays.sort_by_key(|d| (d.x, d.y));
+
+    if !displays.iter().any(|d| d.is_primary) {
+        let primary = displays
+            .iter()
+            .position(|d| d.x == 0 && d.y == 0)
+            .unwrap_or(0);
+        if let Some(display) = displays.get_mut(primary) {
+            display.is_primary = true;
+        }
+    }
+
+    for (id, display) in displays.iter_mut().enumerate() {
+        display.id = id as u32;
+    }
+    displays
+}
+
+fn int(value: &Value, key: &str) -> Option<i64> {
+    value.get(key)?.as_i64()
+}
+
+fn scale(value: &Value) -> f64 {
+    value
+        .get("scale")
+        .and_then(Value::as_f64)
+        .filter(|s| *s > 0.0)
+        .unwrap_or(1.0)
+}
+
+/// Logical size of a mode shown at `scale`, rotated by quarter turns
+fn logical_size(width: i64, height: i64, scale: f64, rotated: bool) -> (u32, u32) {
+    let (width, height) = if rotated { (height, width) } else { (width, height) };
+    (
+        (width as f64 / scale).round() as u32,
+        (height as f64 / scale).round() as u32,
+    )
+}
+
+/// `swaymsg -t get_outputs -r`; `rect` is already logical
+fn parse_sway(json: &Value) -> Vec<DisplayInfo> {
+    json.as_array()
+        .into_iter()
+        .flatten()
+        .filter(|output| output.get("active").and_then(Value::as_bool).unwrap_or(true))
+        .filter_map(|output| {
+            let rect = output.get("rect")?;
+            Some(DisplayInfo {
+                id: 0,
+                name: output.get("name")?.as_str()?.to_string(),
+                x: int(rect, "x")? as i32,
+                y: int(rect, "y")? as i32,
+                width: int(rect, "width")? as u32,
+                height: int(rect, "height")? as u32,
+                scale_factor: scale(output),
+                is_primary: output.get("primary").and_then(Value::as_bool).unwrap_or(false),
+            })
+        })
+        .collect()
+}
+
+/// `hyprctl monitors -j`; sizes are the physical mode
+fn parse_hyprland(json: &Value) -> Vec<DisplayInfo> {
+    json.as_array()
+        .into_iter()
+        .flatten()
+        .filter(|monitor| !monitor.get("disabled").and_then(Value::as_bool).unwrap_or(false))
+        .filter_map(|monitor| {
+            let scale = scale(monitor);
+            // Transforms 1, 3, 5 and 7 turn the output sideways
+            let rotated = int(monitor, "transform").unwrap_or(0) % 2 == 1;
+            let (width, height) = logical_size(
+                int(monitor, "width")?,
+                int(monitor, "height")?,
+                scale,
+                rotated,
+            );
+
+            Some(DisplayInfo {
+                id: 0,
+                name: monitor.get("name")?.as_str()?.to_string(),
+                x: int(monitor, "x")? as i32,
+                y: int(monitor, "y")? as i32,
+                width,
+                height,
+                scale_factor: scale,
+                is_primary: false,
+            })
+        })
+        .collect()
+}
+
+/// `wlr-randr --json`; sizes come from the current mode
+fn parse_wlr_randr(json: &Value) -> Vec<DisplayInfo> {
+    json.as_array()
+        .into_iter()
+        .flatten()
+        .filter(|output| output.get("enabled").and_then(Value::as_bool).unwrap_or(false))
+        .filter_map(|output| {
+            let mode = output
+                .get("modes")?
+                .as_array()?
+                .iter()
+                .find(|m| m.get("current").and_then(Value::as_bool).unwrap_or(false))?;
+            let position = output.get("position")?;
+
+            let scale = scale(output);
+            let transform = output.get("transform").and_then(Value::as_str).unwrap_or("normal");
+            let rotated = transform.ends_with("90") || transform.ends_with("270");
+            let (width, height) =
+                logical_size(int(mode, "width")?, int(mode, "height")?, scale, rotated);
+
+            Some(DisplayInfo {
+                id: 0,
+                name: output.get("name")?.as_str()?.to_string(),
+                x: int(position, "x")? as i32,
+                y: int(position, "y")? as i32,
+                width,
+                height,
+                scale_factor: scale,
+                is_primary: false,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_sway() {
+        let json = json!([
+            {
+                "name": "DP-1", "active": true, "primary": false, "scale": 2.0,
+                "rect": { "x": 1920, "y": 0, "width": 1920, "height": 1080 }
+            },
+            {
+                "name": "HDMI-A-1", "active": false, "scale": 1.0,
+                "rect": { "x": 0, "y": 0, "width": 0, "height": 0 }
+            }
+        ]);
+
+        let displays = parse_sway(&json);
+        assert_eq!(displays.len(), 1);
+        assert_eq!(displays[0].name, "DP-1");
+        assert_eq!((displays[0].x, displays[0].width), (1920, 1920));
+        assert_eq!(displays[0].scale_factor, 2.0);
+    }
+
+    #[test]
+    fn test_parse_hyprland_scaled_and_rotated() {
+        let json = json!([
+            { "id": 0, "name": "eDP-1", "width": 2880, "height": 1800, "x": 0, "y": 0,
+              "scale": 2.0, "transform": 0, "disabled": false },
+            { "id": 1, "name": "DP-2", "width": 2560, "height": 1440, "x": 1440, "y": 0,
+              "scale": 1.0, "transform": 1, "disabled": false }
+        ]);
+
+        let displays = parse_hyprland(&json);
+        assert_eq!((displays[0].width, displays[0].height), (1440, 900));
+        assert_eq!((displays[1].width, displays[1].height), (1440, 2560));
+    }
+
+    #[test]
+    fn test_parse_wlr_randr() {
+        let json = json!([{
+            "name": "eDP-1", "enabled": true, "scale": 1.5, "transform": "normal",
+            "position": { "x": 0, "y": 0 },
+            "modes": [
+                { "width": 1920, "height": 1080, "current": false },
+                { "width": 3000, "height": 2000, "current": true }
+            ]
+        }]);
+
+        let displays = parse_wlr_randr(&json);
+        assert_eq!((displays[0].width, displays[0].height), (2000, 1333));
+        assert_eq!(displays[0].scale_factor, 1.5);
+    }
+
+    #[test]
+    fn test_finish_orders_and_picks_primary() {
+        let display = |name: &str, x: i32| DisplayInfo {
+            id: 9,
+            name: name.to_string(),
+            x,
+            y: 0,
+            width: 100,
+            height: 100,
+            scale_factor: 1.0,
+            is_primary: false,
+        };
+
+        let displays = finish(vec![display("right", 100), display("left", -100), display("middle", 0)]);
+
+        let names: Vec<_> = displays.iter().map(|d| d.name.as_str()).collect();
+        assert_eq!(names, ["left", "middle", "right"]);
+        assert_eq!(displays.iter().map(|d| d.id).collect::<Vec<_>>(), [0, 1, 2]);
+        assert!(displays[1].is_primary);
+        assert!(!displays[0].is_primary);
+    }
+}
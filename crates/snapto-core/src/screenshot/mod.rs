@@ -3,10 +3,26 @@
 //! Provides functionality to capture screenshots on multiple platforms:
 //! - Full screen capture
 //! - Region selection capture
-//! - Window capture (future)
+//! - Window capture
+//!
+//! On Linux the work is done by a [`backend::CaptureBackend`] chosen for the
+//! session (grim on wlroots Wayland, the desktop portal on GNOME, a native
+//! X11 grab on X11).
 
-use image::{DynamicImage, ImageBuffer, Rgba, RgbaImage};
+#[cfg(target_os = "linux")]
+pub mod backend;
+#[cfg(target_os = "linux")]
+mod displays;
+#[cfg(target_os = "linux")]
+mod portal;
+#[cfg(target_os = "linux")]
+mod x11;
+
+use image::codecs::avif::AvifEncoder;
+use image::codecs::jpeg::JpegEncoder;
+use image::{DynamicImage, GenericImageView, ImageEncoder};
 use std::io::Cursor;
+use std::str::FromStr;
 
 #[cfg(target_os = "macos")]
 use std::process::Command;
@@ -22,8 +38,21 @@ pub struct ScreenshotConfig {
     pub include_cursor: bool,
     /// Delay before capture in milliseconds
     pub delay_ms: u64,
+    /// Capture backend to use on Linux; `None` or `auto` detects one
+    pub backend: Option<String>,
 }
 
+/// Capture backends that can be selected on Linux
+pub const BACKEND_NAMES: &[&str] = &[
+    "x11",
+    "grim",
+    "portal",
+    "spectacle",
+    "maim",
+    "scrot",
+    "gnome-screenshot",
+];
+
 impl Default for ScreenshotConfig {
     fn default() -> Self {
         Self {
@@ -31,6 +60,7 @@ impl Default for ScreenshotConfig {
             quality: 90,
             include_cursor: false,
             delay_ms: 0,
+            backend: None,
         }
     }
 }
@@ -41,6 +71,7 @@ pub enum ImageFormat {
     Png,
     Jpeg,
     WebP,
+    Avif,
 }
 
 impl ImageFormat {
@@ -49,6 +80,7 @@ impl ImageFormat {
             ImageFormat::Png => "png",
             ImageFormat::Jpeg => "jpg",
             ImageFormat::WebP => "webp",
+            ImageFormat::Avif => "avif",
         }
     }
 
@@ -57,12 +89,24 @@ impl ImageFormat {
             ImageFormat::Png => "image/png",
             ImageFormat::Jpeg => "image/jpeg",
             ImageFormat::WebP => "image/webp",
+            ImageFormat::Avif => "image/avif",
+        }
+    }
+
+    /// Format for a file extension, if supported
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.trim_start_matches('.').to_lowercase().as_str() {
+            "png" => Some(ImageFormat::Png),
+            "jpg" | "jpeg" => Some(ImageFormat::Jpeg),
+            "webp" => Some(ImageFormat::WebP),
+            "avif" => Some(ImageFormat::Avif),
+            _ => None,
         }
     }
 }
 
 /// Screen region for capture
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Region {
     pub x: i32,
     pub y: i32,
@@ -70,6 +114,37 @@ pub struct Region {
     pub height: u32,
 }
 
+impl FromStr for Region {
+    type Err = ScreenshotError;
+
+    /// Parses `x,y,width,height`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ScreenshotError::InvalidRegion {
+            message: format!("expected x,y,width,height, got '{}'", s),
+        };
+
+        let parts: Vec<&str> = s.split(',').map(str::trim).collect();
+        let [x, y, width, height] = parts.as_slice() else {
+            return Err(invalid());
+        };
+
+        let region = Region {
+            x: x.parse().map_err(|_| invalid())?,
+            y: y.parse().map_err(|_| invalid())?,
+            width: width.parse().map_err(|_| invalid())?,
+            height: height.parse().map_err(|_| invalid())?,
+        };
+
+        if region.width == 0 || region.height == 0 {
+            return Err(ScreenshotError::InvalidRegion {
+                message: "width and height must be greater than zero".to_string(),
+            });
+        }
+
+        Ok(region)
+    }
+}
+
 /// Screenshot capture result
 #[derive(Debug)]
 pub struct CaptureResult {
@@ -83,6 +158,33 @@ pub struct CaptureResult {
     pub format: ImageFormat,
 }
 
+/// AVIF encoder speed (1 slowest and smallest, 10 fastest)
+const AVIF_SPEED: u8 = 6;
+
+/// Encodes `img` as `format`
+///
+/// `quality` (1-100) applies to JPEG and AVIF; PNG and WebP are lossless.
+/// JPEG has no alpha channel, so transparency is dropped.
+pub fn encode_image(img: &DynamicImage, format: ImageFormat, quality: u8) -> Result<Vec<u8>, ScreenshotError> {
+    let mut buffer = Cursor::new(Vec::new());
+    let quality = quality.clamp(1, 100);
+    let (width, height) = (img.width(), img.height());
+
+    let result = match format {
+        ImageFormat::Png => img.write_to(&mut buffer, image::ImageFormat::Png),
+        ImageFormat::Jpeg => JpegEncoder::new_with_quality(&mut buffer, quality)
+            .write_image(&img.to_rgb8(), width, height, image::ExtendedColorType::Rgb8),
+        ImageFormat::WebP => img.write_to(&mut buffer, image::ImageFormat::WebP),
+        ImageFormat::Avif => AvifEncoder::new_with_speed_quality(&mut buffer, AVIF_SPEED, quality)
+            .write_image(&img.to_rgba8(), width, height, image::ExtendedColorType::Rgba8),
+    };
+
+    result.map_err(|e| ScreenshotError::ConversionError {
+        message: format!("Failed to encode {}: {}", format.extension().to_uppercase(), e),
+    })?;
+    Ok(buffer.into_inner())
+}
+
 /// Screenshot capture manager
 pub struct ScreenshotManager {
     config: ScreenshotConfig,
@@ -101,16 +203,19 @@ impl ScreenshotManager {
         Self { config }
     }
 
+    /// Wait for the configured delay before capturing
+    fn apply_delay(&self) {
+        if self.config.delay_ms > 0 {
+            std::thread::sleep(std::time::Duration::from_millis(self.config.delay_ms));
+        }
+    }
+
     /// Capture the entire screen
     #[cfg(target_os = "macos")]
     pub fn capture_fullscreen(&self) -> Result<CaptureResult, ScreenshotError> {
         use std::fs;
-        use std::path::PathBuf;
 
-        // Apply delay if configured
-        if self.config.delay_ms > 0 {
-            std::thread::sleep(std::time::Duration::from_millis(self.config.delay_ms));
-        }
+        self.apply_delay();
 
         // Use macOS screencapture command
         let temp_path = std::env::temp_dir().join(format!(
@@ -168,10 +273,7 @@ impl ScreenshotManager {
     pub fn capture_region(&self, region: Region) -> Result<CaptureResult, ScreenshotError> {
         use std::fs;
 
-        // Apply delay if configured
-        if self.config.delay_ms > 0 {
-            std::thread::sleep(std::time::Duration::from_millis(self.config.delay_ms));
-        }
+        self.apply_delay();
 
         let temp_path = std::env::temp_dir().join(format!(
             "snapto_screenshot_{}.png",
@@ -225,6 +327,8 @@ impl ScreenshotManager {
     pub fn capture_interactive(&self) -> Result<CaptureResult, ScreenshotError> {
         use std::fs;
 
+        self.apply_delay();
+
         let temp_path = std::env::temp_dir().join(format!(
             "snapto_screenshot_{}.png",
             uuid::Uuid::new_v4()
@@ -278,6 +382,8 @@ impl ScreenshotManager {
     pub fn capture_window(&self, window_id: u32) -> Result<CaptureResult, ScreenshotError> {
         use std::fs;
 
+        self.apply_delay();
+
         let temp_path = std::env::temp_dir().join(format!(
             "snapto_screenshot_{}.png",
             uuid::Uuid::new_v4()
@@ -320,35 +426,61 @@ impl ScreenshotManager {
         })
     }
 
+    /// Capture one display (IDs from [`list_displays`](Self::list_displays))
+    #[cfg(target_os = "macos")]
+    pub fn capture_display(&self, display_id: u32) -> Result<CaptureResult, ScreenshotError> {
+        use std::fs;
+
+        self.apply_delay();
+
+        let temp_path = std::env::temp_dir().join(format!(
+            "snapto_screenshot_{}.png",
+            uuid::Uuid::new_v4()
+        ));
+
+        // screencapture numbers displays from 1
+        let mut cmd = Command::new("screencapture");
+        cmd.arg("-x")
+            .arg("-D")
+            .arg((display_id + 1).to_string())
+            .arg(&temp_path);
+
+        let output = cmd.output().map_err(|e| ScreenshotError::CaptureError {
+            message: format!("Failed to execute screencapture: {}", e),
+        })?;
+
+        if !output.status.success() || !temp_path.exists() {
+            return Err(ScreenshotError::DisplayNotFound { id: display_id });
+        }
+
+        let data = fs::read(&temp_path).map_err(|e| ScreenshotError::CaptureError {
+            message: format!("Failed to read screenshot: {}", e),
+        })?;
+
+        let _ = fs::remove_file(&temp_path);
+
+        let img = image::load_from_memory(&data).map_err(|e| ScreenshotError::CaptureError {
+            message: format!("Failed to load image: {}", e),
+        })?;
+
+        let (width, height) = img.dimensions();
+        let (data, format) = self.convert_format(img)?;
+
+        Ok(CaptureResult {
+            data,
+            width,
+            height,
+            format,
+        })
+    }
+
     /// Convert image to the configured format
     fn convert_format(
         &self,
         img: DynamicImage,
     ) -> Result<(Vec<u8>, ImageFormat), ScreenshotError> {
-        let mut buffer = Cursor::new(Vec::new());
-
-        match self.config.format {
-            ImageFormat::Png => {
-                img.write_to(&mut buffer, image::ImageFormat::Png)
-                    .map_err(|e| ScreenshotError::ConversionError {
-                        message: format!("Failed to encode PNG: {}", e),
-                    })?;
-            }
-            ImageFormat::Jpeg => {
-                img.write_to(&mut buffer, image::ImageFormat::Jpeg)
-                    .map_err(|e| ScreenshotError::ConversionError {
-                        message: format!("Failed to encode JPEG: {}", e),
-                    })?;
-            }
-            ImageFormat::WebP => {
-                img.write_to(&mut buffer, image::ImageFormat::WebP)
-                    .map_err(|e| ScreenshotError::ConversionError {
-                        message: format!("Failed to encode WebP: {}", e),
-                    })?;
-            }
-        }
-
-        Ok((buffer.into_inner(), self.config.format))
+        let data = encode_image(&img, self.config.format, self.config.quality)?;
+        Ok((data, self.config.format))
     }
 
     /// List available displays/monitors
@@ -359,11 +491,19 @@ impl ScreenshotManager {
         Ok(vec![DisplayInfo {
             id: 0,
             name: "Main Display".to_string(),
+            x: 0,
+            y: 0,
             width: 0,  // Would need Core Graphics to get actual values
             height: 0,
+            scale_factor: 1.0,
             is_primary: true,
         }])
     }
+    /// Title and application of a window; only X11 exposes them
+    #[cfg(target_os = "macos")]
+    pub fn window_info(_window_id: u32) -> Option<WindowInfo> {
+        None
+    }
 }
 
 impl Default for ScreenshotManager {
@@ -373,15 +513,77 @@ impl Default for ScreenshotManager {
 }
 
 /// Display/monitor information
-#[derive(Debug, Clone)]
+///
+/// Position and size are in desktop layout coordinates, the space capture
+/// regions use. On scaled outputs that is `scale_factor` times smaller
+/// than the physical resolution.
+#[derive(Debug, Clone, PartialEq)]
 pub struct DisplayInfo {
     pub id: u32,
     pub name: String,
+    pub x: i32,
+    pub y: i32,
     pub width: u32,
     pub height: u32,
+    /// Physical pixels per layout pixel
+    pub scale_factor: f64,
     pub is_primary: bool,
 }
 
+/// What a captured window is, where the platform can tell
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WindowInfo {
+    /// Window title
+    pub title: Option<String>,
+    /// Application (the X11 window class)
+    pub app: Option<String>,
+}
+
+impl DisplayInfo {
+    /// Area of the desktop covered by this display
+    pub fn region(&self) -> Region {
+        Region {
+            x: self.x,
+            y: self.y,
+            width: self.width,
+            height: self.height,
+        }
+    }
+}
+
+/// Bounding box of all displays
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn layout_bounds(displays: &[DisplayInfo]) -> Option<Region> {
+    let left = displays.iter().map(|d| d.x).min()?;
+    let top = displays.iter().map(|d| d.y).min()?;
+    let right = displays.iter().map(|d| d.x + d.width as i32).max()?;
+    let bottom = displays.iter().map(|d| d.y + d.height as i32).max()?;
+
+    (right > left && bottom > top).then(|| Region {
+        x: left,
+        y: top,
+        width: (right - left) as u32,
+        height: (bottom - top) as u32,
+    })
+}
+
+/// Maps a layout region onto a full-screen image of the `layout`
+///
+/// Screenshots of scaled outputs are larger than the layout, and the
+/// layout can start at negative coordinates.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn layout_to_image(region: Region, layout: Region, image_width: u32) -> Region {
+    let scale = image_width as f64 / layout.width as f64;
+    let scaled = |v: f64| (v * scale).round();
+
+    Region {
+        x: scaled((region.x - layout.x) as f64) as i32,
+        y: scaled((region.y - layout.y) as f64) as i32,
+        width: scaled(region.width as f64) as u32,
+        height: scaled(region.height as f64) as u32,
+    }
+}
+
 /// Screenshot errors
 #[derive(Debug, thiserror::Error)]
 pub enum ScreenshotError {
@@ -397,139 +599,84 @@ pub enum ScreenshotError {
     #[error("Display not found: {id}")]
     DisplayNotFound { id: u32 },
 
+    #[error("Invalid region: {message}")]
+    InvalidRegion { message: String },
+
     #[error("Screenshot not supported on this platform")]
     NotSupported,
 }
 
-// Linux implementation using gnome-screenshot or scrot
-#[cfg(target_os = "linux")]
-impl ScreenshotManager {
-    pub fn capture_fullscreen(&self) -> Result<CaptureResult, ScreenshotError> {
-        use std::fs;
-
-        if self.config.delay_ms > 0 {
-            std::thread::sleep(std::time::Duration::from_millis(self.config.delay_ms));
-        }
-
-        let temp_path = std::env::temp_dir().join(format!(
-            "snapto_screenshot_{}.png",
-            uuid::Uuid::new_v4()
-        ));
-
-        // Try gnome-screenshot first, fallback to scrot
-        let result = Command::new("gnome-screenshot")
-            .arg("-f")
-            .arg(&temp_path)
-            .output();
-
-        let output = match result {
-            Ok(o) if o.status.success() => o,
-            _ => {
-                // Fallback to scrot
-                Command::new("scrot")
-                    .arg(&temp_path)
-                    .output()
-                    .map_err(|e| ScreenshotError::CaptureError {
-                        message: format!("Failed to capture screenshot: {}", e),
-                    })?
-            }
-        };
-
-        if !temp_path.exists() {
-            return Err(ScreenshotError::CaptureError {
-                message: "Screenshot file not created".to_string(),
-            });
+/// Crops `region` out of a full-screen image, clamped to its bounds
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn crop_region(img: &DynamicImage, region: Region) -> Result<DynamicImage, ScreenshotError> {
+    let (img_width, img_height) = img.dimensions();
+    let visible = crate::image_ops::clip(region, img_width, img_height).ok_or_else(|| {
+        ScreenshotError::InvalidRegion {
+            message: format!("region lies outside the {}x{} screen", img_width, img_height),
         }
+    })?;
 
-        let data = fs::read(&temp_path).map_err(|e| ScreenshotError::CaptureError {
-            message: format!("Failed to read screenshot: {}", e),
-        })?;
-
-        let _ = fs::remove_file(&temp_path);
-
-        let img = image::load_from_memory(&data).map_err(|e| ScreenshotError::CaptureError {
-            message: format!("Failed to load image: {}", e),
-        })?;
-
-        let (width, height) = img.dimensions();
-        let (data, format) = self.convert_format(img)?;
+    Ok(img.crop_imm(visible.x as u32, visible.y as u32, visible.width, visible.height))
+}
 
-        Ok(CaptureResult {
-            data,
-            width,
-            height,
-            format,
-        })
+// Linux implementation on top of the detected or configured backend
+#[cfg(target_os = "linux")]
+impl ScreenshotManager {
+    pub fn capture_fullscreen(&self) -> Result<CaptureResult, ScreenshotError> {
+        self.capture_with_backend(backend::CaptureMode::Fullscreen)
     }
 
     pub fn capture_region(&self, region: Region) -> Result<CaptureResult, ScreenshotError> {
-        // Capture full screen and crop
-        let full = self.capture_fullscreen()?;
-        let img = image::load_from_memory(&full.data).map_err(|e| ScreenshotError::CaptureError {
-            message: format!("Failed to load image: {}", e),
-        })?;
-
-        let cropped = img.crop_imm(
-            region.x as u32,
-            region.y as u32,
-            region.width,
-            region.height,
-        );
-
-        let (data, format) = self.convert_format(cropped)?;
-
-        Ok(CaptureResult {
-            data,
-            width: region.width,
-            height: region.height,
-            format,
-        })
+        self.capture_with_backend(backend::CaptureMode::Region(region))
     }
 
     pub fn capture_interactive(&self) -> Result<CaptureResult, ScreenshotError> {
-        use std::fs;
-
-        let temp_path = std::env::temp_dir().join(format!(
-            "snapto_screenshot_{}.png",
-            uuid::Uuid::new_v4()
-        ));
-
-        // Try gnome-screenshot with area selection
-        let result = Command::new("gnome-screenshot")
-            .arg("-a") // Area selection
-            .arg("-f")
-            .arg(&temp_path)
-            .output();
-
-        let success = match result {
-            Ok(o) => o.status.success() && temp_path.exists(),
-            Err(_) => false,
-        };
-
-        if !success {
-            // Fallback to scrot with selection
-            Command::new("scrot")
-                .arg("-s") // Selection mode
-                .arg(&temp_path)
-                .output()
-                .map_err(|e| ScreenshotError::CaptureError {
-                    message: format!("Failed to capture screenshot: {}", e),
-                })?;
-        }
+        self.capture_with_backend(backend::CaptureMode::Interactive)
+    }
 
-        if !temp_path.exists() {
-            return Err(ScreenshotError::Cancelled);
-        }
+    pub fn capture_window(&self, window_id: u32) -> Result<CaptureResult, ScreenshotError> {
+        self.capture_with_backend(backend::CaptureMode::Window(window_id))
+    }
 
-        let data = fs::read(&temp_path).map_err(|e| ScreenshotError::CaptureError {
-            message: format!("Failed to read screenshot: {}", e),
-        })?;
+    /// Capture one display (IDs from [`list_displays`](Self::list_displays))
+    pub fn capture_display(&self, display_id: u32) -> Result<CaptureResult, ScreenshotError> {
+        let display = Self::list_displays()?
+            .into_iter()
+            .find(|d| d.id == display_id)
+            .ok_or(ScreenshotError::DisplayNotFound { id: display_id })?;
 
-        let _ = fs::remove_file(&temp_path);
+        self.capture_with_backend(backend::CaptureMode::Region(display.region()))
+    }
 
-        let img = image::load_from_memory(&data).map_err(|e| ScreenshotError::CaptureError {
-            message: format!("Failed to load image: {}", e),
-        })?;
+    fn capture_with_backend(
+        &self,
+        mode: backend::CaptureMode,
+    ) -> Result<CaptureResult, ScreenshotError> {
+        use backend::CaptureMode;
+
+        let backend = backend::select(self.config.backend.as_deref(), &mode)?;
+        let include_cursor = self.config.include_cursor;
+
+        self.apply_delay();
+
+        let img = match mode {
+            CaptureMode::Region(region) if !backend.supports(&mode) => {
+                // Capture everything and crop, converting the layout
+                // region to image pixels on scaled desktops
+                let full = backend.grab(&CaptureMode::Fullscreen, include_cursor)?;
+                let region = match Self::list_displays().ok().as_deref().and_then(layout_bounds) {
+                    Some(layout) => layout_to_image(region, layout, full.width()),
+                    None => region,
+                };
+                crop_region(&full, region)?
+            }
+            _ if !backend.supports(&mode) => {
+                return Err(ScreenshotError::CaptureError {
+                    message: format!("{} cannot capture {:?}", backend.name(), mode),
+                });
+            }
+            _ => backend.grab(&mode, include_cursor)?,
+        };
 
         let (width, height) = img.dimensions();
         let (data, format) = self.convert_format(img)?;
@@ -542,18 +689,18 @@ impl ScreenshotManager {
         })
     }
 
-    pub fn capture_window(&self, _window_id: u32) -> Result<CaptureResult, ScreenshotError> {
-        Err(ScreenshotError::NotSupported)
+    /// Lists monitors, numbered left to right
+    pub fn list_displays() -> Result<Vec<DisplayInfo>, ScreenshotError> {
+        displays::list()
     }
 
-    pub fn list_displays() -> Result<Vec<DisplayInfo>, ScreenshotError> {
-        Ok(vec![DisplayInfo {
-            id: 0,
-            name: "Main Display".to_string(),
-            width: 0,
-            height: 0,
-            is_primary: true,
-        }])
+    /// Title and application of a window; only X11 exposes them
+    pub fn window_info(window_id: u32) -> Option<WindowInfo> {
+        if x11::has_display() {
+            x11::window_info(window_id)
+        } else {
+            None
+        }
     }
 }
 
@@ -576,9 +723,16 @@ impl ScreenshotManager {
         Err(ScreenshotError::NotSupported)
     }
 
+    pub fn capture_display(&self, _display_id: u32) -> Result<CaptureResult, ScreenshotError> {
+        Err(ScreenshotError::NotSupported)
+    }
+
     pub fn list_displays() -> Result<Vec<DisplayInfo>, ScreenshotError> {
         Ok(vec![])
     }
+    pub fn window_info(_window_id: u32) -> Option<WindowInfo> {
+        None
+    }
 }
 
 #[cfg(test)]
@@ -592,6 +746,67 @@ mod tests {
         assert_eq!(ImageFormat::WebP.extension(), "webp");
     }
 
+    #[test]
+    fn test_image_format_from_extension() {
+        assert_eq!(ImageFormat::from_extension("PNG"), Some(ImageFormat::Png));
+        assert_eq!(ImageFormat::from_extension("jpeg"), Some(ImageFormat::Jpeg));
+        assert_eq!(ImageFormat::from_extension(".webp"), Some(ImageFormat::WebP));
+        assert_eq!(ImageFormat::from_extension("gif"), None);
+    }
+
+    #[test]
+    fn test_region_parsing() {
+        let region: Region = "10, -20,300,200".parse().unwrap();
+        assert_eq!(region, Region { x: 10, y: -20, width: 300, height: 200 });
+
+        assert!("10,20,300".parse::<Region>().is_err());
+        assert!("a,b,c,d".parse::<Region>().is_err());
+        assert!("0,0,0,10".parse::<Region>().is_err());
+    }
+
+    #[test]
+    fn test_crop_region_is_clamped() {
+        let img = DynamicImage::new_rgba8(100, 50);
+
+        let cropped = crop_region(&img, Region { x: 80, y: -10, width: 50, height: 20 }).unwrap();
+        assert_eq!(cropped.dimensions(), (20, 10));
+        let cropped = crop_region(&img, Region { x: -30, y: 0, width: 50, height: 20 }).unwrap();
+        assert_eq!(cropped.dimensions(), (20, 20));
+
+        assert!(crop_region(&img, Region { x: 100, y: 0, width: 10, height: 10 }).is_err());
+        assert!(crop_region(&img, Region { x: -10, y: 0, width: 10, height: 10 }).is_err());
+    }
+
+    #[test]
+    fn test_layout_to_image() {
+        let display = |x: i32, width: u32| DisplayInfo {
+            id: 0,
+            name: String::new(),
+            x,
+            y: 0,
+            width,
+            height: 1080,
+            scale_factor: 1.0,
+            is_primary: false,
+        };
+        let layout = layout_bounds(&[display(-1920, 1920), display(0, 1920)]).unwrap();
+        assert_eq!(layout, Region { x: -1920, y: 0, width: 3840, height: 1080 });
+
+        let right = display(0, 1920).region();
+        // Unscaled: just shifted by the layout origin
+        assert_eq!(
+            layout_to_image(right, layout, 3840),
+            Region { x: 1920, y: 0, width: 1920, height: 1080 }
+        );
+        // Screenshot taken at 2x
+        assert_eq!(
+            layout_to_image(right, layout, 7680),
+            Region { x: 3840, y: 0, width: 3840, height: 2160 }
+        );
+
+        assert!(layout_bounds(&[]).is_none());
+    }
+
     #[test]
     fn test_default_config() {
         let config = ScreenshotConfig::default();
@@ -599,5 +814,6 @@ mod tests {
         assert_eq!(config.quality, 90);
         assert!(!config.include_cursor);
         assert_eq!(config.delay_ms, 0);
+        assert!(config.backend.is_none());
     }
 }
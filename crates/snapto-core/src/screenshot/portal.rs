@@ -0,0 +1,166 @@
+//! Screenshots through xdg-desktop-portal
+//!
+//! GNOME and KDE on Wayland do not let clients read the screen directly.
+//! The portal's `Screenshot` method asks the compositor instead, and the
+//! result arrives later as a `Response` signal on a request object.
+
+use std::collections::HashMap;
+use std::env;
+use std::fmt::Display;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::debug;
+use zbus::blocking::{Connection, MessageIterator, Proxy};
+use zbus::message::Type as MessageType;
+use zbus::zvariant::{OwnedObjectPath, OwnedValue, Value};
+use zbus::MatchRule;
+
+use super::backend::{CaptureBackend, CaptureMode};
+use super::ScreenshotError;
+
+const DESTINATION: &str = "org.freedesktop.portal.Desktop";
+const OBJECT_PATH: &str = "/org/freedesktop/portal/desktop";
+const SCREENSHOT_INTERFACE: &str = "org.freedesktop.portal.Screenshot";
+const REQUEST_INTERFACE: &str = "org.freedesktop.portal.Request";
+
+/// xdg-desktop-portal `Screenshot` backend
+pub struct Portal;
+
+impl CaptureBackend for Portal {
+    fn name(&self) -> &'static str {
+        "portal"
+    }
+
+    /// The portal needs a session bus; whether a portal implementation
+    /// answers is only known when calling it
+    fn is_available(&self) -> bool {
+        env::var_os("DBUS_SESSION_BUS_ADDRESS").is_some()
+            || env::var_os("XDG_RUNTIME_DIR")
+                .map(|dir| Path::new(&dir).join("bus").exists())
+                .unwrap_or(false)
+    }
+
+    fn supports(&self, mode: &CaptureMode) -> bool {
+        matches!(mode, CaptureMode::Fullscreen | CaptureMode::Interactive)
+    }
+
+    fn capture(
+        &self,
+        mode: &CaptureMode,
+        _include_cursor: bool,
+        output: &Path,
+    ) -> Result<(), ScreenshotError> {
+        let uri = request_screenshot(matches!(mode, CaptureMode::Interactive))?;
+        let saved = uri_to_path(&uri).ok_or_else(|| ScreenshotError::CaptureError {
+            message: format!("portal returned an unsupported URI: {}", uri),
+        })?;
+
+        debug!("Portal saved screenshot to {}", saved.display());
+        fs::copy(&saved, output).map_err(|e| ScreenshotError::CaptureError {
+            message: format!("Failed to copy {}: {}", saved.display(), e),
+        })?;
+
+        // The portal saves into the user's pictures folder; snapto keeps
+        // its own copy, so do not leave a stray file behind
+        let _ = fs::remove_file(&saved);
+        Ok(())
+    }
+}
+
+fn portal_error(e: impl Display) -> ScreenshotError {
+    ScreenshotError::CaptureError {
+        message: format!("xdg-desktop-portal: {}", e),
+    }
+}
+
+/// Calls `Screenshot` and waits for the URI of the saved image
+fn request_screenshot(interactive: bool) -> Result<String, ScreenshotError> {
+    let conn = Connection::session().map_err(portal_error)?;
+
+    // The request path is predictable from our bus name and a token, so
+    // subscribe before calling and a fast response cannot be missed
+    let token = format!("snapto_{}", uuid::Uuid::new_v4().simple());
+    let sender = conn
+        .unique_name()
+        .map(|name| name.trim_start_matches(':').replace('.', "_"))
+        .ok_or_else(|| portal_error("connection has no bus name"))?;
+    let handle = format!("{}/request/{}/{}", OBJECT_PATH, sender, token);
+
+    let rule = MatchRule::builder()
+        .msg_type(MessageType::Signal)
+        .interface(REQUEST_INTERFACE)
+        .and_then(|b| b.member("Response"))
+        .and_then(|b| b.path(handle.as_str()))
+        .map_err(portal_error)?
+        .build();
+    let mut responses = MessageIterator::for_match_rule(rule, &conn, Some(1)).map_err(portal_error)?;
+
+    let proxy = Proxy::new(&conn, DESTINATION, OBJECT_PATH, SCREENSHOT_INTERFACE)
+        .map_err(portal_error)?;
+
+    let mut options: HashMap<&str, Value> = HashMap::new();
+    options.insert("handle_token", Value::from(token.as_str()));
+    options.insert("interactive", Value::from(interactive));
+    let _: OwnedObjectPath = proxy
+        .call("Screenshot", &("", options))
+        .map_err(portal_error)?;
+
+    let message = responses
+        .next()
+        .ok_or_else(|| portal_error("connection closed before the screenshot finished"))?
+        .map_err(portal_error)?;
+    let (response, results): (u32, HashMap<String, OwnedValue>) =
+        message.body().deserialize().map_err(portal_error)?;
+
+    match response {
+        0 => {}
+        1 => return Err(ScreenshotError::Cancelled),
+        code => return Err(portal_error(format!("screenshot request failed (response {})", code))),
+    }
+
+    results
+        .get("uri")
+        .and_then(|uri| <&str>::try_from(uri).ok())
+        .map(str::to_string)
+        .ok_or_else(|| portal_error("response has no uri"))
+}
+
+/// Converts a `file://` URI to a path, decoding `%XX` escapes
+fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let encoded = uri.strip_prefix("file://")?;
+    let bytes = encoded.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = encoded.get(i + 1..i + 3)?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+
+    Some(PathBuf::from(String::from_utf8(decoded).ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_uri_to_path() {
+        assert_eq!(
+            uri_to_path("file:///home/me/Pictures/Screenshot%20from%202024.png"),
+            Some(PathBuf::from("/home/me/Pictures/Screenshot from 2024.png"))
+        );
+        assert_eq!(
+            uri_to_path("file:///tmp/caf%C3%A9.png"),
+            Some(PathBuf::from("/tmp/café.png"))
+        );
+        assert_eq!(uri_to_path("https://example.com/a.png"), None);
+        assert_eq!(uri_to_path("file:///tmp/bad%2"), None);
+    }
+}
@@ -0,0 +1,634 @@
+//! Native X11 capture through x11rb
+//!
+//! Reads pixels from the root window with MIT-SHM when the server supports
+//! it, falling back to a plain `GetImage` request, so capturing on X11 needs
+//! no external tools. Monitors are listed through XRandR.
+
+use image::{DynamicImage, ImageFormat as ImageFileFormat, Rgba, RgbaImage};
+use std::env;
+use std::fmt::Display;
+use std::fs::{self, File, OpenOptions};
+use std::os::unix::fs::FileExt;
+use std::path::{Path, PathBuf};
+use tracing::debug;
+use x11rb::connection::{Connection, RequestConnection};
+use x11rb::protocol::randr::ConnectionExt as _;
+use x11rb::protocol::shm::{self, ConnectionExt as _};
+use x11rb::protocol::xfixes::ConnectionExt as _;
+use x11rb::protocol::xproto::{
+    AtomEnum, ConnectionExt as _, ImageFormat, ImageOrder, Screen, Window,
+};
+use x11rb::rust_connection::RustConnection;
+
+use super::backend::{CaptureBackend, CaptureMode};
+use super::{DisplayInfo, Region, ScreenshotError, WindowInfo};
+
+/// Native X11 backend
+pub struct X11;
+
+impl CaptureBackend for X11 {
+    fn name(&self) -> &'static str {
+        "x11"
+    }
+
+    fn is_available(&self) -> bool {
+        has_display()
+    }
+
+    fn supports(&self, mode: &CaptureMode) -> bool {
+        !matches!(mode, CaptureMode::Interactive)
+    }
+
+    fn capture(
+        &self,
+        mode: &CaptureMode,
+        include_cursor: bool,
+        output: &Path,
+    ) -> Result<(), ScreenshotError> {
+        self.grab(mode, include_cursor)?
+            .save_with_format(output, ImageFileFormat::Png)
+            .map_err(|e| ScreenshotError::CaptureError {
+                message: format!("Failed to write {}: {}", output.display(), e),
+            })
+    }
+
+    fn grab(&self, mode: &CaptureMode, include_cursor: bool) -> Result<DynamicImage, ScreenshotError> {
+        let display = X11Display::open()?;
+
+        let area = match mode {
+            CaptureMode::Fullscreen => display.screen_area(),
+            CaptureMode::Region(region) => *region,
+            CaptureMode::Window(id) => display.window_area(*id)?,
+            CaptureMode::Interactive => {
+                return Err(ScreenshotError::CaptureError {
+                    message: "x11 cannot select an area; use maim or scrot".to_string(),
+                })
+            }
+        };
+
+        let mut image = display.read_area(area)?;
+        if include_cursor {
+            display.draw_cursor(&mut image, area);
+        }
+        Ok(DynamicImage::ImageRgba8(image))
+    }
+}
+
+/// Whether an X display is configured
+pub fn has_display() -> bool {
+    env::var_os("DISPLAY").is_some_and(|display| !display.is_empty())
+}
+
+/// Monitors reported by XRandR, or the whole screen without RandR 1.5
+///
+/// The order and primary flag are left as the server reports them.
+pub fn list_displays() -> Result<Vec<DisplayInfo>, ScreenshotError> {
+    X11Display::open()?.monitors()
+}
+
+/// Title and application class of a window, for naming its screenshot
+pub fn window_info(window: Window) -> Option<WindowInfo> {
+    let display = X11Display::open().ok()?;
+    Some(WindowInfo {
+        title: display.window_title(window),
+        app: display.window_class(window),
+    })
+}
+
+fn x11_error(e: impl Display) -> ScreenshotError {
+    ScreenshotError::CaptureError {
+        message: format!("X11: {}", e),
+    }
+}
+
+/// Layout of pixels in a Z-format image
+#[derive(Debug, Clone, Copy)]
+struct PixelLayout {
+    bits_per_pixel: u8,
+    scanline_pad: u8,
+    lsb_first: bool,
+    red_mask: u32,
+    green_mask: u32,
+    blue_mask: u32,
+}
+
+impl PixelLayout {
+    /// Bytes per row of an image `width` pixels wide
+    fn stride(&self, width: u32) -> usize {
+        let pad = self.scanline_pad.max(8) as usize;
+        let bits = width as usize * self.bits_per_pixel as usize;
+        bits.div_ceil(pad) * pad / 8
+    }
+
+    /// Converts raw image data to RGBA
+    fn decode(&self, data: &[u8], width: u32, height: u32) -> Result<RgbaImage, ScreenshotError> {
+        let bytes_per_pixel = match self.bits_per_pixel {
+            16 | 24 | 32 => self.bits_per_pixel as usize / 8,
+            bpp => {
+                return Err(x11_error(format!("unsupported {} bits per pixel", bpp)));
+            }
+        };
+
+        let stride = self.stride(width);
+        if data.len() < stride * height as usize {
+            return Err(x11_error("image data is shorter than expected"));
+        }
+
+        let mut image = RgbaImage::new(width, height);
+        for (y, row) in data.chunks_exact(stride).take(height as usize).enumerate() {
+            for x in 0..width as usize {
+                let bytes = &row[x * bytes_per_pixel..(x + 1) * bytes_per_pixel];
+                let pixel = bytes.iter().enumerate().fold(0u32, |acc, (i, &b)| {
+                    let shift = if self.lsb_first { i } else { bytes_per_pixel - 1 - i };
+                    acc | (b as u32) << (shift * 8)
+                });
+
+                image.put_pixel(
+                    x as u32,
+                    y as u32,
+                    Rgba([
+                        channel(pixel, self.red_mask),
+                        channel(pixel, self.green_mask),
+                        channel(pixel, self.blue_mask),
+                        255,
+                    ]),
+                );
+            }
+        }
+        Ok(image)
+    }
+}
+
+/// Extracts the channel selected by `mask`, scaled to 8 bits
+fn channel(pixel: u32, mask: u32) -> u8 {
+    if mask == 0 {
+        return 0;
+    }
+
+    let shift = mask.trailing_zeros();
+    let bits = (mask >> shift).count_ones();
+    let value = (pixel & mask) >> shift;
+
+    if bits >= 8 {
+        (value >> (bits - 8)) as u8
+    } else {
+        (value * 255 / ((1 << bits) - 1)) as u8
+    }
+}
+
+/// An open connection to the X server
+struct X11Display {
+    conn: RustConnection,
+    screen_num: usize,
+}
+
+impl X11Display {
+    fn open() -> Result<Self, ScreenshotError> {
+        let (conn, screen_num) = x11rb::connect(None).map_err(x11_error)?;
+        Ok(Self { conn, screen_num })
+    }
+
+    fn screen(&self) -> &Screen {
+        &self.conn.setup().roots[self.screen_num]
+    }
+
+    fn screen_area(&self) -> Region {
+        let screen = self.screen();
+        Region {
+            x: 0,
+            y: 0,
+            width: screen.width_in_pixels as u32,
+            height: screen.height_in_pixels as u32,
+        }
+    }
+
+    /// Pixel layout of the root window
+    fn layout(&self) -> Result<PixelLayout, ScreenshotError> {
+        let setup = self.conn.setup();
+        let screen = self.screen();
+
+        let format = setup
+            .pixmap_formats
+            .iter()
+            .find(|f| f.depth == screen.root_depth)
+            .ok_or_else(|| x11_error("no pixmap format for the root depth"))?;
+        let visual = screen
+            .allowed_depths
+            .iter()
+            .flat_map(|d| &d.visuals)
+            .find(|v| v.visual_id == screen.root_visual)
+            .ok_or_else(|| x11_error("root visual not found"))?;
+
+        Ok(PixelLayout {
+            bits_per_pixel: format.bits_per_pixel,
+            scanline_pad: format.scanline_pad,
+            lsb_first: setup.image_byte_order == ImageOrder::LSB_FIRST,
+            red_mask: visual.red_mask,
+            green_mask: visual.green_mask,
+            blue_mask: visual.blue_mask,
+        })
+    }
+
+    /// Screen area covered by a window, borders included
+    fn window_area(&self, window: Window) -> Result<Region, ScreenshotError> {
+        let geometry = self
+            .conn
+            .get_geometry(window)
+            .map_err(x11_error)?
+            .reply()
+            .map_err(|_| ScreenshotError::CaptureError {
+                message: format!("X11 window 0x{:x} not found", window),
+            })?;
+        let origin = self
+            .conn
+            .translate_coordinates(window, self.screen().root, 0, 0)
+            .map_err(x11_error)?
+            .reply()
+            .map_err(x11_error)?;
+
+        let border = geometry.border_width as i32;
+        Ok(Region {
+            x: origin.dst_x as i32 - border,
+            y: origin.dst_y as i32 - border,
+            width: geometry.width as u32 + 2 * border as u32,
+            height: geometry.height as u32 + 2 * border as u32,
+        })
+    }
+
+    /// Reads `area` of the root window, clamped to the screen
+    fn read_area(&self, area: Region) -> Result<RgbaImage, ScreenshotError> {
+        let screen = self.screen_area();
+        let x = area.x.max(0);
+        let y = area.y.max(0);
+        let right = (area.x + area.width as i32).min(screen.width as i32);
+        let bottom = (area.y + area.height as i32).min(screen.height as i32);
+
+        if right <= x || bottom <= y {
+            return Err(ScreenshotError::InvalidRegion {
+                message: format!("region is outside the {}x{} screen", screen.width, screen.height),
+            });
+        }
+
+        let clamped = Region {
+            x,
+            y,
+            width: (right - x) as u32,
+            height: (bottom - y) as u32,
+        };
+        let layout = self.layout()?;
+
+        let data = match self.read_shm(clamped, &layout) {
+            Ok(data) => data,
+            Err(e) => {
+                debug!("MIT-SHM capture unavailable ({}), using GetImage", e);
+                self.read_get_image(clamped)?
+            }
+        };
+
+        layout.decode(&data, clamped.width, clamped.height)
+    }
+
+    fn read_get_image(&self, area: Region) -> Result<Vec<u8>, ScreenshotError> {
+        let reply = self
+            .conn
+            .get_image(
+                ImageFormat::Z_PIXMAP,
+                self.screen().root,
+                area.x as i16,
+                area.y as i16,
+                area.width as u16,
+                area.height as u16,
+                !0,
+            )
+            .map_err(x11_error)?
+            .reply()
+            .map_err(x11_error)?;
+        Ok(reply.data)
+    }
+
+    /// Reads through a shared memory segment backed by an unlinked file
+    ///
+    /// Uses the fd-passing variant of MIT-SHM (1.2), which needs no SysV
+    /// shared memory and only works with a local server.
+    fn read_shm(&self, area: Region, layout: &PixelLayout) -> Result<Vec<u8>, ScreenshotError> {
+        if self
+            .conn
+            .extension_information(shm::X11_EXTENSION_NAME)
+            .map_err(x11_error)?
+            .is_none()
+        {
+            return Err(x11_error("MIT-SHM extension missing"));
+        }
+        let version = self.conn.shm_query_version().map_err(x11_error)?.reply().map_err(x11_error)?;
+        if (version.major_version, version.minor_version) < (1, 2) {
+            return Err(x11_error("MIT-SHM 1.2 required for fd passing"));
+        }
+
+        let size = layout.stride(area.width) * area.height as usize;
+        let file = shm_file(size as u64).map_err(x11_error)?;
+
+        let segment = self.conn.generate_id().map_err(x11_error)?;
+        self.conn
+            .shm_attach_fd(segment, file.try_clone().map_err(x11_error)?, false)
+            .map_err(x11_error)?
+            .check()
+            .map_err(x11_error)?;
+
+        let reply = self
+            .conn
+            .shm_get_image(
+                self.screen().root,
+                area.x as i16,
+                area.y as i16,
+                area.width as u16,
+                area.height as u16,
+                !0,
+                ImageFormat::Z_PIXMAP.into(),
+                segment,
+                0,
+            )
+            .map_err(x11_error)
+            .and_then(|cookie| cookie.reply().map_err(x11_error));
+        let _ = self.conn.shm_detach(segment);
+        let reply = reply?;
+
+        let mut data = vec![0u8; (reply.size as usize).min(size)];
+        file.read_exact_at(&mut data, 0).map_err(x11_error)?;
+        Ok(data)
+    }
+
+    /// Composites the pointer over an image of `area`
+    fn draw_cursor(&self, image: &mut RgbaImage, area: Region) {
+        let cursor = self
+            .conn
+            .xfixes_query_version(4, 0)
+            .ok()
+            .and_then(|cookie| cookie.reply().ok())
+            .and_then(|_| self.conn.xfixes_get_cursor_image().ok())
+            .and_then(|cookie| cookie.reply().ok());
+
+        let Some(cursor) = cursor else {
+            debug!("XFIXES unavailable, capturing without cursor");
+            return;
+        };
+
+        let left = cursor.x as i32 - cursor.xhot as i32 - area.x.max(0);
+        let top = cursor.y as i32 - cursor.yhot as i32 - area.y.max(0);
+        blend_cursor(image, &cursor.cursor_image, cursor.width as u32, left, top);
+    }
+
+    fn monitors(&self) -> Result<Vec<DisplayInfo>, ScreenshotError> {
+        let monitors = self
+            .conn
+            .randr_query_version(1, 5)
+            .ok()
+            .and_then(|cookie| cookie.reply().ok())
+            .filter(|v| (v.major_version, v.minor_version) >= (1, 5))
+            .and_then(|_| self.conn.randr_get_monitors(self.screen().root, true).ok())
+            .and_then(|cookie| cookie.reply().ok())
+            .map(|reply| reply.monitors)
+            .unwrap_or_default();
+
+        let scale_factor = self.scale_factor();
+
+        if monitors.is_empty() {
+            let screen = self.screen_area();
+            return Ok(vec![DisplayInfo {
+                id: 0,
+                name: format!("screen {}", self.screen_num),
+                x: 0,
+                y: 0,
+                width: screen.width,
+                height: screen.height,
+                scale_factor,
+                is_primary: true,
+            }]);
+        }
+
+        Ok(monitors
+            .iter()
+            .enumerate()
+            .map(|(i, monitor)| DisplayInfo {
+                id: i as u32,
+                name: self.atom_name(monitor.name).unwrap_or_else(|| format!("monitor {}", i)),
+                x: monitor.x as i32,
+                y: monitor.y as i32,
+                width: monitor.width as u32,
+                height: monitor.height as u32,
+                scale_factor,
+                is_primary: monitor.primary,
+            })
+            .collect())
+    }
+
+    /// Desktop scale from `Xft.dpi` in the root window's resources
+    ///
+    /// X11 has no per-monitor scale; desktops set one DPI for the screen.
+    fn scale_factor(&self) -> f64 {
+        self.conn
+            .get_property(
+                false,
+                self.screen().root,
+                AtomEnum::RESOURCE_MANAGER,
+                AtomEnum::STRING,
+                0,
+                u32::MAX / 4,
+            )
+            .ok()
+            .and_then(|cookie| cookie.reply().ok())
+            .map(|reply| xft_scale(&String::from_utf8_lossy(&reply.value)))
+            .unwrap_or(1.0)
+    }
+
+    /// Value of a text property, `None` when unset or empty
+    fn text_property(&self, window: Window, property: u32, kind: u32) -> Option<Vec<u8>> {
+        let reply = self
+            .conn
+            .get_property(false, window, property, kind, 0, u32::MAX / 4)
+            .ok()?
+            .reply()
+            .ok()?;
+        (!reply.value.is_empty()).then_some(reply.value)
+    }
+
+    /// `_NET_WM_NAME`, falling back to the legacy `WM_NAME`
+    fn window_title(&self, window: Window) -> Option<String> {
+        let atom = |name: &[u8]| Some(self.conn.intern_atom(true, name).ok()?.reply().ok()?.atom);
+        let utf8 = atom(b"UTF8_STRING");
+        let net_wm_name = atom(b"_NET_WM_NAME");
+
+        let value = net_wm_name
+            .zip(utf8)
+            .filter(|(name, utf8)| *name != 0 && *utf8 != 0)
+            .and_then(|(name, utf8)| self.text_property(window, name, utf8))
+            .or_else(|| self.text_property(window, AtomEnum::WM_NAME.into(), AtomEnum::STRING.into()))?;
+        Some(String::from_utf8_lossy(&value).into_owned())
+    }
+
+    /// Class part of `WM_CLASS` (`firefox`, `Alacritty`)
+    fn window_class(&self, window: Window) -> Option<String> {
+        let value = self.text_property(window, AtomEnum::WM_CLASS.into(), AtomEnum::STRING.into())?;
+        // Two NUL-terminated strings: instance, then class
+        let mut parts = value.split(|&b| b == 0).filter(|part| !part.is_empty());
+        let instance = parts.next()?;
+        let class = parts.next().unwrap_or(instance);
+        Some(String::from_utf8_lossy(class).into_owned())
+    }
+
+    fn atom_name(&self, atom: u32) -> Option<String> {
+        let reply = self.conn.get_atom_name(atom).ok()?.reply().ok()?;
+        String::from_utf8(reply.name).ok()
+    }
+}
+
+/// Scale factor for the `Xft.dpi` resource, relative to 96 DPI
+fn xft_scale(resources: &str) -> f64 {
+    resources
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(key, _)| key.trim() == "Xft.dpi")
+        .and_then(|(_, value)| value.trim().parse::<f64>().ok())
+        .filter(|dpi| *dpi > 0.0)
+        .map(|dpi| dpi / 96.0)
+        .unwrap_or(1.0)
+}
+
+/// Alpha-blends a premultiplied ARGB cursor with its top-left at `left, top`
+fn blend_cursor(image: &mut RgbaImage, pixels: &[u32], cursor_width: u32, left: i32, top: i32) {
+    if cursor_width == 0 {
+        return;
+    }
+
+    for (i, &argb) in pixels.iter().enumerate() {
+        let x = left + (i as u32 % cursor_width) as i32;
+        let y = top + (i as u32 / cursor_width) as i32;
+        if x < 0 || y < 0 || x >= image.width() as i32 || y >= image.height() as i32 {
+            continue;
+        }
+
+        let alpha = argb >> 24;
+        if alpha == 0 {
+            continue;
+        }
+
+        let dst = image.get_pixel_mut(x as u32, y as u32);
+        for (c, shift) in [16, 8, 0].into_iter().enumerate() {
+            let src = (argb >> shift) & 0xff;
+            dst.0[c] = (src + dst.0[c] as u32 * (255 - alpha) / 255).min(255) as u8;
+        }
+    }
+}
+
+/// Creates an unlinked file of `size` bytes to share with the server
+fn shm_file(size: u64) -> std::io::Result<File> {
+    let dir = Path::new("/dev/shm");
+    let dir = if dir.is_dir() { dir.to_path_buf() } else { env::temp_dir() };
+    let path: PathBuf = dir.join(format!("snapto-shm-{}", uuid::Uuid::new_v4()));
+
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create_new(true)
+        .open(&path)?;
+    let _ = fs::remove_file(&path);
+
+    file.set_len(size)?;
+    Ok(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BGRX: PixelLayout = PixelLayout {
+        bits_per_pixel: 32,
+        scanline_pad: 32,
+        lsb_first: true,
+        red_mask: 0xff0000,
+        green_mask: 0x00ff00,
+        blue_mask: 0x0000ff,
+    };
+
+    #[test]
+    fn test_channel_scaling() {
+        assert_eq!(channel(0x00ab0000, 0xff0000), 0xab);
+        // 5-bit red in RGB565
+        assert_eq!(channel(0xf800, 0xf800), 255);
+        assert_eq!(channel(0x0000, 0xf800), 0);
+        assert_eq!(channel(0x1234, 0), 0);
+    }
+
+    #[test]
+    fn test_bgrx_to_rgba() {
+        let data = [0x30, 0x20, 0x10, 0x00, 0x03, 0x02, 0x01, 0xff];
+        let image = BGRX.decode(&data, 2, 1).unwrap();
+
+        assert_eq!(image.get_pixel(0, 0), &Rgba([0x10, 0x20, 0x30, 255]));
+        assert_eq!(image.get_pixel(1, 0), &Rgba([0x01, 0x02, 0x03, 255]));
+    }
+
+    #[test]
+    fn test_rows_are_padded() {
+        let layout = PixelLayout {
+            bits_per_pixel: 24,
+            scanline_pad: 32,
+            ..BGRX
+        };
+        // 1 pixel of 3 bytes padded to 4 per row
+        assert_eq!(layout.stride(1), 4);
+
+        let data = [0x03, 0x02, 0x01, 0x00, 0x06, 0x05, 0x04, 0x00];
+        let image = layout.decode(&data, 1, 2).unwrap();
+        assert_eq!(image.get_pixel(0, 1), &Rgba([0x04, 0x05, 0x06, 255]));
+
+        assert!(layout.decode(&data[..6], 1, 2).is_err());
+    }
+
+    #[test]
+    fn test_msb_first_order() {
+        let layout = PixelLayout { lsb_first: false, ..BGRX };
+        let data = [0x00, 0x10, 0x20, 0x30];
+        let image = layout.decode(&data, 1, 1).unwrap();
+        assert_eq!(image.get_pixel(0, 0), &Rgba([0x10, 0x20, 0x30, 255]));
+    }
+
+    #[test]
+    fn test_xft_scale() {
+        assert_eq!(xft_scale("Xft.antialias:\t1\nXft.dpi:\t192\n"), 2.0);
+        assert_eq!(xft_scale("Xft.dpi: 144"), 1.5);
+        assert_eq!(xft_scale("Xcursor.size:\t24\n"), 1.0);
+        assert_eq!(xft_scale("Xft.dpi:\tlarge"), 1.0);
+    }
+
+    #[test]
+    fn test_blend_cursor_is_clipped() {
+        let mut image = RgbaImage::from_pixel(2, 2, Rgba([0, 0, 0, 255]));
+        // Opaque white, half-transparent (premultiplied) red, transparent
+        let cursor = [0xffffffff, 0x80800000, 0x00000000, 0xffffffff];
+
+        blend_cursor(&mut image, &cursor, 2, 1, 0);
+
+        assert_eq!(image.get_pixel(1, 0), &Rgba([255, 255, 255, 255]));
+        assert_eq!(image.get_pixel(1, 1), &Rgba([0, 0, 0, 255]));
+        assert_eq!(image.get_pixel(0, 0), &Rgba([0, 0, 0, 255]));
+    }
+
+    /// Runs against a real server, e.g. `xvfb-run cargo test -- --ignored`
+    #[test]
+    #[ignore = "needs a running X server"]
+    fn test_capture_on_live_display() {
+        assert!(has_display(), "DISPLAY is not set");
+        X11Display::open().unwrap();
+
+        let displays = list_displays().unwrap();
+        assert!(!displays.is_empty());
+        assert!(displays.iter().all(|d| d.width > 0 && d.scale_factor > 0.0));
+
+        let full = X11.grab(&CaptureMode::Fullscreen, true).unwrap();
+        let region = Region { x: 1, y: 2, width: 30, height: 20 };
+        let part = X11.grab(&CaptureMode::Region(region), false).unwrap();
+
+        assert!(full.width() >= 31 && full.height() >= 22);
+        assert_eq!((part.width(), part.height()), (30, 20));
+    }
+}
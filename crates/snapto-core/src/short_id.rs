@@ -0,0 +1,271 @@
+//! Short IDs for `{id:N}` in naming templates
+//!
+//! An ID is N characters from an alphabet, either drawn at random or taken
+//! from a persistent counter pushed through a keyed permutation (a small
+//! Feistel network), so consecutive uploads get unrelated-looking IDs that
+//! never repeat until the N-character space is used up. Without the key the
+//! permutation cannot be undone, so IDs reveal neither order nor count.
+//!
+//! A sequence counts up to 2^62 at most (11 base62 or 13 Crockford
+//! characters); longer IDs start with characters derived from the key and
+//! the permuted value, so they still look random.
+
+use rand::Rng;
+use sha2::{Digest, Sha256};
+use std::path::Path;
+
+use crate::config::{IdMode, NamingConfig};
+use crate::counter::CounterStore;
+use crate::error::{ConfigError, Result};
+
+/// Digits and both cases: the shortest IDs for a given count
+pub const BASE62: &str = "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+/// Crockford's base32 in lowercase: no i, l, o or u to misread or spell words
+pub const CROCKFORD: &str = "0123456789abcdefghjkmnpqrstvwxyz";
+
+/// Longest `{id:N}`
+pub const MAX_ID_LENGTH: usize = 32;
+
+/// Largest sequence space, so the permutation math stays within u64
+const MAX_SPACE: u64 = 1 << 62;
+
+/// Feistel rounds; four make a strong pseudo-random permutation
+const ROUNDS: u8 = 4;
+
+/// Characters IDs are spelled with
+#[derive(Debug, Clone, PartialEq)]
+pub struct Alphabet(Vec<char>);
+
+impl Alphabet {
+    /// `base62`, `crockford`, or the characters themselves (`"abcdefghkmnpqrstuvwxyz23456789"`)
+    pub fn parse(spec: &str) -> std::result::Result<Self, String> {
+        let chars: Vec<char> = match spec {
+            "base62" => BASE62.chars().collect(),
+            "crockford" => CROCKFORD.chars().collect(),
+            custom => custom.chars().collect(),
+        };
+
+        if chars.len() < 2 {
+            return Err(format!("'{}' needs at least 2 characters", spec));
+        }
+        if let Some(c) = chars
+            .iter()
+            .find(|c| matches!(c, '/' | '\\' | '.') || c.is_whitespace() || c.is_control())
+        {
+            return Err(format!("'{}' cannot be used in an id", c.escape_default()));
+        }
+        if let Some((i, c)) = chars.iter().enumerate().find(|(i, c)| chars[..*i].contains(c)) {
+            return Err(format!("'{}' appears twice (position {})", c, i + 1));
+        }
+        Ok(Alphabet(chars))
+    }
+
+    /// `length` characters picked uniformly at random
+    pub fn random(&self, length: usize) -> String {
+        let mut rng = rand::thread_rng();
+        (0..length).map(|_| self.0[rng.gen_range(0..self.0.len())]).collect()
+    }
+
+    /// `value` in this base, left-padded with the first character to `length`
+    pub fn encode(&self, mut value: u64, length: usize) -> String {
+        let base = self.0.len() as u64;
+        let mut digits = vec![self.0[0]; length];
+        for digit in digits.iter_mut().rev() {
+            *digit = self.0[(value % base) as usize];
+            value /= base;
+        }
+        digits.into_iter().collect()
+    }
+
+    /// Characters needed to write every value below `space`
+    fn width(&self, space: u64) -> usize {
+        let base = self.0.len() as u64;
+        let (mut width, mut covered) = (1, base);
+        while covered < space {
+            covered = covered.saturating_mul(base);
+            width += 1;
+        }
+        width
+    }
+
+    /// How many IDs of `length` characters a sequence can hand out
+    fn space(&self, length: usize) -> u64 {
+        (self.0.len() as u64)
+            .checked_pow(length as u32)
+            .map_or(MAX_SPACE, |space| space.min(MAX_SPACE))
+    }
+}
+
+impl Default for Alphabet {
+    fn default() -> Self {
+        Alphabet(BASE62.chars().collect())
+    }
+}
+
+/// Produces the `{id:N}` values for a naming config
+#[derive(Debug, Clone, Default)]
+pub struct IdGenerator {
+    alphabet: Alphabet,
+    mode: IdMode,
+    key: Option<String>,
+}
+
+impl IdGenerator {
+    /// Reads `id_alphabet`, `id_mode` and `id_key`
+    pub fn from_config(naming: &NamingConfig) -> Result<Self> {
+        let alphabet = Alphabet::parse(&naming.id_alphabet)
+            .map_err(|e| ConfigError::Invalid(format!("naming.id_alphabet: {}", e)))?;
+        Ok(Self {
+            alphabet,
+            mode: naming.id_mode,
+            key: naming.id_key.clone(),
+        })
+    }
+
+    /// A new ID of `length` characters
+    ///
+    /// Sequences keep their counter (and key, unless one is configured) in
+    /// the [`CounterStore`] at `store`; without one IDs are random.
+    pub fn generate(&self, length: usize, store: Option<&Path>) -> Result<String> {
+        let store = match (self.mode, store) {
+            (IdMode::Sequence, Some(path)) => CounterStore::open(path)?,
+            _ => return Ok(self.alphabet.random(length)),
+        };
+
+        let space = self.alphabet.space(length);
+        let index = store.next(&format!("id:{}", length))? - 1;
+        if index >= space {
+            return Err(ConfigError::Invalid(format!(
+                "All {} ids of {} characters are used; use a longer {{id:N}}",
+                space, length
+            ))
+            .into());
+        }
+
+        let key = match &self.key {
+            Some(key) => key.clone(),
+            None => store.key("id")?,
+        };
+        let value = permute(index, space, key.as_bytes());
+
+        // Past MAX_SPACE the leading characters would always be the first
+        // one; the value alone keeps IDs unique, so these can be anything
+        let width = self.alphabet.width(space).min(length);
+        let base = self.alphabet.0.len() as u64;
+        let prefix: String = (width..length)
+            .map(|i| self.alphabet.0[(round_function(key.as_bytes(), ROUNDS + i as u8, value) % base) as usize])
+            .collect();
+        Ok(prefix + &self.alphabet.encode(value, width))
+    }
+}
+
+/// Keyed bijection of `0..space` onto itself
+///
+/// A balanced Feistel network over the smallest even number of bits that
+/// covers `space`, cycle-walking past results outside the range.
+fn permute(value: u64, space: u64, key: &[u8]) -> u64 {
+    debug_assert!(value < space);
+    let bits = u64::BITS - space.saturating_sub(1).leading_zeros();
+    let half = bits.div_ceil(2).max(1);
+    let mask = (1u64 << half) - 1;
+
+    let mut x = value;
+    loop {
+        let (mut left, mut right) = (x >> half, x & mask);
+        for round in 0..ROUNDS {
+            let f = round_function(key, round, right) & mask;
+            (left, right) = (right, left ^ f);
+        }
+        x = (left << half) | right;
+        if x < space {
+            return x;
+        }
+    }
+}
+
+fn round_function(key: &[u8], round: u8, value: u64) -> u64 {
+    let digest = Sha256::new()
+        .chain_update(key)
+        .chain_update([round])
+        .chain_update(value.to_le_bytes())
+        .finalize();
+    u64::from_le_bytes(digest[..8].try_into().expect("SHA-256 is 32 bytes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_alphabets() {
+        assert_eq!(Alphabet::parse("base62").unwrap(), Alphabet::default());
+        let crockford = Alphabet::parse("crockford").unwrap();
+        assert_eq!(crockford.encode(31, 3), "00z");
+        assert_eq!(crockford.encode(32, 3), "010");
+
+        let custom = Alphabet::parse("ab").unwrap();
+        assert_eq!(custom.encode(5, 4), "abab");
+        assert!(custom.random(16).chars().all(|c| c == 'a' || c == 'b'));
+
+        assert!(Alphabet::parse("a").is_err());
+        assert!(Alphabet::parse("abca").is_err());
+        assert!(Alphabet::parse("ab/").is_err());
+        assert!(Alphabet::parse("ab c").is_err());
+    }
+
+    #[test]
+    fn test_permutation_is_a_keyed_bijection() {
+        for space in [2, 7, 62 * 62, 1000] {
+            let ids: HashSet<u64> = (0..space).map(|v| permute(v, space, b"key")).collect();
+            assert_eq!(ids.len() as u64, space);
+            assert!(ids.iter().all(|&id| id < space));
+        }
+
+        // Consecutive values scatter, and differently per key
+        let first: Vec<u64> = (0..8).map(|v| permute(v, 62 * 62 * 62, b"key")).collect();
+        let other: Vec<u64> = (0..8).map(|v| permute(v, 62 * 62 * 62, b"other")).collect();
+        assert_ne!(first, (0..8).collect::<Vec<_>>());
+        assert_ne!(first, other);
+
+        // Large spaces are capped, not overflowed
+        assert!(permute(12345, Alphabet::default().space(32), b"key") < MAX_SPACE);
+    }
+
+    #[test]
+    fn test_sequence_ids_are_unique_until_exhausted() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("counters.db");
+        let naming = NamingConfig {
+            id_alphabet: "abc".to_string(),
+            id_mode: IdMode::Sequence,
+            ..crate::Config::default().naming
+        };
+        let ids = IdGenerator::from_config(&naming).unwrap();
+
+        let all: HashSet<String> = (0..9).map(|_| ids.generate(2, Some(&path)).unwrap()).collect();
+        assert_eq!(all.len(), 9);
+        assert!(ids.generate(2, Some(&path)).is_err());
+        assert_eq!(ids.generate(3, Some(&path)).unwrap().len(), 3);
+
+        // The generated key is stored, not redrawn on every call
+        let store = CounterStore::open(&path).unwrap();
+        assert_eq!(store.key("id").unwrap(), store.key("id").unwrap());
+
+        // Past the capped space, ids do not all start the same way
+        let base62 = IdGenerator::from_config(&NamingConfig {
+            id_alphabet: "base62".to_string(),
+            ..naming
+        })
+        .unwrap();
+        let long: Vec<String> = (0..20).map(|_| base62.generate(16, Some(&path)).unwrap()).collect();
+        assert!(long.iter().all(|id| id.len() == 16));
+        assert_eq!(long.iter().collect::<HashSet<_>>().len(), 20);
+        assert!(long.iter().map(|id| &id[..5]).collect::<HashSet<_>>().len() > 1);
+
+        // Random ids need no store
+        let random = IdGenerator::default();
+        assert_eq!(random.generate(10, Some(&path)).unwrap().len(), 10);
+    }
+}
@@ -0,0 +1,572 @@
+//! Image transform pipeline for SnapTo
+//!
+//! Runs between reading an image (clipboard, capture) and handing it to an
+//! [`Uploader`](crate::Uploader), with settings from the destination's
+//! [`TransformConfig`], [`BeautifyPreset`] and [`WatermarkConfig`]. Steps, in
+//! order: frame the image (beautify), downscale by the display's scale
+//! factor, fit within a maximum width and height, draw the watermark, then
+//! encode to the target format. The original bytes are returned untouched
+//! when nothing applies, and for anything that is not a single still frame
+//! (animated GIF, WebP or PNG, video), which the steps would flatten.
+
+use image::codecs::gif::GifDecoder;
+use image::codecs::png::{CompressionType, FilterType, PngDecoder, PngEncoder};
+use image::codecs::webp::WebPDecoder;
+use image::imageops::FilterType as ResizeFilter;
+use image::{AnimationDecoder, DynamicImage, GenericImageView, ImageDecoder, ImageEncoder};
+use std::io::Cursor;
+use std::path::Path;
+use tracing::debug;
+
+use crate::config::{BeautifyPreset, Config, MetadataPolicy, TransformConfig, UploadConfig, WatermarkConfig};
+use crate::image_ops;
+use crate::metadata::{self, MetadataError};
+use crate::screenshot::{encode_image, ImageFormat, ScreenshotManager};
+
+/// Quality used for JPEG and AVIF when the config sets none
+pub const DEFAULT_QUALITY: u8 = 85;
+
+/// Result of running the pipeline
+#[derive(Debug, Clone)]
+pub struct Transformed {
+    /// Encoded image
+    pub data: Vec<u8>,
+    /// Format of `data`
+    pub format: ImageFormat,
+    /// Size of the input in bytes
+    pub original_size: usize,
+    /// Whether `data` differs from the input
+    pub changed: bool,
+}
+
+impl Transformed {
+    /// `filename` with the extension of the output format
+    pub fn filename(&self, filename: &str) -> String {
+        if !self.changed || ImageFormat::from_extension(extension(filename)) == Some(self.format) {
+            return filename.to_string();
+        }
+        Path::new(filename)
+            .with_extension(self.format.extension())
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    /// Applies the metadata `policy` to the output
+    ///
+    /// Runs after the transform, on the bytes that are actually uploaded.
+    pub fn scrub_metadata(&mut self, policy: MetadataPolicy) -> Result<(), MetadataError> {
+        let data = metadata::apply(&self.data, policy)?;
+        if data != self.data {
+            self.data = data;
+            self.changed = true;
+        }
+        Ok(())
+    }
+}
+
+/// Transform errors
+#[derive(Debug, thiserror::Error)]
+pub enum TransformError {
+    #[error("Failed to decode image: {0}")]
+    Decode(String),
+
+    #[error("Failed to encode {format}: {message}")]
+    Encode { format: String, message: String },
+
+    #[error("Unsupported output format: {0}")]
+    UnsupportedFormat(String),
+
+    #[error("Failed to apply watermark: {0}")]
+    Watermark(String),
+
+    #[error("Failed to beautify image: {0}")]
+    Beautify(String),
+
+    #[error("Destination '{destination}' uses unknown beautify preset '{preset}'")]
+    UnknownPreset { destination: String, preset: String },
+}
+
+/// Scale factor of the primary display, or 1.0 when it cannot be found
+pub fn display_scale_factor() -> f64 {
+    ScreenshotManager::list_displays()
+        .ok()
+        .and_then(|displays| displays.into_iter().find(|d| d.is_primary))
+        .map(|d| d.scale_factor)
+        .filter(|scale| *scale > 0.0)
+        .unwrap_or(1.0)
+}
+
+/// Scale factor for the pipelines of `destinations`
+///
+/// The display is only looked up when one of them downscales HiDPI images.
+pub fn scale_factor_for<'a>(destinations: impl IntoIterator<Item = &'a UploadConfig>) -> f64 {
+    let needed = destinations
+        .into_iter()
+        .any(|dest| dest.transform.as_ref().is_some_and(|t| t.downscale_hidpi));
+    if needed {
+        display_scale_factor()
+    } else {
+        1.0
+    }
+}
+
+/// Beautify preset destination `name` frames its images with, if any
+pub fn beautify_preset(config: &Config, name: &str, dest: &UploadConfig) -> Result<Option<BeautifyPreset>, TransformError> {
+    match &dest.beautify {
+        Some(preset) => config.beautify.preset(preset).map(Some).ok_or_else(|| TransformError::UnknownPreset {
+            destination: name.to_string(),
+            preset: preset.clone(),
+        }),
+        None => Ok(None),
+    }
+}
+
+/// The bytes destination `name` gets for `data`
+///
+/// Its transform, beautify preset and watermark, then the metadata policy:
+/// every front end uploads through here so none of them skips a step.
+pub fn for_destination(
+    data: &[u8],
+    config: &Config,
+    name: &str,
+    dest: &UploadConfig,
+    scale_factor: f64,
+) -> crate::error::Result<Transformed> {
+    let beautify = beautify_preset(config, name, dest)?;
+    let mut transformed = apply(
+        data,
+        &dest.transform.clone().unwrap_or_default(),
+        beautify.as_ref(),
+        dest.watermark.as_ref(),
+        scale_factor,
+    )?;
+    transformed.scrub_metadata(config.security.metadata)?;
+    Ok(transformed)
+}
+
+/// Runs the pipeline on encoded image `data`
+///
+/// `scale_factor` is only used when `downscale_hidpi` is set.
+pub fn apply(
+    data: &[u8],
+    config: &TransformConfig,
+    beautify: Option<&BeautifyPreset>,
+    watermark: Option<&WatermarkConfig>,
+    scale_factor: f64,
+) -> Result<Transformed, TransformError> {
+    let source_format = image::guess_format(data).ok().and_then(from_image_format);
+    let unchanged = || Transformed {
+        data: data.to_vec(),
+        format: source_format.unwrap_or(ImageFormat::Png),
+        original_size: data.len(),
+        changed: false,
+    };
+
+    if config.is_noop() && beautify.is_none() && watermark.is_none() {
+        return Ok(unchanged());
+    }
+    if !is_still_image(data) {
+        debug!("Not a still image, leaving it untouched");
+        return Ok(unchanged());
+    }
+
+    let target_format = match &config.format {
+        Some(format) => ImageFormat::from_extension(format)
+            .ok_or_else(|| TransformError::UnsupportedFormat(format.clone()))?,
+        None => source_format.unwrap_or(ImageFormat::Png),
+    };
+
+    let img = decode_oriented(data)?;
+    let img = match beautify {
+        Some(preset) => {
+            let framed = image_ops::beautify(&img.to_rgba8(), preset).map_err(|e| TransformError::Beautify(e.to_string()))?;
+            DynamicImage::ImageRgba8(framed)
+        }
+        None => img,
+    };
+    let (width, height) = img.dimensions();
+    let (target_width, target_height) = target_size(width, height, config, scale_factor);
+    let resized = (target_width, target_height) != (width, height);
+
+    let optimize = target_format == ImageFormat::Png && config.optimize_png;
+    let reencode_only = !resized && beautify.is_none() && watermark.is_none() && Some(target_format) == source_format;
+    if reencode_only && !optimize {
+        return Ok(unchanged());
+    }
+
+    let img = if resized {
+        debug!("Resizing {}x{} to {}x{}", width, height, target_width, target_height);
+        img.resize_exact(target_width, target_height, ResizeFilter::Lanczos3)
+    } else {
+        img
+    };
+
+    let img = match watermark {
+        Some(watermark) => {
+            let mut rgba = img.to_rgba8();
+            image_ops::watermark(&mut rgba, watermark).map_err(|e| TransformError::Watermark(e.to_string()))?;
+            DynamicImage::ImageRgba8(rgba)
+        }
+        None => img,
+    };
+
+    let quality = config.quality.unwrap_or(DEFAULT_QUALITY);
+    let encoded = encode(&img, target_format, quality, config.optimize_png)?;
+
+    // Re-encoding to the same format at the same size must not grow the file
+    if reencode_only && encoded.len() >= data.len() {
+        return Ok(unchanged());
+    }
+
+    debug!(
+        "Transformed image: {} -> {} bytes as {}",
+        data.len(),
+        encoded.len(),
+        target_format.extension()
+    );
+
+    Ok(Transformed {
+        data: encoded,
+        format: target_format,
+        original_size: data.len(),
+        changed: true,
+    })
+}
+
+/// Whether `data` is an image with a single frame
+fn is_still_image(data: &[u8]) -> bool {
+    match image::guess_format(data) {
+        Ok(image::ImageFormat::Gif) => GifDecoder::new(Cursor::new(data))
+            .map(|decoder| decoder.into_frames().take(2).count() < 2)
+            .unwrap_or(false),
+        Ok(image::ImageFormat::WebP) => WebPDecoder::new(Cursor::new(data))
+            .map(|decoder| !decoder.has_animation())
+            .unwrap_or(false),
+        Ok(image::ImageFormat::Png) => PngDecoder::new(Cursor::new(data))
+            .and_then(|decoder| decoder.is_apng())
+            .is_ok_and(|apng| !apng),
+        Ok(_) => true,
+        Err(_) => false,
+    }
+}
+
+/// Decodes `data` with its EXIF orientation applied to the pixels
+///
+/// Encoders drop EXIF, so a re-encoded image would otherwise show rotated.
+fn decode_oriented(data: &[u8]) -> Result<DynamicImage, TransformError> {
+    let decode_error = |e: image::ImageError| TransformError::Decode(e.to_string());
+
+    let mut decoder = image::ImageReader::new(Cursor::new(data))
+        .with_guessed_format()
+        .map_err(|e| TransformError::Decode(e.to_string()))?
+        .into_decoder()
+        .map_err(decode_error)?;
+    let orientation = decoder.orientation().map_err(decode_error)?;
+
+    let mut img = DynamicImage::from_decoder(decoder).map_err(decode_error)?;
+    img.apply_orientation(orientation);
+    Ok(img)
+}
+
+/// Output size after HiDPI downscaling and fitting in the max bounds
+fn target_size(width: u32, height: u32, config: &TransformConfig, scale_factor: f64) -> (u32, u32) {
+    let mut scale = 1.0f64;
+
+    if config.downscale_hidpi && scale_factor > 1.0 {
+        scale = 1.0 / scale_factor;
+    }
+    if let Some(max) = config.max_width {
+        scale = scale.min(max as f64 / width as f64);
+    }
+    if let Some(max) = config.max_height {
+        scale = scale.min(max as f64 / height as f64);
+    }
+
+    if scale >= 1.0 {
+        return (width, height);
+    }
+    (
+        ((width as f64 * scale).round() as u32).max(1),
+        ((height as f64 * scale).round() as u32).max(1),
+    )
+}
+
+/// Encodes `img` as `format`
+///
+/// Same as [`encode_image`], except that with `optimize_png` PNGs drop
+/// unused channels and use maximum compression, without losing any pixel
+/// data.
+pub fn encode(img: &DynamicImage, format: ImageFormat, quality: u8, optimize_png: bool) -> Result<Vec<u8>, TransformError> {
+    let encode_error = |message: String| TransformError::Encode {
+        format: format.extension().to_string(),
+        message,
+    };
+
+    if format != ImageFormat::Png || !optimize_png {
+        return encode_image(img, format, quality).map_err(|e| encode_error(e.to_string()));
+    }
+
+    let mut buffer = Vec::new();
+    let (width, height) = img.dimensions();
+    let img = reduce_channels(img);
+    PngEncoder::new_with_quality(&mut buffer, CompressionType::Best, FilterType::Adaptive)
+        .write_image(img.as_bytes(), width, height, img.color().into())
+        .map_err(|e| encode_error(e.to_string()))?;
+    Ok(buffer)
+}
+
+/// Drops the alpha channel when fully opaque and color when all gray
+fn reduce_channels(img: &DynamicImage) -> DynamicImage {
+    let rgba = img.to_rgba8();
+    let opaque = rgba.pixels().all(|p| p[3] == 255);
+    let gray = rgba.pixels().all(|p| p[0] == p[1] && p[1] == p[2]);
+
+    match (opaque, gray) {
+        (true, true) => DynamicImage::ImageLuma8(img.to_luma8()),
+        (true, false) => DynamicImage::ImageRgb8(img.to_rgb8()),
+        (false, true) => DynamicImage::ImageLumaA8(img.to_luma_alpha8()),
+        (false, false) => DynamicImage::ImageRgba8(rgba),
+    }
+}
+
+fn from_image_format(format: image::ImageFormat) -> Option<ImageFormat> {
+    match format {
+        image::ImageFormat::Png => Some(ImageFormat::Png),
+        image::ImageFormat::Jpeg => Some(ImageFormat::Jpeg),
+        image::ImageFormat::WebP => Some(ImageFormat::WebP),
+        image::ImageFormat::Avif => Some(ImageFormat::Avif),
+        _ => None,
+    }
+}
+
+fn extension(filename: &str) -> &str {
+    filename.rsplit_once('.').map(|(_, ext)| ext).unwrap_or("")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let img = RgbaImage::from_fn(width, height, |x, y| Rgba([(x % 256) as u8, (y % 256) as u8, 128, 255]));
+        encode(&DynamicImage::ImageRgba8(img), ImageFormat::Png, 100, false).unwrap()
+    }
+
+    fn dimensions(data: &[u8]) -> (u32, u32) {
+        image::load_from_memory(data).unwrap().dimensions()
+    }
+
+    #[test]
+    fn test_noop_returns_original() {
+        let data = png(40, 20);
+        let result = apply(&data, &TransformConfig::default(), None, None, 2.0).unwrap();
+
+        assert!(!result.changed);
+        assert_eq!(result.data, data);
+        assert_eq!(result.format, ImageFormat::Png);
+    }
+
+    #[test]
+    fn test_fits_within_max_size() {
+        let config = TransformConfig {
+            max_width: Some(100),
+            max_height: Some(100),
+            ..Default::default()
+        };
+        let result = apply(&png(400, 200), &config, None, None, 1.0).unwrap();
+
+        assert!(result.changed);
+        assert_eq!(dimensions(&result.data), (100, 50));
+    }
+
+    #[test]
+    fn test_hidpi_downscale() {
+        let config = TransformConfig {
+            downscale_hidpi: true,
+            ..Default::default()
+        };
+
+        let result = apply(&png(200, 100), &config, None, None, 2.0).unwrap();
+        assert_eq!(dimensions(&result.data), (100, 50));
+
+        // Nothing to do on a 1x display
+        assert!(!apply(&png(200, 100), &config, None, None, 1.0).unwrap().changed);
+    }
+
+    #[test]
+    fn test_convert_to_jpeg_renames_file() {
+        let config = TransformConfig {
+            format: Some("jpg".to_string()),
+            quality: Some(60),
+            ..Default::default()
+        };
+        let data = png(64, 64);
+        let result = apply(&data, &config, None, None, 1.0).unwrap();
+
+        assert_eq!(image::guess_format(&result.data).unwrap(), image::ImageFormat::Jpeg);
+        assert_eq!(result.original_size, data.len());
+        assert_eq!(result.filename("shot.png"), "shot.jpg");
+    }
+
+    #[test]
+    fn test_convert_to_webp_and_avif() {
+        let data = png(16, 16);
+        for (format, expected) in [("webp", image::ImageFormat::WebP), ("avif", image::ImageFormat::Avif)] {
+            let config = TransformConfig {
+                format: Some(format.to_string()),
+                ..Default::default()
+            };
+            let result = apply(&data, &config, None, None, 1.0).unwrap();
+            assert_eq!(image::guess_format(&result.data).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_png_optimization_is_lossless_and_smaller() {
+        // Flat gray, stored as RGBA at the lowest compression
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_pixel(128, 128, Rgba([90, 90, 90, 255])));
+        let mut data = Vec::new();
+        PngEncoder::new_with_quality(&mut data, CompressionType::Fast, FilterType::NoFilter)
+            .write_image(img.as_bytes(), 128, 128, image::ExtendedColorType::Rgba8)
+            .unwrap();
+
+        let config = TransformConfig {
+            optimize_png: true,
+            ..Default::default()
+        };
+        let result = apply(&data, &config, None, None, 1.0).unwrap();
+
+        assert!(result.data.len() < data.len());
+        assert_eq!(image::load_from_memory(&result.data).unwrap().to_rgba8(), img.to_rgba8());
+    }
+
+    #[test]
+    fn test_exif_orientation_is_applied() {
+        let mut jpeg = Vec::new();
+        let img = DynamicImage::ImageRgb8(image::RgbImage::new(40, 20));
+        img.write_to(&mut Cursor::new(&mut jpeg), image::ImageFormat::Jpeg).unwrap();
+
+        // APP1 with orientation 6 (rotate 90 degrees clockwise)
+        let mut exif = b"Exif\0\0II*\0\x08\0\0\0\x01\0\x12\x01\x03\0\x01\0\0\0\x06\0\0\0\0\0\0\0".to_vec();
+        let mut app1 = vec![0xFF, 0xE1];
+        app1.extend_from_slice(&((exif.len() + 2) as u16).to_be_bytes());
+        app1.append(&mut exif);
+        let data = [&jpeg[..2], &app1, &jpeg[2..]].concat();
+
+        let config = TransformConfig {
+            format: Some("png".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(dimensions(&apply(&data, &config, None, None, 1.0).unwrap().data), (20, 40));
+    }
+
+    #[test]
+    fn test_animations_and_video_pass_through() {
+        use image::codecs::gif::GifEncoder;
+        use image::{Delay, Frame};
+
+        let mut gif = Vec::new();
+        let frames = [Rgba([255, 0, 0, 255]), Rgba([0, 0, 255, 255])]
+            .map(|color| Frame::from_parts(RgbaImage::from_pixel(40, 40, color), 0, 0, Delay::from_numer_denom_ms(100, 1)));
+        GifEncoder::new(&mut gif).encode_frames(frames).unwrap();
+
+        let config = TransformConfig {
+            max_width: Some(20),
+            format: Some("png".to_string()),
+            ..Default::default()
+        };
+        let result = apply(&gif, &config, None, None, 1.0).unwrap();
+        assert!(!result.changed);
+        assert_eq!(result.data, gif);
+        assert_eq!(result.filename("clip.gif"), "clip.gif");
+
+        // Not an image at all, e.g. an MP4
+        let mp4 = b"\0\0\0\x18ftypmp42\0\0\0\0mp42isom".to_vec();
+        assert!(!apply(&mp4, &config, None, None, 1.0).unwrap().changed);
+
+        // A single still frame is still transformed
+        assert!(apply(&png(40, 40), &config, None, None, 1.0).unwrap().changed);
+    }
+
+    #[test]
+    fn test_unsupported_format() {
+        let config = TransformConfig {
+            format: Some("bmp".to_string()),
+            ..Default::default()
+        };
+        assert!(matches!(apply(&png(4, 4), &config, None, None, 1.0), Err(TransformError::UnsupportedFormat(_))));
+    }
+
+    #[test]
+    fn test_watermark_drawn_after_resize() {
+        let watermark = WatermarkConfig {
+            text: Some("X".to_string()),
+            anchor: crate::config::WatermarkAnchor::TopLeft,
+            opacity: 1.0,
+            margin: 0,
+            scale: 0.5,
+            ..Default::default()
+        };
+        let config = TransformConfig {
+            max_width: Some(20),
+            ..Default::default()
+        };
+        let result = apply(&png(40, 40), &config, None, Some(&watermark), 1.0).unwrap();
+        assert!(result.changed);
+
+        // "X" is 5 font pixels wide, so it is drawn 2x to fit half of 20px
+        let img = image::load_from_memory(&result.data).unwrap().to_rgba8();
+        assert_eq!(img.dimensions(), (20, 20));
+        assert_eq!(*img.get_pixel(0, 0), Rgba([255, 255, 255, 255]));
+        assert_ne!(*img.get_pixel(2, 0), Rgba([255, 255, 255, 255]));
+
+        // A watermark alone still re-encodes, even if the file grows
+        let only_watermark = apply(&png(40, 40), &TransformConfig::default(), None, Some(&watermark), 1.0).unwrap();
+        assert!(only_watermark.changed);
+    }
+
+    #[test]
+    fn test_for_destination() {
+        let config = Config::default();
+        let dest = UploadConfig {
+            transform: Some(TransformConfig {
+                max_width: Some(20),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let result = for_destination(&png(40, 40), &config, "vps", &dest, 1.0).unwrap();
+        assert_eq!(dimensions(&result.data), (20, 20));
+
+        let framed = UploadConfig {
+            beautify: Some("missing".to_string()),
+            ..dest
+        };
+        assert!(matches!(
+            beautify_preset(&config, "vps", &framed),
+            Err(TransformError::UnknownPreset { .. })
+        ));
+        assert!(for_destination(&png(40, 40), &config, "vps", &framed, 1.0).is_err());
+
+        // No destination downscales, so the display is not looked up
+        assert_eq!(scale_factor_for([&UploadConfig::default()]), 1.0);
+    }
+
+    #[test]
+    fn test_beautify_frames_before_resize() {
+        let preset = BeautifyPreset {
+            padding: 10,
+            ..Default::default()
+        };
+        let config = TransformConfig {
+            max_width: Some(30),
+            ..Default::default()
+        };
+        let result = apply(&png(40, 20), &config, Some(&preset), None, 1.0).unwrap();
+
+        // 60x40 once framed, then fitted to 30px wide
+        assert!(result.changed);
+        assert_eq!(dimensions(&result.data), (30, 20));
+    }
+}
@@ -63,6 +63,13 @@ impl Uploader for LocalUploader {
         // Preparar ruta
         let full_path = self.prepare_path(filename)?;
 
+        // El nombre puede llevar subdirectorios ({date:%Y}/{date:%m}/...)
+        if let Some(parent) = full_path.parent() {
+            fs::create_dir_all(parent).map_err(|e| {
+                SnaptoError::Upload(format!("No se pudo crear directorio {}: {}", parent.display(), e))
+            })?;
+        }
+
         // Escribir archivo
         fs::write(&full_path, data).map_err(|e| {
             SnaptoError::Upload(format!(
@@ -87,6 +94,10 @@ impl Uploader for LocalUploader {
         })
     }
 
+    async fn exists(&self, filename: &str) -> Result<bool> {
+        Ok(self.prepare_path(filename)?.exists())
+    }
+
     fn name(&self) -> &str {
         &self.name
     }
@@ -130,6 +141,7 @@ mod tests {
             use_key_auth: None,
             key_path: None,
             timeout: None,
+            ..Default::default()
         };
 
         let uploader = LocalUploader::new("test".to_string(), config);
@@ -153,6 +165,7 @@ mod tests {
             use_key_auth: None,
             key_path: None,
             timeout: None,
+            ..Default::default()
         };
 
         let uploader = LocalUploader::new("test".to_string(), config);
@@ -178,11 +191,14 @@ mod tests {
             use_key_auth: None,
             key_path: None,
             timeout: None,
+            ..Default::default()
         };
 
         let uploader = LocalUploader::new("test".to_string(), config);
         let data = b"test data";
+        assert!(!uploader.exists("test.txt").await.unwrap());
         let result = uploader.upload(data, "test.txt").await.unwrap();
+        assert!(uploader.exists("test.txt").await.unwrap());
 
         assert_eq!(result.size, 9);
         assert!(result.remote_path.ends_with("test.txt"));
@@ -212,6 +228,7 @@ mod tests {
             use_key_auth: None,
             key_path: None,
             timeout: None,
+            ..Default::default()
         };
 
         let uploader = LocalUploader::new("test".to_string(), config);
@@ -228,4 +245,46 @@ mod tests {
         let file_path = dir_path.join("test.txt");
         assert!(file_path.exists());
     }
+
+    #[tokio::test]
+    async fn test_local_uploader_nested_filename() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = UploadConfig {
+            uploader_type: "local".to_string(),
+            enabled: true,
+            local_path: Some(temp_dir.path().to_str().unwrap().to_string()),
+            base_url: Some("https://example.com/".to_string()),
+            ..Default::default()
+        };
+
+        let uploader = LocalUploader::new("test".to_string(), config);
+        assert!(!uploader.exists("2026/10/shot.png").await.unwrap());
+        let result = uploader.upload(b"test data", "2026/10/shot.png").await.unwrap();
+        assert!(uploader.exists("2026/10/shot.png").await.unwrap());
+
+        assert_eq!(result.url.as_deref(), Some("https://example.com/2026/10/shot.png"));
+        let contents = fs::read_to_string(temp_dir.path().join("2026/10/shot.png")).unwrap();
+        assert_eq!(contents, "test data");
+    }
+
+    #[tokio::test]
+    async fn test_local_uploader_sharded_hash_names() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = UploadConfig {
+            uploader_type: "local".to_string(),
+            enabled: true,
+            local_path: Some(temp_dir.path().to_str().unwrap().to_string()),
+            ..Default::default()
+        };
+        let uploader = LocalUploader::new("test".to_string(), config);
+
+        let data = b"abc";
+        let parser = crate::TemplateParser::default().with_content_hash(crate::content_hash(data));
+        let filename = parser.generate("{hash:2}/{hash:12}", "png").unwrap();
+        assert_eq!(filename, "ba/ba7816bf8f01.png");
+
+        uploader.upload(data, &filename).await.unwrap();
+        assert!(uploader.exists(&filename).await.unwrap());
+        assert_eq!(fs::read(temp_dir.path().join("ba/ba7816bf8f01.png")).unwrap(), data);
+    }
 }
@@ -1,9 +1,9 @@
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 
 use crate::error::Result;
 
-pub mod sftp;
 pub mod local;
 pub mod ssh;
 
@@ -20,7 +20,13 @@ pub struct UploadResult {
     pub duration_ms: u64,
 }
 
+/// Callback de progreso: `(bytes_enviados, bytes_totales)`
+pub type ProgressCallback = Arc<dyn Fn(u64, u64) + Send + Sync>;
+
 /// Trait para implementar uploaders personalizados
+///
+/// Cancelar una subida consiste en soltar (drop) su future; los uploaders
+/// que lo soportan detienen la transferencia en curso.
 #[async_trait]
 pub trait Uploader: Send + Sync {
     /// Sube datos a un destino remoto
@@ -33,6 +39,28 @@ pub trait Uploader: Send + Sync {
     /// Resultado de la subida con información sobre la ubicación
     async fn upload(&self, data: &[u8], filename: &str) -> Result<UploadResult>;
 
+    /// Sube datos informando del progreso
+    ///
+    /// Por defecto solo informa al terminar; los uploaders que transfieren
+    /// por bloques lo sobrescriben.
+    async fn upload_with_progress(
+        &self,
+        data: &[u8],
+        filename: &str,
+        progress: ProgressCallback,
+    ) -> Result<UploadResult> {
+        let result = self.upload(data, filename).await?;
+        progress(data.len() as u64, data.len() as u64);
+        Ok(result)
+    }
+
+    /// Indica si ya hay un archivo `filename` en el destino
+    ///
+    /// Por defecto no se puede saber y se asume que no.
+    async fn exists(&self, _filename: &str) -> Result<bool> {
+        Ok(false)
+    }
+
     /// Nombre identificador del uploader
     fn name(&self) -> &str;
 
@@ -54,6 +82,22 @@ pub trait Uploader: Send + Sync {
     }
 }
 
+/// Tipo MIME según la extensión del nombre de archivo
+pub fn mime_type_for(filename: &str) -> &'static str {
+    let extension = filename.rsplit_once('.').map(|(_, ext)| ext).unwrap_or("");
+
+    match extension.to_ascii_lowercase().as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "gif" => "image/gif",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        _ => "application/octet-stream",
+    }
+}
+
 /// Información sobre un uploader
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UploaderInfo {
@@ -109,6 +153,35 @@ mod tests {
         assert_eq!(result.size, 9);
     }
 
+    #[tokio::test]
+    async fn test_default_progress_reports_completion() {
+        use std::sync::Mutex;
+
+        let uploader = MockUploader {
+            name: "test".to_string(),
+            enabled: true,
+        };
+
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&seen);
+        uploader
+            .upload_with_progress(b"abc", "a.png", Arc::new(move |sent, total| {
+                sink.lock().unwrap().push((sent, total));
+            }))
+            .await
+            .unwrap();
+
+        assert_eq!(*seen.lock().unwrap(), vec![(3, 3)]);
+    }
+
+    #[test]
+    fn test_mime_type_for() {
+        assert_eq!(mime_type_for("shot.PNG"), "image/png");
+        assert_eq!(mime_type_for("clip.tar.mp4"), "video/mp4");
+        assert_eq!(mime_type_for("anim.gif"), "image/gif");
+        assert_eq!(mime_type_for("README"), "application/octet-stream");
+    }
+
     #[test]
     fn test_uploader_info() {
         let uploader = MockUploader {
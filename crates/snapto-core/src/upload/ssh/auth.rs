@@ -0,0 +1,500 @@
+//! Ordered SSH authentication chain
+//!
+//! Each configured [`AuthMethod`] is tried in turn until the session is
+//! authenticated. Every attempt is recorded so that a failure can report
+//! exactly what was tried and why each method did not succeed.
+
+use ssh2::{KeyboardInteractivePrompt, Prompt, Session};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use tracing::{debug, info};
+
+use crate::config::{AuthMethod, UploadConfig};
+use crate::error::{Result, SnaptoError};
+
+use super::{totp, PasswordCallback};
+
+/// Private keys probed by [`AuthMethod::DefaultKeys`], in OpenSSH order
+const DEFAULT_KEY_NAMES: [&str; 4] = ["id_ed25519", "id_ecdsa", "id_rsa", "id_dsa"];
+
+/// Words that identify a one-time password prompt
+const OTP_PROMPT_WORDS: [&str; 8] = [
+    "verification code",
+    "one-time",
+    "otp",
+    "token",
+    "2fa",
+    "two-factor",
+    "authenticator",
+    "passcode",
+];
+
+/// Whether a keyboard-interactive prompt asks for a one-time code
+pub fn is_otp_prompt(text: &str) -> bool {
+    let text = text.to_lowercase();
+    OTP_PROMPT_WORDS.iter().any(|w| text.contains(w))
+}
+
+/// Whether a keyboard-interactive prompt asks for the account password
+pub fn is_password_prompt(text: &str) -> bool {
+    text.to_lowercase().contains("password") && !is_otp_prompt(text)
+}
+
+/// Outcome of a single authentication attempt
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuthAttempt {
+    pub method: AuthMethod,
+    pub detail: String,
+}
+
+impl fmt::Display for AuthAttempt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.method.as_str(), self.detail)
+    }
+}
+
+/// Runs the configured authentication methods against a session
+pub struct AuthChain {
+    username: String,
+    target: String,
+    methods: Vec<AuthMethod>,
+    key_path: Option<PathBuf>,
+    password: Option<String>,
+    password_callback: Option<PasswordCallback>,
+    totp_secret: Option<String>,
+    attempts: Vec<AuthAttempt>,
+}
+
+impl AuthChain {
+    /// Builds the chain for an upload destination
+    ///
+    /// When `auth_methods` is not configured the default order is used.
+    /// `use_key_auth = false` removes the explicit key from the default order.
+    pub fn from_config(config: &UploadConfig, username: &str, host: &str) -> Self {
+        let methods = match &config.auth_methods {
+            Some(methods) => methods.clone(),
+            None => AuthMethod::DEFAULT_CHAIN
+                .iter()
+                .copied()
+                .filter(|m| *m != AuthMethod::Key || config.use_key_auth != Some(false))
+                .collect(),
+        };
+
+        let key_path = config
+            .key_path
+            .as_ref()
+            .map(|p| PathBuf::from(shellexpand::tilde(p).to_string()));
+
+        Self {
+            username: username.to_string(),
+            target: format!("{}@{}", username, host),
+            methods,
+            key_path,
+            password: None,
+            password_callback: None,
+            totp_secret: None,
+            attempts: Vec::new(),
+        }
+    }
+
+    /// Sets a known password (also used as passphrase for encrypted keys)
+    pub fn with_password(mut self, password: Option<String>) -> Self {
+        self.password = password;
+        self
+    }
+
+    /// Sets the callback used to ask for a password when none is known
+    pub fn with_password_callback(mut self, callback: Option<PasswordCallback>) -> Self {
+        self.password_callback = callback;
+        self
+    }
+
+    /// Sets the base32 seed used to answer one-time password prompts
+    pub fn with_totp_secret(mut self, secret: Option<String>) -> Self {
+        self.totp_secret = secret;
+        self
+    }
+
+    /// Methods that will be tried, in order
+    pub fn methods(&self) -> &[AuthMethod] {
+        &self.methods
+    }
+
+    /// Attempts recorded so far
+    pub fn attempts(&self) -> &[AuthAttempt] {
+        &self.attempts
+    }
+
+    /// Tries each method until the session is authenticated
+    ///
+    /// # Errors
+    /// Returns `SshAuthentication` listing every method tried when all of them fail
+    pub fn authenticate(&mut self, session: &Session) -> Result<()> {
+        // Asking for the method list may already authenticate ("none" auth)
+        let offered = session
+            .auth_methods(&self.username)
+            .map(|m| m.to_string())
+            .ok();
+        if session.authenticated() {
+            debug!("SSH server accepted \"none\" authentication");
+            return Ok(());
+        }
+
+        for method in self.methods.clone() {
+            if let Some(offered) = &offered {
+                if !offered.split(',').any(|m| m == server_method_name(method)) {
+                    self.record(method, "not offered by server");
+                    continue;
+                }
+            }
+
+            debug!("Trying SSH authentication method: {}", method.as_str());
+            let outcome = match method {
+                AuthMethod::Agent => self.try_agent(session),
+                AuthMethod::Key => self.try_key(session),
+                AuthMethod::DefaultKeys => self.try_default_keys(session),
+                AuthMethod::KeyboardInteractive => self.try_keyboard_interactive(session),
+                AuthMethod::Password => self.try_password(session),
+            };
+
+            if session.authenticated() {
+                info!("SSH authentication succeeded for {} using {}", self.target, method.as_str());
+                return Ok(());
+            }
+
+            let detail = match outcome {
+                Ok(()) => "rejected".to_string(),
+                Err(detail) => detail,
+            };
+            self.record(method, &detail);
+        }
+
+        Err(self.failure())
+    }
+
+    /// Builds the error reported when every method failed
+    pub fn failure(&self) -> SnaptoError {
+        let tried = if self.attempts.is_empty() {
+            "no methods configured".to_string()
+        } else {
+            self.attempts
+                .iter()
+                .map(|a| a.to_string())
+                .collect::<Vec<_>>()
+                .join("; ")
+        };
+
+        SnaptoError::SshAuthentication(format!(
+            "Authentication failed for {}; tried: {}",
+            self.target, tried
+        ))
+    }
+
+    fn record(&mut self, method: AuthMethod, detail: &str) {
+        debug!("SSH auth method {} failed: {}", method.as_str(), detail);
+        self.attempts.push(AuthAttempt {
+            method,
+            detail: detail.to_string(),
+        });
+    }
+
+    fn try_agent(&self, session: &Session) -> std::result::Result<(), String> {
+        session
+            .userauth_agent(&self.username)
+            .map_err(|e| e.message().to_string())
+    }
+
+    fn try_key(&self, session: &Session) -> std::result::Result<(), String> {
+        let key_path = self
+            .key_path
+            .as_ref()
+            .ok_or_else(|| "no key_path configured".to_string())?;
+
+        self.try_key_file(session, key_path)
+    }
+
+    fn try_default_keys(&self, session: &Session) -> std::result::Result<(), String> {
+        let keys = default_key_paths()
+            .into_iter()
+            .filter(|p| Some(p) != self.key_path.as_ref() && p.exists())
+            .collect::<Vec<_>>();
+
+        if keys.is_empty() {
+            return Err("no ~/.ssh/id_* keys found".to_string());
+        }
+
+        let mut failures = Vec::new();
+        for key in keys {
+            match self.try_key_file(session, &key) {
+                Ok(()) if session.authenticated() => return Ok(()),
+                Ok(()) => failures.push(format!("{}: rejected", key.display())),
+                Err(e) => failures.push(e),
+            }
+        }
+
+        Err(failures.join(", "))
+    }
+
+    fn try_key_file(&self, session: &Session, key_path: &Path) -> std::result::Result<(), String> {
+        if !key_path.exists() {
+            return Err(format!("{}: not found", key_path.display()));
+        }
+
+        let first = session.userauth_pubkey_file(&self.username, None, key_path, None);
+        if first.is_ok() || session.authenticated() {
+            return Ok(());
+        }
+
+        // The key may be encrypted; retry using the known password as passphrase
+        if let Some(passphrase) = self.password.as_deref() {
+            if session
+                .userauth_pubkey_file(&self.username, None, key_path, Some(passphrase))
+                .is_ok()
+            {
+                return Ok(());
+            }
+        }
+
+        Err(format!(
+            "{}: {}",
+            key_path.display(),
+            first.err().map(|e| e.message().to_string()).unwrap_or_default()
+        ))
+    }
+
+    fn try_keyboard_interactive(&mut self, session: &Session) -> std::result::Result<(), String> {
+        let username = self.username.clone();
+        let mut prompter = InteractivePrompter {
+            chain: self,
+            unanswered: Vec::new(),
+        };
+
+        let result = session.userauth_keyboard_interactive(&username, &mut prompter);
+        let unanswered = prompter.unanswered;
+
+        result.map_err(|e| {
+            if unanswered.is_empty() {
+                e.message().to_string()
+            } else {
+                format!("no answer for {}", unanswered.join(", "))
+            }
+        })
+    }
+
+    fn try_password(&mut self, session: &Session) -> std::result::Result<(), String> {
+        let password = self
+            .password()
+            .ok_or_else(|| "no password available".to_string())?;
+
+        session
+            .userauth_password(&self.username, &password)
+            .map_err(|e| e.message().to_string())
+    }
+
+    /// Returns the known password, asking through the callback only once
+    fn password(&mut self) -> Option<String> {
+        if self.password.is_none() {
+            let prompt = format!("Password for {}: ", self.target);
+            self.password = self.ask(&prompt);
+        }
+
+        self.password.clone()
+    }
+
+    /// Asks the callback to answer a prompt
+    fn ask(&self, prompt: &str) -> Option<String> {
+        let callback = self.password_callback.as_ref()?;
+        let mut guard = callback.blocking_lock();
+        guard.as_mut().and_then(|cb| cb(prompt))
+    }
+
+    /// Answers a single keyboard-interactive prompt
+    ///
+    /// OTP prompts use the TOTP seed when one is configured and password
+    /// prompts reuse the known password; anything else goes to the callback.
+    fn answer(&mut self, prompt: &str) -> Option<String> {
+        if is_otp_prompt(prompt) {
+            if let Some(secret) = &self.totp_secret {
+                match totp::current_code(secret) {
+                    Ok(code) => return Some(code),
+                    Err(e) => debug!("Could not generate TOTP code: {}", e),
+                }
+            }
+        }
+
+        if is_password_prompt(prompt) {
+            if let Some(password) = &self.password {
+                return Some(password.clone());
+            }
+            let answer = self.ask(prompt);
+            if answer.is_some() {
+                self.password = answer.clone();
+            }
+            return answer;
+        }
+
+        self.ask(prompt)
+    }
+}
+
+/// Name of the SSH protocol method backing an [`AuthMethod`]
+fn server_method_name(method: AuthMethod) -> &'static str {
+    match method {
+        AuthMethod::Agent | AuthMethod::Key | AuthMethod::DefaultKeys => "publickey",
+        AuthMethod::KeyboardInteractive => "keyboard-interactive",
+        AuthMethod::Password => "password",
+    }
+}
+
+/// Candidate default private keys under ~/.ssh
+fn default_key_paths() -> Vec<PathBuf> {
+    let ssh_dir = PathBuf::from(shellexpand::tilde("~/.ssh").to_string());
+    DEFAULT_KEY_NAMES.iter().map(|name| ssh_dir.join(name)).collect()
+}
+
+/// Answers keyboard-interactive rounds through the chain's credentials
+struct InteractivePrompter<'c> {
+    chain: &'c mut AuthChain,
+    unanswered: Vec<String>,
+}
+
+impl KeyboardInteractivePrompt for InteractivePrompter<'_> {
+    fn prompt<'a>(
+        &mut self,
+        _username: &str,
+        instructions: &str,
+        prompts: &[Prompt<'a>],
+    ) -> Vec<String> {
+        if !instructions.is_empty() {
+            debug!("Keyboard-interactive instructions: {}", instructions);
+        }
+
+        prompts
+            .iter()
+            .map(|p| {
+                let text = p.text.trim();
+                self.chain.answer(text).unwrap_or_else(|| {
+                    self.unanswered.push(text.to_string());
+                    String::new()
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ssh_config() -> UploadConfig {
+        UploadConfig {
+            uploader_type: "ssh".to_string(),
+            enabled: true,
+            host: Some("example.com".to_string()),
+            username: Some("user".to_string()),
+            remote_path: Some("/uploads".to_string()),
+            key_path: Some("~/.ssh/id_rsa".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_default_chain_order() {
+        let chain = AuthChain::from_config(&ssh_config(), "user", "example.com");
+        assert_eq!(chain.methods(), &AuthMethod::DEFAULT_CHAIN);
+    }
+
+    #[test]
+    fn test_key_auth_disabled_drops_explicit_key() {
+        let mut config = ssh_config();
+        config.use_key_auth = Some(false);
+
+        let chain = AuthChain::from_config(&config, "user", "example.com");
+        assert!(!chain.methods().contains(&AuthMethod::Key));
+        assert!(chain.methods().contains(&AuthMethod::DefaultKeys));
+    }
+
+    #[test]
+    fn test_configured_chain_is_used_verbatim() {
+        let mut config = ssh_config();
+        config.auth_methods = Some(vec![AuthMethod::Password, AuthMethod::Agent]);
+
+        let chain = AuthChain::from_config(&config, "user", "example.com");
+        assert_eq!(chain.methods(), &[AuthMethod::Password, AuthMethod::Agent]);
+    }
+
+    #[test]
+    fn test_failure_lists_tried_methods() {
+        let mut chain = AuthChain::from_config(&ssh_config(), "user", "example.com");
+        chain.record(AuthMethod::Agent, "no identities");
+        chain.record(AuthMethod::Password, "no password available");
+
+        let message = chain.failure().to_string();
+        assert!(message.contains("user@example.com"));
+        assert!(message.contains("agent (no identities)"));
+        assert!(message.contains("password (no password available)"));
+    }
+
+    #[test]
+    fn test_prompt_classification() {
+        assert!(is_password_prompt("Password: "));
+        assert!(is_password_prompt("user@host's password:"));
+        assert!(is_otp_prompt("Verification code: "));
+        assert!(is_otp_prompt("Enter your OTP:"));
+        assert!(!is_password_prompt("One-time password:"));
+        assert!(!is_otp_prompt("Password: "));
+    }
+
+    #[test]
+    fn test_otp_prompt_uses_totp_secret() {
+        let mut chain = AuthChain::from_config(&ssh_config(), "user", "example.com")
+            .with_totp_secret(Some("GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ".to_string()));
+
+        let code = chain.answer("Verification code:").unwrap();
+        assert_eq!(code.len(), 6);
+        assert!(code.chars().all(|c| c.is_ascii_digit()));
+    }
+
+    #[test]
+    fn test_prompts_fall_back_to_callback() {
+        use std::sync::Arc;
+
+        let callback: PasswordCallback = Arc::new(tokio::sync::Mutex::new(Some(Box::new(
+            |prompt: &str| match prompt {
+                "Verification code:" => Some("123456".to_string()),
+                _ => None,
+            },
+        ))));
+
+        let mut chain = AuthChain::from_config(&ssh_config(), "user", "example.com")
+            .with_password(Some("secret".to_string()))
+            .with_password_callback(Some(callback));
+
+        assert_eq!(chain.answer("Password:"), Some("secret".to_string()));
+        assert_eq!(chain.answer("Verification code:"), Some("123456".to_string()));
+        assert_eq!(chain.answer("Favourite colour?"), None);
+    }
+
+    #[test]
+    fn test_password_callback_is_asked_once() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let callback: PasswordCallback = Arc::new(tokio::sync::Mutex::new(Some(Box::new(
+            move |prompt: &str| {
+                assert!(prompt.contains("user@example.com"));
+                counter.fetch_add(1, Ordering::SeqCst);
+                Some("secret".to_string())
+            },
+        ))));
+
+        let mut chain = AuthChain::from_config(&ssh_config(), "user", "example.com")
+            .with_password_callback(Some(callback));
+
+        assert_eq!(chain.password(), Some("secret".to_string()));
+        assert_eq!(chain.password(), Some("secret".to_string()));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,200 @@
+//! Dedicated thread for blocking SSH IO
+//!
+//! ssh2 is a blocking library. Instead of borrowing a tokio blocking-pool
+//! thread per upload, each uploader owns one IO thread fed through a
+//! command channel, and callers await the reply. Dropping the future sets
+//! the job's [`CancelFlag`], and the job stops at its next check.
+//!
+//! One thread means one job at a time: concurrent uploads through the same
+//! uploader (or its clones) queue up and transfer one after another, which
+//! matches the single pooled session per destination. Uploaders for
+//! different destinations have their own threads and run in parallel.
+
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use tokio::sync::oneshot;
+use tracing::{debug, error};
+
+use crate::error::{Result, SnaptoError};
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Set when the caller no longer wants the result
+#[derive(Clone, Default)]
+pub struct CancelFlag(Arc<AtomicBool>);
+
+impl CancelFlag {
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns an error when cancelled, for use with `?` between steps
+    pub fn check(&self) -> Result<()> {
+        if self.is_cancelled() {
+            Err(SnaptoError::Upload("Upload cancelled".to_string()))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Cancels the flag when the awaiting future is dropped early
+struct CancelOnDrop(Option<CancelFlag>);
+
+impl CancelOnDrop {
+    fn disarm(&mut self) {
+        self.0 = None;
+    }
+}
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        if let Some(flag) = &self.0 {
+            flag.cancel();
+        }
+    }
+}
+
+/// A lazily started thread that runs jobs in submission order, one at a time
+pub struct IoThread {
+    name: String,
+    sender: Mutex<Option<mpsc::Sender<Job>>>,
+}
+
+impl IoThread {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            sender: Mutex::new(None),
+        }
+    }
+
+    /// Runs `job` on the IO thread and waits for its result without
+    /// blocking the runtime
+    pub async fn run<T, F>(&self, job: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&CancelFlag) -> Result<T> + Send + 'static,
+    {
+        let cancel = CancelFlag::default();
+        let mut guard = CancelOnDrop(Some(cancel.clone()));
+        let (reply, response) = oneshot::channel();
+
+        self.send(Box::new(move || {
+            // Skip jobs whose caller went away while they were queued
+            if cancel.is_cancelled() {
+                return;
+            }
+            let _ = reply.send(job(&cancel));
+        }))?;
+
+        let result = response.await.map_err(|_| {
+            SnaptoError::Upload("SSH IO thread stopped before finishing the upload".to_string())
+        })?;
+
+        // Finished normally: nothing left to cancel
+        guard.disarm();
+        result
+    }
+
+    fn send(&self, job: Job) -> Result<()> {
+        let mut sender = self.sender.lock().unwrap_or_else(|e| e.into_inner());
+
+        if let Some(tx) = sender.as_ref() {
+            match tx.send(job) {
+                Ok(()) => return Ok(()),
+                // The thread is gone; start a new one with the job
+                Err(mpsc::SendError(job)) => return self.spawn(&mut sender, job),
+            }
+        }
+
+        self.spawn(&mut sender, job)
+    }
+
+    fn spawn(&self, sender: &mut Option<mpsc::Sender<Job>>, job: Job) -> Result<()> {
+        let (tx, rx) = mpsc::channel::<Job>();
+        tx.send(job).map_err(|_| SnaptoError::Upload("SSH IO channel closed".to_string()))?;
+
+        let name = self.name.clone();
+        thread::Builder::new()
+            .name(format!("ssh-io-{}", name))
+            .spawn(move || {
+                debug!("SSH IO thread for {} started", name);
+                while let Ok(job) = rx.recv() {
+                    if panic::catch_unwind(AssertUnwindSafe(job)).is_err() {
+                        error!("SSH IO job for {} panicked", name);
+                    }
+                }
+                debug!("SSH IO thread for {} stopped", name);
+            })?;
+
+        *sender = Some(tx);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_runs_jobs_in_order() {
+        let io = IoThread::new("test");
+        let log = Arc::new(Mutex::new(Vec::new()));
+
+        for i in 0..3 {
+            let log = Arc::clone(&log);
+            let value = io
+                .run(move |_| {
+                    log.lock().unwrap().push(i);
+                    Ok(i * 10)
+                })
+                .await
+                .unwrap();
+            assert_eq!(value, i * 10);
+        }
+
+        assert_eq!(*log.lock().unwrap(), vec![0, 1, 2]);
+    }
+
+    #[tokio::test]
+    async fn test_panicking_job_does_not_kill_thread() {
+        let io = IoThread::new("test");
+
+        let result: Result<()> = io.run(|_| panic!("boom")).await;
+        assert!(result.is_err());
+
+        assert_eq!(io.run(|_| Ok(7)).await.unwrap(), 7);
+    }
+
+    #[tokio::test]
+    async fn test_dropping_future_cancels_job() {
+        let io = IoThread::new("test");
+        let (started_tx, started_rx) = oneshot::channel();
+        let (seen_tx, seen_rx) = std::sync::mpsc::channel();
+
+        let upload = io.run(move |cancel| {
+            let _ = started_tx.send(());
+            while !cancel.is_cancelled() {
+                thread::sleep(Duration::from_millis(1));
+            }
+            seen_tx.send(()).unwrap();
+            cancel.check()
+        });
+
+        // Drop the upload once the job is running
+        tokio::select! {
+            _ = upload => panic!("job should not finish on its own"),
+            _ = started_rx => {}
+        }
+
+        seen_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    }
+}
@@ -0,0 +1,142 @@
+//! Host key verification against `~/.ssh/known_hosts`
+//!
+//! Every hop is checked on its own. New hosts are appended to the file
+//! rather than rewriting it, so entries libssh2 cannot parse are preserved.
+
+use base64::Engine;
+use ssh2::{CheckResult, HostKeyType, KnownHostFileKind, Session};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use tracing::{debug, info, warn};
+
+use crate::config::HostKeyChecking;
+use crate::error::{Result, SnaptoError};
+
+/// What to do with a host after looking it up
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Decision {
+    Accept,
+    Record,
+    Reject(&'static str),
+}
+
+/// Checks the server's host key for `host:port` according to `policy`
+pub fn verify(session: &Session, host: &str, port: u16, policy: HostKeyChecking) -> Result<()> {
+    if policy == HostKeyChecking::Off {
+        debug!("Host key checking disabled for {}", host);
+        return Ok(());
+    }
+
+    let (key, key_type) = session
+        .host_key()
+        .ok_or_else(|| SnaptoError::HostKeyVerification(format!("{} sent no host key", host)))?;
+
+    let path = known_hosts_path();
+    let mut known_hosts = session.known_hosts()?;
+    if path.exists() {
+        if let Err(e) = known_hosts.read_file(&path, KnownHostFileKind::OpenSSH) {
+            warn!("Could not fully read {}: {}", path.display(), e);
+        }
+    }
+
+    match decide(known_hosts.check_port(host, port, key), policy) {
+        Decision::Accept => Ok(()),
+        Decision::Record => {
+            append_entry(&path, host, port, key, key_type)?;
+            info!("Added {} to {}", host_pattern(host, port), path.display());
+            Ok(())
+        }
+        Decision::Reject(reason) => Err(SnaptoError::HostKeyVerification(format!(
+            "{}: {}",
+            host_pattern(host, port),
+            reason
+        ))),
+    }
+}
+
+fn decide(result: CheckResult, policy: HostKeyChecking) -> Decision {
+    match (result, policy) {
+        (_, HostKeyChecking::Off) | (CheckResult::Match, _) => Decision::Accept,
+        (CheckResult::Mismatch, _) => {
+            Decision::Reject("host key does not match known_hosts (possible man-in-the-middle)")
+        }
+        (CheckResult::NotFound, HostKeyChecking::AcceptNew) => Decision::Record,
+        (CheckResult::NotFound, HostKeyChecking::Strict) => {
+            Decision::Reject("host is not in known_hosts and host_key_checking is strict")
+        }
+        (CheckResult::Failure, _) => Decision::Reject("known_hosts lookup failed"),
+    }
+}
+
+fn known_hosts_path() -> PathBuf {
+    PathBuf::from(shellexpand::tilde("~/.ssh/known_hosts").to_string())
+}
+
+/// Host as written in known_hosts: bare for port 22, `[host]:port` otherwise
+fn host_pattern(host: &str, port: u16) -> String {
+    if port == 22 {
+        host.to_string()
+    } else {
+        format!("[{}]:{}", host, port)
+    }
+}
+
+fn key_type_name(key_type: HostKeyType) -> Option<&'static str> {
+    match key_type {
+        HostKeyType::Rsa => Some("ssh-rsa"),
+        HostKeyType::Dss => Some("ssh-dss"),
+        HostKeyType::Ecdsa256 => Some("ecdsa-sha2-nistp256"),
+        HostKeyType::Ecdsa384 => Some("ecdsa-sha2-nistp384"),
+        HostKeyType::Ecdsa521 => Some("ecdsa-sha2-nistp521"),
+        HostKeyType::Ed25519 => Some("ssh-ed25519"),
+        HostKeyType::Unknown => None,
+    }
+}
+
+fn format_entry(host: &str, port: u16, key: &[u8], key_type: HostKeyType) -> Option<String> {
+    let name = key_type_name(key_type)?;
+    let encoded = base64::engine::general_purpose::STANDARD.encode(key);
+    Some(format!("{} {} {}\n", host_pattern(host, port), name, encoded))
+}
+
+fn append_entry(path: &PathBuf, host: &str, port: u16, key: &[u8], key_type: HostKeyType) -> Result<()> {
+    let entry = format_entry(host, port, key, key_type).ok_or_else(|| {
+        SnaptoError::HostKeyVerification(format!("{}: unsupported host key type", host))
+    })?;
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    file.write_all(entry.as_bytes())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decisions() {
+        use HostKeyChecking::*;
+
+        assert_eq!(decide(CheckResult::Match, Strict), Decision::Accept);
+        assert_eq!(decide(CheckResult::NotFound, AcceptNew), Decision::Record);
+        assert!(matches!(decide(CheckResult::NotFound, Strict), Decision::Reject(_)));
+        assert!(matches!(decide(CheckResult::Mismatch, AcceptNew), Decision::Reject(_)));
+        assert_eq!(decide(CheckResult::Mismatch, Off), Decision::Accept);
+    }
+
+    #[test]
+    fn test_entry_format() {
+        let entry = format_entry("example.com", 22, b"key", HostKeyType::Ed25519).unwrap();
+        assert_eq!(entry, "example.com ssh-ed25519 a2V5\n");
+
+        let entry = format_entry("example.com", 2222, b"key", HostKeyType::Rsa).unwrap();
+        assert!(entry.starts_with("[example.com]:2222 ssh-rsa "));
+
+        assert!(format_entry("example.com", 22, b"key", HostKeyType::Unknown).is_none());
+    }
+}
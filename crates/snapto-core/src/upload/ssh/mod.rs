@@ -0,0 +1,767 @@
+//! SSH/SFTP transport
+//!
+//! Both the `sftp` and `ssh` uploader types are served by [`SshUploader`].
+//! Connection setup lives here: an optional proxy, then any jump hosts, then
+//! the destination. Authentication is delegated to the ordered [`AuthChain`].
+
+use async_trait::async_trait;
+use ssh2::Session;
+use std::io::Write;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use tracing::{debug, error, info};
+
+use crate::config::UploadConfig;
+use crate::error::{ConfigError, Result, SnaptoError};
+use crate::upload::{ProgressCallback, UploadResult, Uploader, UploaderInfo};
+
+pub mod auth;
+mod io_thread;
+pub mod known_hosts;
+pub mod pool;
+pub mod proxy;
+pub mod ssh_config;
+pub mod totp;
+mod tunnel;
+
+pub use auth::{is_otp_prompt, is_password_prompt, AuthAttempt, AuthChain};
+pub use pool::SessionPool;
+pub use proxy::{ProxyConfig, ProxyKind};
+pub use ssh_config::{HostSettings, SshConfig};
+
+use io_thread::{CancelFlag, IoThread};
+use pool::Connection;
+
+/// Bytes written per SFTP call; progress and cancellation are checked between chunks
+const CHUNK_SIZE: usize = 32 * 1024;
+
+/// Callback type for password prompts
+pub type PasswordCallback = Arc<Mutex<Option<Box<dyn FnMut(&str) -> Option<String> + Send>>>>;
+
+/// The `sftp` uploader type shares the SSH transport
+pub type SftpUploader = SshUploader;
+
+/// SSH/SFTP uploader
+///
+/// When `ssh_host` is set, unset connection fields are filled from
+/// `~/.ssh/config` at construction time; a config that cannot be read is
+/// reported by [`Uploader::validate`]. Transfers run on the uploader's own
+/// IO thread, shared by its clones, so they go out one at a time.
+#[derive(Clone)]
+pub struct SshUploader {
+    io: Arc<IoThread>,
+    name: String,
+    config: UploadConfig,
+    password: Option<String>,
+    password_callback: Option<PasswordCallback>,
+    totp_secret: Option<String>,
+    pool: Option<SessionPool>,
+    ssh_config_error: Option<String>,
+}
+
+impl SshUploader {
+    /// Create a new SSH uploader
+    pub fn new(name: String, config: UploadConfig) -> Self {
+        let (config, ssh_config_error) = match ssh_config::resolve_upload_config(&config) {
+            Ok(resolved) => (resolved, None),
+            Err(e) => {
+                error!("Failed to read SSH config for '{}': {}", name, e);
+                (config, Some(e.to_string()))
+            }
+        };
+
+        Self {
+            io: Arc::new(IoThread::new(name.clone())),
+            name,
+            config,
+            password: None,
+            password_callback: None,
+            totp_secret: None,
+            pool: None,
+            ssh_config_error,
+        }
+    }
+
+    /// Sets the password for authentication
+    pub fn with_password(mut self, password: String) -> Self {
+        self.password = Some(password);
+        self
+    }
+
+    /// Sets a password callback for interactive password prompts
+    pub fn with_password_callback(mut self, callback: PasswordCallback) -> Self {
+        self.password_callback = Some(callback);
+        self
+    }
+
+    /// Sets the password directly (mutable version)
+    pub fn set_password(&mut self, password: String) {
+        self.password = Some(password);
+    }
+
+    /// Sets the base32 TOTP seed used to answer one-time password prompts
+    pub fn with_totp_secret(mut self, secret: String) -> Self {
+        self.totp_secret = Some(secret);
+        self
+    }
+
+    /// Keeps the session open in `pool` between uploads
+    pub fn with_session_pool(mut self, pool: SessionPool) -> Self {
+        self.pool = Some(pool);
+        self
+    }
+
+    /// Gets the password from keychain
+    pub fn get_password_from_keychain(&self, keychain: &crate::KeychainManager) -> Option<String> {
+        let key = format!("ssh_password_{}", self.name);
+        keychain.get(&key).ok().flatten()
+    }
+
+    /// Stores the password in keychain
+    pub fn store_password_in_keychain(&self, keychain: &crate::KeychainManager, password: &str) -> Result<()> {
+        let key = format!("ssh_password_{}", self.name);
+        keychain.set(&key, password)
+    }
+
+    /// Gets the TOTP seed from keychain
+    pub fn get_totp_secret_from_keychain(&self, keychain: &crate::KeychainManager) -> Option<String> {
+        let key = format!("ssh_totp_{}", self.name);
+        keychain.get(&key).ok().flatten()
+    }
+
+    /// Stores the TOTP seed in keychain
+    pub fn store_totp_secret_in_keychain(&self, keychain: &crate::KeychainManager, secret: &str) -> Result<()> {
+        totp::current_code(secret)?;
+        let key = format!("ssh_totp_{}", self.name);
+        keychain.set(&key, secret)
+    }
+
+    /// Establish an authenticated SSH connection
+    ///
+    /// Jump hosts are traversed in order, each with its own host key check
+    /// and authentication; only the first hop goes through `proxy`.
+    fn connect(&self) -> Result<Session> {
+        let mut hops = Vec::new();
+        for jump in self.config.jump_chain()? {
+            hops.push(ssh_config::resolve_upload_config(&jump.to_upload_config())?);
+        }
+        hops.push(self.config.clone());
+
+        let timeout = self.config.timeout.map(Duration::from_secs);
+        let last = hops.len() - 1;
+        let mut session: Option<Session> = None;
+
+        for (i, hop) in hops.iter().enumerate() {
+            let (host, port) = endpoint(hop)?;
+
+            let stream = match session.take() {
+                Some(previous) => tunnel::open(previous, host, port)?,
+                None => match proxy::ProxyConfig::from_config(&self.config)? {
+                    Some(proxy) => proxy.connect(host, port, timeout)?,
+                    None => open_tcp(host, port, timeout)?,
+                },
+            };
+
+            session = Some(self.open_session(stream, hop, timeout, i == last)?);
+        }
+
+        session.ok_or_else(|| SnaptoError::SshConnection("No SSH hops to connect".to_string()))
+    }
+
+    /// Handshake, host key check and authentication for one hop
+    ///
+    /// Stored credentials belong to the destination, so jump hosts only get
+    /// the interactive callback.
+    fn open_session(
+        &self,
+        stream: TcpStream,
+        hop: &UploadConfig,
+        timeout: Option<Duration>,
+        is_destination: bool,
+    ) -> Result<Session> {
+        let (host, port) = endpoint(hop)?;
+        let username = hop
+            .username
+            .as_ref()
+            .ok_or_else(|| SnaptoError::Config(ConfigError::Invalid(format!("Username not configured for {}", host))))?;
+
+        debug!("Connecting to {}@{}:{}", username, host, port);
+
+        let mut session = Session::new().map_err(|e| {
+            error!("Failed to create SSH session: {}", e);
+            SnaptoError::SshConnection(format!("Session creation failed: {}", e))
+        })?;
+
+        if let Some(timeout) = timeout {
+            session.set_timeout(timeout.as_millis() as u32);
+        }
+
+        session.set_tcp_stream(stream);
+        session.handshake().map_err(|e| {
+            error!("SSH handshake with {} failed: {}", host, e);
+            SnaptoError::SshConnection(format!("Handshake with {} failed: {}", host, e))
+        })?;
+
+        known_hosts::verify(&session, host, port, hop.host_key_checking.unwrap_or_default())?;
+
+        debug!("SSH connection to {} established, authenticating...", host);
+
+        let mut chain = AuthChain::from_config(hop, username, host)
+            .with_password_callback(self.password_callback.clone());
+        if is_destination {
+            chain = chain
+                .with_password(self.password.clone())
+                .with_totp_secret(self.totp_secret.clone());
+        }
+        chain.authenticate(&session)?;
+
+        Ok(session)
+    }
+
+    /// Remote directory files are written to
+    ///
+    /// A leading `~/` is resolved by the SFTP server against the login directory.
+    fn remote_dir(&self) -> Result<String> {
+        let remote_path = self
+            .config
+            .remote_path
+            .as_ref()
+            .ok_or_else(|| SnaptoError::Config(ConfigError::Invalid("Remote path not configured".to_string())))?;
+
+        let dir = match remote_path.as_str() {
+            "~" => ".",
+            path => path.strip_prefix("~/").unwrap_or(path),
+        };
+
+        Ok(dir.trim_end_matches('/').to_string())
+    }
+
+    /// Generate the public URL for a file based on base_url
+    fn generate_url(&self, filename: &str) -> Option<String> {
+        self.config.base_url.as_ref().map(|base| {
+            format!("{}/{}", base.trim_end_matches('/'), filename)
+        })
+    }
+
+    /// Ensure the remote directory and its parents exist, like `mkdir -p`
+    fn ensure_remote_dir(&self, sftp: &ssh2::Sftp, remote_path: &str) -> Result<()> {
+        debug!("Ensuring remote directory exists: {}", remote_path);
+
+        if sftp.stat(Path::new(remote_path)).is_ok_and(|stat| stat.is_dir()) {
+            debug!("Remote directory already exists");
+            return Ok(());
+        }
+
+        for dir in dir_chain(remote_path) {
+            match sftp.stat(Path::new(&dir)) {
+                Ok(stat) if stat.is_dir() => continue,
+                Ok(_) => {
+                    return Err(SnaptoError::Sftp(format!(
+                        "Path exists but is not a directory: {}",
+                        dir
+                    )));
+                }
+                Err(_) => {
+                    debug!("Creating remote directory: {}", dir);
+                    sftp.mkdir(Path::new(&dir), 0o755)
+                        .map_err(|e| {
+                            error!("Failed to create remote directory: {}", e);
+                            SnaptoError::Sftp(format!("Failed to create directory {}: {}", dir, e))
+                        })?;
+                    info!("Created remote directory: {}", dir);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Connect and open the SFTP subsystem
+    fn open_connection(&self) -> Result<Connection> {
+        let session = self.connect()?;
+
+        debug!("Opening SFTP session");
+        let sftp = session.sftp().map_err(|e| {
+            error!("Failed to open SFTP session: {}", e);
+            SnaptoError::Sftp(format!("Failed to open SFTP: {}", e))
+        })?;
+
+        Ok(Connection { session, sftp })
+    }
+
+    /// Settings a pooled session must have been opened with to be reused
+    fn fingerprint(&self) -> String {
+        format!("{:?}", self.config)
+    }
+
+    /// Blocking upload: connect (or reuse a pooled session) and write the file
+    fn upload_blocking(
+        &self,
+        data: &[u8],
+        filename: &str,
+        progress: Option<&ProgressCallback>,
+        cancel: &CancelFlag,
+    ) -> Result<(String, Option<String>)> {
+        let remote_dir = self.remote_dir()?;
+        let remote_file_path = format!("{}/{}", remote_dir, filename);
+
+        // The filename may add subdirectories of its own
+        let file_dir = match filename.rsplit_once('/') {
+            Some((subdir, _)) => format!("{}/{}", remote_dir, subdir),
+            None => remote_dir,
+        };
+
+        let write = |conn: &Connection| {
+            cancel.check()?;
+            self.ensure_remote_dir(&conn.sftp, &file_dir)?;
+            self.write_file(&conn.sftp, &remote_file_path, data, progress, cancel)
+        };
+
+        match &self.pool {
+            Some(pool) => pool.run(&self.name, &self.fingerprint(), cancel, || self.open_connection(), write)?,
+            None => write(&self.open_connection()?)?,
+        }
+
+        info!("Successfully uploaded {} to {}", filename, remote_file_path);
+
+        let url = self.generate_url(filename);
+        if let Some(ref url) = url {
+            info!("Generated URL: {}", url);
+        }
+
+        Ok((remote_file_path, url))
+    }
+
+    /// Runs the blocking transfer on the IO thread
+    async fn transfer(
+        &self,
+        data: &[u8],
+        filename: &str,
+        progress: Option<ProgressCallback>,
+    ) -> Result<UploadResult> {
+        let start = Instant::now();
+        info!("Starting SSH upload: {} ({} bytes)", filename, data.len());
+
+        let uploader = self.clone();
+        let data = data.to_vec();
+        let filename = filename.to_string();
+        let size = data.len();
+
+        let (remote_path, url) = self
+            .io
+            .run(move |cancel| uploader.upload_blocking(&data, &filename, progress.as_ref(), cancel))
+            .await?;
+
+        Ok(UploadResult {
+            remote_path,
+            url,
+            size,
+            duration_ms: start.elapsed().as_millis() as u64,
+        })
+    }
+
+    /// Blocking check for `filename` in the remote directory
+    fn exists_blocking(&self, filename: &str, cancel: &CancelFlag) -> Result<bool> {
+        let remote_file_path = format!("{}/{}", self.remote_dir()?, filename);
+        let stat = |conn: &Connection| Ok(conn.sftp.stat(Path::new(&remote_file_path)).is_ok());
+
+        match &self.pool {
+            Some(pool) => pool.run(&self.name, &self.fingerprint(), cancel, || self.open_connection(), stat),
+            None => stat(&self.open_connection()?),
+        }
+    }
+
+    /// Writes the file in chunks; a cancelled upload removes the partial file
+    fn write_file(
+        &self,
+        sftp: &ssh2::Sftp,
+        remote_file_path: &str,
+        data: &[u8],
+        progress: Option<&ProgressCallback>,
+        cancel: &CancelFlag,
+    ) -> Result<()> {
+        debug!("Creating remote file: {}", remote_file_path);
+
+        let mut remote_file = sftp.create(Path::new(remote_file_path)).map_err(|e| {
+            error!("Failed to create remote file: {}", e);
+            SnaptoError::Sftp(format!("Failed to create file: {}", e))
+        })?;
+
+        let written = write_chunks(&mut remote_file, data, progress, cancel);
+        if written.is_err() && cancel.is_cancelled() {
+            drop(remote_file);
+            info!("Upload cancelled, removing partial {}", remote_file_path);
+            let _ = sftp.unlink(Path::new(remote_file_path));
+        }
+        written
+    }
+}
+
+/// Copies `data` to `writer` in chunks, reporting progress and honoring cancellation
+fn write_chunks<W: Write>(
+    writer: &mut W,
+    data: &[u8],
+    progress: Option<&ProgressCallback>,
+    cancel: &CancelFlag,
+) -> Result<()> {
+    let total = data.len() as u64;
+    let mut sent = 0u64;
+
+    for chunk in data.chunks(CHUNK_SIZE) {
+        cancel.check()?;
+        writer.write_all(chunk).map_err(|e| {
+            error!("Failed to write data to remote file: {}", e);
+            SnaptoError::Sftp(format!("Failed to write file: {}", e))
+        })?;
+        sent += chunk.len() as u64;
+        if let Some(progress) = progress {
+            progress(sent, total);
+        }
+    }
+
+    writer.flush().map_err(|e| {
+        error!("Failed to flush remote file: {}", e);
+        SnaptoError::Sftp(format!("Failed to flush file: {}", e))
+    })
+}
+
+/// Host and port of a resolved hop
+fn endpoint(config: &UploadConfig) -> Result<(&str, u16)> {
+    let host = config
+        .host
+        .as_deref()
+        .ok_or_else(|| SnaptoError::Config(ConfigError::Invalid("Host not configured".to_string())))?;
+    Ok((host, config.port.unwrap_or(22)))
+}
+
+/// Open the TCP connection, honoring the configured timeout
+pub(crate) fn open_tcp(host: &str, port: u16, timeout: Option<Duration>) -> Result<TcpStream> {
+    let addr = format!("{}:{}", host, port);
+
+    let connect = || -> std::io::Result<TcpStream> {
+        match timeout {
+            Some(timeout) => {
+                let mut last_err = None;
+                for socket_addr in addr.to_socket_addrs()? {
+                    match TcpStream::connect_timeout(&socket_addr, timeout) {
+                        Ok(stream) => return Ok(stream),
+                        Err(e) => last_err = Some(e),
+                    }
+                }
+                Err(last_err.unwrap_or_else(|| {
+                    std::io::Error::new(std::io::ErrorKind::NotFound, "no addresses resolved")
+                }))
+            }
+            None => TcpStream::connect(&addr),
+        }
+    };
+
+    connect().map_err(|e| {
+        error!("Failed to connect to SSH host: {}", e);
+        SnaptoError::SshConnection(format!("Connection to {} failed: {}", addr, e))
+    })
+}
+
+#[async_trait]
+impl Uploader for SshUploader {
+    async fn upload(&self, data: &[u8], filename: &str) -> Result<UploadResult> {
+        self.transfer(data, filename, None).await
+    }
+
+    async fn upload_with_progress(
+        &self,
+        data: &[u8],
+        filename: &str,
+        progress: ProgressCallback,
+    ) -> Result<UploadResult> {
+        self.transfer(data, filename, Some(progress)).await
+    }
+
+    async fn exists(&self, filename: &str) -> Result<bool> {
+        let uploader = self.clone();
+        let filename = filename.to_string();
+        self.io.run(move |cancel| uploader.exists_blocking(&filename, cancel)).await
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    fn validate(&self) -> Result<()> {
+        if let Some(e) = &self.ssh_config_error {
+            return Err(SnaptoError::Config(ConfigError::Invalid(format!(
+                "Failed to read SSH config: {}",
+                e
+            ))));
+        }
+
+        if self.config.host.is_none() {
+            return Err(SnaptoError::Config(ConfigError::Invalid("Host required".to_string())));
+        }
+
+        if self.config.username.is_none() {
+            return Err(SnaptoError::Config(ConfigError::Invalid("Username required".to_string())));
+        }
+
+        if self.config.remote_path.is_none() {
+            return Err(SnaptoError::Config(ConfigError::Invalid("Remote path required".to_string())));
+        }
+
+        self.config.jump_chain()?;
+        proxy::ProxyConfig::from_config(&self.config)?;
+
+        if self.config.use_key_auth.unwrap_or(false) && self.config.key_path.is_none() {
+            return Err(SnaptoError::Config(ConfigError::Invalid(
+                "Key path required for key authentication".to_string(),
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn info(&self) -> UploaderInfo {
+        UploaderInfo {
+            name: self.name.clone(),
+            enabled: self.config.enabled,
+            uploader_type: self.config.uploader_type.clone(),
+        }
+    }
+}
+
+/// `path` and each of its parents, outermost first: `a/b` → `a`, `a/b`
+fn dir_chain(path: &str) -> Vec<String> {
+    let mut chain = Vec::new();
+    let mut current = if path.starts_with('/') { "/".to_string() } else { String::new() };
+    for part in path.split('/').filter(|part| !part.is_empty()) {
+        if !current.is_empty() && !current.ends_with('/') {
+            current.push('/');
+        }
+        current.push_str(part);
+        chain.push(current.clone());
+    }
+    chain
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::UploadConfig;
+
+    #[test]
+    fn test_write_chunks_reports_progress() {
+        use std::sync::Mutex as StdMutex;
+
+        let data = vec![7u8; CHUNK_SIZE * 2 + 10];
+        let seen = Arc::new(StdMutex::new(Vec::new()));
+        let sink = Arc::clone(&seen);
+        let progress: ProgressCallback = Arc::new(move |sent, total| sink.lock().unwrap().push((sent, total)));
+
+        let mut out = Vec::new();
+        write_chunks(&mut out, &data, Some(&progress), &CancelFlag::default()).unwrap();
+
+        let total = data.len() as u64;
+        assert_eq!(out, data);
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![(CHUNK_SIZE as u64, total), (2 * CHUNK_SIZE as u64, total), (total, total)]
+        );
+    }
+
+    #[test]
+    fn test_write_chunks_stops_when_cancelled() {
+        let cancel = CancelFlag::default();
+        cancel.cancel();
+
+        let mut out = Vec::new();
+        assert!(write_chunks(&mut out, b"data", None, &cancel).is_err());
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn test_ssh_uploader_validation() {
+        let config = UploadConfig {
+            uploader_type: "ssh".to_string(),
+            enabled: true,
+            host: None,
+            port: Some(22),
+            username: None,
+            remote_path: None,
+            base_url: None,
+            local_path: None,
+            use_key_auth: None,
+            key_path: None,
+            timeout: None,
+            ..Default::default()
+        };
+
+        let uploader = SshUploader::new("test".to_string(), config);
+        assert!(uploader.validate().is_err());
+    }
+
+    #[test]
+    fn test_ssh_uploader_with_valid_config() {
+        let config = UploadConfig {
+            uploader_type: "ssh".to_string(),
+            enabled: true,
+            host: Some("example.com".to_string()),
+            port: Some(22),
+            username: Some("user".to_string()),
+            remote_path: Some("/uploads".to_string()),
+            base_url: Some("https://example.com/uploads".to_string()),
+            local_path: None,
+            use_key_auth: Some(true),
+            key_path: Some("~/.ssh/id_rsa".to_string()),
+            timeout: Some(30),
+            ..Default::default()
+        };
+
+        let uploader = SshUploader::new("test".to_string(), config);
+        assert!(uploader.validate().is_ok());
+        assert_eq!(uploader.name(), "test");
+        assert!(uploader.is_enabled());
+    }
+
+    #[test]
+    fn test_sftp_uploader_validation() {
+        let config = UploadConfig {
+            uploader_type: "sftp".to_string(),
+            enabled: true,
+            host: None,
+            port: Some(22),
+            username: None,
+            remote_path: None,
+            base_url: None,
+            local_path: None,
+            use_key_auth: None,
+            key_path: None,
+            timeout: None,
+            ..Default::default()
+        };
+
+        let uploader = SftpUploader::new("test".to_string(), config);
+        assert!(uploader.validate().is_err());
+    }
+
+    #[test]
+    fn test_sftp_uploader_with_valid_config() {
+        let config = UploadConfig {
+            uploader_type: "sftp".to_string(),
+            enabled: true,
+            host: Some("example.com".to_string()),
+            port: Some(22),
+            username: Some("user".to_string()),
+            remote_path: Some("/uploads".to_string()),
+            base_url: Some("https://example.com/uploads".to_string()),
+            local_path: None,
+            use_key_auth: Some(true),
+            key_path: Some("~/.ssh/id_rsa".to_string()),
+            timeout: Some(30),
+            ..Default::default()
+        };
+
+        let uploader = SftpUploader::new("test".to_string(), config);
+        assert!(uploader.validate().is_ok());
+        assert_eq!(uploader.name(), "test");
+        assert!(uploader.is_enabled());
+        assert_eq!(uploader.info().uploader_type, "sftp");
+    }
+
+    #[test]
+    fn test_generate_url() {
+        let config = UploadConfig {
+            uploader_type: "ssh".to_string(),
+            enabled: true,
+            host: Some("example.com".to_string()),
+            port: Some(22),
+            username: Some("user".to_string()),
+            remote_path: Some("/uploads".to_string()),
+            base_url: Some("https://example.com/files".to_string()),
+            local_path: None,
+            use_key_auth: Some(true),
+            key_path: Some("~/.ssh/id_rsa".to_string()),
+            timeout: Some(30),
+            ..Default::default()
+        };
+
+        let uploader = SshUploader::new("test".to_string(), config);
+        let url = uploader.generate_url("test.png");
+
+        assert_eq!(url, Some("https://example.com/files/test.png".to_string()));
+    }
+
+    #[test]
+    fn test_generate_url_no_template() {
+        let config = UploadConfig {
+            uploader_type: "ssh".to_string(),
+            enabled: true,
+            host: Some("example.com".to_string()),
+            port: Some(22),
+            username: Some("user".to_string()),
+            remote_path: Some("/uploads".to_string()),
+            base_url: None,
+            local_path: None,
+            use_key_auth: Some(true),
+            key_path: Some("~/.ssh/id_rsa".to_string()),
+            timeout: Some(30),
+            ..Default::default()
+        };
+
+        let uploader = SshUploader::new("test".to_string(), config);
+        let url = uploader.generate_url("test.png");
+
+        assert_eq!(url, None);
+    }
+
+    #[test]
+    fn test_unreadable_ssh_config_fails_validation() {
+        let config = UploadConfig {
+            uploader_type: "ssh".to_string(),
+            host: Some("example.com".to_string()),
+            username: Some("user".to_string()),
+            remote_path: Some("/uploads".to_string()),
+            ..Default::default()
+        };
+
+        let mut uploader = SshUploader::new("test".to_string(), config);
+        assert!(uploader.validate().is_ok());
+
+        uploader.ssh_config_error = Some("Include loop".to_string());
+        let err = uploader.validate().unwrap_err().to_string();
+        assert!(err.contains("Include loop"), "{}", err);
+    }
+
+    #[test]
+    fn test_remote_dir_resolves_home_relative_paths() {
+        let mut config = UploadConfig {
+            uploader_type: "ssh".to_string(),
+            remote_path: Some("~/public_html/shots/".to_string()),
+            ..Default::default()
+        };
+
+        let uploader = SshUploader::new("test".to_string(), config.clone());
+        assert_eq!(uploader.remote_dir().unwrap(), "public_html/shots");
+
+        config.remote_path = Some("/var/www/shots".to_string());
+        let uploader = SshUploader::new("test".to_string(), config);
+        assert_eq!(uploader.remote_dir().unwrap(), "/var/www/shots");
+    }
+
+    #[test]
+    fn test_dir_chain_lists_parents_first() {
+        assert_eq!(dir_chain("public_html/shots/2026/10"), [
+            "public_html",
+            "public_html/shots",
+            "public_html/shots/2026",
+            "public_html/shots/2026/10",
+        ]);
+        assert_eq!(dir_chain("/var/www"), ["/var", "/var/www"]);
+        assert_eq!(dir_chain("./ba"), [".", "./ba"]);
+    }
+}
@@ -0,0 +1,317 @@
+//! Pooled, keep-alive SSH/SFTP sessions
+//!
+//! Connecting, handshaking and authenticating can take seconds on
+//! high-latency links. A [`SessionPool`] keeps one authenticated session per
+//! destination, sends keep-alives while it is idle, checks it before reuse
+//! and reconnects transparently when it has gone away.
+
+use ssh2::{Session, Sftp};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex, Weak};
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+use super::io_thread::CancelFlag;
+use crate::error::Result;
+
+/// Sessions idle for longer than this are closed
+const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// How often idle sessions are sent a keep-alive
+const DEFAULT_KEEPALIVE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// An authenticated session with its SFTP channel
+pub(crate) struct Connection {
+    pub session: Session,
+    pub sftp: Sftp,
+}
+
+struct PooledConnection {
+    connection: Connection,
+    /// Connection settings the session was opened with
+    fingerprint: String,
+    last_used: Instant,
+}
+
+type Slot = Arc<Mutex<Option<PooledConnection>>>;
+
+struct PoolInner {
+    slots: Mutex<HashMap<String, Slot>>,
+    idle_timeout: Duration,
+    keepalive_interval: Duration,
+    keepalive_started: Mutex<bool>,
+}
+
+/// Keeps SSH sessions open between uploads
+///
+/// Cloning is cheap and clones share the same sessions. Uploads to different
+/// destinations run in parallel; uploads to the same destination take turns
+/// on its session.
+#[derive(Clone)]
+pub struct SessionPool {
+    inner: Arc<PoolInner>,
+}
+
+impl Default for SessionPool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SessionPool {
+    /// Creates an empty pool with default timeouts
+    pub fn new() -> Self {
+        Self::with_timeouts(DEFAULT_IDLE_TIMEOUT, DEFAULT_KEEPALIVE_INTERVAL)
+    }
+
+    /// Creates an empty pool with custom idle timeout and keep-alive interval
+    pub fn with_timeouts(idle_timeout: Duration, keepalive_interval: Duration) -> Self {
+        Self {
+            inner: Arc::new(PoolInner {
+                slots: Mutex::new(HashMap::new()),
+                idle_timeout,
+                keepalive_interval: keepalive_interval.max(Duration::from_secs(2)),
+                keepalive_started: Mutex::new(false),
+            }),
+        }
+    }
+
+    /// Number of open sessions
+    pub fn len(&self) -> usize {
+        self.slots()
+            .iter()
+            .filter(|slot| slot.lock().map(|c| c.is_some()).unwrap_or(false))
+            .count()
+    }
+
+    /// Whether no sessions are open
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Closes the session for a destination, if any
+    pub fn close(&self, name: &str) {
+        let slot = self.inner.slots.lock().ok().and_then(|mut s| s.remove(name));
+        if let Some(slot) = slot {
+            if let Ok(mut conn) = slot.lock() {
+                conn.take();
+            }
+        }
+    }
+
+    /// Closes every session
+    pub fn close_all(&self) {
+        if let Ok(mut slots) = self.inner.slots.lock() {
+            slots.clear();
+        }
+    }
+
+    /// Runs `op` on the pooled session for `name`, connecting when needed
+    ///
+    /// When the operation fails on a reused session that no longer answers,
+    /// the session is dropped and the operation is retried once on a fresh
+    /// connection, so a session the server closed while idle is invisible to
+    /// the caller. Other failures (a missing directory, a denied write) are
+    /// returned as they are, and nothing is retried once `cancel` is set.
+    pub(crate) fn run<T>(
+        &self,
+        name: &str,
+        fingerprint: &str,
+        cancel: &CancelFlag,
+        mut connect: impl FnMut() -> Result<Connection>,
+        mut op: impl FnMut(&Connection) -> Result<T>,
+    ) -> Result<T> {
+        let slot = self.slot(name);
+        let mut guard = slot.lock().unwrap_or_else(|e| e.into_inner());
+
+        if let Some(pooled) = guard.take() {
+            if self.is_reusable(&pooled, fingerprint) {
+                debug!("Reusing SSH session for {}", name);
+                match op(&pooled.connection) {
+                    Ok(value) => {
+                        *guard = Some(PooledConnection { last_used: Instant::now(), ..pooled });
+                        return Ok(value);
+                    }
+                    Err(e) if cancel.is_cancelled() || is_alive(&pooled.connection) => {
+                        *guard = Some(PooledConnection { last_used: Instant::now(), ..pooled });
+                        return Err(e);
+                    }
+                    Err(e) => warn!("Pooled session for {} failed ({}), reconnecting", name, e),
+                }
+            } else {
+                debug!("Discarding stale SSH session for {}", name);
+            }
+        }
+
+        cancel.check()?;
+        let connection = connect()?;
+        connection.session.set_keepalive(false, self.inner.keepalive_interval.as_secs() as u32);
+
+        let value = op(&connection)?;
+        *guard = Some(PooledConnection {
+            connection,
+            fingerprint: fingerprint.to_string(),
+            last_used: Instant::now(),
+        });
+        drop(guard);
+
+        self.start_keepalive();
+        Ok(value)
+    }
+
+    fn slots(&self) -> Vec<Slot> {
+        self.inner
+            .slots
+            .lock()
+            .map(|s| s.values().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    fn slot(&self, name: &str) -> Slot {
+        let mut slots = self.inner.slots.lock().unwrap_or_else(|e| e.into_inner());
+        slots.entry(name.to_string()).or_default().clone()
+    }
+
+    /// Health check before reuse
+    ///
+    /// Sessions used recently are trusted; older ones must answer a cheap
+    /// SFTP round trip.
+    fn is_reusable(&self, pooled: &PooledConnection, fingerprint: &str) -> bool {
+        if pooled.fingerprint != fingerprint {
+            return false;
+        }
+
+        let idle = pooled.last_used.elapsed();
+        if idle > self.inner.idle_timeout {
+            return false;
+        }
+        if idle < self.inner.keepalive_interval {
+            return true;
+        }
+
+        is_alive(&pooled.connection)
+    }
+
+    /// Starts the background keep-alive thread once
+    fn start_keepalive(&self) {
+        let mut started = self.inner.keepalive_started.lock().unwrap_or_else(|e| e.into_inner());
+        if *started {
+            return;
+        }
+
+        let weak = Arc::downgrade(&self.inner);
+        let interval = self.inner.keepalive_interval;
+        let spawned = thread::Builder::new()
+            .name("ssh-keepalive".to_string())
+            .spawn(move || keepalive_loop(weak, interval));
+
+        match spawned {
+            Ok(_) => *started = true,
+            Err(e) => warn!("Could not start SSH keep-alive thread: {}", e),
+        }
+    }
+}
+
+/// Whether the session still answers a cheap SFTP round trip
+fn is_alive(connection: &Connection) -> bool {
+    connection.sftp.realpath(Path::new(".")).is_ok()
+}
+
+/// Sends keep-alives and closes idle or dead sessions until the pool is dropped
+fn keepalive_loop(pool: Weak<PoolInner>, interval: Duration) {
+    loop {
+        thread::sleep(interval);
+
+        let Some(inner) = pool.upgrade() else {
+            return;
+        };
+
+        let slots: Vec<(String, Slot)> = match inner.slots.lock() {
+            Ok(slots) => slots.iter().map(|(k, v)| (k.clone(), v.clone())).collect(),
+            Err(_) => return,
+        };
+
+        for (name, slot) in slots {
+            // Busy sessions are in use, so they are alive
+            let Ok(mut guard) = slot.try_lock() else {
+                continue;
+            };
+            let Some(pooled) = guard.as_ref() else {
+                continue;
+            };
+
+            if pooled.last_used.elapsed() > inner.idle_timeout {
+                info!("Closing idle SSH session for {}", name);
+                guard.take();
+            } else if let Err(e) = pooled.connection.session.keepalive_send() {
+                warn!("SSH keep-alive for {} failed: {}", name, e);
+                guard.take();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_pool_is_empty() {
+        let pool = SessionPool::new();
+        assert!(pool.is_empty());
+        pool.close("missing");
+        pool.close_all();
+        assert_eq!(pool.len(), 0);
+    }
+
+    #[test]
+    fn test_failed_connect_is_not_pooled() {
+        let pool = SessionPool::new();
+        let mut attempts = 0;
+
+        let result: Result<()> = pool.run(
+            "dest",
+            "fp",
+            &CancelFlag::default(),
+            || {
+                attempts += 1;
+                Err(crate::error::SnaptoError::SshConnection("unreachable".to_string()))
+            },
+            |_| Ok(()),
+        );
+
+        assert!(result.is_err());
+        assert_eq!(attempts, 1);
+        assert!(pool.is_empty());
+    }
+
+    #[test]
+    fn test_cancelled_job_does_not_connect() {
+        let pool = SessionPool::new();
+        let cancel = CancelFlag::default();
+        cancel.cancel();
+        let mut attempts = 0;
+
+        let result: Result<()> = pool.run(
+            "dest",
+            "fp",
+            &cancel,
+            || {
+                attempts += 1;
+                Err(crate::error::SnaptoError::SshConnection("unreachable".to_string()))
+            },
+            |_| Ok(()),
+        );
+
+        assert!(result.is_err());
+        assert_eq!(attempts, 0);
+    }
+
+    #[test]
+    fn test_keepalive_interval_has_floor() {
+        let pool = SessionPool::with_timeouts(Duration::from_secs(1), Duration::from_millis(1));
+        assert_eq!(pool.inner.keepalive_interval, Duration::from_secs(2));
+    }
+}
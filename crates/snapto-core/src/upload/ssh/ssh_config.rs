@@ -0,0 +1,504 @@
+//! OpenSSH client configuration (`~/.ssh/config`)
+//!
+//! Destinations and jump hosts with `ssh_host` set take HostName, Port, User,
+//! IdentityFile, ProxyJump and ConnectTimeout from the matching `Host` blocks. As in
+//! OpenSSH, the first value found for each option wins, `Include` is expanded
+//! in place and `Match` blocks are skipped.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+use crate::config::UploadConfig;
+use crate::error::{ConfigError, Result, SnaptoError};
+
+/// Maximum nesting depth for `Include`, same as OpenSSH
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+/// Settings resolved for one host alias
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HostSettings {
+    pub hostname: Option<String>,
+    pub port: Option<u16>,
+    pub user: Option<String>,
+    pub identity_files: Vec<String>,
+    pub proxy_jump: Option<String>,
+    pub connect_timeout: Option<u64>,
+}
+
+/// A single option with the `Host` patterns it applies to
+#[derive(Debug, Clone)]
+struct Entry {
+    /// `None` for options before the first `Host` line (they apply to all hosts)
+    patterns: Option<Vec<String>>,
+    key: String,
+    value: String,
+}
+
+/// Parsed OpenSSH client configuration
+#[derive(Debug, Clone, Default)]
+pub struct SshConfig {
+    entries: Vec<Entry>,
+}
+
+impl SshConfig {
+    /// Loads `~/.ssh/config`, returning an empty config when it does not exist
+    pub fn load_default() -> Result<Self> {
+        let path = ssh_dir().join("config");
+        if !path.exists() {
+            debug!("No OpenSSH config at {}", path.display());
+            return Ok(Self::default());
+        }
+        Self::load(&path)
+    }
+
+    /// Loads a config file, expanding `Include` relative to `~/.ssh`
+    pub fn load(path: &Path) -> Result<Self> {
+        let mut config = Self::default();
+        config.read_file(path, None, 0)?;
+        Ok(config)
+    }
+
+    /// Parses config text; `Include` paths are resolved relative to `base_dir`
+    pub fn parse(text: &str, base_dir: &Path) -> Result<Self> {
+        let mut config = Self::default();
+        config.read_text(text, base_dir, None, 0)?;
+        Ok(config)
+    }
+
+    fn read_file(&mut self, path: &Path, patterns: Option<Vec<String>>, depth: usize) -> Result<()> {
+        let text = fs::read_to_string(path).map_err(|e| {
+            SnaptoError::Config(ConfigError::Invalid(format!(
+                "Cannot read SSH config {}: {}",
+                path.display(),
+                e
+            )))
+        })?;
+        self.read_text(&text, &ssh_dir(), patterns, depth)
+    }
+
+    fn read_text(
+        &mut self,
+        text: &str,
+        base_dir: &Path,
+        mut patterns: Option<Vec<String>>,
+        depth: usize,
+    ) -> Result<()> {
+        if depth > MAX_INCLUDE_DEPTH {
+            return Err(SnaptoError::Config(ConfigError::Invalid(
+                "SSH config Include nested too deeply".to_string(),
+            )));
+        }
+
+        // Lines after a Match are ignored until the next Host
+        let mut in_match = false;
+
+        for line in text.lines() {
+            let Some((key, value)) = split_line(line) else {
+                continue;
+            };
+
+            match key.as_str() {
+                "host" => {
+                    patterns = Some(split_args(&value));
+                    in_match = false;
+                }
+                "match" => in_match = true,
+                _ if in_match => {}
+                "include" => {
+                    for arg in split_args(&value) {
+                        for path in include_paths(&arg, base_dir) {
+                            self.read_file(&path, patterns.clone(), depth + 1)?;
+                        }
+                    }
+                }
+                _ => self.entries.push(Entry {
+                    patterns: patterns.clone(),
+                    key,
+                    value,
+                }),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Resolves the effective settings for a host alias
+    pub fn resolve(&self, alias: &str) -> HostSettings {
+        let mut settings = HostSettings::default();
+
+        for entry in &self.entries {
+            if let Some(patterns) = &entry.patterns {
+                if !host_matches(patterns, alias) {
+                    continue;
+                }
+            }
+
+            let value = unquote(&entry.value);
+            match entry.key.as_str() {
+                "hostname" if settings.hostname.is_none() => {
+                    settings.hostname = Some(value.replace("%h", alias));
+                }
+                "port" if settings.port.is_none() => settings.port = value.parse().ok(),
+                "user" if settings.user.is_none() => settings.user = Some(value),
+                "identityfile" if !settings.identity_files.contains(&value) => {
+                    settings.identity_files.push(value);
+                }
+                "proxyjump" if settings.proxy_jump.is_none() => {
+                    settings.proxy_jump = (!value.eq_ignore_ascii_case("none")).then_some(value);
+                }
+                "connecttimeout" if settings.connect_timeout.is_none() => {
+                    settings.connect_timeout = value.parse().ok();
+                }
+                _ => {}
+            }
+        }
+
+        settings
+    }
+}
+
+impl HostSettings {
+    /// Fills the unset fields of `config` from these settings
+    ///
+    /// Explicit snapto fields always win; the alias itself is the host when
+    /// no HostName is given, as `ssh` does.
+    pub fn apply_to(&self, alias: &str, config: &UploadConfig) -> UploadConfig {
+        let mut resolved = config.clone();
+
+        if resolved.host.is_none() {
+            resolved.host = Some(self.hostname.clone().unwrap_or_else(|| alias.to_string()));
+        }
+        if resolved.port.is_none() {
+            resolved.port = self.port;
+        }
+
+        let host = resolved.host.clone().unwrap_or_default();
+        if resolved.username.is_none() {
+            resolved.username = self.user.clone().or_else(local_user);
+        }
+        let user = resolved.username.clone().unwrap_or_default();
+
+        if resolved.key_path.is_none() {
+            resolved.key_path = self
+                .identity_files
+                .iter()
+                .map(|f| expand_tokens(f, alias, &host, &user))
+                .find(|f| Path::new(&shellexpand::tilde(f).to_string()).exists());
+        }
+        if resolved.timeout.is_none() {
+            resolved.timeout = self.connect_timeout;
+        }
+        if resolved.proxy_jump.is_none() && resolved.jump_hosts.is_none() {
+            resolved.proxy_jump = self.proxy_jump.clone();
+        }
+
+        resolved
+    }
+}
+
+/// Resolves `ssh_host` against `~/.ssh/config`, leaving other configs unchanged
+pub fn resolve_upload_config(config: &UploadConfig) -> Result<UploadConfig> {
+    match &config.ssh_host {
+        Some(alias) => {
+            let settings = SshConfig::load_default()?.resolve(alias);
+            debug!("Resolved ssh_host '{}': {:?}", alias, settings);
+            Ok(settings.apply_to(alias, config))
+        }
+        None => Ok(config.clone()),
+    }
+}
+
+/// Directory relative includes are resolved against
+fn ssh_dir() -> PathBuf {
+    PathBuf::from(shellexpand::tilde("~/.ssh").to_string())
+}
+
+fn local_user() -> Option<String> {
+    std::env::var("USER").or_else(|_| std::env::var("USERNAME")).ok()
+}
+
+/// Splits a config line into a lowercase keyword and its raw value
+fn split_line(line: &str) -> Option<(String, String)> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+
+    let end = line
+        .find(|c: char| c.is_whitespace() || c == '=')
+        .unwrap_or(line.len());
+    let key = line[..end].to_lowercase();
+    let value = line[end..]
+        .trim_start()
+        .strip_prefix('=')
+        .unwrap_or(line[end..].trim_start())
+        .trim()
+        .to_string();
+
+    Some((key, value))
+}
+
+/// Splits a value into whitespace-separated arguments, honoring quotes
+fn split_args(value: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+
+    for c in value.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !current.is_empty() {
+                    args.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        args.push(current);
+    }
+
+    args
+}
+
+fn unquote(value: &str) -> String {
+    let value = value.trim();
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .unwrap_or(value)
+        .to_string()
+}
+
+/// Expands an `Include` argument to the files it names, sorted
+fn include_paths(arg: &str, base_dir: &Path) -> Vec<PathBuf> {
+    let expanded = shellexpand::tilde(arg).to_string();
+    let pattern = if Path::new(&expanded).is_absolute() {
+        PathBuf::from(expanded)
+    } else {
+        base_dir.join(expanded)
+    };
+
+    let mut paths = match glob::glob(&pattern.to_string_lossy()) {
+        Ok(paths) => paths.filter_map(|p| p.ok()).filter(|p| p.is_file()).collect(),
+        Err(e) => {
+            debug!("Invalid Include pattern {}: {}", pattern.display(), e);
+            Vec::new()
+        }
+    };
+    paths.sort();
+    paths
+}
+
+/// Whether an alias matches a `Host` pattern list
+///
+/// A negated pattern (`!foo`) that matches excludes the host even when
+/// another pattern matches.
+fn host_matches(patterns: &[String], alias: &str) -> bool {
+    let mut matched = false;
+
+    for pattern in patterns {
+        match pattern.strip_prefix('!') {
+            Some(negated) if wildcard_match(negated, alias) => return false,
+            Some(_) => {}
+            None if wildcard_match(pattern, alias) => matched = true,
+            None => {}
+        }
+    }
+
+    matched
+}
+
+/// Glob match supporting `*` and `?`, case-insensitive like OpenSSH
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((sp, st)) = star {
+            p = sp + 1;
+            t = st + 1;
+            star = Some((sp, st + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Expands the `%` tokens OpenSSH allows in IdentityFile
+fn expand_tokens(value: &str, alias: &str, host: &str, user: &str) -> String {
+    let home = shellexpand::tilde("~").to_string();
+    let mut out = String::new();
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('%') => out.push('%'),
+            Some('d') => out.push_str(&home),
+            Some('h') => out.push_str(host),
+            Some('n') => out.push_str(alias),
+            Some('r') => out.push_str(user),
+            Some('u') => out.push_str(&local_user().unwrap_or_default()),
+            Some(other) => {
+                out.push('%');
+                out.push(other);
+            }
+            None => out.push('%'),
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const SAMPLE: &str = r#"
+# Global defaults come last in most real configs
+Host prod-web
+    HostName 10.0.0.5
+    User deploy
+    IdentityFile ~/.ssh/prod_ed25519
+    ProxyJump bastion
+
+Host *.internal !legacy.internal
+    Port 2222
+    User ops
+
+Host bastion
+    HostName bastion.example.com
+
+Match host legacy
+    User nobody
+
+Host *
+    User fallback
+    Port=22
+    ConnectTimeout 10
+"#;
+
+    fn sample() -> SshConfig {
+        SshConfig::parse(SAMPLE, Path::new("/nonexistent")).unwrap()
+    }
+
+    #[test]
+    fn test_first_value_wins() {
+        let settings = sample().resolve("prod-web");
+        assert_eq!(settings.hostname.as_deref(), Some("10.0.0.5"));
+        assert_eq!(settings.user.as_deref(), Some("deploy"));
+        assert_eq!(settings.port, Some(22));
+        assert_eq!(settings.proxy_jump.as_deref(), Some("bastion"));
+        assert_eq!(settings.connect_timeout, Some(10));
+        assert_eq!(settings.identity_files, vec!["~/.ssh/prod_ed25519".to_string()]);
+    }
+
+    #[test]
+    fn test_wildcard_and_negated_hosts() {
+        let settings = sample().resolve("db.internal");
+        assert_eq!(settings.port, Some(2222));
+        assert_eq!(settings.user.as_deref(), Some("ops"));
+        assert_eq!(settings.hostname, None);
+
+        let settings = sample().resolve("legacy.internal");
+        assert_eq!(settings.port, Some(22));
+        assert_eq!(settings.user.as_deref(), Some("fallback"));
+    }
+
+    #[test]
+    fn test_match_blocks_are_skipped() {
+        let settings = sample().resolve("legacy");
+        assert_eq!(settings.user.as_deref(), Some("fallback"));
+    }
+
+    #[test]
+    fn test_include_expands_globs() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir(dir.path().join("conf.d")).unwrap();
+        fs::write(
+            dir.path().join("conf.d/10-web.conf"),
+            "Host web\n    HostName web.example.com\n    Port 2200\n",
+        )
+        .unwrap();
+        fs::write(dir.path().join("conf.d/20-other.conf"), "Host other\n    User other\n").unwrap();
+
+        let text = format!(
+            "Include {}/conf.d/*.conf\n\nHost *\n    User everyone\n",
+            dir.path().display()
+        );
+        let config = SshConfig::parse(&text, dir.path()).unwrap();
+
+        let settings = config.resolve("web");
+        assert_eq!(settings.hostname.as_deref(), Some("web.example.com"));
+        assert_eq!(settings.port, Some(2200));
+        assert_eq!(settings.user.as_deref(), Some("everyone"));
+        assert_eq!(config.resolve("other").user.as_deref(), Some("other"));
+    }
+
+    #[test]
+    fn test_include_inside_host_block_is_conditional() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("extra"), "User scoped\n").unwrap();
+
+        let config = SshConfig::parse("Host scoped\n    Include extra\n", dir.path()).unwrap();
+        assert_eq!(config.resolve("scoped").user.as_deref(), Some("scoped"));
+        assert_eq!(config.resolve("elsewhere").user, None);
+    }
+
+    #[test]
+    fn test_explicit_fields_win() {
+        let config = UploadConfig {
+            uploader_type: "sftp".to_string(),
+            enabled: true,
+            username: Some("me".to_string()),
+            ssh_host: Some("prod-web".to_string()),
+            ..Default::default()
+        };
+
+        let resolved = sample().resolve("prod-web").apply_to("prod-web", &config);
+        assert_eq!(resolved.host.as_deref(), Some("10.0.0.5"));
+        assert_eq!(resolved.port, Some(22));
+        assert_eq!(resolved.username.as_deref(), Some("me"));
+        assert_eq!(resolved.timeout, Some(10));
+        assert_eq!(resolved.proxy_jump.as_deref(), Some("bastion"));
+    }
+
+    #[test]
+    fn test_alias_is_host_without_hostname() {
+        let config = UploadConfig {
+            uploader_type: "sftp".to_string(),
+            ssh_host: Some("db.internal".to_string()),
+            ..Default::default()
+        };
+
+        let resolved = sample().resolve("db.internal").apply_to("db.internal", &config);
+        assert_eq!(resolved.host.as_deref(), Some("db.internal"));
+        assert_eq!(resolved.port, Some(2222));
+    }
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match("*.example.com", "a.example.com"));
+        assert!(wildcard_match("web-?", "WEB-1"));
+        assert!(!wildcard_match("web-?", "web-10"));
+        assert!(wildcard_match("*", ""));
+    }
+}
@@ -0,0 +1,107 @@
+//! `direct-tcpip` forwarding for jump hosts
+//!
+//! libssh2 sessions need a real socket, so a channel opened on a jump host
+//! is bridged to a loopback TCP connection by a background thread. The next
+//! hop's session runs over the other end of that connection.
+
+use ssh2::{Channel, Session};
+use std::io::{ErrorKind, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::thread;
+use std::time::Duration;
+use tracing::{debug, error};
+
+use crate::error::{Result, SnaptoError};
+
+/// Idle wait between polls when neither side has data
+const POLL_INTERVAL: Duration = Duration::from_millis(2);
+
+/// Opens `host:port` through `session` and returns a socket connected to it
+///
+/// The forwarding thread owns the jump session and ends when either side
+/// closes, which drops the session with it.
+pub fn open(session: Session, host: &str, port: u16) -> Result<TcpStream> {
+    debug!("Opening direct-tcpip channel to {}:{}", host, port);
+
+    let channel = session.channel_direct_tcpip(host, port, None).map_err(|e| {
+        error!("direct-tcpip to {}:{} failed: {}", host, port, e);
+        SnaptoError::SshConnection(format!("Jump host could not reach {}:{}: {}", host, port, e))
+    })?;
+
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let client = TcpStream::connect(listener.local_addr()?)?;
+    let (local, _) = listener.accept()?;
+
+    let target = format!("{}:{}", host, port);
+    thread::Builder::new()
+        .name(format!("ssh-tunnel-{}", target))
+        .spawn(move || {
+            if let Err(e) = pump(&session, channel, local) {
+                debug!("Tunnel to {} closed: {}", target, e);
+            }
+        })?;
+
+    Ok(client)
+}
+
+/// Copies bytes both ways until either side reaches end of stream
+fn pump(session: &Session, mut channel: Channel, mut local: TcpStream) -> std::io::Result<()> {
+    session.set_blocking(false);
+    local.set_nonblocking(true)?;
+
+    let mut buf = vec![0u8; 32 * 1024];
+
+    loop {
+        let mut idle = true;
+
+        match local.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => {
+                write_all(&mut channel, &buf[..n])?;
+                idle = false;
+            }
+            Err(e) if e.kind() == ErrorKind::WouldBlock => {}
+            Err(e) => return Err(e),
+        }
+
+        match channel.read(&mut buf) {
+            Ok(0) if channel.eof() => break,
+            Ok(0) => {}
+            Ok(n) => {
+                write_all(&mut local, &buf[..n])?;
+                idle = false;
+            }
+            Err(e) if e.kind() == ErrorKind::WouldBlock => {}
+            Err(e) => return Err(e),
+        }
+
+        if idle {
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    let _ = local.shutdown(Shutdown::Both);
+    let _ = channel.send_eof();
+    let _ = channel.close();
+    Ok(())
+}
+
+/// `write_all` for non-blocking writers
+fn write_all<W: Write>(writer: &mut W, mut data: &[u8]) -> std::io::Result<()> {
+    while !data.is_empty() {
+        match writer.write(data) {
+            Ok(0) => return Err(ErrorKind::WriteZero.into()),
+            Ok(n) => data = &data[n..],
+            Err(e) if e.kind() == ErrorKind::WouldBlock => thread::sleep(POLL_INTERVAL),
+            Err(e) => return Err(e),
+        }
+    }
+
+    loop {
+        match writer.flush() {
+            Ok(()) => return Ok(()),
+            Err(e) if e.kind() == ErrorKind::WouldBlock => thread::sleep(POLL_INTERVAL),
+            Err(e) => return Err(e),
+        }
+    }
+}
@@ -1,11 +1,14 @@
 use anyhow::Result;
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use snapto_core::{
-    ClipboardManager, ClipboardCopyMode, Config, HistoryEntry, HistoryManager, HistoryMode,
-    KeychainManager, LocalUploader, SftpUploader, SshUploader, UploadConfig, Uploader,
+    is_password_prompt, ClipboardManager, ClipboardCopyMode, Config, CounterScope, HistoryEntry, HistoryManager, HistoryMode, IdMode, MetadataPolicy,
+    KeychainManager, LocalUploader, PasswordCallback, SessionPool, SshUploader, UploadConfig, Uploader,
 };
+use snapto_core::transform;
+use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Screen {
@@ -76,7 +79,13 @@ pub struct App {
     pub show_password_prompt: bool,
     pub password_buffer: String,
     pub pending_reupload: Option<PendingReupload>,
+    /// Keyboard-interactive prompt being answered (None asks for the password)
+    pub pending_prompt: Option<String>,
+    /// Answers to keyboard-interactive prompts, consumed by the next attempt
+    pub prompt_answers: Arc<Mutex<HashMap<String, String>>>,
     pub keychain_manager: Option<KeychainManager>,
+    /// SSH sessions kept open between re-uploads
+    pub session_pool: SessionPool,
     // Upload screen state
     pub upload_progress: Option<f64>,
     pub upload_result: Option<UploadStatus>,
@@ -88,6 +97,7 @@ pub struct PendingReupload {
     pub uploader_name: String,
     pub uploader_config: UploadConfig,
     pub file_data: Vec<u8>,
+    pub password: Option<String>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -129,16 +139,6 @@ impl SettingsSection {
             SettingsSection::Security => "Security",
         }
     }
-
-    pub fn field_count(&self) -> usize {
-        match self {
-            SettingsSection::General => 5,  // local_save_dir, copy_url, clipboard_mode, notifications, default_uploader
-            SettingsSection::Naming => 4,   // template, date_format, time_format, extension
-            SettingsSection::History => 4,  // enabled, mode, retention_days, max_entries
-            SettingsSection::Uploads => 0,  // Read-only for now (complex editing)
-            SettingsSection::Security => 2, // use_keychain, encrypt_credentials
-        }
-    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -184,12 +184,21 @@ pub fn get_section_fields(section: SettingsSection) -> Vec<SettingsField> {
             SettingsField::enumeration("clipboard_copy_mode", "Clipboard Copy Mode", vec!["auto", "url", "path"]),
             SettingsField::bool("show_notifications", "Show Notifications"),
             SettingsField::text("default_uploader", "Default Uploader"),
+            SettingsField::enumeration(
+                "capture_backend",
+                "Capture Backend",
+                std::iter::once("auto").chain(snapto_core::screenshot::BACKEND_NAMES.iter().copied()).collect(),
+            ),
         ],
         SettingsSection::Naming => vec![
             SettingsField::text("template", "Template"),
             SettingsField::text("date_format", "Date Format"),
             SettingsField::text("time_format", "Time Format"),
             SettingsField::text("default_extension", "Default Extension"),
+            SettingsField::text("recording_extension", "Recording Extension"),
+            SettingsField::enumeration("counter_scope", "Counter Scope", vec!["global", "destination", "day", "template"]),
+            SettingsField::text("id_alphabet", "Id Alphabet"),
+            SettingsField::enumeration("id_mode", "Id Mode", vec!["random", "sequence"]),
         ],
         SettingsSection::History => vec![
             SettingsField::bool("enabled", "Enabled"),
@@ -201,6 +210,7 @@ pub fn get_section_fields(section: SettingsSection) -> Vec<SettingsField> {
         SettingsSection::Security => vec![
             SettingsField::bool("use_system_keychain", "Use System Keychain"),
             SettingsField::bool("encrypt_credentials", "Encrypt Credentials"),
+            SettingsField::enumeration("metadata", "Image Metadata", vec!["strip", "keep_orientation", "keep"]),
         ],
     }
 }
@@ -216,6 +226,7 @@ pub fn get_uploader_fields(uploader_type: &str) -> Vec<SettingsField> {
             fields.push(SettingsField::text("local_path", "Local Path"));
         }
         "sftp" | "ssh" => {
+            fields.push(SettingsField::text("ssh_host", "SSH Config Host"));
             fields.push(SettingsField::text("host", "Host"));
             fields.push(SettingsField::number("port", "Port"));
             fields.push(SettingsField::text("username", "Username"));
@@ -223,8 +234,11 @@ pub fn get_uploader_fields(uploader_type: &str) -> Vec<SettingsField> {
             fields.push(SettingsField::text("base_url", "Base URL"));
             fields.push(SettingsField::bool("use_key_auth", "Use Key Auth"));
             fields.push(SettingsField::text("key_path", "Key Path"));
+            fields.push(SettingsField::text("proxy_jump", "Jump Hosts"));
+            fields.push(SettingsField::text("proxy", "Proxy URL"));
             // Always show password field for SSH/SFTP - user can set it for password auth
             fields.push(SettingsField::password("password", "Password"));
+            fields.push(SettingsField::password("totp_secret", "TOTP Secret"));
             fields.push(SettingsField::number("timeout", "Timeout (s)"));
         }
         _ => {}
@@ -235,9 +249,7 @@ pub fn get_uploader_fields(uploader_type: &str) -> Vec<SettingsField> {
 
 #[derive(Debug, Clone)]
 pub enum UploadStatus {
-    InProgress,
     Success { url: String },
-    Error { message: String },
 }
 
 impl App {
@@ -306,7 +318,10 @@ impl App {
             show_password_prompt: false,
             password_buffer: String::new(),
             pending_reupload: None,
+            pending_prompt: None,
+            prompt_answers: Arc::new(Mutex::new(HashMap::new())),
             keychain_manager,
+            session_pool: SessionPool::new(),
             upload_progress: None,
             upload_result: None,
         })
@@ -368,6 +383,8 @@ impl App {
                     self.show_password_prompt = false;
                     self.password_buffer.clear();
                     self.pending_reupload = None;
+                    self.pending_prompt = None;
+                    self.clear_prompt_answers();
                     self.status_message = Some("Upload cancelled".to_string());
                 }
                 KeyCode::Enter => {
@@ -387,15 +404,11 @@ impl App {
         // Handle reupload menu if open
         if self.show_reupload_menu {
             match key.code {
-                KeyCode::Up | KeyCode::Char('k') => {
-                    if self.reupload_selected > 0 {
-                        self.reupload_selected -= 1;
-                    }
+                KeyCode::Up | KeyCode::Char('k') if self.reupload_selected > 0 => {
+                    self.reupload_selected -= 1;
                 }
-                KeyCode::Down | KeyCode::Char('j') => {
-                    if self.reupload_selected < self.available_uploaders.len().saturating_sub(1) {
-                        self.reupload_selected += 1;
-                    }
+                KeyCode::Down | KeyCode::Char('j') if self.reupload_selected < self.available_uploaders.len().saturating_sub(1) => {
+                    self.reupload_selected += 1;
                 }
                 KeyCode::Enter => {
                     self.perform_reupload();
@@ -410,15 +423,11 @@ impl App {
 
         // Normal history navigation
         match key.code {
-            KeyCode::Up | KeyCode::Char('k') => {
-                if self.history_selected > 0 {
-                    self.history_selected -= 1;
-                }
+            KeyCode::Up | KeyCode::Char('k') if self.history_selected > 0 => {
+                self.history_selected -= 1;
             }
-            KeyCode::Down | KeyCode::Char('j') => {
-                if self.history_selected < self.history.len().saturating_sub(1) {
-                    self.history_selected += 1;
-                }
+            KeyCode::Down | KeyCode::Char('j') if self.history_selected < self.history.len().saturating_sub(1) => {
+                self.history_selected += 1;
             }
             KeyCode::Enter => {
                 self.copy_selected_url();
@@ -462,26 +471,18 @@ impl App {
                     self.edit_buffer.clear();
                     self.edit_cursor = 0;
                 }
-                KeyCode::Backspace => {
-                    if self.edit_cursor > 0 {
-                        self.edit_cursor -= 1;
-                        self.edit_buffer.remove(self.edit_cursor);
-                    }
+                KeyCode::Backspace if self.edit_cursor > 0 => {
+                    self.edit_cursor -= 1;
+                    self.edit_buffer.remove(self.edit_cursor);
                 }
-                KeyCode::Delete => {
-                    if self.edit_cursor < self.edit_buffer.len() {
-                        self.edit_buffer.remove(self.edit_cursor);
-                    }
+                KeyCode::Delete if self.edit_cursor < self.edit_buffer.len() => {
+                    self.edit_buffer.remove(self.edit_cursor);
                 }
-                KeyCode::Left => {
-                    if self.edit_cursor > 0 {
-                        self.edit_cursor -= 1;
-                    }
+                KeyCode::Left if self.edit_cursor > 0 => {
+                    self.edit_cursor -= 1;
                 }
-                KeyCode::Right => {
-                    if self.edit_cursor < self.edit_buffer.len() {
-                        self.edit_cursor += 1;
-                    }
+                KeyCode::Right if self.edit_cursor < self.edit_buffer.len() => {
+                    self.edit_cursor += 1;
                 }
                 KeyCode::Home => {
                     self.edit_cursor = 0;
@@ -508,34 +509,28 @@ impl App {
                 self.settings_section = self.settings_section.next();
                 self.settings_selected = 0;
             }
-            KeyCode::Up | KeyCode::Char('k') => {
-                if self.settings_selected > 0 {
-                    self.settings_selected -= 1;
-                }
+            KeyCode::Up | KeyCode::Char('k') if self.settings_selected > 0 => {
+                self.settings_selected -= 1;
             }
-            KeyCode::Down | KeyCode::Char('j') => {
-                if field_count > 0 && self.settings_selected < field_count - 1 {
-                    self.settings_selected += 1;
-                }
+            KeyCode::Down | KeyCode::Char('j') if field_count > 0 && self.settings_selected < field_count - 1 => {
+                self.settings_selected += 1;
             }
-            KeyCode::Enter | KeyCode::Char(' ') => {
-                if field_count > 0 && self.settings_selected < field_count {
-                    let field = &fields[self.settings_selected];
-                    match field.field_type {
-                        FieldType::Bool => {
-                            // Toggle boolean immediately
-                            self.toggle_bool_field(field.name);
-                        }
-                        FieldType::Enum => {
-                            // Cycle through enum options
-                            self.cycle_enum_field(field.name, &field.enum_options);
-                        }
-                        FieldType::Text | FieldType::Number | FieldType::Password => {
-                            // Start editing
-                            self.edit_buffer = self.get_field_value(field.name);
-                            self.edit_cursor = self.edit_buffer.len();
-                            self.settings_editing = true;
-                        }
+            KeyCode::Enter | KeyCode::Char(' ') if field_count > 0 && self.settings_selected < field_count => {
+                let field = &fields[self.settings_selected];
+                match field.field_type {
+                    FieldType::Bool => {
+                        // Toggle boolean immediately
+                        self.toggle_bool_field(field.name);
+                    }
+                    FieldType::Enum => {
+                        // Cycle through enum options
+                        self.cycle_enum_field(field.name, &field.enum_options);
+                    }
+                    FieldType::Text | FieldType::Number | FieldType::Password => {
+                        // Start editing
+                        self.edit_buffer = self.get_field_value(field.name);
+                        self.edit_cursor = self.edit_buffer.len();
+                        self.settings_editing = true;
                     }
                 }
             }
@@ -556,10 +551,8 @@ impl App {
                     self.new_uploader_name.clear();
                     self.new_uploader_type = 0;
                 }
-                KeyCode::Enter => {
-                    if !self.new_uploader_name.is_empty() {
-                        self.add_new_uploader();
-                    }
+                KeyCode::Enter if !self.new_uploader_name.is_empty() => {
+                    self.add_new_uploader();
                 }
                 KeyCode::Tab => {
                     self.new_uploader_type = (self.new_uploader_type + 1) % 3;
@@ -589,26 +582,18 @@ impl App {
                     self.edit_buffer.clear();
                     self.edit_cursor = 0;
                 }
-                KeyCode::Backspace => {
-                    if self.edit_cursor > 0 {
-                        self.edit_cursor -= 1;
-                        self.edit_buffer.remove(self.edit_cursor);
-                    }
+                KeyCode::Backspace if self.edit_cursor > 0 => {
+                    self.edit_cursor -= 1;
+                    self.edit_buffer.remove(self.edit_cursor);
                 }
-                KeyCode::Delete => {
-                    if self.edit_cursor < self.edit_buffer.len() {
-                        self.edit_buffer.remove(self.edit_cursor);
-                    }
+                KeyCode::Delete if self.edit_cursor < self.edit_buffer.len() => {
+                    self.edit_buffer.remove(self.edit_cursor);
                 }
-                KeyCode::Left => {
-                    if self.edit_cursor > 0 {
-                        self.edit_cursor -= 1;
-                    }
+                KeyCode::Left if self.edit_cursor > 0 => {
+                    self.edit_cursor -= 1;
                 }
-                KeyCode::Right => {
-                    if self.edit_cursor < self.edit_buffer.len() {
-                        self.edit_cursor += 1;
-                    }
+                KeyCode::Right if self.edit_cursor < self.edit_buffer.len() => {
+                    self.edit_cursor += 1;
                 }
                 KeyCode::Home => {
                     self.edit_cursor = 0;
@@ -657,27 +642,25 @@ impl App {
                     self.uploader_field_selected = 0;
                 }
             }
-            KeyCode::Enter | KeyCode::Char(' ') => {
-                if uploader_count > 0 && self.uploader_field_selected < field_count {
-                    let field = &fields[self.uploader_field_selected];
-                    match field.field_type {
-                        FieldType::Bool => {
-                            self.toggle_uploader_bool(field.name);
-                        }
-                        FieldType::Enum => {
-                            self.cycle_uploader_enum(field.name, &field.enum_options);
-                        }
-                        FieldType::Text | FieldType::Number => {
-                            self.edit_buffer = self.get_uploader_field_value(field.name);
-                            self.edit_cursor = self.edit_buffer.len();
-                            self.uploader_editing = true;
-                        }
-                        FieldType::Password => {
-                            // For password, start with empty buffer (don't show existing password)
-                            self.edit_buffer = String::new();
-                            self.edit_cursor = 0;
-                            self.uploader_editing = true;
-                        }
+            KeyCode::Enter | KeyCode::Char(' ') if uploader_count > 0 && self.uploader_field_selected < field_count => {
+                let field = &fields[self.uploader_field_selected];
+                match field.field_type {
+                    FieldType::Bool => {
+                        self.toggle_uploader_bool(field.name);
+                    }
+                    FieldType::Enum => {
+                        self.cycle_uploader_enum(field.name, &field.enum_options);
+                    }
+                    FieldType::Text | FieldType::Number => {
+                        self.edit_buffer = self.get_uploader_field_value(field.name);
+                        self.edit_cursor = self.edit_buffer.len();
+                        self.uploader_editing = true;
+                    }
+                    FieldType::Password => {
+                        // For password, start with empty buffer (don't show existing password)
+                        self.edit_buffer = String::new();
+                        self.edit_cursor = 0;
+                        self.uploader_editing = true;
                     }
                 }
             }
@@ -687,11 +670,9 @@ impl App {
                 self.new_uploader_name.clear();
                 self.new_uploader_type = 0;
             }
-            KeyCode::Char('d') => {
+            KeyCode::Char('d') if uploader_count > 0 => {
                 // Delete current uploader
-                if uploader_count > 0 {
-                    self.delete_current_uploader();
-                }
+                self.delete_current_uploader();
             }
             KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => {
                 self.save_config();
@@ -738,6 +719,9 @@ impl App {
             "base_url" => uploader.base_url.clone().unwrap_or_default(),
             "local_path" => uploader.local_path.clone().unwrap_or_default(),
             "key_path" => uploader.key_path.clone().unwrap_or_default(),
+            "ssh_host" => uploader.ssh_host.clone().unwrap_or_default(),
+            "proxy_jump" => uploader.proxy_jump.clone().unwrap_or_default(),
+            "proxy" => uploader.proxy.clone().unwrap_or_default(),
             "timeout" => uploader.timeout.map(|t| t.to_string()).unwrap_or_else(|| "30".to_string()),
             "password" => {
                 // Check if password is stored in keychain
@@ -749,6 +733,15 @@ impl App {
                 }
                 String::new() // No password set
             }
+            "totp_secret" => {
+                let keychain_key = format!("ssh_totp_{}", name);
+                if let Some(ref km) = self.keychain_manager {
+                    if km.get(&keychain_key).ok().flatten().is_some() {
+                        return "••••••••".to_string();
+                    }
+                }
+                String::new()
+            }
             _ => String::new(),
         }
     }
@@ -791,6 +784,22 @@ impl App {
             return;
         }
 
+        // TOTP seed is validated before it goes to the keychain
+        if field.name == "totp_secret" {
+            let result = match (&self.keychain_manager, self.config.uploads.get(&name)) {
+                (Some(km), Some(config)) => SshUploader::new(name.clone(), config.clone())
+                    .store_totp_secret_in_keychain(km, value.trim())
+                    .map_err(|e| e.to_string()),
+                (None, _) => Err("Keychain manager not initialized".to_string()),
+                (_, None) => Err(format!("Uploader '{}' not found", name)),
+            };
+            self.status_message = Some(match result {
+                Ok(_) => format!("TOTP secret saved securely for {}", name),
+                Err(e) => format!("Failed to save TOTP secret: {}", e),
+            });
+            return;
+        }
+
         if let Some(uploader) = self.config.uploads.get_mut(&name) {
             match field.name {
                 "host" => uploader.host = if value.is_empty() { None } else { Some(value) },
@@ -800,6 +809,9 @@ impl App {
                 "base_url" => uploader.base_url = if value.is_empty() { None } else { Some(value) },
                 "local_path" => uploader.local_path = if value.is_empty() { None } else { Some(value) },
                 "key_path" => uploader.key_path = if value.is_empty() { None } else { Some(value) },
+                "ssh_host" => uploader.ssh_host = if value.is_empty() { None } else { Some(value) },
+                "proxy_jump" => uploader.proxy_jump = if value.is_empty() { None } else { Some(value) },
+                "proxy" => uploader.proxy = if value.is_empty() { None } else { Some(value) },
                 "timeout" => uploader.timeout = value.parse().ok(),
                 _ => {}
             }
@@ -871,6 +883,7 @@ impl App {
             use_key_auth: if uploader_type != "local" { Some(true) } else { None },
             key_path: if uploader_type != "local" { Some("~/.ssh/id_rsa".to_string()) } else { None },
             timeout: if uploader_type != "local" { Some(30) } else { None },
+            ..Default::default()
         };
 
         self.config.uploads.insert(name.clone(), new_config);
@@ -918,6 +931,8 @@ impl App {
                 "date_format" => self.config.naming.date_format.clone(),
                 "time_format" => self.config.naming.time_format.clone(),
                 "default_extension" => self.config.naming.default_extension.clone(),
+                "recording_extension" => self.config.naming.recording_extension.clone(),
+                "id_alphabet" => self.config.naming.id_alphabet.clone(),
                 _ => String::new(),
             },
             SettingsSection::History => match field_name {
@@ -952,6 +967,8 @@ impl App {
                 "date_format" => self.config.naming.date_format = value,
                 "time_format" => self.config.naming.time_format = value,
                 "default_extension" => self.config.naming.default_extension = value,
+                "recording_extension" => self.config.naming.recording_extension = value,
+                "id_alphabet" => self.config.naming.id_alphabet = value,
                 _ => {}
             },
             SettingsSection::History => match field.name {
@@ -983,12 +1000,9 @@ impl App {
                 }
                 _ => {}
             },
-            SettingsSection::History => match field_name {
-                "enabled" => {
-                    self.config.history.enabled = !self.config.history.enabled;
-                }
-                _ => {}
-            },
+            SettingsSection::History if field_name == "enabled" => {
+                self.config.history.enabled = !self.config.history.enabled;
+            }
             SettingsSection::Security => match field_name {
                 "use_system_keychain" => {
                     self.config.security.use_system_keychain = !self.config.security.use_system_keychain;
@@ -1008,40 +1022,85 @@ impl App {
         if opts.is_empty() { return; }
 
         match self.settings_section {
-            SettingsSection::General => match field_name {
-                "clipboard_copy_mode" => {
-                    let current = match self.config.general.clipboard_copy_mode {
-                        ClipboardCopyMode::Auto => "auto",
-                        ClipboardCopyMode::Url => "url",
-                        ClipboardCopyMode::Path => "path",
-                    };
-                    let idx = opts.iter().position(|&o| o == current).unwrap_or(0);
-                    let next_idx = (idx + 1) % opts.len();
-                    self.config.general.clipboard_copy_mode = match opts[next_idx] {
-                        "url" => ClipboardCopyMode::Url,
-                        "path" => ClipboardCopyMode::Path,
-                        _ => ClipboardCopyMode::Auto,
-                    };
-                }
-                _ => {}
-            },
-            SettingsSection::History => match field_name {
-                "mode" => {
-                    let current = match self.config.history.mode {
-                        HistoryMode::Metadata => "metadata",
-                        HistoryMode::Thumbnails => "thumbnails",
-                        HistoryMode::Full => "full",
-                    };
-                    let idx = opts.iter().position(|&o| o == current).unwrap_or(0);
-                    let next_idx = (idx + 1) % opts.len();
-                    self.config.history.mode = match opts[next_idx] {
-                        "thumbnails" => HistoryMode::Thumbnails,
-                        "full" => HistoryMode::Full,
-                        _ => HistoryMode::Metadata,
-                    };
-                }
-                _ => {}
-            },
+            SettingsSection::General if field_name == "clipboard_copy_mode" => {
+                let current = match self.config.general.clipboard_copy_mode {
+                    ClipboardCopyMode::Auto => "auto",
+                    ClipboardCopyMode::Url => "url",
+                    ClipboardCopyMode::Path => "path",
+                };
+                let idx = opts.iter().position(|&o| o == current).unwrap_or(0);
+                let next_idx = (idx + 1) % opts.len();
+                self.config.general.clipboard_copy_mode = match opts[next_idx] {
+                    "url" => ClipboardCopyMode::Url,
+                    "path" => ClipboardCopyMode::Path,
+                    _ => ClipboardCopyMode::Auto,
+                };
+            }
+            SettingsSection::General if field_name == "capture_backend" => {
+                let current = self.config.capture.backend.as_deref().unwrap_or("auto");
+                let idx = opts.iter().position(|&o| o == current).unwrap_or(0);
+                let next_idx = (idx + 1) % opts.len();
+                self.config.capture.backend = match opts[next_idx] {
+                    "auto" => None,
+                    name => Some(name.to_string()),
+                };
+            }
+            SettingsSection::Security if field_name == "metadata" => {
+                let current = match self.config.security.metadata {
+                    MetadataPolicy::Strip => "strip",
+                    MetadataPolicy::KeepOrientation => "keep_orientation",
+                    MetadataPolicy::Keep => "keep",
+                };
+                let idx = opts.iter().position(|&o| o == current).unwrap_or(0);
+                let next_idx = (idx + 1) % opts.len();
+                self.config.security.metadata = match opts[next_idx] {
+                    "strip" => MetadataPolicy::Strip,
+                    "keep" => MetadataPolicy::Keep,
+                    _ => MetadataPolicy::KeepOrientation,
+                };
+            }
+            SettingsSection::Naming if field_name == "counter_scope" => {
+                let current = match self.config.naming.counter_scope {
+                    CounterScope::Global => "global",
+                    CounterScope::Destination => "destination",
+                    CounterScope::Day => "day",
+                    CounterScope::Template => "template",
+                };
+                let idx = opts.iter().position(|&o| o == current).unwrap_or(0);
+                let next_idx = (idx + 1) % opts.len();
+                self.config.naming.counter_scope = match opts[next_idx] {
+                    "destination" => CounterScope::Destination,
+                    "day" => CounterScope::Day,
+                    "template" => CounterScope::Template,
+                    _ => CounterScope::Global,
+                };
+            }
+            SettingsSection::Naming if field_name == "id_mode" => {
+                let current = match self.config.naming.id_mode {
+                    IdMode::Random => "random",
+                    IdMode::Sequence => "sequence",
+                };
+                let idx = opts.iter().position(|&o| o == current).unwrap_or(0);
+                let next_idx = (idx + 1) % opts.len();
+                self.config.naming.id_mode = match opts[next_idx] {
+                    "sequence" => IdMode::Sequence,
+                    _ => IdMode::Random,
+                };
+            }
+            SettingsSection::History if field_name == "mode" => {
+                let current = match self.config.history.mode {
+                    HistoryMode::Metadata => "metadata",
+                    HistoryMode::Thumbnails => "thumbnails",
+                    HistoryMode::Full => "full",
+                };
+                let idx = opts.iter().position(|&o| o == current).unwrap_or(0);
+                let next_idx = (idx + 1) % opts.len();
+                self.config.history.mode = match opts[next_idx] {
+                    "thumbnails" => HistoryMode::Thumbnails,
+                    "full" => HistoryMode::Full,
+                    _ => HistoryMode::Metadata,
+                };
+            }
             _ => {}
         }
         self.status_message = Some("Value changed (Ctrl+S to save)".to_string());
@@ -1218,6 +1277,7 @@ impl App {
                 uploader_name,
                 uploader_config,
                 file_data,
+                password: None,
             });
             self.show_password_prompt = true;
             self.password_buffer.clear();
@@ -1226,7 +1286,7 @@ impl App {
     }
 
     fn execute_reupload_with_password(&mut self) {
-        let password = self.password_buffer.clone();
+        let answer = self.password_buffer.clone();
         self.show_password_prompt = false;
         self.password_buffer.clear();
 
@@ -1238,6 +1298,18 @@ impl App {
             }
         };
 
+        // Answers to other prompts are handed to the next attempt and the
+        // password stays what it was
+        let password = match self.pending_prompt.take() {
+            Some(prompt) => {
+                if let Ok(mut answers) = self.prompt_answers.lock() {
+                    answers.insert(prompt, answer);
+                }
+                pending.password.clone()
+            }
+            None => Some(answer),
+        };
+
         self.status_message = Some(format!("Uploading {} to {}...", pending.entry.filename, pending.uploader_name));
 
         let success = self.execute_upload(
@@ -1245,11 +1317,11 @@ impl App {
             pending.uploader_name.clone(),
             pending.uploader_config,
             pending.file_data,
-            Some(password.clone()),
+            password.clone(),
         );
 
         // If successful, store password in keychain
-        if success {
+        if let (true, Some(password)) = (success, password) {
             if let Some(ref keychain) = self.keychain_manager {
                 let keychain_key = format!("ssh_password_{}", pending.uploader_name);
                 if let Err(e) = keychain.set(&keychain_key, &password) {
@@ -1263,6 +1335,29 @@ impl App {
         }
     }
 
+    fn clear_prompt_answers(&self) {
+        if let Ok(mut answers) = self.prompt_answers.lock() {
+            answers.clear();
+        }
+    }
+
+    /// Builds a callback that answers prompts from `prompt_answers`
+    ///
+    /// The upload blocks the UI thread, so prompts without a stored answer
+    /// are recorded in `unanswered` and asked for after the attempt fails.
+    fn prompt_callback(&self, unanswered: Arc<Mutex<Vec<String>>>) -> PasswordCallback {
+        let answers = Arc::clone(&self.prompt_answers);
+        Arc::new(tokio::sync::Mutex::new(Some(Box::new(move |prompt: &str| {
+            let answer = answers.lock().ok().and_then(|mut a| a.remove(prompt));
+            if answer.is_none() {
+                if let Ok(mut list) = unanswered.lock() {
+                    list.push(prompt.to_string());
+                }
+            }
+            answer
+        }))))
+    }
+
     fn execute_upload(
         &mut self,
         entry: HistoryEntry,
@@ -1271,19 +1366,22 @@ impl App {
         file_data: Vec<u8>,
         password: Option<String>,
     ) -> bool {
+        // Prompts the server asked that had no answer yet
+        let unanswered = Arc::new(Mutex::new(Vec::new()));
+
         // Create uploader based on type with password
         let uploader: Box<dyn Uploader> = match uploader_config.uploader_type.as_str() {
-            "sftp" => {
-                let mut u = SftpUploader::new(uploader_name.clone(), uploader_config.clone());
+            "sftp" | "ssh" => {
+                let mut u = SshUploader::new(uploader_name.clone(), uploader_config.clone())
+                    .with_password_callback(self.prompt_callback(Arc::clone(&unanswered)))
+                    .with_session_pool(self.session_pool.clone());
                 if let Some(ref pwd) = password {
                     u.set_password(pwd.clone());
                 }
-                Box::new(u)
-            }
-            "ssh" => {
-                let mut u = SshUploader::new(uploader_name.clone(), uploader_config.clone());
-                if let Some(ref pwd) = password {
-                    u.set_password(pwd.clone());
+                if let Some(secret) = self.keychain_manager.as_ref()
+                    .and_then(|km| u.get_totp_secret_from_keychain(km))
+                {
+                    u = u.with_totp_secret(secret);
                 }
                 Box::new(u)
             }
@@ -1300,6 +1398,22 @@ impl App {
             return false;
         }
 
+        // Same transform, watermark and metadata scrubbing as the CLI
+        let scale_factor = transform::scale_factor_for([&uploader_config]);
+        let transformed = match transform::for_destination(
+            &file_data,
+            &self.config,
+            &uploader_name,
+            &uploader_config,
+            scale_factor,
+        ) {
+            Ok(transformed) => transformed,
+            Err(e) => {
+                self.status_message = Some(format!("✗ Upload failed: {}", e));
+                return false;
+            }
+        };
+
         // Run the async upload in a blocking manner
         let rt = match tokio::runtime::Runtime::new() {
             Ok(rt) => rt,
@@ -1309,9 +1423,9 @@ impl App {
             }
         };
 
-        let filename = entry.filename.clone();
+        let filename = transformed.filename(&entry.filename);
         let result = rt.block_on(async {
-            uploader.upload(&file_data, &filename).await
+            uploader.upload(&transformed.data, &filename).await
         });
 
         match result {
@@ -1323,6 +1437,7 @@ impl App {
                     let _ = clipboard.set_text(url_or_path);
                 }
 
+                self.clear_prompt_answers();
                 self.status_message = Some(format!(
                     "✓ Re-uploaded to {}: {}",
                     uploader_name,
@@ -1334,16 +1449,29 @@ impl App {
                 let error_msg = format!("{}", e);
                 // Check if it's an auth error - prompt for password
                 if error_msg.contains("authentication") || error_msg.contains("password") || error_msg.contains("Authentication") {
+                    // Ask for the first prompt the server sent that we could
+                    // not answer; password prompts use the password popup
+                    let prompt = unanswered
+                        .lock()
+                        .ok()
+                        .and_then(|list| list.iter().find(|p| !is_password_prompt(p)).cloned());
+
+                    self.status_message = Some(match &prompt {
+                        Some(text) => format!("Server asks: {}", text),
+                        None => "Authentication failed. Enter password:".to_string(),
+                    });
+                    self.pending_prompt = prompt;
                     self.pending_reupload = Some(PendingReupload {
                         entry,
                         uploader_name,
                         uploader_config,
                         file_data,
+                        password,
                     });
                     self.show_password_prompt = true;
                     self.password_buffer.clear();
-                    self.status_message = Some("Authentication failed. Enter password:".to_string());
                 } else {
+                    self.clear_prompt_answers();
                     self.status_message = Some(format!("✗ Upload failed: {}", e));
                 }
                 false
@@ -219,10 +219,22 @@ fn draw_password_prompt(f: &mut Frame, app: &App, area: Rect) {
     let password_display = "*".repeat(app.password_buffer.len());
     let cursor = if app.password_buffer.is_empty() { "_" } else { "" };
 
+    // Keyboard-interactive prompts show the server's own text
+    let (title, label) = match &app.pending_prompt {
+        Some(prompt) => (
+            format!(" Authenticate to {} ", uploader_name),
+            format!("  {} ", prompt.trim_end()),
+        ),
+        None => (
+            format!(" Enter Password for {} ", uploader_name),
+            "  Password: ".to_string(),
+        ),
+    };
+
     let content = vec![
         Line::from(""),
         Line::from(vec![
-            Span::styled("  Password: ", Style::default().fg(Color::White)),
+            Span::styled(label, Style::default().fg(Color::White)),
             Span::styled(
                 format!("{}{}", password_display, cursor),
                 Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
@@ -238,7 +250,7 @@ fn draw_password_prompt(f: &mut Frame, app: &App, area: Rect) {
     ];
 
     let block = Block::default()
-        .title(format!(" Enter Password for {} ", uploader_name))
+        .title(title)
         .title_style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
         .borders(Borders::ALL)
         .border_style(Style::default().fg(Color::Magenta));
@@ -6,7 +6,7 @@ use ratatui::{
     widgets::{Block, Borders, Clear, List, ListItem, Paragraph},
     Frame,
 };
-use snapto_core::{ClipboardCopyMode, HistoryMode};
+use snapto_core::{ClipboardCopyMode, CounterScope, HistoryMode, IdMode, MetadataPolicy};
 
 pub fn draw(f: &mut Frame, app: &App, area: Rect) {
     let chunks = Layout::default()
@@ -32,7 +32,7 @@ pub fn draw(f: &mut Frame, app: &App, area: Rect) {
 }
 
 fn draw_sections(f: &mut Frame, app: &App, area: Rect) {
-    let sections = vec![
+    let sections = [
         SettingsSection::General,
         SettingsSection::Naming,
         SettingsSection::History,
@@ -198,6 +198,7 @@ fn get_display_value(app: &App, field_name: &str) -> String {
             }.to_string(),
             "show_notifications" => if app.config.general.show_notifications { "Yes" } else { "No" }.to_string(),
             "default_uploader" => app.config.general.default_uploader.clone(),
+            "capture_backend" => app.config.capture.backend.clone().unwrap_or_else(|| "auto".to_string()),
             _ => String::new(),
         },
         SettingsSection::Naming => match field_name {
@@ -205,6 +206,18 @@ fn get_display_value(app: &App, field_name: &str) -> String {
             "date_format" => app.config.naming.date_format.clone(),
             "time_format" => app.config.naming.time_format.clone(),
             "default_extension" => app.config.naming.default_extension.clone(),
+            "recording_extension" => app.config.naming.recording_extension.clone(),
+            "counter_scope" => match app.config.naming.counter_scope {
+                CounterScope::Global => "global",
+                CounterScope::Destination => "destination",
+                CounterScope::Day => "day",
+                CounterScope::Template => "template",
+            }.to_string(),
+            "id_alphabet" => app.config.naming.id_alphabet.clone(),
+            "id_mode" => match app.config.naming.id_mode {
+                IdMode::Random => "random",
+                IdMode::Sequence => "sequence",
+            }.to_string(),
             _ => String::new(),
         },
         SettingsSection::History => match field_name {
@@ -227,6 +240,11 @@ fn get_display_value(app: &App, field_name: &str) -> String {
         SettingsSection::Security => match field_name {
             "use_system_keychain" => if app.config.security.use_system_keychain { "Yes" } else { "No" }.to_string(),
             "encrypt_credentials" => if app.config.security.encrypt_credentials { "Yes" } else { "No" }.to_string(),
+            "metadata" => match app.config.security.metadata {
+                MetadataPolicy::Strip => "strip",
+                MetadataPolicy::KeepOrientation => "keep_orientation",
+                MetadataPolicy::Keep => "keep",
+            }.to_string(),
             _ => String::new(),
         },
         _ => String::new(),
@@ -391,6 +409,9 @@ fn get_uploader_display_value(upload: &snapto_core::UploadConfig, field_name: &s
         "local_path" => upload.local_path.clone().unwrap_or_else(|| "Not set".to_string()),
         "use_key_auth" => if upload.use_key_auth.unwrap_or(true) { "Yes" } else { "No" }.to_string(),
         "key_path" => upload.key_path.clone().unwrap_or_else(|| "~/.ssh/id_rsa".to_string()),
+        "ssh_host" => upload.ssh_host.clone().unwrap_or_else(|| "(none)".to_string()),
+        "proxy_jump" => upload.proxy_jump.clone().unwrap_or_else(|| "(direct)".to_string()),
+        "proxy" => upload.proxy.clone().unwrap_or_else(|| "(none)".to_string()),
         "timeout" => upload.timeout.map(|t| format!("{}s", t)).unwrap_or_else(|| "30s".to_string()),
         "password" => {
             // Check if password is stored in keychain
@@ -404,6 +425,17 @@ fn get_uploader_display_value(upload: &snapto_core::UploadConfig, field_name: &s
             }
             "(no keychain)".to_string()
         }
+        "totp_secret" => {
+            let keychain_key = format!("ssh_totp_{}", uploader_name);
+            if let Some(ref km) = app.keychain_manager {
+                match km.get(&keychain_key) {
+                    Ok(Some(_)) => return "••••••••".to_string(),
+                    Ok(None) => return "(not set)".to_string(),
+                    Err(e) => return format!("(error: {})", e),
+                }
+            }
+            "(no keychain)".to_string()
+        }
         _ => String::new(),
     }
 }
@@ -93,17 +93,6 @@ fn draw_destination(f: &mut Frame, app: &App, area: Rect) {
 
 fn draw_result(f: &mut Frame, app: &App, area: Rect) {
     let (title, content, border_color) = match &app.upload_result {
-        Some(UploadStatus::InProgress) => (
-            " Status ",
-            vec![
-                Line::from(""),
-                Line::from(Span::styled(
-                    "Uploading...",
-                    Style::default().fg(Color::Yellow),
-                )),
-            ],
-            Color::Yellow,
-        ),
         Some(UploadStatus::Success { url }) => (
             " Success ",
             vec![
@@ -125,22 +114,6 @@ fn draw_result(f: &mut Frame, app: &App, area: Rect) {
             ],
             Color::Green,
         ),
-        Some(UploadStatus::Error { message }) => (
-            " Error ",
-            vec![
-                Line::from(""),
-                Line::from(Span::styled(
-                    "Upload failed!",
-                    Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
-                )),
-                Line::from(""),
-                Line::from(vec![
-                    Span::styled("Error: ", Style::default().fg(Color::DarkGray)),
-                    Span::styled(message, Style::default().fg(Color::Red)),
-                ]),
-            ],
-            Color::Red,
-        ),
         None => (
             " Status ",
             vec![